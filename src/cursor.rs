@@ -1,3 +1,19 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::{Bound, Range, RangeBounds};
+use std::task::{Context, Poll};
+
+use regex_automata::util::captures::Captures;
+use regex_automata::util::primitives::NonMaxUsize;
+use regex_automata::{Match, Span};
+
+#[cfg(feature = "inflate")]
+use crate::util::inflate::{Decoder, Source};
+use crate::util::utf8;
+
+/// The maximum number of bytes in a UTF-8 encoded codepoint.
+const MAX_CODEPOINT_LEN: usize = 4;
+
 pub trait IntoCursor {
     type Cursor: Cursor;
     fn into_cursor(self) -> Self::Cursor;
@@ -44,6 +60,32 @@ pub trait Cursor {
     fn total_bytes(&self) -> Option<usize>;
     /// The offset of the current chunk from the start of the haystack in bytes
     fn offset(&self) -> usize;
+    /// Moves the cursor directly to the chunk containing the byte at offset
+    /// `at`, or to the last chunk if `at` is the end of data. This is an
+    /// optional capability for collections that can locate a chunk faster
+    /// than by calling [`advance`](Cursor::advance) or
+    /// [`backtrack`](Cursor::backtrack) repeatedly (like ropes, which can
+    /// seek in `O(log N)`). In that case `true` must be returned.
+    ///
+    /// The default implementation returns `false`, which means that seeking
+    /// is not supported and the cursor **must not have moved**. The search
+    /// then walks to `at` one chunk at a time instead.
+    fn seek(&mut self, at: usize) -> bool {
+        let _ = at;
+        false
+    }
+    /// Returns (and clears) the error that made this cursor fail to move.
+    /// Cursors backed by I/O (like [`ReaderCursor`]) can't report a failed
+    /// read from [`advance`](Cursor::advance) or
+    /// [`backtrack`](Cursor::backtrack), so they return `false` (which ends
+    /// the haystack) and keep the error until it is taken. Fallible searches
+    /// like [`Regex::try_search`](crate::engines::meta::Regex::try_search)
+    /// return it as an error.
+    ///
+    /// The default implementation never fails.
+    fn take_error(&mut self) -> Option<io::Error> {
+        None
+    }
 }
 
 impl<C: Cursor> Cursor for &mut C {
@@ -70,6 +112,14 @@ impl<C: Cursor> Cursor for &mut C {
     fn offset(&self) -> usize {
         C::offset(self)
     }
+
+    fn seek(&mut self, at: usize) -> bool {
+        C::seek(self, at)
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        C::take_error(self)
+    }
 }
 
 impl Cursor for &[u8] {
@@ -124,95 +174,365 @@ impl Cursor for &str {
     }
 }
 
-#[cfg(feature = "ropey")]
-#[derive(Clone, Copy)]
-enum Pos {
-    ChunkStart,
-    ChunkEnd,
+/// A cursor over a list of byte slices, like the buffers of a scatter/gather
+/// I/O operation or the frames of a network message.
+///
+/// Empty slices are skipped. The offset of every chunk is computed up front,
+/// so [`seek`](Cursor::seek) is a binary search. The cursor is only
+/// [utf-8 aware](Cursor::utf8_aware) if none of the slices starts in the
+/// middle of a codepoint.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Input};
+/// use regex_cursor::regex_automata::Match;
+///
+/// let re = Regex::new("foo[0-9]+")?;
+/// let chunks: &[&[u8]] = &[b"xfo", b"", b"o1", b"2 y"];
+/// assert_eq!(re.find(Input::new(chunks)), Some(Match::must(0, 1..6)));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct SliceChunksCursor<'a> {
+    /// The non-empty slices.
+    chunks: Vec<&'a [u8]>,
+    /// The offset of each slice in `chunks`.
+    offsets: Vec<usize>,
+    len: usize,
+    utf8_aware: bool,
+    /// The index of the current chunk.
+    pos: usize,
 }
 
-#[cfg(feature = "ropey")]
-#[derive(Clone)]
-pub struct RopeyCursor<'a> {
-    iter: ropey::iter::Chunks<'a>,
-    current: &'a [u8],
-    pos: Pos,
-    len: usize,
-    offset: usize,
+impl<'a> SliceChunksCursor<'a> {
+    pub fn new<T: AsRef<[u8]>>(slices: &'a [T]) -> Self {
+        slices.iter().map(|slice| slice.as_ref()).collect()
+    }
 }
 
-#[cfg(feature = "ropey")]
-impl<'a> RopeyCursor<'a> {
-    pub fn new(slice: ropey::RopeSlice<'a>) -> Self {
-        let iter = slice.chunks();
+impl<'a> FromIterator<&'a [u8]> for SliceChunksCursor<'a> {
+    fn from_iter<I: IntoIterator<Item = &'a [u8]>>(slices: I) -> Self {
+        let chunks: Vec<&[u8]> = slices.into_iter().filter(|slice| !slice.is_empty()).collect();
+        let mut len = 0;
+        let offsets = chunks
+            .iter()
+            .map(|chunk| {
+                len += chunk.len();
+                len - chunk.len()
+            })
+            .collect();
+        let utf8_aware = chunks.iter().skip(1).all(|chunk| utf8::is_boundary(chunk, 0));
+        Self { chunks, offsets, len, utf8_aware, pos: 0 }
+    }
+}
+
+impl Cursor for SliceChunksCursor<'_> {
+    fn chunk(&self) -> &[u8] {
+        self.chunks.get(self.pos).copied().unwrap_or_default()
+    }
+
+    fn utf8_aware(&self) -> bool {
+        self.utf8_aware
+    }
+
+    fn advance(&mut self) -> bool {
+        if self.pos + 1 < self.chunks.len() {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn backtrack(&mut self) -> bool {
+        if self.pos > 0 {
+            self.pos -= 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        Some(self.len)
+    }
+
+    fn offset(&self) -> usize {
+        self.offsets.get(self.pos).copied().unwrap_or(0)
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        self.pos = self.offsets.partition_point(|&offset| offset <= at).saturating_sub(1);
+        true
+    }
+}
+
+impl<'a> IntoCursor for &'a [&[u8]] {
+    type Cursor = SliceChunksCursor<'a>;
+
+    fn into_cursor(self) -> Self::Cursor {
+        SliceChunksCursor::new(self)
+    }
+}
+
+impl<'a> IntoCursor for &'a [Vec<u8>] {
+    type Cursor = SliceChunksCursor<'a>;
+
+    fn into_cursor(self) -> Self::Cursor {
+        SliceChunksCursor::new(self)
+    }
+}
+
+impl<'a> IntoCursor for &'a [String] {
+    type Cursor = SliceChunksCursor<'a>;
+
+    fn into_cursor(self) -> Self::Cursor {
+        SliceChunksCursor::new(self)
+    }
+}
+
+/// Searches the two slices of a ring buffer (see [`VecDeque::as_slices`]).
+/// If the ring buffer wraps around in the middle of a codepoint, the cursor
+/// isn't [utf-8 aware](Cursor::utf8_aware).
+impl<'a> IntoCursor for &'a VecDeque<u8> {
+    type Cursor = SliceChunksCursor<'a>;
+
+    fn into_cursor(self) -> Self::Cursor {
+        let (front, back) = self.as_slices();
+        [front, back].into_iter().collect()
+    }
+}
+
+/// A cursor over the text of a gap buffer, which is stored in two slices
+/// before and after the gap. The gap is not part of the haystack, so offsets
+/// are the same as if the two slices were contiguous.
+///
+/// If the gap splits a codepoint (for example, in the middle of an edit), the
+/// bytes of that codepoint are copied into a small chunk of their own, so that
+/// the cursor is always [utf-8 aware](Cursor::utf8_aware).
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, GapBufferCursor, Input};
+/// use regex_cursor::regex_automata::Match;
+///
+/// let re = Regex::new(r"\bfoo\w*")?;
+/// let cursor = GapBufferCursor::new(b"a fo", b"obar b");
+/// assert_eq!(re.find(Input::new(cursor)), Some(Match::must(0, 2..8)));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct GapBufferCursor<'a> {
+    /// The text before the gap, except for the start of a codepoint split
+    /// by the gap.
+    before: &'a [u8],
+    /// The codepoint split by the gap, if any.
+    split: [u8; 2 * (MAX_CODEPOINT_LEN - 1)],
+    split_len: usize,
+    /// The text after the gap, except for the end of a codepoint split by
+    /// the gap.
+    after: &'a [u8],
+    /// The index of the current chunk: 0 for `before`, 1 for `split` and 2
+    /// for `after`.
+    pos: usize,
+}
+
+impl<'a> GapBufferCursor<'a> {
+    pub fn new(before: &'a [u8], after: &'a [u8]) -> Self {
         let mut res =
-            Self { current: &[], iter, pos: Pos::ChunkEnd, len: slice.len_bytes(), offset: 0 };
-        res.advance();
+            Self { before, split: [0; 2 * (MAX_CODEPOINT_LEN - 1)], split_len: 0, after, pos: 0 };
+        if !before.is_empty() && !utf8::is_boundary(after, 0) {
+            let tail = before.len().saturating_sub(MAX_CODEPOINT_LEN - 1);
+            let start =
+                (tail..before.len()).rev().find(|&i| utf8::is_boundary(before, i)).unwrap_or(tail);
+            let end = (1..after.len().min(MAX_CODEPOINT_LEN))
+                .find(|&i| utf8::is_boundary(after, i))
+                .unwrap_or(after.len().min(MAX_CODEPOINT_LEN - 1));
+            let (head, tail) = (&before[start..], &after[..end]);
+            res.split[..head.len()].copy_from_slice(head);
+            res.split[head.len()..head.len() + tail.len()].copy_from_slice(tail);
+            res.split_len = head.len() + tail.len();
+            res.before = &before[..start];
+            res.after = &after[end..];
+        }
+        res.pos = (0..3).find(|&i| !res.part(i).is_empty()).unwrap_or(0);
         res
     }
 
-    pub fn at(slice: ropey::RopeSlice<'a>, at: usize) -> Self {
-        let (iter, offset, _, _) = slice.chunks_at_byte(at);
-        if offset == slice.len_bytes() {
-            let mut res =
-                Self { current: &[], iter, pos: Pos::ChunkStart, len: slice.len_bytes(), offset };
-            res.backtrack();
-            res
-        } else {
-            let mut res =
-                Self { current: &[], iter, pos: Pos::ChunkEnd, len: slice.len_bytes(), offset };
-            res.advance();
-            res
+    fn part(&self, i: usize) -> &[u8] {
+        match i {
+            0 => self.before,
+            1 => &self.split[..self.split_len],
+            _ => self.after,
         }
     }
 }
 
-#[cfg(feature = "ropey")]
-impl Cursor for RopeyCursor<'_> {
+impl Cursor for GapBufferCursor<'_> {
     fn chunk(&self) -> &[u8] {
-        self.current
+        self.part(self.pos)
+    }
+
+    fn utf8_aware(&self) -> bool {
+        true
     }
 
     fn advance(&mut self) -> bool {
-        match self.pos {
-            Pos::ChunkStart => {
-                self.iter.next();
-                self.pos = Pos::ChunkEnd;
+        match (self.pos + 1..3).find(|&i| !self.part(i).is_empty()) {
+            Some(pos) => {
+                self.pos = pos;
+                true
             }
-            Pos::ChunkEnd => (),
-        }
-        for next in self.iter.by_ref() {
-            if next.is_empty() {
-                continue;
-            }
-            self.offset += self.current.len();
-            self.current = next.as_bytes();
-            return true;
+            None => false,
         }
-        false
     }
 
     fn backtrack(&mut self) -> bool {
-        match self.pos {
-            Pos::ChunkStart => {}
-            Pos::ChunkEnd => {
-                self.iter.prev();
-                self.pos = Pos::ChunkStart;
+        match (0..self.pos).rev().find(|&i| !self.part(i).is_empty()) {
+            Some(pos) => {
+                self.pos = pos;
+                true
             }
+            None => false,
         }
-        while let Some(prev) = self.iter.prev() {
-            if prev.is_empty() {
-                continue;
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        Some(self.before.len() + self.split_len + self.after.len())
+    }
+
+    fn offset(&self) -> usize {
+        (0..self.pos).map(|i| self.part(i).len()).sum()
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        let mut offset = 0;
+        for i in 0..3 {
+            let len = self.part(i).len();
+            if len != 0 {
+                self.pos = i;
+                if at < offset + len {
+                    break;
+                }
             }
-            self.offset -= prev.len();
-            self.current = prev.as_bytes();
-            return true;
+            offset += len;
+        }
+        true
+    }
+}
+
+/// A cursor over the pieces of a piece table. Each piece is a
+/// `(buffer_id, start, len)` triple referring to `len` bytes at `start` in
+/// one of the buffers of the table (usually the original file and the
+/// append-only add buffer), and `resolve` returns the buffer for a
+/// `buffer_id`. Pieces are only resolved when the cursor moves to them.
+///
+/// Empty pieces are skipped. The offset of every piece is computed up front,
+/// so [`seek`](Cursor::seek) is a binary search. The cursor is only
+/// [utf-8 aware](Cursor::utf8_aware) if none of the pieces starts in the
+/// middle of a codepoint.
+///
+/// # Panics
+///
+/// Creating or moving the cursor panics if a piece is out of bounds of its
+/// buffer.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Input, PieceTableCursor};
+/// use regex_cursor::regex_automata::Match;
+///
+/// #[derive(Clone, Copy)]
+/// enum Buffer {
+///     Original,
+///     Add,
+/// }
+///
+/// let (original, add) = (b"let x = 42;", b"foo");
+/// let pieces = [(Buffer::Original, 0, 4), (Buffer::Add, 0, 3), (Buffer::Original, 5, 6)];
+/// let cursor = PieceTableCursor::new(&pieces, |buffer| match buffer {
+///     Buffer::Original => original,
+///     Buffer::Add => add,
+/// });
+/// let re = Regex::new(r"[a-z]+ =")?;
+/// assert_eq!(re.find(Input::new(cursor)), Some(Match::must(0, 4..9)));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone)]
+pub struct PieceTableCursor<'a, B, F> {
+    /// The non-empty pieces.
+    pieces: Vec<&'a (B, usize, usize)>,
+    resolve: F,
+    /// The offset of each piece in `pieces`.
+    offsets: Vec<usize>,
+    len: usize,
+    utf8_aware: bool,
+    /// The index of the current piece.
+    pos: usize,
+    current: &'a [u8],
+}
+
+impl<'a, B, F: Fn(&B) -> &'a [u8]> PieceTableCursor<'a, B, F> {
+    pub fn new(pieces: &'a [(B, usize, usize)], resolve: F) -> Self {
+        let pieces: Vec<_> = pieces.iter().filter(|piece| piece.2 != 0).collect();
+        let mut len = 0;
+        let offsets = pieces
+            .iter()
+            .map(|piece| {
+                len += piece.2;
+                len - piece.2
+            })
+            .collect();
+        let mut res =
+            Self { pieces, resolve, offsets, len, utf8_aware: true, pos: 0, current: &[] };
+        res.utf8_aware = (1..res.pieces.len()).all(|i| utf8::is_boundary(res.piece(i), 0));
+        if !res.pieces.is_empty() {
+            res.current = res.piece(0);
+        }
+        res
+    }
+
+    fn piece(&self, i: usize) -> &'a [u8] {
+        let (buffer, start, len) = self.pieces[i];
+        &(self.resolve)(buffer)[*start..*start + *len]
+    }
+
+    fn move_to(&mut self, pos: usize) {
+        if pos != self.pos {
+            self.pos = pos;
+            self.current = self.piece(pos);
         }
-        false
+    }
+}
+
+impl<'a, B, F: Fn(&B) -> &'a [u8]> Cursor for PieceTableCursor<'a, B, F> {
+    fn chunk(&self) -> &[u8] {
+        self.current
     }
 
     fn utf8_aware(&self) -> bool {
-        true
+        self.utf8_aware
+    }
+
+    fn advance(&mut self) -> bool {
+        if self.pos + 1 < self.pieces.len() {
+            self.move_to(self.pos + 1);
+            true
+        } else {
+            false
+        }
+    }
+
+    fn backtrack(&mut self) -> bool {
+        if self.pos > 0 {
+            self.move_to(self.pos - 1);
+            true
+        } else {
+            false
+        }
     }
 
     fn total_bytes(&self) -> Option<usize> {
@@ -220,61 +540,4347 @@ impl Cursor for RopeyCursor<'_> {
     }
 
     fn offset(&self) -> usize {
-        self.offset
+        self.offsets.get(self.pos).copied().unwrap_or(0)
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        if !self.pieces.is_empty() {
+            self.move_to(self.offsets.partition_point(|&offset| offset <= at).saturating_sub(1));
+        }
+        true
     }
 }
 
-#[cfg(feature = "ropey")]
-impl<'a> IntoCursor for ropey::RopeSlice<'a> {
-    type Cursor = RopeyCursor<'a>;
+/// A cursor over a document that is stored as a list of lines without their
+/// line terminators.
+///
+/// Every line is followed by a virtual line terminator (`\n` by default, see
+/// [`LinesCursor::terminator`]) which is yielded as a chunk of its own and
+/// counted by the offsets, so the cursor behaves exactly like the contiguous
+/// document, including its final line terminator. Patterns can match across
+/// lines and multi-line anchors like `(?m:^)` and `(?m:$)` (or `(?Rm:$)` for
+/// `\r\n`) see the terminators. [`LinesCursor::position`] maps offsets back
+/// to lines.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Input, LinesCursor};
+/// use regex_cursor::regex_automata::Match;
+///
+/// let lines = vec!["foo".to_string(), "bar baz".to_string()];
+/// let re = Regex::new(r"(?m)o\nbar|baz$")?;
+/// let matches: Vec<Match> = re.find_iter(Input::new(LinesCursor::new(&lines))).collect();
+/// assert_eq!(matches, vec![Match::must(0, 2..7), Match::must(0, 8..11)]);
+///
+/// let cursor = LinesCursor::new(&lines);
+/// assert_eq!(cursor.position(2), (0, 2));
+/// assert_eq!(cursor.position(8), (1, 4));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct LinesCursor<'a> {
+    lines: Vec<&'a [u8]>,
+    /// The offset of each line in `lines`.
+    offsets: Vec<usize>,
+    terminator: &'a [u8],
+    len: usize,
+    utf8_aware: bool,
+    /// The index of the current chunk: `2 * i` is line `i` and `2 * i + 1`
+    /// is the terminator after it.
+    pos: usize,
+}
 
-    fn into_cursor(self) -> Self::Cursor {
-        RopeyCursor::new(self)
+impl<'a> LinesCursor<'a> {
+    pub fn new<T: AsRef<[u8]>>(lines: &'a [T]) -> Self {
+        let lines: Vec<&[u8]> = lines.iter().map(|line| line.as_ref()).collect();
+        let utf8_aware = lines.iter().all(|line| utf8::is_boundary(line, 0));
+        let mut res =
+            Self { lines, offsets: Vec::new(), terminator: b"", len: 0, utf8_aware, pos: 0 };
+        res.set_terminator(b"\n");
+        res
     }
-}
 
-#[cfg(feature = "ropey")]
-impl<'a> IntoCursor for &'a ropey::Rope {
-    type Cursor = RopeyCursor<'a>;
+    /// Sets the line terminator that follows every line, for example,
+    /// `b"\r\n"`. This resets the cursor to the start of the document.
+    pub fn terminator(mut self, terminator: &'a [u8]) -> Self {
+        self.set_terminator(terminator);
+        self
+    }
 
-    fn into_cursor(self) -> Self::Cursor {
-        RopeyCursor::new(self.slice(..))
+    fn set_terminator(&mut self, terminator: &'a [u8]) {
+        self.terminator = terminator;
+        self.len = 0;
+        self.offsets = self
+            .lines
+            .iter()
+            .map(|line| {
+                self.len += line.len() + terminator.len();
+                self.len - line.len() - terminator.len()
+            })
+            .collect();
+        self.pos = 0;
+        self.skip_empty();
+    }
+
+    /// Converts an offset into the document into the index of its line and
+    /// the offset within that line. Offsets in a line terminator are past
+    /// the end of the line, and the offset at the end of the document is
+    /// `(number_of_lines, 0)`.
+    pub fn position(&self, offset: usize) -> (usize, usize) {
+        let line = self.offsets.partition_point(|&line_offset| line_offset <= offset);
+        if line == 0 || offset >= self.len {
+            return (self.lines.len(), offset - self.len);
+        }
+        (line - 1, offset - self.offsets[line - 1])
+    }
+
+    fn chunk_at(&self, pos: usize) -> &'a [u8] {
+        match self.lines.get(pos / 2) {
+            Some(line) if pos % 2 == 0 => line,
+            Some(_) => self.terminator,
+            None => &[],
+        }
+    }
+
+    /// Moves to the next non-empty chunk at or after the current one, or
+    /// to the last non-empty chunk before it if there is none.
+    fn skip_empty(&mut self) {
+        let chunks = 2 * self.lines.len();
+        if let Some(pos) = (self.pos..chunks).find(|&pos| !self.chunk_at(pos).is_empty()) {
+            self.pos = pos;
+        } else if let Some(pos) = (0..self.pos).rev().find(|&pos| !self.chunk_at(pos).is_empty()) {
+            self.pos = pos;
+        }
     }
 }
-#[cfg(all(feature = "ropey", test))]
-mod ropey_test {
-    use ropey::Rope;
 
-    use crate::cursor::IntoCursor;
-    use crate::Cursor;
+impl Cursor for LinesCursor<'_> {
+    fn chunk(&self) -> &[u8] {
+        self.chunk_at(self.pos)
+    }
 
-    #[test]
-    fn smoke_test() {
-        let rope = Rope::from_str("abc");
-        let mut cursor = rope.into_cursor();
-        assert_eq!(cursor.chunk(), "abc".as_bytes());
-        assert!(!cursor.advance());
-        assert_eq!(cursor.chunk(), "abc".as_bytes());
-        assert!(!cursor.backtrack());
-        assert_eq!(cursor.chunk(), "abc".as_bytes());
-        let rope = Rope::from("abc".repeat(5000));
-        let mut cursor = rope.into_cursor();
-        let mut offset = 0;
-        loop {
-            assert_eq!(cursor.offset(), offset);
-            offset += cursor.chunk().len();
-            if !cursor.advance() {
-                break;
+    fn utf8_aware(&self) -> bool {
+        self.utf8_aware
+    }
+
+    fn advance(&mut self) -> bool {
+        let chunks = 2 * self.lines.len();
+        match (self.pos + 1..chunks).find(|&pos| !self.chunk_at(pos).is_empty()) {
+            Some(pos) => {
+                self.pos = pos;
+                true
             }
+            None => false,
         }
-        loop {
-            offset -= cursor.chunk().len();
-            assert_eq!(cursor.offset(), offset);
-            if !cursor.backtrack() {
-                break;
+    }
+
+    fn backtrack(&mut self) -> bool {
+        match (0..self.pos).rev().find(|&pos| !self.chunk_at(pos).is_empty()) {
+            Some(pos) => {
+                self.pos = pos;
+                true
             }
+            None => false,
         }
-        assert_eq!(cursor.offset(), 0);
-        assert_eq!(offset, 0);
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        Some(self.len)
+    }
+
+    fn offset(&self) -> usize {
+        match self.offsets.get(self.pos / 2) {
+            Some(offset) if self.pos % 2 == 0 => *offset,
+            Some(offset) => offset + self.lines[self.pos / 2].len(),
+            None => 0,
+        }
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        let line = self.offsets.partition_point(|&offset| offset <= at).saturating_sub(1);
+        if line < self.lines.len() {
+            let in_line = at < self.offsets[line] + self.lines[line].len();
+            self.pos = if in_line { 2 * line } else { 2 * line + 1 };
+            self.skip_empty();
+        }
+        true
+    }
+}
+
+/// A cursor that reads the haystack from an [`io::Read`] implementation
+/// (like a file or a pipe) in chunks of a fixed size, as the search advances.
+///
+/// Only a window of the most recently read chunks is kept in memory, so the
+/// cursor can only [`backtrack`](Cursor::backtrack) (or
+/// [`seek`](Cursor::seek)) within that window. The engines backtrack to find
+/// the start of a match (and to evaluate look-behind assertions), so matches
+/// must fit into the window. The length of the haystack isn't known in
+/// advance, so [`total_bytes`](Cursor::total_bytes) returns `None`.
+///
+/// Errors returned by the reader end the haystack, and backtracking beyond
+/// the window fails with an error explaining that the data before the window
+/// is no longer available. Since the search can't know what it missed,
+/// infallible searches like [`Regex::find`](crate::engines::meta::Regex::find)
+/// panic once the cursor failed to backtrack, while fallible searches like
+/// [`Regex::try_search`](crate::engines::meta::Regex::try_search) return the
+/// error. Errors of the reader can also be retrieved with
+/// [`ReaderCursor::error`] after the search.
+/// Chunks may split codepoints, so the cursor isn't
+/// [utf-8 aware](Cursor::utf8_aware).
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Input, ReaderCursor};
+///
+/// let re = Regex::new(r"[0-9]{4}-[0-9]{2}-[0-9]{2}")?;
+/// let log = "2024-01-01 start\n".repeat(100) + "2024-12-31 stop\n";
+/// let cursor = ReaderCursor::with_window(log.as_bytes(), 64, 2);
+/// assert_eq!(re.find_iter(Input::new(cursor)).count(), 101);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct ReaderCursor<R> {
+    reader: R,
+    chunk_size: usize,
+    /// The maximum number of chunks in `chunks`.
+    window: usize,
+    /// The chunks in the window. All of them are `chunk_size` bytes long,
+    /// except for the last one if the end of the reader has been reached.
+    chunks: VecDeque<Vec<u8>>,
+    /// The allocation of the last chunk that was dropped from the window.
+    spare: Vec<u8>,
+    /// The offset of the first chunk in `chunks`.
+    window_offset: usize,
+    /// Set once the end of the reader (or an error) has been reached.
+    eof: bool,
+    error: Option<io::Error>,
+    /// The index of the current chunk in `chunks`.
+    pos: usize,
+}
+
+impl<R: Read> ReaderCursor<R> {
+    /// The chunk size used by [`ReaderCursor::new`].
+    pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+    /// The window size (in chunks) used by [`ReaderCursor::new`].
+    pub const DEFAULT_WINDOW: usize = 16;
+
+    /// Creates a cursor that reads chunks of
+    /// [`DEFAULT_CHUNK_SIZE`](Self::DEFAULT_CHUNK_SIZE) bytes and keeps the
+    /// last [`DEFAULT_WINDOW`](Self::DEFAULT_WINDOW) of them. The first chunk
+    /// is read right away.
+    pub fn new(reader: R) -> Self {
+        Self::with_window(reader, Self::DEFAULT_CHUNK_SIZE, Self::DEFAULT_WINDOW)
+    }
+
+    /// Creates a cursor that reads chunks of `chunk_size` bytes and keeps the
+    /// last `window` of them, so it can backtrack by at least
+    /// `(window - 1) * chunk_size` bytes. The first chunk is read right away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size` or `window` is zero.
+    pub fn with_window(reader: R, chunk_size: usize, window: usize) -> Self {
+        assert!(chunk_size > 0, "chunk size must be greater than zero");
+        assert!(window > 0, "window must hold at least one chunk");
+        let mut res = Self {
+            reader,
+            chunk_size,
+            window,
+            chunks: VecDeque::with_capacity(window),
+            spare: Vec::new(),
+            window_offset: 0,
+            eof: false,
+            error: None,
+            pos: 0,
+        };
+        res.read_chunk();
+        res
+    }
+
+    /// Returns the error returned by the reader (or the error of a search
+    /// that backtracked beyond the window), if any. The haystack ends where
+    /// a read error occurred.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Reads the next chunk into the window, dropping the first chunk if the
+    /// window is full. Returns false if the reader has no more data.
+    fn read_chunk(&mut self) -> bool {
+        if self.eof {
+            return false;
+        }
+        let mut buf = std::mem::take(&mut self.spare);
+        buf.clear();
+        buf.resize(self.chunk_size, 0);
+        let mut filled = 0;
+        while filled < buf.len() {
+            match self.reader.read(&mut buf[filled..]) {
+                Ok(0) => self.eof = true,
+                Ok(n) => filled += n,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    self.eof = true;
+                    self.error = Some(err);
+                }
+            }
+            if self.eof {
+                break;
+            }
+        }
+        buf.truncate(filled);
+        if buf.is_empty() {
+            self.spare = buf;
+            return false;
+        }
+        self.chunks.push_back(buf);
+        if self.chunks.len() > self.window {
+            self.spare = self.chunks.pop_front().unwrap();
+            self.window_offset += self.spare.len();
+        }
+        true
+    }
+}
+
+impl<R: Read> Cursor for ReaderCursor<R> {
+    fn chunk(&self) -> &[u8] {
+        self.chunks.get(self.pos).map_or(&[], |chunk| chunk)
+    }
+
+    fn utf8_aware(&self) -> bool {
+        false
+    }
+
+    fn advance(&mut self) -> bool {
+        if self.pos + 1 < self.chunks.len() {
+            self.pos += 1;
+            true
+        } else if self.read_chunk() {
+            self.pos = self.chunks.len() - 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn backtrack(&mut self) -> bool {
+        if self.pos > 0 {
+            self.pos -= 1;
+            true
+        } else {
+            if self.window_offset != 0 && self.error.is_none() {
+                self.error = Some(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!(
+                        "the haystack before offset {} is no longer available, it was dropped \
+                         from the window of the ReaderCursor",
+                        self.window_offset
+                    ),
+                ));
+            }
+            false
+        }
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        None
+    }
+
+    fn offset(&self) -> usize {
+        self.window_offset + self.pos * self.chunk_size
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        if at < self.window_offset || self.chunks.is_empty() {
+            return false;
+        }
+        let pos = (at - self.window_offset) / self.chunk_size;
+        if pos < self.chunks.len() {
+            self.pos = pos;
+            true
+        } else if self.eof {
+            self.pos = self.chunks.len() - 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+/// A cursor over a seekable file (or any other [`Read`] + [`Seek`]
+/// implementation) that is too large to be loaded into memory.
+///
+/// The file is split into blocks of a fixed size which are read on demand,
+/// and the most recently used blocks are kept in a cache of bounded size.
+/// Blocks that were evicted from the cache are read again when the cursor
+/// moves back to them, so unlike [`ReaderCursor`] this cursor can backtrack
+/// (and [`seek`](Cursor::seek)) anywhere. The length of the file is
+/// determined when the cursor is created and must not change while it is
+/// searched.
+///
+/// If reading a block fails, the cursor doesn't move, which ends the
+/// haystack early (or makes backtracking fail, which makes infallible
+/// searches panic). The error can be retrieved
+/// with [`FileCursor::error`] after the search, and fallible searches like
+/// [`Regex::try_search`](crate::engines::meta::Regex::try_search) return it.
+/// Blocks may split codepoints, so the cursor isn't
+/// [utf-8 aware](Cursor::utf8_aware).
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+/// use regex_cursor::{engines::meta::Regex, FileCursor, Input};
+///
+/// let log = "INFO ok\n".repeat(1000) + "ERROR disk full\n" + &"INFO ok\n".repeat(1000);
+/// let file = io::Cursor::new(log.as_bytes());
+/// let re = Regex::new(r"(?m)^ERROR.*$")?;
+/// let cursor = FileCursor::with_cache(file, 0, 256, 4)?;
+/// let m = re.find(Input::new(cursor)).unwrap();
+/// assert_eq!(&log[m.range()], "ERROR disk full");
+///
+/// // start searching after the error
+/// let file = io::Cursor::new(log.as_bytes());
+/// let input = Input::new(FileCursor::at(file, m.end())?).range(m.end()..);
+/// assert!(re.find(input).is_none());
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct FileCursor<F> {
+    file: F,
+    block_size: usize,
+    /// The maximum number of blocks in `cache`.
+    cache_blocks: usize,
+    /// The cached blocks and their indices, from least to most recently
+    /// used. The last one is the current block.
+    cache: Vec<(usize, Vec<u8>)>,
+    /// The allocation of the last block that was evicted from the cache.
+    spare: Vec<u8>,
+    len: usize,
+    error: Option<io::Error>,
+}
+
+impl<F: Read + Seek> FileCursor<F> {
+    /// The block size used by [`FileCursor::new`] and [`FileCursor::at`].
+    pub const DEFAULT_BLOCK_SIZE: usize = 64 * 1024;
+    /// The cache size (in blocks) used by [`FileCursor::new`] and
+    /// [`FileCursor::at`].
+    pub const DEFAULT_CACHE_BLOCKS: usize = 64;
+
+    /// Creates a cursor at the start of the file, with blocks of
+    /// [`DEFAULT_BLOCK_SIZE`](Self::DEFAULT_BLOCK_SIZE) bytes and a cache of
+    /// [`DEFAULT_CACHE_BLOCKS`](Self::DEFAULT_CACHE_BLOCKS) blocks.
+    pub fn new(file: F) -> io::Result<Self> {
+        Self::at(file, 0)
+    }
+
+    /// Like [`FileCursor::new`], but starts at the block that contains the
+    /// byte at `offset`, so that searches starting at `offset` don't need
+    /// to read the file before it.
+    pub fn at(file: F, offset: usize) -> io::Result<Self> {
+        Self::with_cache(file, offset, Self::DEFAULT_BLOCK_SIZE, Self::DEFAULT_CACHE_BLOCKS)
+    }
+
+    /// Creates a cursor at the block that contains the byte at `offset`
+    /// (or the last block if `offset` is past the end of the file), with
+    /// blocks of `block_size` bytes and a cache of `cache_blocks` blocks.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `block_size` or `cache_blocks` is zero.
+    pub fn with_cache(
+        mut file: F,
+        offset: usize,
+        block_size: usize,
+        cache_blocks: usize,
+    ) -> io::Result<Self> {
+        assert!(block_size > 0, "block size must be greater than zero");
+        assert!(cache_blocks > 0, "cache must hold at least one block");
+        let len = file.seek(SeekFrom::End(0))?;
+        let len = usize::try_from(len).map_err(|_| {
+            io::Error::new(io::ErrorKind::InvalidInput, "file is too large to be searched")
+        })?;
+        let mut res = Self {
+            file,
+            block_size,
+            cache_blocks,
+            cache: Vec::with_capacity(cache_blocks + 1),
+            spare: Vec::new(),
+            len,
+            error: None,
+        };
+        if len != 0 {
+            let block = offset.min(len - 1) / block_size;
+            if !res.load(block) {
+                return Err(res.error.take().unwrap());
+            }
+        }
+        Ok(res)
+    }
+
+    /// Returns the last error that occurred while reading a block, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns the underlying file.
+    pub fn into_inner(self) -> F {
+        self.file
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.cache.last().map(|&(block, _)| block)
+    }
+
+    /// Makes `block` the current block, reading it from the file unless it
+    /// is cached. Returns false (without moving) if the read fails.
+    fn load(&mut self, block: usize) -> bool {
+        if let Some(i) = self.cache.iter().position(|&(cached, _)| cached == block) {
+            let entry = self.cache.remove(i);
+            self.cache.push(entry);
+            return true;
+        }
+        let start = block * self.block_size;
+        let mut buf = std::mem::take(&mut self.spare);
+        buf.clear();
+        buf.resize(self.block_size.min(self.len - start), 0);
+        let res = self
+            .file
+            .seek(SeekFrom::Start(start as u64))
+            .and_then(|_| self.file.read_exact(&mut buf));
+        match res {
+            Ok(()) => {
+                self.cache.push((block, buf));
+                if self.cache.len() > self.cache_blocks {
+                    self.spare = self.cache.remove(0).1;
+                }
+                true
+            }
+            Err(err) => {
+                self.spare = buf;
+                self.error = Some(err);
+                false
+            }
+        }
+    }
+}
+
+impl<F: Read + Seek> Cursor for FileCursor<F> {
+    fn chunk(&self) -> &[u8] {
+        self.cache.last().map_or(&[], |(_, block)| block)
+    }
+
+    fn utf8_aware(&self) -> bool {
+        false
+    }
+
+    fn advance(&mut self) -> bool {
+        match self.current() {
+            Some(block) if (block + 1) * self.block_size < self.len => self.load(block + 1),
+            _ => false,
+        }
+    }
+
+    fn backtrack(&mut self) -> bool {
+        match self.current() {
+            Some(block) if block > 0 => self.load(block - 1),
+            _ => false,
+        }
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        Some(self.len)
+    }
+
+    fn offset(&self) -> usize {
+        self.current().map_or(0, |block| block * self.block_size)
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        if self.len == 0 {
+            return true;
+        }
+        self.load(at.min(self.len - 1) / self.block_size)
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+/// A cursor over the haystack of one cursor followed by the haystack of
+/// another, for example, a header and a body that are stored separately.
+///
+/// The offsets of the second cursor are shifted by the length of the first
+/// haystack, which is taken from [`total_bytes`](Cursor::total_bytes) or
+/// determined once the first cursor can't advance anymore.
+/// [`Chain::map_span`] maps a span of the chained haystack back to the two
+/// haystacks. Both cursors must be at their first chunk when the chain is
+/// created.
+///
+/// The chain is [utf-8 aware](Cursor::utf8_aware) if both cursors are and the
+/// second haystack doesn't start in the middle of a codepoint.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Chain, Input};
+/// use regex_cursor::regex_automata::Span;
+///
+/// let re = Regex::new(r"(?m)^Subject: (.*)\n\n(\w+)")?;
+/// let chain = Chain::new("From: me\nSubject: hi", "\n\nhello world");
+/// let m = re.find(Input::new(chain.clone())).unwrap();
+/// assert_eq!(m.range(), 9..27);
+/// let (header, body) = chain.map_span(m.span());
+/// assert_eq!(header, Some(Span::from(9..20)));
+/// assert_eq!(body, Some(Span::from(0..7)));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+    /// The length of the haystack of `first`, once it is known.
+    first_len: Option<usize>,
+    /// Whether the current chunk is a chunk of `second`.
+    in_second: bool,
+    /// Whether the cursor that isn't current is next to the seam, that is,
+    /// at the last chunk of `first` or at the first chunk of `second`. Only
+    /// seeking moves it away from there.
+    parked: bool,
+    utf8_aware: bool,
+}
+
+impl<A: Cursor, B: Cursor> Chain<A, B> {
+    pub fn new<T: IntoCursor<Cursor = A>, U: IntoCursor<Cursor = B>>(first: T, second: U) -> Self {
+        let (first, second) = (first.into_cursor(), second.into_cursor());
+        let utf8_aware =
+            first.utf8_aware() && second.utf8_aware() && utf8::is_boundary(second.chunk(), 0);
+        let in_second = first.chunk().is_empty();
+        let first_len = if in_second { Some(0) } else { first.total_bytes() };
+        Self { first, second, first_len, in_second, parked: true, utf8_aware }
+    }
+
+    /// Returns the length of the first haystack, if it is known.
+    pub fn first_len(&self) -> Option<usize> {
+        self.first_len
+    }
+
+    /// Splits a span of the chained haystack into the parts that belong to
+    /// the first and the second haystack, in their own coordinates. An empty
+    /// span at the seam belongs to the first haystack.
+    pub fn map_span(&self, span: Span) -> (Option<Span>, Option<Span>) {
+        // a span that extends past the first haystack can only have been
+        // found after its length was determined
+        let first_len = self.first_len.unwrap_or(usize::MAX);
+        let first = (span.start < first_len || span.end <= first_len)
+            .then(|| Span { start: span.start, end: span.end.min(first_len) });
+        let second = (span.end > first_len).then(|| Span {
+            start: span.start.max(first_len) - first_len,
+            end: span.end - first_len,
+        });
+        (first, second)
+    }
+
+    /// Returns the two cursors.
+    pub fn into_inner(self) -> (A, B) {
+        (self.first, self.second)
+    }
+
+    /// Moves the cursor that isn't current back to the seam.
+    fn park(&mut self) {
+        if self.parked {
+            return;
+        }
+        self.parked = true;
+        if self.in_second {
+            let first_len = self.first_len.unwrap_or_default();
+            if !self.first.seek(first_len) {
+                while self.first.advance() {}
+            }
+        } else if !self.second.seek(0) {
+            while self.second.backtrack() {}
+        }
+    }
+}
+
+impl<A: Cursor, B: Cursor> Cursor for Chain<A, B> {
+    fn chunk(&self) -> &[u8] {
+        if self.in_second {
+            self.second.chunk()
+        } else {
+            self.first.chunk()
+        }
+    }
+
+    fn utf8_aware(&self) -> bool {
+        self.utf8_aware
+    }
+
+    fn advance(&mut self) -> bool {
+        if self.in_second {
+            return self.second.advance();
+        }
+        if self.first.advance() {
+            return true;
+        }
+        self.first_len = Some(self.first.offset() + self.first.chunk().len());
+        self.park();
+        if self.second.chunk().is_empty() {
+            return false;
+        }
+        self.in_second = true;
+        true
+    }
+
+    fn backtrack(&mut self) -> bool {
+        if !self.in_second {
+            return self.first.backtrack();
+        }
+        if self.second.backtrack() {
+            return true;
+        }
+        // the second cursor failed to backtrack to data it no longer has
+        if self.second.offset() != 0 {
+            return false;
+        }
+        self.park();
+        if self.first.chunk().is_empty() {
+            return false;
+        }
+        self.in_second = false;
+        true
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        let first_len = self.first_len.or_else(|| self.first.total_bytes())?;
+        Some(first_len + self.second.total_bytes()?)
+    }
+
+    fn offset(&self) -> usize {
+        if self.in_second {
+            self.first_len.unwrap_or_default() + self.second.offset()
+        } else {
+            self.first.offset()
+        }
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        let Some(first_len) = self.first_len.or_else(|| self.first.total_bytes()) else {
+            return false;
+        };
+        self.first_len = Some(first_len);
+        let in_second = at >= first_len && (first_len == 0 || self.second.total_bytes() != Some(0));
+        let moved = if in_second { self.second.seek(at - first_len) } else { self.first.seek(at) };
+        if moved && in_second != self.in_second {
+            self.in_second = in_second;
+            self.parked = false;
+        }
+        moved
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.first.take_error().or_else(|| self.second.take_error())
+    }
+}
+
+/// A cursor over a range of the haystack of another cursor, which is
+/// searched as a standalone haystack: offsets start at zero at the start of
+/// the range, and the bytes around the range are invisible to the search
+/// (so `^` and `\A` match at the start of the range and `\b` doesn't look
+/// at the bytes before it). This is different from
+/// [`Input::range`](crate::Input::range), which only restricts where matches
+/// may occur. [`Window::map_span`] maps a span back to the haystack of the
+/// underlying cursor.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Input, Window};
+///
+/// let re = Regex::new(r"\A\w+")?;
+/// let haystack = "foobar baz";
+/// assert!(re.find(Input::new(haystack).range(3..)).is_none());
+/// let window = Window::new(haystack, 3..);
+/// let m = re.find(Input::new(window.clone())).unwrap();
+/// assert_eq!(m.range(), 0..3);
+/// assert_eq!(window.map_span(m.span()).range(), 3..6);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Window<C> {
+    cursor: C,
+    start: usize,
+    /// The end of the range, or `usize::MAX` if it's unbounded and the
+    /// length of the haystack isn't known.
+    end: usize,
+}
+
+impl<C: Cursor> Window<C> {
+    /// Creates a cursor over the bytes in `range` of the haystack of
+    /// `cursor`, and moves `cursor` to the chunk that contains the start of
+    /// the range. The range is clamped to the haystack if its length is
+    /// known. Otherwise, the length of the window isn't known either, since
+    /// the haystack may end before the range does.
+    pub fn new<T: IntoCursor<Cursor = C>, R: RangeBounds<usize>>(cursor: T, range: R) -> Self {
+        let mut cursor = cursor.into_cursor();
+        let mut end = match range.end_bound() {
+            Bound::Included(&end) => end.saturating_add(1),
+            Bound::Excluded(&end) => end,
+            Bound::Unbounded => usize::MAX,
+        };
+        if let Some(len) = cursor.total_bytes() {
+            end = end.min(len);
+        }
+        let start = match range.start_bound() {
+            Bound::Included(&start) => start,
+            Bound::Excluded(&start) => start.saturating_add(1),
+            Bound::Unbounded => 0,
+        }
+        .min(end);
+        let in_chunk =
+            |cursor: &C| (cursor.offset()..cursor.offset() + cursor.chunk().len()).contains(&start);
+        if !in_chunk(&cursor) && !cursor.seek(start) {
+            while start < cursor.offset() && cursor.backtrack() {}
+            while !in_chunk(&cursor) && cursor.advance() {}
+        }
+        Self { cursor, start, end }
+    }
+
+    /// Returns the offset of the start of the range in the haystack of the
+    /// underlying cursor.
+    pub fn start(&self) -> usize {
+        self.start
+    }
+
+    /// Maps a span of the range to the haystack of the underlying cursor.
+    pub fn map_span(&self, span: Span) -> Span {
+        Span { start: span.start + self.start, end: span.end + self.start }
+    }
+
+    /// Returns the underlying cursor.
+    pub fn into_inner(self) -> C {
+        self.cursor
+    }
+}
+
+impl<C: Cursor> Cursor for Window<C> {
+    fn chunk(&self) -> &[u8] {
+        let (chunk, offset) = (self.cursor.chunk(), self.cursor.offset());
+        let start = self.start.saturating_sub(offset).min(chunk.len());
+        let end = self.end.saturating_sub(offset).clamp(start, chunk.len());
+        &chunk[start..end]
+    }
+
+    fn utf8_aware(&self) -> bool {
+        self.cursor.utf8_aware()
+    }
+
+    fn advance(&mut self) -> bool {
+        self.cursor.offset() + self.cursor.chunk().len() < self.end && self.cursor.advance()
+    }
+
+    fn backtrack(&mut self) -> bool {
+        self.cursor.offset() > self.start && self.cursor.backtrack()
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        // `end` was clamped to the length of the haystack if it's known
+        self.cursor.total_bytes().map(|_| self.end - self.start)
+    }
+
+    fn offset(&self) -> usize {
+        self.cursor.offset().max(self.start) - self.start
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        let at = self.start.saturating_add(at).min(self.end.saturating_sub(1)).max(self.start);
+        self.cursor.seek(at)
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.cursor.take_error()
+    }
+}
+
+/// A cursor over the haystack of another cursor in reverse, for matching a
+/// reversed regex from the end of the haystack towards its start (for
+/// example, to find the last token before a position). Offsets count from
+/// the end of the underlying haystack, and [`Reversed::map_span`] maps a
+/// span back to it.
+///
+/// [`Reversed::new`] reverses the bytes, which scrambles multi-byte
+/// codepoints, so the cursor isn't [utf-8 aware](Cursor::utf8_aware).
+/// [`Reversed::utf8`] reverses the order of the codepoints instead and
+/// keeps the bytes of every codepoint intact. This requires that the chunks
+/// of the underlying cursor don't split codepoints, so the reversed cursor is
+/// utf-8 aware if the underlying one is.
+///
+/// Every chunk is copied into a buffer when the cursor moves to it. The
+/// underlying cursor is moved to its last chunk when the reversed cursor is
+/// created.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Input, Reversed, Window};
+///
+/// let text = "let foo = bar.baz(1);";
+/// let caret = 17;
+/// // the regex is reversed as well, so this finds the start of the token
+/// let re = Regex::new(r"\A[a-z]+(\.[a-z]+)*")?;
+/// let before_caret = Reversed::utf8(Window::new(text, ..caret));
+/// let m = re.find(Input::new(before_caret.clone())).unwrap();
+/// assert_eq!(&text[before_caret.map_span(m.span()).range()], "bar.baz");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Reversed<C> {
+    cursor: C,
+    utf8: bool,
+    len: usize,
+    /// The current chunk of `cursor` in reverse.
+    buf: Vec<u8>,
+}
+
+impl<C: Cursor> Reversed<C> {
+    /// Creates a cursor over the bytes of the haystack of `cursor` in
+    /// reverse.
+    pub fn new<T: IntoCursor<Cursor = C>>(cursor: T) -> Self {
+        Self::with_mode(cursor.into_cursor(), false)
+    }
+
+    /// Creates a cursor over the codepoints of the haystack of `cursor` in
+    /// reverse. Invalid UTF-8 is reversed byte by byte.
+    pub fn utf8<T: IntoCursor<Cursor = C>>(cursor: T) -> Self {
+        Self::with_mode(cursor.into_cursor(), true)
+    }
+
+    fn with_mode(mut cursor: C, utf8: bool) -> Self {
+        let moved = cursor.total_bytes().map_or(false, |len| cursor.seek(len));
+        if !moved {
+            while cursor.advance() {}
+        }
+        let len = cursor.offset() + cursor.chunk().len();
+        let mut res = Self { cursor, utf8, len, buf: Vec::new() };
+        res.load();
+        res
+    }
+
+    /// Maps a span of the reversed haystack to the haystack of the
+    /// underlying cursor.
+    pub fn map_span(&self, span: Span) -> Span {
+        Span { start: self.len - span.end, end: self.len - span.start }
+    }
+
+    /// Returns the underlying cursor.
+    pub fn into_inner(self) -> C {
+        self.cursor
+    }
+
+    /// Copies the current chunk of the underlying cursor into `buf`.
+    fn load(&mut self) {
+        let chunk = self.cursor.chunk();
+        self.buf.clear();
+        if !self.utf8 {
+            self.buf.extend(chunk.iter().rev());
+            return;
+        }
+        let mut end = chunk.len();
+        while end > 0 {
+            let min_start = end.saturating_sub(MAX_CODEPOINT_LEN);
+            let start = (min_start..end).rev().find(|&i| utf8::is_boundary(chunk, i));
+            // a run of continuation bytes that's too long isn't a codepoint
+            let start = start.filter(|&start| is_complete(&chunk[start..end])).unwrap_or(end - 1);
+            self.buf.extend_from_slice(&chunk[start..end]);
+            end = start;
+        }
+    }
+}
+
+/// Returns true if `bytes` starts with a leading byte that is followed by
+/// exactly the number of continuation bytes it requires.
+fn is_complete(bytes: &[u8]) -> bool {
+    bytes.len() == codepoint_len(bytes[0])
+}
+
+/// Returns the length of the codepoint that starts with `lead`, or 0 if
+/// `lead` can't start a codepoint.
+fn codepoint_len(lead: u8) -> usize {
+    match lead {
+        0x00..=0x7F => 1,
+        0xC0..=0xDF => 2,
+        0xE0..=0xEF => 3,
+        0xF0..=0xF7 => 4,
+        _ => 0,
+    }
+}
+
+impl<C: Cursor> Cursor for Reversed<C> {
+    fn chunk(&self) -> &[u8] {
+        &self.buf
+    }
+
+    fn utf8_aware(&self) -> bool {
+        self.utf8 && self.cursor.utf8_aware()
+    }
+
+    fn advance(&mut self) -> bool {
+        if self.cursor.offset() == 0 || !self.cursor.backtrack() {
+            return false;
+        }
+        self.load();
+        true
+    }
+
+    fn backtrack(&mut self) -> bool {
+        if !self.cursor.advance() {
+            return false;
+        }
+        self.load();
+        true
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        Some(self.len)
+    }
+
+    fn offset(&self) -> usize {
+        self.len - self.cursor.offset() - self.cursor.chunk().len()
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        if !self.cursor.seek(self.len.saturating_sub(at.saturating_add(1))) {
+            return false;
+        }
+        self.load();
+        true
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.cursor.take_error()
+    }
+}
+
+/// An adapter that makes any cursor [utf-8 aware](Cursor::utf8_aware), so
+/// that Unicode word boundaries and UTF-8 empty match handling can be used
+/// with cursors whose chunks split codepoints (like byte ropes or network
+/// segments).
+///
+/// Whenever a chunk ends in the middle of a codepoint, its trailing bytes
+/// and the continuation bytes at the start of the following chunk(s) are
+/// copied into a small buffer which is yielded as a chunk of its own, between
+/// the rest of the two chunks. All other bytes are yielded without copying,
+/// and the offsets are the same as the ones of the underlying cursor.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Input, SliceChunksCursor, Utf8Boundaries};
+///
+/// let haystack = "Шерлок Холмс".as_bytes();
+/// let chunks: SliceChunksCursor = haystack.chunks(3).collect();
+/// let cursor = Utf8Boundaries::new(chunks);
+/// let re = Regex::new(r"\b\w+\b")?;
+/// let words: Vec<_> = re.find_iter(Input::new(cursor)).map(|m| m.range()).collect();
+/// assert_eq!(words, vec![0..12, 13..23]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Utf8Boundaries<C> {
+    cursor: C,
+    /// Whether the current chunk is `seam` (or the body of the current chunk
+    /// of `cursor`).
+    in_seam: bool,
+    /// The offset of the chunk of `cursor` whose body is the current chunk,
+    /// or whose trailing bytes start the current seam.
+    owner: usize,
+    /// The range of the body of the current chunk of `cursor`: everything
+    /// but the continuation bytes at its start and a split codepoint at its
+    /// end, which are part of the seams around it.
+    body: (usize, usize),
+    /// A codepoint split by chunks of `cursor`.
+    seam: Vec<u8>,
+    seam_offset: usize,
+}
+
+impl<C: Cursor> Utf8Boundaries<C> {
+    /// Wraps `cursor`, keeping its current position (moved back to the
+    /// start of a codepoint if it is in the middle of one).
+    pub fn new<T: IntoCursor<Cursor = C>>(cursor: T) -> Self {
+        let mut res = Self {
+            cursor: cursor.into_cursor(),
+            in_seam: false,
+            owner: 0,
+            body: (0, 0),
+            seam: Vec::new(),
+            seam_offset: 0,
+        };
+        res.enter_owner();
+        if res.chunk().is_empty() && !res.advance() {
+            res.backtrack();
+        }
+        res
+    }
+
+    /// Returns the underlying cursor.
+    pub fn into_inner(self) -> C {
+        self.cursor
+    }
+
+    /// Returns the number of continuation bytes (at most three) at the start
+    /// of the current chunk of `cursor`, which belong to a codepoint that
+    /// started in a previous chunk.
+    fn lead(&self) -> usize {
+        if self.cursor.offset() == 0 {
+            return 0;
+        }
+        let chunk = self.cursor.chunk();
+        let max = chunk.len().min(MAX_CODEPOINT_LEN - 1);
+        (0..max).find(|&i| utf8::is_boundary(chunk, i)).unwrap_or(max)
+    }
+
+    /// Whether the current chunk of `cursor` consists entirely of
+    /// continuation bytes (so it has no body and belongs to a seam).
+    fn is_continuation(&self) -> bool {
+        !self.cursor.chunk().is_empty() && self.lead() == self.cursor.chunk().len()
+    }
+
+    /// Makes the body of the current chunk of `cursor` current.
+    fn enter_body(&mut self) {
+        let chunk = self.cursor.chunk();
+        let lead = self.lead();
+        self.in_seam = false;
+        self.owner = self.cursor.offset();
+        self.body = (lead, chunk.len() - split_tail(chunk, lead));
+    }
+
+    /// Collects the seam after the body of the current chunk of `cursor` into
+    /// `seam`, advancing `cursor` past any chunks that belong to it. Returns
+    /// false if `cursor` could not be advanced at all.
+    fn fill_seam(&mut self) -> bool {
+        let chunk = self.cursor.chunk();
+        self.seam.clear();
+        self.seam.extend_from_slice(&chunk[self.body.1..]);
+        self.seam_offset = self.owner + self.body.1;
+        let mut advanced = false;
+        while self.cursor.advance() {
+            advanced = true;
+            let (chunk, lead) = (self.cursor.chunk(), self.lead());
+            self.seam.extend_from_slice(&chunk[..lead]);
+            if lead < chunk.len() {
+                break;
+            }
+        }
+        advanced
+    }
+
+    /// Returns to the body (or the seam after the body) of the chunk of
+    /// `cursor` at `owner`, after a failed attempt to move away from it.
+    fn restore(&mut self, in_seam: bool, owner: usize) {
+        while self.cursor.offset() > owner && self.cursor.backtrack() {}
+        while self.cursor.offset() < owner && self.cursor.advance() {}
+        self.enter_body();
+        if in_seam {
+            self.fill_seam();
+            self.in_seam = true;
+        }
+    }
+
+    /// Moves `cursor` back to the first chunk that doesn't belong to a seam
+    /// entirely, and makes its body current.
+    fn enter_owner(&mut self) {
+        while self.is_continuation() && self.cursor.backtrack() {}
+        self.enter_body();
+    }
+}
+
+/// Returns the number of bytes at the end of `chunk` (but after `lead`) that
+/// belong to a codepoint which continues in the next chunk.
+fn split_tail(chunk: &[u8], lead: usize) -> usize {
+    let min_start = lead.max(chunk.len().saturating_sub(MAX_CODEPOINT_LEN - 1));
+    match (min_start..chunk.len()).rev().find(|&i| utf8::is_boundary(chunk, i)) {
+        Some(start) if codepoint_len(chunk[start]) > chunk.len() - start => chunk.len() - start,
+        _ => 0,
+    }
+}
+
+impl<C: Cursor> Cursor for Utf8Boundaries<C> {
+    fn chunk(&self) -> &[u8] {
+        if self.in_seam {
+            &self.seam
+        } else {
+            &self.cursor.chunk()[self.body.0..self.body.1]
+        }
+    }
+
+    fn utf8_aware(&self) -> bool {
+        true
+    }
+
+    fn advance(&mut self) -> bool {
+        let (in_seam, owner) = (self.in_seam, self.owner);
+        loop {
+            if self.in_seam {
+                // move to the first chunk after the seam
+                while self.cursor.offset() == self.owner || self.is_continuation() {
+                    if !self.cursor.advance() {
+                        self.restore(in_seam, owner);
+                        return false;
+                    }
+                }
+                self.enter_body();
+            } else {
+                let advanced = self.fill_seam();
+                if !self.seam.is_empty() {
+                    self.in_seam = true;
+                    return true;
+                }
+                if !advanced {
+                    self.restore(in_seam, owner);
+                    return false;
+                }
+                self.enter_body();
+            }
+            if !self.chunk().is_empty() {
+                return true;
+            }
+        }
+    }
+
+    fn backtrack(&mut self) -> bool {
+        let (in_seam, owner) = (self.in_seam, self.owner);
+        loop {
+            if self.in_seam {
+                while self.cursor.offset() > self.owner {
+                    if !self.cursor.backtrack() {
+                        self.restore(in_seam, owner);
+                        return false;
+                    }
+                }
+                self.enter_body();
+                if !self.chunk().is_empty() {
+                    return true;
+                }
+                continue;
+            }
+            if self.cursor.offset() == 0 {
+                self.restore(in_seam, owner);
+                return false;
+            }
+            // move to the chunk that owns the seam before this one
+            loop {
+                if !self.cursor.backtrack() {
+                    // the data before this chunk is no longer available
+                    self.restore(in_seam, owner);
+                    return false;
+                }
+                if !self.is_continuation() {
+                    break;
+                }
+            }
+            self.enter_body();
+            let owner = self.owner;
+            self.fill_seam();
+            if !self.seam.is_empty() {
+                self.in_seam = true;
+                return true;
+            }
+            while self.cursor.offset() > owner && self.cursor.backtrack() {}
+            self.enter_body();
+            if !self.chunk().is_empty() {
+                return true;
+            }
+        }
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        self.cursor.total_bytes()
+    }
+
+    fn offset(&self) -> usize {
+        if self.in_seam {
+            self.seam_offset
+        } else {
+            self.owner + self.body.0
+        }
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        if !self.cursor.seek(at) {
+            return false;
+        }
+        self.enter_owner();
+        while at < self.offset() && self.backtrack() {}
+        while at >= self.offset() + self.chunk().len() && self.advance() {}
+        true
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.cursor.take_error()
+    }
+}
+
+/// The encoding of the haystack of a [`Transcoded`] cursor.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// UTF-16 in little-endian byte order. Unpaired surrogates and a trailing
+    /// odd byte are decoded as U+FFFD.
+    Utf16Le,
+    /// ISO 8859-1, where every byte encodes the codepoint of the same value.
+    Latin1,
+}
+
+impl Encoding {
+    /// Returns the size of a code unit of this encoding in bytes.
+    pub fn unit_len(self) -> usize {
+        match self {
+            Encoding::Utf16Le => 2,
+            Encoding::Latin1 => 1,
+        }
+    }
+}
+
+/// A run of codepoints which are all encoded with the same number of bytes,
+/// both in UTF-8 and in the source encoding.
+#[derive(Clone, Copy, Debug)]
+struct Run {
+    offset: usize,
+    source: usize,
+    len: usize,
+    source_len: usize,
+}
+
+/// A cursor that decodes the haystack of another cursor from UTF-16 or
+/// Latin-1 into UTF-8 while it is searched, one chunk at a time.
+///
+/// Every chunk of the underlying cursor is decoded when the cursor moves to
+/// it (or back to it), into a chunk that contains the codepoints which end
+/// in it, so the chunks never split a codepoint and the cursor is
+/// [utf-8 aware](Cursor::utf8_aware). Offsets are offsets of the UTF-8
+/// haystack. While decoding, the cursor records where the width of the
+/// codepoints changes, which is used by [`Transcoded::map_span`] to map
+/// spans back to code units of the source. Seeking is only supported within
+/// the part of the haystack that was already decoded.
+///
+/// The underlying cursor is moved to its first chunk when the transcoding
+/// cursor is created. The length of the decoded haystack depends on its
+/// contents, so [`total_bytes`](Cursor::total_bytes) always returns `None`.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Encoding, Input, SliceChunksCursor, Transcoded};
+///
+/// let source: Vec<u8> = "größe: 42".encode_utf16().flat_map(u16::to_le_bytes).collect();
+/// let chunks: SliceChunksCursor = source.chunks(3).collect();
+/// let mut cursor = Transcoded::new(chunks, Encoding::Utf16Le);
+/// let re = Regex::new(r"\d+")?;
+/// let m = re.find(Input::new(&mut cursor)).unwrap();
+/// assert_eq!(m.range(), 9..11);
+/// // offsets of UTF-16 code units
+/// assert_eq!(cursor.map_span(m.span()).range(), 7..9);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Transcoded<C> {
+    cursor: C,
+    encoding: Encoding,
+    /// The codepoints that end in the current chunk of `cursor`, in UTF-8.
+    buf: Vec<u8>,
+    offset: usize,
+    /// The last bytes (at most four) before the current chunk of `cursor`.
+    look_behind: Vec<u8>,
+    /// The runs of the haystack decoded so far, in order.
+    runs: Vec<Run>,
+    /// The offset and the source offset up to which the haystack was
+    /// decoded.
+    decoded: (usize, usize),
+}
+
+impl<C: Cursor> Transcoded<C> {
+    /// Creates a cursor that decodes the haystack of `cursor` from
+    /// `encoding`.
+    pub fn new<T: IntoCursor<Cursor = C>>(cursor: T, encoding: Encoding) -> Self {
+        let mut cursor = cursor.into_cursor();
+        if !cursor.seek(0) {
+            while cursor.backtrack() {}
+        }
+        let mut res = Self {
+            cursor,
+            encoding,
+            buf: Vec::new(),
+            offset: 0,
+            look_behind: Vec::new(),
+            runs: Vec::new(),
+            decoded: (0, 0),
+        };
+        res.decode();
+        if res.buf.is_empty() {
+            res.advance();
+        }
+        res
+    }
+
+    /// Returns the encoding of the haystack.
+    pub fn encoding(&self) -> Encoding {
+        self.encoding
+    }
+
+    /// Maps a span of the decoded haystack to offsets of code units in the
+    /// haystack of the underlying cursor (so for UTF-16, the byte offsets are
+    /// divided by two). Offsets in the middle of a codepoint are mapped to
+    /// its start. The span must be within the part of the haystack that was
+    /// decoded so far.
+    pub fn map_span(&self, span: Span) -> Span {
+        let unit_len = self.encoding.unit_len();
+        Span {
+            start: self.source_offset(span.start) / unit_len,
+            end: self.source_offset(span.end) / unit_len,
+        }
+    }
+
+    /// Returns the underlying cursor.
+    pub fn into_inner(self) -> C {
+        self.cursor
+    }
+
+    /// Maps an offset of the decoded haystack to a byte offset of the source.
+    fn source_offset(&self, offset: usize) -> usize {
+        let i = self.runs.partition_point(|run| run.offset <= offset);
+        let Some(run) = i.checked_sub(1).map(|i| self.runs[i]) else {
+            return 0;
+        };
+        let offset = offset.min(self.decoded.0);
+        run.source + (offset - run.offset) / run.len * run.source_len
+    }
+
+    /// Maps a byte offset of the source to an offset of the decoded
+    /// haystack.
+    fn decoded_offset(&self, source: usize) -> usize {
+        let i = self.runs.partition_point(|run| run.source <= source);
+        let Some(run) = i.checked_sub(1).map(|i| self.runs[i]) else {
+            return 0;
+        };
+        let source = source.min(self.decoded.1);
+        run.offset + (source - run.source) / run.source_len * run.len
+    }
+
+    /// Returns the number of bytes at the end of `look_behind` that belong
+    /// to a codepoint which ends in the current chunk of `cursor` (or a later
+    /// one).
+    fn carry(&self) -> usize {
+        let lb = &self.look_behind;
+        match self.encoding {
+            Encoding::Latin1 => 0,
+            Encoding::Utf16Le => {
+                let odd = self.cursor.offset() % 2;
+                let high_surrogate =
+                    lb.len() >= odd + 2 && matches!(lb[lb.len() - odd - 1], 0xD8..=0xDB);
+                // the bytes before the chunk may no longer be available
+                (odd + if high_surrogate { 2 } else { 0 }).min(lb.len())
+            }
+        }
+    }
+
+    /// Decodes the codepoints that end in the current chunk of `cursor` into
+    /// `buf`. The last chunk also includes a trailing incomplete codepoint.
+    fn decode(&mut self) {
+        let carry = self.carry();
+        let mut source = self.cursor.offset() - carry;
+        let mut bytes = self.look_behind[self.look_behind.len() - carry..].to_vec();
+        bytes.extend_from_slice(self.cursor.chunk());
+        self.buf.clear();
+        let mut rest = &bytes[..];
+        let mut last = None;
+        while !rest.is_empty() {
+            let (c, len) = match self.encoding {
+                Encoding::Latin1 => (char::from(rest[0]), 1),
+                Encoding::Utf16Le => {
+                    let unit = |i: usize| {
+                        rest.get(i..i + 2).map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+                    };
+                    match (unit(0), unit(2)) {
+                        (None, _) | (Some(0xD800..=0xDBFF), None)
+                            if !*last.get_or_insert_with(|| is_last_chunk(&mut self.cursor)) =>
+                        {
+                            break
+                        }
+                        (None, _) => (char::REPLACEMENT_CHARACTER, rest.len()),
+                        (Some(high @ 0xD800..=0xDBFF), Some(low @ 0xDC00..=0xDFFF)) => {
+                            let c =
+                                0x10000 + ((high as u32 - 0xD800) << 10) + (low as u32 - 0xDC00);
+                            (char::from_u32(c).unwrap(), 4)
+                        }
+                        (Some(unit), _) => {
+                            (char::from_u32(unit.into()).unwrap_or(char::REPLACEMENT_CHARACTER), 2)
+                        }
+                    }
+                }
+            };
+            let mut encoded = [0; MAX_CODEPOINT_LEN];
+            let encoded = c.encode_utf8(&mut encoded).as_bytes();
+            self.record(self.offset + self.buf.len(), source, encoded.len(), len);
+            self.buf.extend_from_slice(encoded);
+            source += len;
+            rest = &rest[len..];
+        }
+    }
+
+    /// Records a decoded codepoint in the offset map, unless it was decoded
+    /// before.
+    fn record(&mut self, offset: usize, source: usize, len: usize, source_len: usize) {
+        if source < self.decoded.1 {
+            return;
+        }
+        let same_run =
+            self.runs.last().map_or(false, |run| run.len == len && run.source_len == source_len);
+        if !same_run {
+            self.runs.push(Run { offset, source, len, source_len });
+        }
+        self.decoded = (offset + len, source + source_len);
+    }
+
+    /// Reads the last bytes before the current chunk of `cursor` into
+    /// `look_behind`, moving `cursor` back as far as necessary.
+    fn load_look_behind(&mut self) {
+        self.look_behind.clear();
+        let mut steps = 0;
+        while self.look_behind.len() < MAX_CODEPOINT_LEN && self.cursor.backtrack() {
+            steps += 1;
+            self.look_behind.splice(..0, self.cursor.chunk().iter().copied());
+        }
+        for _ in 0..steps {
+            self.cursor.advance();
+        }
+        let excess = self.look_behind.len().saturating_sub(MAX_CODEPOINT_LEN);
+        self.look_behind.drain(..excess);
+    }
+
+    /// Moves `cursor` back to the chunk at `source` (after a failed attempt to
+    /// move away from it) and decodes it again.
+    fn restore(&mut self, source: usize, offset: usize, look_behind: Vec<u8>) {
+        while self.cursor.offset() > source && self.cursor.backtrack() {}
+        while self.cursor.offset() < source && self.cursor.advance() {}
+        self.look_behind = look_behind;
+        self.offset = offset;
+        self.decode();
+    }
+}
+
+impl<C: Cursor> Cursor for Transcoded<C> {
+    fn chunk(&self) -> &[u8] {
+        &self.buf
+    }
+
+    fn utf8_aware(&self) -> bool {
+        true
+    }
+
+    fn advance(&mut self) -> bool {
+        let (source, offset) = (self.cursor.offset(), self.offset);
+        let look_behind = self.look_behind.clone();
+        loop {
+            self.look_behind.extend_from_slice(self.cursor.chunk());
+            let excess = self.look_behind.len().saturating_sub(MAX_CODEPOINT_LEN);
+            self.look_behind.drain(..excess);
+            if !self.cursor.advance() {
+                self.restore(source, offset, look_behind);
+                return false;
+            }
+            self.offset += self.buf.len();
+            self.decode();
+            if !self.buf.is_empty() {
+                return true;
+            }
+        }
+    }
+
+    fn backtrack(&mut self) -> bool {
+        let (source, offset) = (self.cursor.offset(), self.offset);
+        let look_behind = self.look_behind.clone();
+        loop {
+            if !self.cursor.backtrack() {
+                self.restore(source, offset, look_behind);
+                return false;
+            }
+            self.load_look_behind();
+            self.decode();
+            self.offset -= self.buf.len();
+            if !self.buf.is_empty() {
+                return true;
+            }
+        }
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        None
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        if at > self.decoded.0 || !self.cursor.seek(self.source_offset(at)) {
+            return false;
+        }
+        self.load_look_behind();
+        self.offset = self.decoded_offset(self.cursor.offset() - self.carry());
+        self.decode();
+        if self.buf.is_empty() || at >= self.offset + self.buf.len() {
+            while self.advance() && at >= self.offset + self.buf.len() {}
+        }
+        while at < self.offset && self.backtrack() {}
+        true
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.cursor.take_error()
+    }
+}
+
+/// Returns true if the current chunk of `cursor` is its last one, peeking at
+/// the next chunk if the length of the haystack isn't known.
+fn is_last_chunk<C: Cursor>(cursor: &mut C) -> bool {
+    let end = cursor.offset() + cursor.chunk().len();
+    if let Some(len) = cursor.total_bytes() {
+        return end == len;
+    }
+    if cursor.advance() {
+        cursor.backtrack();
+        false
+    } else {
+        true
+    }
+}
+
+/// A cursor that presents the `\r\n` line endings of the haystack of another
+/// cursor as `\n`, so that patterns written for `\n` line endings match
+/// documents with either, without copying the whole document.
+///
+/// Every chunk of the underlying cursor is copied into a buffer without the
+/// `\r` of its line endings when the cursor moves to it. A `\r` at the end
+/// of a chunk is kept back until the next chunk shows whether a `\n`
+/// follows, so a line ending that is split by chunks is normalized as well.
+/// Offsets are offsets of the normalized haystack. The cursor records the
+/// offsets of the removed `\r` bytes, which [`NormalizeNewlines::map_span`]
+/// uses to map spans back to the underlying haystack. Seeking is only
+/// supported within the part of the haystack that was already visited.
+///
+/// The underlying cursor is moved to its first chunk when the normalizing
+/// cursor is created. The length of the normalized haystack depends on its
+/// contents, so [`total_bytes`](Cursor::total_bytes) always returns `None`.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Input, NormalizeNewlines, SliceChunksCursor};
+///
+/// let text = "first\r\nsecond\r\nthird";
+/// let chunks: SliceChunksCursor = text.as_bytes().chunks(6).collect();
+/// let mut cursor = NormalizeNewlines::new(chunks);
+/// let re = Regex::new(r"(?m)^second\n")?;
+/// let m = re.find(Input::new(&mut cursor)).unwrap();
+/// assert_eq!(m.range(), 6..13);
+/// assert_eq!(&text[cursor.map_span(m.span()).range()], "second\r\n");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct NormalizeNewlines<C> {
+    cursor: C,
+    /// The current chunk of `cursor`, normalized.
+    buf: Vec<u8>,
+    offset: usize,
+    /// The last byte before the current chunk of `cursor`.
+    look_behind: Option<u8>,
+    /// The offsets in the underlying haystack of the `\r` bytes that were
+    /// removed so far, in order.
+    removed: Vec<usize>,
+    /// The offset and the offset in the underlying haystack up to which the
+    /// haystack was visited.
+    visited: (usize, usize),
+}
+
+impl<C: Cursor> NormalizeNewlines<C> {
+    /// Creates a cursor that normalizes the line endings of the haystack of
+    /// `cursor`.
+    pub fn new<T: IntoCursor<Cursor = C>>(cursor: T) -> Self {
+        let mut cursor = cursor.into_cursor();
+        if !cursor.seek(0) {
+            while cursor.backtrack() {}
+        }
+        let mut res = Self {
+            cursor,
+            buf: Vec::new(),
+            offset: 0,
+            look_behind: None,
+            removed: Vec::new(),
+            visited: (0, 0),
+        };
+        res.normalize();
+        if res.buf.is_empty() {
+            res.advance();
+        }
+        res
+    }
+
+    /// Maps a span of the normalized haystack to the haystack of the
+    /// underlying cursor. A span that starts at a `\n` of a `\r\n` line
+    /// ending includes the `\r`. The span must be within the part of the
+    /// haystack that was visited so far.
+    pub fn map_span(&self, span: Span) -> Span {
+        Span { start: self.original_offset(span.start), end: self.original_offset(span.end) }
+    }
+
+    /// Returns the underlying cursor.
+    pub fn into_inner(self) -> C {
+        self.cursor
+    }
+
+    /// Maps an offset of the normalized haystack to the underlying haystack.
+    fn original_offset(&self, offset: usize) -> usize {
+        // the `\n` after the i-th removed `\r` is at `removed[i] - i`
+        let (mut lo, mut hi) = (0, self.removed.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if self.removed[mid] - mid < offset {
+                lo = mid + 1;
+            } else {
+                hi = mid;
+            }
+        }
+        offset + lo
+    }
+
+    /// Maps an offset of the underlying haystack to the normalized haystack.
+    fn normalized_offset(&self, original: usize) -> usize {
+        original - self.removed.partition_point(|&removed| removed < original)
+    }
+
+    /// Copies the current chunk of `cursor` into `buf`, preceded by a `\r`
+    /// that was kept back at the end of the previous chunk. A `\r` at the end
+    /// of the chunk is kept back unless it is the last one.
+    fn normalize(&mut self) {
+        let carry = self.look_behind == Some(b'\r');
+        let start = self.cursor.offset() - usize::from(carry);
+        let chunk = self.cursor.chunk();
+        self.buf.clear();
+        if carry && chunk.first() != Some(&b'\n') && !chunk.is_empty() {
+            self.buf.push(b'\r');
+        }
+        if carry && chunk.first() == Some(&b'\n') && start >= self.visited.1 {
+            self.removed.push(start);
+        }
+        let mut kept_back = false;
+        for (i, &b) in chunk.iter().enumerate() {
+            if b == b'\r' {
+                match chunk.get(i + 1) {
+                    Some(b'\n') => {
+                        let at = self.cursor.offset() + i;
+                        if at >= self.visited.1 {
+                            self.removed.push(at);
+                        }
+                        continue;
+                    }
+                    None => {
+                        kept_back = true;
+                        break;
+                    }
+                    Some(_) => (),
+                }
+            }
+            self.buf.push(b);
+        }
+        let mut kept_back = kept_back || (carry && chunk.is_empty());
+        // at the end of the haystack, nothing can follow the `\r`
+        if kept_back && is_last_chunk(&mut self.cursor) {
+            self.buf.push(b'\r');
+            kept_back = false;
+        }
+        let end = self.cursor.offset() + self.cursor.chunk().len() - usize::from(kept_back);
+        if end > self.visited.1 {
+            self.visited = (self.offset + self.buf.len(), end);
+        }
+    }
+
+    /// Reads the last byte before the current chunk of `cursor` into
+    /// `look_behind`, moving `cursor` back as far as necessary.
+    fn load_look_behind(&mut self) {
+        self.look_behind = None;
+        let mut steps = 0;
+        while self.look_behind.is_none() && self.cursor.backtrack() {
+            steps += 1;
+            self.look_behind = self.cursor.chunk().last().copied();
+        }
+        for _ in 0..steps {
+            self.cursor.advance();
+        }
+    }
+
+    /// Moves `cursor` back to the chunk at `original` (after a failed attempt
+    /// to move away from it) and normalizes it again.
+    fn restore(&mut self, original: usize, offset: usize, look_behind: Option<u8>) {
+        while self.cursor.offset() > original && self.cursor.backtrack() {}
+        while self.cursor.offset() < original && self.cursor.advance() {}
+        self.look_behind = look_behind;
+        self.offset = offset;
+        self.normalize();
+    }
+}
+
+impl<C: Cursor> Cursor for NormalizeNewlines<C> {
+    fn chunk(&self) -> &[u8] {
+        &self.buf
+    }
+
+    fn utf8_aware(&self) -> bool {
+        self.cursor.utf8_aware()
+    }
+
+    fn advance(&mut self) -> bool {
+        let (original, offset, look_behind) = (self.cursor.offset(), self.offset, self.look_behind);
+        loop {
+            if let Some(&last) = self.cursor.chunk().last() {
+                self.look_behind = Some(last);
+            }
+            if !self.cursor.advance() {
+                self.restore(original, offset, look_behind);
+                return false;
+            }
+            self.offset += self.buf.len();
+            self.normalize();
+            if !self.buf.is_empty() {
+                return true;
+            }
+        }
+    }
+
+    fn backtrack(&mut self) -> bool {
+        let (original, offset, look_behind) = (self.cursor.offset(), self.offset, self.look_behind);
+        loop {
+            if !self.cursor.backtrack() {
+                self.restore(original, offset, look_behind);
+                return false;
+            }
+            self.load_look_behind();
+            self.normalize();
+            self.offset -= self.buf.len();
+            if !self.buf.is_empty() {
+                return true;
+            }
+        }
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        None
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        if at > self.visited.0 || !self.cursor.seek(self.original_offset(at)) {
+            return false;
+        }
+        self.load_look_behind();
+        let carry = usize::from(self.look_behind == Some(b'\r'));
+        self.offset = self.normalized_offset(self.cursor.offset() - carry);
+        self.normalize();
+        if self.buf.is_empty() || at >= self.offset + self.buf.len() {
+            while self.advance() && at >= self.offset + self.buf.len() {}
+        }
+        while at < self.offset && self.backtrack() {}
+        true
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.cursor.take_error()
+    }
+}
+
+/// A contiguous part of the haystack of a [`Concealed`] cursor: either a
+/// visible range of the underlying haystack or an inserted text.
+#[derive(Clone, Copy, Debug)]
+struct Segment {
+    visible: usize,
+    /// The start of the range in the underlying haystack, or the offset the
+    /// text was inserted at.
+    underlying: usize,
+    len: usize,
+    /// The index of the inserted text.
+    inserted: Option<usize>,
+}
+
+/// Converts offsets between the haystack of a [`Concealed`] cursor (the
+/// visible text) and the haystack of its underlying cursor.
+///
+/// Offsets in a hidden range are mapped to the visible offset the range was
+/// removed at, and offsets in an inserted text are mapped to the underlying
+/// offset it was inserted at.
+#[derive(Clone, Debug)]
+pub struct OffsetMap {
+    /// The segments of the visible text in order. Only the last one may be
+    /// empty, and it extends to the end of the underlying haystack.
+    segments: Vec<Segment>,
+}
+
+impl OffsetMap {
+    /// Creates the map for a visible text that hides the `hidden` ranges of
+    /// the underlying haystack and inserts texts of the given lengths at the
+    /// given underlying offsets.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the hidden ranges aren't sorted or overlap, or if the
+    /// inserted texts aren't sorted by their offset. Texts inserted within a
+    /// hidden range (but not at its bounds) are hidden as well.
+    pub fn new(hidden: &[Range<usize>], inserted: &[(usize, usize)]) -> OffsetMap {
+        assert!(
+            hidden.windows(2).all(|w| w[0].end <= w[1].start)
+                && hidden.iter().all(|range| range.start <= range.end),
+            "hidden ranges must be sorted and must not overlap"
+        );
+        assert!(
+            inserted.windows(2).all(|w| w[0].0 <= w[1].0),
+            "inserted texts must be sorted by their offset"
+        );
+        let mut segments = Vec::new();
+        let mut visible = 0;
+        let mut push = |underlying: usize, len: usize, inserted: Option<usize>| {
+            if len != 0 {
+                segments.push(Segment { visible, underlying, len, inserted });
+                visible = visible.saturating_add(len);
+            }
+        };
+        let mut texts = inserted.iter().enumerate().peekable();
+        let mut underlying = 0;
+        for range in hidden.iter().chain([&(usize::MAX..usize::MAX)]) {
+            while let Some((i, &(at, len))) = texts.next_if(|(_, &(at, _))| at <= range.start) {
+                if at >= underlying {
+                    push(underlying, at - underlying, None);
+                    push(at, len, Some(i));
+                    underlying = at;
+                }
+            }
+            push(underlying, range.start - underlying, None);
+            underlying = range.end;
+        }
+        if segments
+            .last()
+            .map_or(true, |segment| segment.underlying.saturating_add(segment.len) != usize::MAX)
+        {
+            segments.push(Segment { visible, underlying: usize::MAX, len: 0, inserted: None });
+        }
+        OffsetMap { segments }
+    }
+
+    /// Maps a visible offset to the underlying haystack. `end` selects how an
+    /// offset where text was hidden is mapped: to the end of the text before
+    /// it (for the end of a span) or to the start of the text after it.
+    fn underlying_offset(&self, offset: usize, end: bool) -> usize {
+        let i = if end {
+            self.segments.partition_point(|segment| segment.visible < offset)
+        } else {
+            self.segments.partition_point(|segment| segment.visible <= offset)
+        };
+        let Some(segment) = i.checked_sub(1).map(|i| self.segments[i]) else {
+            return self.segments[0].underlying;
+        };
+        if segment.inserted.is_some() {
+            segment.underlying
+        } else {
+            segment.underlying + (offset - segment.visible)
+        }
+    }
+
+    /// Maps an underlying offset to the visible text. `end` selects how an
+    /// offset where text was inserted is mapped: to the start of the
+    /// inserted text (for the end of a span) or to its end.
+    fn visible_offset(&self, offset: usize, end: bool) -> usize {
+        let i = if end {
+            self.segments.partition_point(|segment| segment.underlying < offset)
+        } else {
+            self.segments.partition_point(|segment| segment.underlying <= offset)
+        };
+        let Some(segment) = i.checked_sub(1).map(|i| self.segments[i]) else {
+            return 0;
+        };
+        let segment_end = segment.underlying.saturating_add(segment.len);
+        if segment.inserted.is_none() && (offset < segment_end || end && offset == segment_end) {
+            segment.visible + (offset - segment.underlying)
+        } else {
+            segment.visible + segment.len
+        }
+    }
+
+    /// Maps a span of the visible text to the underlying haystack. The span
+    /// includes the hidden ranges within it, but not the ones at its bounds.
+    pub fn to_underlying(&self, span: Span) -> Span {
+        let start = self.underlying_offset(span.start, false);
+        if span.is_empty() {
+            return Span { start, end: start };
+        }
+        Span { start, end: self.underlying_offset(span.end, true).max(start) }
+    }
+
+    /// Maps a span of the underlying haystack to the visible text.
+    pub fn to_visible(&self, span: Span) -> Span {
+        let start = self.visible_offset(span.start, false);
+        if span.is_empty() {
+            return Span { start, end: start };
+        }
+        Span { start, end: self.visible_offset(span.end, true).max(start) }
+    }
+
+    /// Maps a match of the visible text to the underlying haystack.
+    pub fn map_match(&self, m: Match) -> Match {
+        Match::new(m.pattern(), self.to_underlying(m.span()))
+    }
+
+    /// Maps the spans of all capture groups in `caps` from the visible text
+    /// to the underlying haystack.
+    pub fn map_captures(&self, caps: &mut Captures) {
+        for slots in caps.slots_mut().chunks_mut(2) {
+            let [Some(start), Some(end)] = *slots else { continue };
+            let span = self.to_underlying(Span { start: start.get(), end: end.get() });
+            slots[0] = NonMaxUsize::new(span.start);
+            slots[1] = NonMaxUsize::new(span.end);
+        }
+    }
+}
+
+/// A cursor over the visible text of the haystack of another cursor, which
+/// leaves out hidden ranges (like folded regions in an editor) and can
+/// include texts that are inserted at given offsets (like inline hints).
+///
+/// The chunks of the underlying cursor are yielded without copying, cut at
+/// the bounds of the hidden ranges. Offsets are offsets of the visible text,
+/// and the [`OffsetMap`] of the cursor converts spans between the visible
+/// text and the underlying haystack.
+///
+/// The cursor is [utf-8 aware](Cursor::utf8_aware) if the underlying cursor
+/// is and the inserted texts are valid UTF-8. The hidden ranges mustn't
+/// split codepoints in that case. The underlying cursor is moved to its first
+/// chunk when the concealed cursor is created.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::meta::Regex, Concealed, Input};
+///
+/// let text = "fn main() {\n    let x = 1;\n}\nfn foo() {}";
+/// // `main` is folded and `foo` has an inlay hint
+/// let cursor = Concealed::with_inserted(text, &[11..27], &[(32, b"/*fn*/")]);
+/// let re = Regex::new(r"\w+\(")?;
+/// let matches: Vec<_> = re.find_iter(Input::new(cursor.clone())).collect();
+/// let map = cursor.offset_map();
+/// let visible: Vec<_> = matches.iter().map(|m| m.range()).collect();
+/// assert_eq!(visible, vec![3..8, 22..26]);
+/// let underlying: Vec<_> = matches.iter().map(|&m| map.map_match(m).range()).collect();
+/// assert_eq!(underlying, vec![3..8, 32..36]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Clone, Debug)]
+pub struct Concealed<'a, C> {
+    cursor: C,
+    map: OffsetMap,
+    inserted: Vec<&'a [u8]>,
+    /// The index of the current segment.
+    segment: usize,
+    utf8_aware: bool,
+}
+
+impl<'a, C: Cursor> Concealed<'a, C> {
+    /// Creates a cursor that hides the `hidden` ranges of the haystack of
+    /// `cursor`.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the hidden ranges aren't sorted or overlap.
+    pub fn new<T: IntoCursor<Cursor = C>>(cursor: T, hidden: &[Range<usize>]) -> Self {
+        Self::with_inserted(cursor, hidden, &[])
+    }
+
+    /// Creates a cursor that hides the `hidden` ranges of the haystack of
+    /// `cursor` and inserts texts at the given offsets of it. Texts that are
+    /// inserted at the same offset appear in the given order.
+    ///
+    /// # Panics
+    ///
+    /// This panics if the hidden ranges aren't sorted or overlap, or if the
+    /// inserted texts aren't sorted by their offset.
+    pub fn with_inserted<T: IntoCursor<Cursor = C>>(
+        cursor: T,
+        hidden: &[Range<usize>],
+        inserted: &[(usize, &'a [u8])],
+    ) -> Self {
+        let mut cursor = cursor.into_cursor();
+        if !cursor.seek(0) {
+            while cursor.backtrack() {}
+        }
+        let lens: Vec<_> = inserted.iter().map(|&(at, text)| (at, text.len())).collect();
+        let utf8_aware = cursor.utf8_aware()
+            && inserted.iter().all(|(_, text)| std::str::from_utf8(text).is_ok());
+        let mut res = Self {
+            cursor,
+            map: OffsetMap::new(hidden, &lens),
+            inserted: inserted.iter().map(|&(_, text)| text).collect(),
+            segment: 0,
+            utf8_aware,
+        };
+        if !res.enter(0, true) || res.chunk().is_empty() {
+            res.advance();
+        }
+        res
+    }
+
+    /// Returns the map between offsets of the visible text and the
+    /// underlying haystack.
+    pub fn offset_map(&self) -> &OffsetMap {
+        &self.map
+    }
+
+    /// Returns the underlying cursor.
+    pub fn into_inner(self) -> C {
+        self.cursor
+    }
+
+    /// Makes the segment at index `i` current, moving `cursor` to its first
+    /// (or last) chunk that overlaps it. Returns false if the underlying
+    /// haystack ends before the segment.
+    fn enter(&mut self, i: usize, first: bool) -> bool {
+        self.segment = i;
+        let segment = self.map.segments[i];
+        if segment.inserted.is_some() {
+            return true;
+        }
+        let end = segment.underlying.saturating_add(segment.len);
+        let target = if first { segment.underlying } else { end - 1 };
+        let chunk_end = self.cursor.offset() + self.cursor.chunk().len();
+        if (target < self.cursor.offset() || target >= chunk_end) && !self.cursor.seek(target) {
+            while target < self.cursor.offset() && self.cursor.backtrack() {}
+            while target >= self.cursor.offset() + self.cursor.chunk().len()
+                && self.cursor.advance()
+            {}
+        }
+        self.cursor.offset() < end
+            && self.cursor.offset() + self.cursor.chunk().len() > segment.underlying
+    }
+
+    /// Returns to the segment at index `i` and the chunk of `cursor` at
+    /// `offset` after a failed attempt to move away from them.
+    fn restore(&mut self, i: usize, offset: usize) {
+        self.segment = i;
+        while self.cursor.offset() > offset && self.cursor.backtrack() {}
+        while self.cursor.offset() < offset && self.cursor.advance() {}
+    }
+}
+
+impl<C: Cursor> Cursor for Concealed<'_, C> {
+    fn chunk(&self) -> &[u8] {
+        let segment = self.map.segments[self.segment];
+        if let Some(i) = segment.inserted {
+            return self.inserted[i];
+        }
+        let (chunk, offset) = (self.cursor.chunk(), self.cursor.offset());
+        let end = segment.underlying.saturating_add(segment.len);
+        let start = segment.underlying.clamp(offset, offset + chunk.len());
+        let end = end.clamp(start, offset + chunk.len());
+        &chunk[start - offset..end - offset]
+    }
+
+    fn utf8_aware(&self) -> bool {
+        self.utf8_aware
+    }
+
+    fn advance(&mut self) -> bool {
+        let (segment, offset) = (self.segment, self.cursor.offset());
+        loop {
+            let current = self.map.segments[self.segment];
+            if current.inserted.is_none() {
+                let end = current.underlying.saturating_add(current.len);
+                if self.cursor.offset() + self.cursor.chunk().len() < end {
+                    if !self.cursor.advance() {
+                        // the underlying haystack ends in this segment
+                        self.restore(segment, offset);
+                        return false;
+                    }
+                    if !self.chunk().is_empty() {
+                        return true;
+                    }
+                    continue;
+                }
+            }
+            if self.segment + 1 == self.map.segments.len() || !self.enter(self.segment + 1, true) {
+                self.restore(segment, offset);
+                return false;
+            }
+            if !self.chunk().is_empty() {
+                return true;
+            }
+        }
+    }
+
+    fn backtrack(&mut self) -> bool {
+        let (segment, offset) = (self.segment, self.cursor.offset());
+        loop {
+            let current = self.map.segments[self.segment];
+            if current.inserted.is_none() && self.cursor.offset() > current.underlying {
+                if !self.cursor.backtrack() {
+                    self.restore(segment, offset);
+                    return false;
+                }
+                if !self.chunk().is_empty() {
+                    return true;
+                }
+                continue;
+            }
+            if self.segment == 0 || !self.enter(self.segment - 1, false) {
+                self.restore(segment, offset);
+                return false;
+            }
+            if !self.chunk().is_empty() {
+                return true;
+            }
+        }
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        let len = self.cursor.total_bytes()?;
+        Some(self.map.visible_offset(len, false))
+    }
+
+    fn offset(&self) -> usize {
+        let segment = self.map.segments[self.segment];
+        if segment.inserted.is_some() {
+            return segment.visible;
+        }
+        segment.visible + self.cursor.offset().max(segment.underlying) - segment.underlying
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        let segments = &self.map.segments;
+        let i = segments.partition_point(|segment| segment.visible <= at).saturating_sub(1);
+        let segment = segments[i];
+        if segment.inserted.is_none()
+            && !self.cursor.seek(segment.underlying.saturating_add(at - segment.visible))
+        {
+            return false;
+        }
+        self.segment = i;
+        if self.chunk().is_empty() {
+            self.backtrack();
+        }
+        true
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.cursor.take_error()
+    }
+}
+
+/// The format of the compressed stream of an [`InflateCursor`].
+#[cfg(feature = "inflate")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Compression {
+    /// One or more gzip members (as written by `gzip`). The checksum and
+    /// length stored at the end of each member are verified. Data that
+    /// follows the last member is ignored.
+    Gzip,
+    /// A raw DEFLATE stream without any header or trailer.
+    Deflate,
+}
+
+/// A cursor over the decompressed contents of a gzip (or raw DEFLATE)
+/// stream that is read from a seekable file.
+///
+/// The stream is decompressed into chunks of a fixed size on demand and the
+/// most recently used chunks are kept in a cache of bounded size, like the
+/// blocks of a [`FileCursor`]. DEFLATE can't be decompressed starting at an
+/// arbitrary position, so the cursor records a checkpoint of the decoder
+/// every `checkpoint_interval` chunks. When the cursor backtracks (or
+/// [seeks](Cursor::seek)) to a chunk that was evicted from the cache, it
+/// restarts decompressing from the closest checkpoint before that chunk. A
+/// checkpoint holds the state of the decoder including its 32 KiB history,
+/// so the memory used for checkpoints grows by about 42 KiB for every
+/// `checkpoint_interval` chunks that were decompressed. The length of the
+/// haystack isn't known in advance, so [`total_bytes`](Cursor::total_bytes)
+/// returns `None`.
+///
+/// The DEFLATE stream is decoded by `miniz_oxide`. This cursor is only
+/// available with the `inflate` feature (enabled by default).
+///
+/// If reading or decompressing fails (including corrupt data and checksum
+/// mismatches), the cursor doesn't move, which ends the haystack early. The
+/// error can be retrieved with [`InflateCursor::error`] after the search,
+/// and fallible searches like
+/// [`Regex::try_search`](crate::engines::meta::Regex::try_search) return it.
+/// Chunks may split codepoints, so the cursor isn't
+/// [utf-8 aware](Cursor::utf8_aware).
+///
+/// # Example
+///
+/// ```
+/// use std::io;
+/// use regex_cursor::{engines::meta::Regex, Compression, InflateCursor, Input};
+///
+/// let text = std::fs::read_to_string("test_cases/syntax.rs")?;
+/// let compressed = miniz_oxide::deflate::compress_to_vec(text.as_bytes(), 6);
+/// let re = Regex::new(r"fn (\w+)\(")?;
+/// let reader = io::Cursor::new(compressed);
+/// let cursor = InflateCursor::with_cache(reader, Compression::Deflate, 4096, 4, 2)?;
+/// let m = re.try_search(Input::new(cursor))?.unwrap();
+/// assert_eq!(&text[m.range()], "fn default_timeout(");
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[cfg(feature = "inflate")]
+#[derive(Debug)]
+pub struct InflateCursor<R> {
+    source: Source<R>,
+    /// The decoder, positioned at the start of `next_chunk`. It's `None`
+    /// after an error, because its state is lost then.
+    decoder: Option<Decoder>,
+    next_chunk: usize,
+    chunk_size: usize,
+    checkpoint_interval: usize,
+    /// The state of the decoder at the start of every
+    /// `checkpoint_interval`-th chunk that was decompressed so far.
+    checkpoints: Vec<Decoder>,
+    /// The maximum number of chunks in `cache`.
+    cache_chunks: usize,
+    /// The cached chunks and their indices, from least to most recently
+    /// used. The last one is the current chunk.
+    cache: Vec<(usize, Vec<u8>)>,
+    /// The allocation of the last chunk that was evicted from the cache.
+    spare: Vec<u8>,
+    /// The length of the haystack, once the end of the stream was reached.
+    len: Option<usize>,
+    error: Option<io::Error>,
+}
+
+#[cfg(feature = "inflate")]
+impl<R: Read + Seek> InflateCursor<R> {
+    /// The chunk size used by [`InflateCursor::new`].
+    pub const DEFAULT_CHUNK_SIZE: usize = 64 * 1024;
+    /// The number of chunks between checkpoints used by
+    /// [`InflateCursor::new`].
+    pub const DEFAULT_CHECKPOINT_INTERVAL: usize = 16;
+    /// The cache size (in chunks) used by [`InflateCursor::new`].
+    pub const DEFAULT_CACHE_CHUNKS: usize = 32;
+
+    /// Creates a cursor over the stream that starts at the current position
+    /// of `reader`, with chunks of
+    /// [`DEFAULT_CHUNK_SIZE`](Self::DEFAULT_CHUNK_SIZE) bytes, a checkpoint
+    /// every [`DEFAULT_CHECKPOINT_INTERVAL`](Self::DEFAULT_CHECKPOINT_INTERVAL)
+    /// chunks and a cache of [`DEFAULT_CACHE_CHUNKS`](Self::DEFAULT_CACHE_CHUNKS)
+    /// chunks.
+    pub fn new(reader: R, compression: Compression) -> io::Result<Self> {
+        Self::with_cache(
+            reader,
+            compression,
+            Self::DEFAULT_CHUNK_SIZE,
+            Self::DEFAULT_CHECKPOINT_INTERVAL,
+            Self::DEFAULT_CACHE_CHUNKS,
+        )
+    }
+
+    /// Creates a cursor over the stream that starts at the current position
+    /// of `reader`, with chunks of `chunk_size` bytes, a checkpoint every
+    /// `checkpoint_interval` chunks and a cache of `cache_chunks` chunks.
+    /// The first chunk is decompressed right away.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `chunk_size`, `checkpoint_interval` or `cache_chunks` is
+    /// zero.
+    pub fn with_cache(
+        reader: R,
+        compression: Compression,
+        chunk_size: usize,
+        checkpoint_interval: usize,
+        cache_chunks: usize,
+    ) -> io::Result<Self> {
+        assert!(chunk_size > 0, "chunk size must be greater than zero");
+        assert!(checkpoint_interval > 0, "checkpoint interval must be greater than zero");
+        assert!(cache_chunks > 0, "cache must hold at least one chunk");
+        let source = Source::new(reader)?;
+        let decoder = Decoder::new(compression == Compression::Gzip, source.offset());
+        let mut res = Self {
+            source,
+            decoder: Some(decoder.clone()),
+            next_chunk: 0,
+            chunk_size,
+            checkpoint_interval,
+            checkpoints: vec![decoder],
+            cache_chunks,
+            cache: Vec::with_capacity(cache_chunks + 1),
+            spare: Vec::new(),
+            len: None,
+            error: None,
+        };
+        if !res.load(0) {
+            return Err(res.error.take().unwrap());
+        }
+        Ok(res)
+    }
+
+    /// Returns the last error that occurred while decompressing a chunk, if
+    /// any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Returns the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.source.into_inner()
+    }
+
+    fn current(&self) -> Option<usize> {
+        self.cache.last().map(|&(chunk, _)| chunk)
+    }
+
+    /// Returns the chunk that contains the byte at `at` (or the last chunk
+    /// if `at` is past the end of the haystack, as far as it's known).
+    fn chunk_at(&self, at: usize) -> usize {
+        match self.len {
+            Some(len) => at.min(len.saturating_sub(1)) / self.chunk_size,
+            None => at / self.chunk_size,
+        }
+    }
+
+    /// Makes `chunk` the current chunk, decompressing it unless it is
+    /// cached. Returns false (without moving) if the chunk is past the end
+    /// of the stream or decompressing fails.
+    fn load(&mut self, chunk: usize) -> bool {
+        if let Some(i) = self.cache.iter().position(|&(cached, _)| cached == chunk) {
+            let entry = self.cache.remove(i);
+            self.cache.push(entry);
+            return true;
+        }
+        match self.decompress(chunk) {
+            Ok(Some(buf)) => {
+                self.cache.push((chunk, buf));
+                if self.cache.len() > self.cache_chunks {
+                    self.spare = self.cache.remove(0).1;
+                }
+                true
+            }
+            Ok(None) => false,
+            Err(err) => {
+                self.decoder = None;
+                self.error = Some(err);
+                false
+            }
+        }
+    }
+
+    /// Decompresses `chunk`, restarting from a checkpoint if the decoder is
+    /// already past it. Returns `None` if the chunk is past the end.
+    fn decompress(&mut self, chunk: usize) -> io::Result<Option<Vec<u8>>> {
+        if self.decoder.is_none() || self.next_chunk > chunk {
+            let i = (chunk / self.checkpoint_interval).min(self.checkpoints.len() - 1);
+            let decoder = self.checkpoints[i].clone();
+            self.source.seek(decoder.input_offset())?;
+            self.decoder = Some(decoder);
+            self.next_chunk = i * self.checkpoint_interval;
+        }
+        let decoder = self.decoder.as_mut().unwrap();
+        let mut buf = std::mem::take(&mut self.spare);
+        loop {
+            if self.next_chunk == self.checkpoints.len() * self.checkpoint_interval {
+                self.checkpoints.push(decoder.clone());
+            }
+            buf.clear();
+            if let Err(err) = decoder.decode(&mut self.source, &mut buf, self.chunk_size) {
+                self.spare = buf;
+                return Err(err);
+            }
+            let current = self.next_chunk;
+            self.next_chunk += 1;
+            if buf.len() < self.chunk_size {
+                self.len = Some(current * self.chunk_size + buf.len());
+                if current != chunk || (buf.is_empty() && chunk != 0) {
+                    self.spare = buf;
+                    return Ok(None);
+                }
+            }
+            if current == chunk {
+                return Ok(Some(buf));
+            }
+        }
+    }
+}
+
+#[cfg(feature = "inflate")]
+impl<R: Read + Seek> Cursor for InflateCursor<R> {
+    fn chunk(&self) -> &[u8] {
+        self.cache.last().map_or(&[], |(_, chunk)| chunk)
+    }
+
+    fn utf8_aware(&self) -> bool {
+        false
+    }
+
+    fn advance(&mut self) -> bool {
+        match (self.current(), self.len) {
+            (Some(chunk), Some(len)) if (chunk + 1) * self.chunk_size >= len => false,
+            (Some(chunk), _) => self.load(chunk + 1),
+            (None, _) => false,
+        }
+    }
+
+    fn backtrack(&mut self) -> bool {
+        match self.current() {
+            Some(chunk) if chunk > 0 => self.load(chunk - 1),
+            _ => false,
+        }
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        None
+    }
+
+    fn offset(&self) -> usize {
+        self.current().map_or(0, |chunk| chunk * self.chunk_size)
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        let chunk = self.chunk_at(at);
+        // the stream may end before `at`, which is only noticed by trying
+        self.load(chunk) || (self.chunk_at(at) != chunk && self.load(self.chunk_at(at)))
+    }
+
+    fn take_error(&mut self) -> Option<io::Error> {
+        self.error.take()
+    }
+}
+
+/// A cursor whose chunks may not be available right away, for example
+/// because they are loaded lazily from storage.
+///
+/// This is the asynchronous counterpart of [`Cursor`] and is used by the
+/// async search routines of the meta regex, like
+/// [`Regex::find_iter_async`](crate::engines::meta::Regex::find_iter_async).
+/// Instead of blocking until the next (or previous) chunk is loaded,
+/// [`poll_advance`](AsyncCursor::poll_advance) and
+/// [`poll_backtrack`](AsyncCursor::poll_backtrack) may return
+/// [`Poll::Pending`] and wake the task once the chunk is available. The
+/// search is suspended at the chunk boundary until then.
+pub trait AsyncCursor {
+    /// Returns the current chunk. See [`Cursor::chunk`].
+    fn chunk(&self) -> &[u8];
+    /// Whether this cursor is aware of utf-8 codepoint boundaries. See
+    /// [`Cursor::utf8_aware`].
+    fn utf8_aware(&self) -> bool {
+        true
+    }
+    /// Attempts to advance the cursor to the next chunk. Returns
+    /// `Poll::Ready(true)` if the cursor moved and `Poll::Ready(false)` if
+    /// the end of data is reached. If the next chunk isn't available yet
+    /// `Poll::Pending` is returned and the waker of `cx` is woken once it is.
+    /// The chunk **must not change** unless `Poll::Ready(true)` is returned.
+    fn poll_advance(&mut self, cx: &mut Context<'_>) -> Poll<bool>;
+    /// Attempts to move the cursor to the previous chunk. Behaves like
+    /// [`poll_advance`](AsyncCursor::poll_advance), except that
+    /// `Poll::Ready(false)` means the start of data is reached.
+    fn poll_backtrack(&mut self, cx: &mut Context<'_>) -> Poll<bool>;
+    /// Returns the total length of the data. See [`Cursor::total_bytes`].
+    fn total_bytes(&self) -> Option<usize>;
+    /// The offset of the current chunk from the start of the haystack in bytes
+    fn offset(&self) -> usize;
+}
+
+impl<A: AsyncCursor> AsyncCursor for &mut A {
+    fn chunk(&self) -> &[u8] {
+        A::chunk(self)
+    }
+
+    fn utf8_aware(&self) -> bool {
+        A::utf8_aware(self)
+    }
+
+    fn poll_advance(&mut self, cx: &mut Context<'_>) -> Poll<bool> {
+        A::poll_advance(self, cx)
+    }
+
+    fn poll_backtrack(&mut self, cx: &mut Context<'_>) -> Poll<bool> {
+        A::poll_backtrack(self, cx)
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        A::total_bytes(self)
+    }
+
+    fn offset(&self) -> usize {
+        A::offset(self)
+    }
+}
+
+#[cfg(feature = "ropey")]
+#[derive(Clone, Copy)]
+enum Pos {
+    ChunkStart,
+    ChunkEnd,
+}
+
+#[cfg(feature = "ropey")]
+#[derive(Clone)]
+pub struct RopeyCursor<'a> {
+    slice: ropey::RopeSlice<'a>,
+    iter: ropey::iter::Chunks<'a>,
+    current: &'a [u8],
+    pos: Pos,
+    len: usize,
+    offset: usize,
+}
+
+#[cfg(feature = "ropey")]
+impl<'a> RopeyCursor<'a> {
+    pub fn new(slice: ropey::RopeSlice<'a>) -> Self {
+        let iter = slice.chunks();
+        let len = slice.len_bytes();
+        let mut res = Self { slice, current: &[], iter, pos: Pos::ChunkEnd, len, offset: 0 };
+        res.advance();
+        res
+    }
+
+    pub fn at(slice: ropey::RopeSlice<'a>, at: usize) -> Self {
+        let (iter, offset, _, _) = slice.chunks_at_byte(at);
+        let len = slice.len_bytes();
+        if offset == len {
+            let mut res = Self { slice, current: &[], iter, pos: Pos::ChunkStart, len, offset };
+            res.backtrack();
+            res
+        } else {
+            let mut res = Self { slice, current: &[], iter, pos: Pos::ChunkEnd, len, offset };
+            res.advance();
+            res
+        }
+    }
+}
+
+#[cfg(feature = "ropey")]
+impl Cursor for RopeyCursor<'_> {
+    fn chunk(&self) -> &[u8] {
+        self.current
+    }
+
+    fn advance(&mut self) -> bool {
+        match self.pos {
+            Pos::ChunkStart => {
+                self.iter.next();
+                self.pos = Pos::ChunkEnd;
+            }
+            Pos::ChunkEnd => (),
+        }
+        for next in self.iter.by_ref() {
+            if next.is_empty() {
+                continue;
+            }
+            self.offset += self.current.len();
+            self.current = next.as_bytes();
+            return true;
+        }
+        false
+    }
+
+    fn backtrack(&mut self) -> bool {
+        match self.pos {
+            Pos::ChunkStart => {}
+            Pos::ChunkEnd => {
+                self.iter.prev();
+                self.pos = Pos::ChunkStart;
+            }
+        }
+        while let Some(prev) = self.iter.prev() {
+            if prev.is_empty() {
+                continue;
+            }
+            self.offset -= prev.len();
+            self.current = prev.as_bytes();
+            return true;
+        }
+        false
+    }
+
+    fn utf8_aware(&self) -> bool {
+        true
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        Some(self.len)
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn seek(&mut self, at: usize) -> bool {
+        *self = Self::at(self.slice, at.min(self.len));
+        true
+    }
+}
+
+#[cfg(feature = "ropey")]
+impl<'a> IntoCursor for ropey::RopeSlice<'a> {
+    type Cursor = RopeyCursor<'a>;
+
+    fn into_cursor(self) -> Self::Cursor {
+        RopeyCursor::new(self)
+    }
+}
+
+#[cfg(feature = "ropey")]
+impl<'a> IntoCursor for &'a ropey::Rope {
+    type Cursor = RopeyCursor<'a>;
+
+    fn into_cursor(self) -> Self::Cursor {
+        RopeyCursor::new(self.slice(..))
+    }
+}
+#[cfg(all(feature = "ropey", test))]
+mod ropey_test {
+    use ropey::Rope;
+
+    use crate::cursor::IntoCursor;
+    use crate::engines::meta::Regex;
+    use crate::{Cursor, Input};
+
+    #[test]
+    fn smoke_test() {
+        let rope = Rope::from_str("abc");
+        let mut cursor = rope.into_cursor();
+        assert_eq!(cursor.chunk(), "abc".as_bytes());
+        assert!(!cursor.advance());
+        assert_eq!(cursor.chunk(), "abc".as_bytes());
+        assert!(!cursor.backtrack());
+        assert_eq!(cursor.chunk(), "abc".as_bytes());
+        let rope = Rope::from("abc".repeat(5000));
+        let mut cursor = rope.into_cursor();
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            offset += cursor.chunk().len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(cursor.offset(), 0);
+        assert_eq!(offset, 0);
+    }
+
+    #[test]
+    fn seek() {
+        let haystack = "abc".repeat(5000);
+        let rope = Rope::from(haystack.as_str());
+        let mut cursor = rope.into_cursor();
+        for at in [7000, 0, 14999, 15000, 3, 12345, 20000] {
+            assert!(cursor.seek(at));
+            let (offset, len) = (cursor.offset(), cursor.chunk().len());
+            assert_eq!(cursor.chunk(), &haystack.as_bytes()[offset..offset + len]);
+            if at < haystack.len() {
+                assert!(offset <= at && at < offset + len, "{at}");
+            } else {
+                assert_eq!(offset + len, haystack.len());
+            }
+        }
+        assert!(cursor.seek(7000));
+        let offset = cursor.offset();
+        assert!(cursor.backtrack());
+        assert_eq!(cursor.offset() + cursor.chunk().len(), offset);
+        assert!(cursor.advance());
+        assert!(cursor.advance());
+        assert!(cursor.offset() > offset);
+    }
+
+    #[test]
+    fn seek_input() {
+        let haystack = "abc".repeat(5000);
+        let rope = Rope::from(haystack.as_str());
+        for needle in ["cab", "abc$", "(?s:.)"] {
+            let re = Regex::new(needle).unwrap();
+            for start in [14000, 7001, 2, 14999, 15000] {
+                let expected = re.find(Input::new(haystack.as_str()).range(start..));
+                assert_eq!(re.find(Input::new(&rope).range(start..)), expected);
+                let expected = re.find(Input::new(haystack.as_str()).range(start / 2..start));
+                assert_eq!(re.find(Input::new(&rope).range(start / 2..start)), expected);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod slice_chunks_test {
+    use std::collections::VecDeque;
+
+    use crate::cursor::IntoCursor;
+    use crate::engines::meta::Regex;
+    use crate::{Cursor, Input};
+
+    #[test]
+    fn smoke_test() {
+        let chunks: &[&[u8]] = &[b"", b"abc", b""];
+        let mut cursor = chunks.into_cursor();
+        assert_eq!(cursor.chunk(), "abc".as_bytes());
+        assert!(!cursor.advance());
+        assert_eq!(cursor.chunk(), "abc".as_bytes());
+        assert!(!cursor.backtrack());
+        assert_eq!(cursor.chunk(), "abc".as_bytes());
+        let chunks: Vec<String> = (0..5000).map(|i| "abc".repeat(i % 7)).collect();
+        let mut cursor = chunks.as_slice().into_cursor();
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            assert!(!cursor.chunk().is_empty());
+            offset += cursor.chunk().len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(cursor.total_bytes(), Some(offset));
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(cursor.offset(), 0);
+        assert_eq!(offset, 0);
+    }
+
+    #[test]
+    fn empty() {
+        let chunks: &[Vec<u8>] = &[vec![], vec![]];
+        let mut cursor = chunks.into_cursor();
+        assert_eq!(cursor.chunk(), b"");
+        assert_eq!(cursor.total_bytes(), Some(0));
+        assert!(!cursor.advance());
+        assert!(!cursor.backtrack());
+        assert!(cursor.seek(0));
+        assert_eq!(cursor.offset(), 0);
+        let re = Regex::new("").unwrap();
+        assert_eq!(re.find_iter(Input::new(chunks)).count(), 1);
+    }
+
+    #[test]
+    fn seek() {
+        let chunks: Vec<String> = (0..100).map(|i| "ab".repeat(i % 3)).collect();
+        let haystack = chunks.concat();
+        let mut cursor = chunks.as_slice().into_cursor();
+        for at in [70, 0, 1, 2, haystack.len() - 1, haystack.len(), 33, haystack.len() + 10] {
+            assert!(cursor.seek(at));
+            let (offset, len) = (cursor.offset(), cursor.chunk().len());
+            assert_eq!(cursor.chunk(), &haystack.as_bytes()[offset..offset + len]);
+            if at < haystack.len() {
+                assert!(offset <= at && at < offset + len, "{at}");
+            } else {
+                assert_eq!(offset + len, haystack.len());
+            }
+        }
+    }
+
+    /// Creates a ring buffer that wraps around after `front`.
+    fn wrapped(front: &[u8], back: &[u8]) -> VecDeque<u8> {
+        let mut ring: VecDeque<u8> = back.iter().copied().collect();
+        for &byte in front.iter().rev() {
+            ring.push_front(byte);
+        }
+        assert_eq!(ring.as_slices(), (front, back));
+        ring
+    }
+
+    #[test]
+    fn vec_deque() {
+        let ring = wrapped(b"xfo", "o12 Ш".as_bytes());
+        let re = Regex::new("foo[0-9]+").unwrap();
+        assert!((&ring).into_cursor().utf8_aware());
+        assert_eq!(re.find(Input::new(&ring)).map(|m| m.range()), Some(1..6));
+        // the ring buffer wraps around in the middle of the 'Ш'
+        let ring = wrapped(b"foo1\xD0", b"\xA8");
+        assert!(!(&ring).into_cursor().utf8_aware());
+        assert_eq!(re.find(Input::new(&ring)).map(|m| m.range()), Some(0..4));
+    }
+
+    #[test]
+    fn utf8_aware() {
+        let chunks: &[&[u8]] = &["aШ".as_bytes(), "b".as_bytes()];
+        assert!(chunks.into_cursor().utf8_aware());
+        let chunks: &[&[u8]] = &[b"a\xD0", b"\xA8b"];
+        assert!(!chunks.into_cursor().utf8_aware());
+        let chunks = ["Шерлок".to_owned(), " Холмс".to_owned()];
+        let re = Regex::new(r"\b\w+\b").unwrap();
+        let matches: Vec<_> =
+            re.find_iter(Input::new(chunks.as_slice())).map(|m| m.range()).collect();
+        assert_eq!(matches, vec![0..12, 13..23]);
+    }
+}
+
+#[cfg(test)]
+mod gap_buffer_test {
+    use crate::engines::meta::Regex;
+    use crate::{Cursor, GapBufferCursor, Input};
+
+    #[test]
+    fn smoke_test() {
+        let mut cursor = GapBufferCursor::new(b"", b"abc");
+        assert_eq!(cursor.chunk(), "abc".as_bytes());
+        assert!(!cursor.advance());
+        assert_eq!(cursor.chunk(), "abc".as_bytes());
+        assert!(!cursor.backtrack());
+        assert_eq!(cursor.chunk(), "abc".as_bytes());
+        let haystack = "abcШ".repeat(5000);
+        for gap in [0, 1, 3, 4, 5, 6, haystack.len() - 1, haystack.len()] {
+            let (before, after) = haystack.as_bytes().split_at(gap);
+            let mut cursor = GapBufferCursor::new(before, after);
+            assert_eq!(cursor.total_bytes(), Some(haystack.len()));
+            let mut offset = 0;
+            loop {
+                assert_eq!(cursor.offset(), offset);
+                assert!(haystack.is_char_boundary(offset));
+                assert_eq!(cursor.chunk(), &haystack.as_bytes()[offset..][..cursor.chunk().len()]);
+                offset += cursor.chunk().len();
+                if !cursor.advance() {
+                    break;
+                }
+            }
+            assert_eq!(offset, haystack.len());
+            loop {
+                offset -= cursor.chunk().len();
+                assert_eq!(cursor.offset(), offset);
+                if !cursor.backtrack() {
+                    break;
+                }
+            }
+            assert_eq!(cursor.offset(), 0);
+            assert_eq!(offset, 0);
+        }
+    }
+
+    #[test]
+    fn seek() {
+        let haystack = "abcШ";
+        for gap in 0..=haystack.len() {
+            let (before, after) = haystack.as_bytes().split_at(gap);
+            let mut cursor = GapBufferCursor::new(before, after);
+            for at in (0..=haystack.len()).rev() {
+                assert!(cursor.seek(at));
+                let (offset, len) = (cursor.offset(), cursor.chunk().len());
+                assert!(offset <= at && (at < offset + len || offset + len == haystack.len()));
+            }
+        }
+    }
+
+    #[test]
+    fn split_codepoint() {
+        let haystack = "Шерлок Холмс";
+        let re = Regex::new(r"\b\w+\b").unwrap();
+        let expected: Vec<_> = re.find_iter(Input::new(haystack)).collect();
+        for gap in 0..=haystack.len() {
+            let (before, after) = haystack.as_bytes().split_at(gap);
+            let input = Input::new(GapBufferCursor::new(before, after));
+            assert_eq!(re.find_iter(input).collect::<Vec<_>>(), expected, "{gap}");
+        }
+        // an empty match can't split the codepoint at the gap either
+        let re = Regex::new(r"").unwrap();
+        let (before, after) = "aШ".as_bytes().split_at(2);
+        let input = Input::new(GapBufferCursor::new(before, after));
+        assert_eq!(re.find_iter(input).map(|m| m.start()).collect::<Vec<_>>(), vec![0, 1, 3]);
+    }
+}
+
+#[cfg(test)]
+mod piece_table_test {
+    use crate::engines::meta::Regex;
+    use crate::{Cursor, Input, PieceTableCursor};
+
+    /// A piece of either the original (`false`) or the add buffer (`true`).
+    type Piece = (bool, usize, usize);
+
+    /// Splits `haystack` into pieces of up to `max_len` bytes that alternate
+    /// between two buffers.
+    fn pieces(haystack: &str, max_len: usize) -> (Vec<Piece>, Vec<u8>, Vec<u8>) {
+        let (mut original, mut add, mut pieces) = (Vec::new(), b"junk".to_vec(), Vec::new());
+        for (i, piece) in haystack.as_bytes().chunks(max_len).enumerate() {
+            let buffer = if i % 2 == 0 { &mut original } else { &mut add };
+            pieces.push((i % 2 != 0, buffer.len(), piece.len()));
+            pieces.push((i % 3 == 0, 1, 0));
+            buffer.extend_from_slice(piece);
+        }
+        (pieces, original, add)
+    }
+
+    #[test]
+    fn smoke_test() {
+        let haystack = "abc".repeat(5000);
+        let (pieces, original, add) = pieces(&haystack, 7);
+        let mut cursor =
+            PieceTableCursor::new(&pieces, |&add_buffer| if add_buffer { &add } else { &original });
+        assert!(cursor.utf8_aware());
+        assert_eq!(cursor.total_bytes(), Some(haystack.len()));
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            assert_eq!(cursor.chunk(), &haystack.as_bytes()[offset..][..cursor.chunk().len()]);
+            offset += cursor.chunk().len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(offset, haystack.len());
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(offset, 0);
+        for at in [10, 0, 7, 6, haystack.len(), haystack.len() - 1, 3000] {
+            assert!(cursor.seek(at));
+            assert_eq!(cursor.offset(), at.min(haystack.len() - 1) / 7 * 7);
+        }
+
+        let empty: [Piece; 1] = [(false, 0, 0)];
+        let mut cursor = PieceTableCursor::new(&empty, |_| b"");
+        assert_eq!(cursor.chunk(), b"");
+        assert!(!cursor.advance());
+        assert!(cursor.seek(0));
+        assert_eq!(cursor.total_bytes(), Some(0));
+    }
+
+    #[test]
+    fn search() {
+        let haystack = "Шерлок Холмс a ending Шерлок Холмс".repeat(50);
+        let re = Regex::new(r"\w+").unwrap();
+        let expected: Vec<_> = re.find_iter(Input::new(haystack.as_str())).collect();
+        let range = 100..haystack.len() - 100;
+        let expected_range: Vec<_> =
+            re.find_iter(Input::new(haystack.as_str()).range(range.clone())).collect();
+        for max_len in [1, 2, 5, 16] {
+            let (pieces, original, add) = pieces(&haystack, max_len);
+            let resolve = |&add_buffer: &bool| if add_buffer { &add[..] } else { &original[..] };
+            let cursor = PieceTableCursor::new(&pieces, resolve);
+            let utf8_aware =
+                (max_len..haystack.len()).step_by(max_len).all(|i| haystack.is_char_boundary(i));
+            assert_eq!(cursor.utf8_aware(), utf8_aware);
+            assert_eq!(re.find_iter(Input::new(cursor.clone())).collect::<Vec<_>>(), expected);
+            let input = Input::new(cursor).range(range.clone());
+            assert_eq!(re.find_iter(input).collect::<Vec<_>>(), expected_range);
+        }
+    }
+}
+
+#[cfg(test)]
+mod lines_test {
+    use crate::engines::meta::Regex;
+    use crate::regex_automata::Match;
+    use crate::{Cursor, Input, LinesCursor};
+
+    #[test]
+    fn smoke_test() {
+        let lines = ["foo", "", "Шерлок", "", "", "bar"];
+        for terminator in ["\n", "\r\n", ""] {
+            let document: String = lines.iter().map(|line| format!("{line}{terminator}")).collect();
+            let mut cursor = LinesCursor::new(&lines).terminator(terminator.as_bytes());
+            assert!(cursor.utf8_aware());
+            assert_eq!(cursor.total_bytes(), Some(document.len()));
+            let mut offset = 0;
+            loop {
+                assert_eq!(cursor.offset(), offset);
+                assert!(!cursor.chunk().is_empty());
+                assert_eq!(cursor.chunk(), &document.as_bytes()[offset..][..cursor.chunk().len()]);
+                offset += cursor.chunk().len();
+                if !cursor.advance() {
+                    break;
+                }
+            }
+            assert_eq!(offset, document.len());
+            loop {
+                offset -= cursor.chunk().len();
+                assert_eq!(cursor.offset(), offset);
+                if !cursor.backtrack() {
+                    break;
+                }
+            }
+            assert_eq!(offset, 0);
+            for at in (0..=document.len()).rev() {
+                assert!(cursor.seek(at));
+                let (offset, len) = (cursor.offset(), cursor.chunk().len());
+                assert!(offset <= at && (at < offset + len || offset + len == document.len()));
+            }
+        }
+
+        let empty: [&str; 0] = [];
+        let mut cursor = LinesCursor::new(&empty);
+        assert_eq!(cursor.chunk(), b"");
+        assert!(!cursor.advance());
+        assert!(cursor.seek(0));
+        assert_eq!(cursor.total_bytes(), Some(0));
+    }
+
+    #[test]
+    fn position() {
+        let lines = ["ab", "", "c"];
+        let cursor = LinesCursor::new(&lines).terminator(b"\r\n");
+        let positions: Vec<_> =
+            (0..=cursor.total_bytes().unwrap()).map(|offset| cursor.position(offset)).collect();
+        assert_eq!(
+            positions,
+            vec![(0, 0), (0, 1), (0, 2), (0, 3), (1, 0), (1, 1), (2, 0), (2, 1), (2, 2), (3, 0)]
+        );
+    }
+
+    #[test]
+    fn search() {
+        let lines = vec!["foo bar".to_string(), "".to_string(), "baz".to_string()];
+        let re = Regex::new(r"(?m)^$|r\s+baz$").unwrap();
+        let input = Input::new(LinesCursor::new(&lines));
+        assert_eq!(
+            re.find_iter(input).collect::<Vec<_>>(),
+            vec![Match::must(0, 6..12), Match::must(0, 13..13)]
+        );
+        let re = Regex::new(r"(?Rm)^$|r\s+baz$").unwrap();
+        let input = Input::new(LinesCursor::new(&lines).terminator(b"\r\n"));
+        assert_eq!(
+            re.find_iter(input).collect::<Vec<_>>(),
+            vec![Match::must(0, 6..14), Match::must(0, 16..16)]
+        );
+
+        let re = Regex::builder()
+            .configure(Regex::config().line_terminator(b'\0'))
+            .build(r"(?m)^\w+$")
+            .unwrap();
+        let input = Input::new(LinesCursor::new(&lines).terminator(b"\0"));
+        let cursor = LinesCursor::new(&lines).terminator(b"\0");
+        let positions: Vec<_> =
+            re.find_iter(input).map(|m| (cursor.position(m.start()), m.len())).collect();
+        assert_eq!(positions, vec![((2, 0), 3)]);
+    }
+}
+
+#[cfg(test)]
+mod reader_test {
+    use std::io::{self, Read};
+
+    use crate::engines::meta::Regex;
+    use crate::{Cursor, Input, ReaderCursor, Window};
+
+    /// A reader that returns at most three bytes per read and fails at the
+    /// end instead of returning EOF.
+    struct ShortReads<'a>(&'a [u8]);
+
+    impl Read for ShortReads<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::new(io::ErrorKind::Other, "broken pipe"));
+            }
+            let n = buf.len().min(self.0.len()).min(3);
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    #[test]
+    fn smoke_test() {
+        let haystack = "abcdefghij".repeat(10);
+        let mut cursor = ReaderCursor::with_window(haystack.as_bytes(), 8, 3);
+        assert_eq!(cursor.total_bytes(), None);
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            assert_eq!(cursor.chunk(), &haystack.as_bytes()[offset..][..cursor.chunk().len()]);
+            offset += cursor.chunk().len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(offset, haystack.len());
+        assert_eq!(cursor.total_bytes(), None);
+        // only the last three chunks are kept
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(offset, 80);
+        assert!(cursor.seek(95));
+        assert_eq!(cursor.offset(), 88);
+        assert!(cursor.seek(100));
+        assert_eq!(cursor.offset(), 96);
+        assert!(!cursor.seek(79));
+        assert_eq!(cursor.offset(), 96);
+
+        let mut cursor = ReaderCursor::new(io::empty());
+        assert_eq!(cursor.chunk(), b"");
+        assert!(!cursor.advance());
+        assert_eq!(cursor.total_bytes(), None);
+    }
+
+    #[test]
+    fn errors() {
+        let mut cursor = ReaderCursor::with_window(ShortReads(b"foo bar baz"), 4, 2);
+        assert_eq!(cursor.chunk(), b"foo ");
+        let re = Regex::new(r"ba[rz]").unwrap();
+        let matches: Vec<_> = re.find_iter(Input::new(&mut cursor)).map(|m| m.range()).collect();
+        assert_eq!(matches, vec![4..7, 8..11]);
+        assert_eq!(cursor.error().unwrap().to_string(), "broken pipe");
+        assert_eq!(cursor.total_bytes(), None);
+    }
+
+    #[test]
+    fn search() {
+        let haystack = "foo 123 Шерлок Холмс\n".repeat(100);
+        let re = Regex::new(r"\w+").unwrap();
+        let expected: Vec<_> = re.find_iter(Input::new(haystack.as_str())).collect();
+        for (chunk_size, window) in [(1, 16), (7, 4), (64, 2), (4096, 1)] {
+            let cursor = ReaderCursor::with_window(haystack.as_bytes(), chunk_size, window);
+            assert_eq!(re.find_iter(Input::new(cursor)).collect::<Vec<_>>(), expected);
+        }
+    }
+
+    #[test]
+    fn end_anchors() {
+        // the length of the haystack isn't known, so the search can't start
+        // at its end
+        for needle in [r"$", r"\z", r"b$", r"(?m)b$", r"[ab]+\z"] {
+            let re = Regex::new(needle).unwrap();
+            for haystack in ["", "ab", "ab\nab"] {
+                let expected: Vec<_> = re.find_iter(Input::new(haystack)).collect();
+                let cursor = ReaderCursor::with_window(haystack.as_bytes(), 1, 8);
+                let matches: Vec<_> = re.find_iter(Input::new(cursor)).collect();
+                assert_eq!(matches, expected, "{needle:?} {haystack:?}");
+                let cursor = ReaderCursor::with_window(haystack.as_bytes(), 1, 8);
+                let m = re.try_search(Input::new(cursor)).unwrap();
+                assert_eq!(m, expected.first().copied(), "{needle:?} {haystack:?}");
+                // a window with an open end (or one past the end of the
+                // haystack) doesn't know where it ends either
+                let start = haystack.len().min(1);
+                let expected: Vec<_> = re.find_iter(Input::new(&haystack[start..])).collect();
+                for end in [None, Some(100)] {
+                    let cursor = ReaderCursor::with_window(haystack.as_bytes(), 1, 8);
+                    let window = match end {
+                        Some(end) => Window::new(cursor, start..end),
+                        None => Window::new(cursor, start..),
+                    };
+                    let matches: Vec<_> = re.find_iter(Input::new(window)).collect();
+                    assert_eq!(matches, expected, "{needle:?} {haystack:?} {end:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "cursor failed to backtrack")]
+    fn backtrack_beyond_window() {
+        let haystack = format!("{}!", "a".repeat(20));
+        let re = Regex::new(r"a+!").unwrap();
+        let cursor = ReaderCursor::with_window(haystack.as_bytes(), 4, 2);
+        // the match can't be found, but that isn't the same as no match
+        let _ = re.find(Input::new(cursor));
+    }
+
+    #[test]
+    #[should_panic(expected = "use 'Regex::try_search' or 'Regex::try_find_iter'")]
+    fn backtrack_beyond_window_iter() {
+        let haystack = format!("{}!", "a".repeat(20));
+        let re = Regex::new(r"a+!").unwrap();
+        let cursor = ReaderCursor::with_window(haystack.as_bytes(), 4, 2);
+        let _: Vec<_> = re.find_iter(Input::new(cursor)).collect();
+    }
+
+    #[test]
+    fn try_search() {
+        let haystack = format!("{}!", "a".repeat(20));
+        let re = Regex::new(r"a+!").unwrap();
+        let cursor = ReaderCursor::with_window(haystack.as_bytes(), 4, 2);
+        let err = re.try_search(Input::new(cursor)).unwrap_err();
+        assert!(err.io_error().unwrap().to_string().contains("dropped from the window"));
+        assert!(err.backtrack_offset().unwrap() > 0);
+        let cursor = ReaderCursor::with_window(haystack.as_bytes(), 4, 8);
+        assert_eq!(re.try_search(Input::new(cursor)).unwrap().unwrap().range(), 0..21);
+
+        let re = Regex::new(r"ba[rz]").unwrap();
+        let mut cursor = ReaderCursor::with_window(ShortReads(b"foo bar baz"), 4, 2);
+        let mut it = re.try_find_iter(Input::new(&mut cursor));
+        assert_eq!(it.next().unwrap().unwrap().range(), 4..7);
+        let err = it.next().unwrap().unwrap_err();
+        assert_eq!(err.io_error().unwrap().to_string(), "broken pipe");
+        assert!(it.next().is_none());
+        // the error was taken by the search
+        assert!(cursor.error().is_none());
+    }
+}
+
+#[cfg(test)]
+mod file_test {
+    use std::io::{self, Read, Seek, SeekFrom};
+
+    use crate::engines::meta::Regex;
+    use crate::{Cursor, FileCursor, Input};
+
+    /// A file that counts the blocks read from it, and fails to read them
+    /// once `fail` is set.
+    struct CountReads<'a> {
+        file: io::Cursor<&'a [u8]>,
+        reads: usize,
+        fail: bool,
+    }
+
+    impl Read for CountReads<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.fail {
+                return Err(io::Error::new(io::ErrorKind::Other, "bad sector"));
+            }
+            self.reads += 1;
+            self.file.read(buf)
+        }
+    }
+
+    impl Seek for CountReads<'_> {
+        fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+            self.file.seek(pos)
+        }
+    }
+
+    #[test]
+    fn smoke_test() {
+        let haystack = "abcdefghij".repeat(10);
+        let file = CountReads { file: io::Cursor::new(haystack.as_bytes()), reads: 0, fail: false };
+        let mut cursor = FileCursor::with_cache(file, 0, 8, 3).unwrap();
+        assert_eq!(cursor.total_bytes(), Some(haystack.len()));
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            assert_eq!(cursor.chunk(), &haystack.as_bytes()[offset..][..cursor.chunk().len()]);
+            offset += cursor.chunk().len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(offset, haystack.len());
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            assert_eq!(cursor.chunk(), &haystack.as_bytes()[offset..][..cursor.chunk().len()]);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(offset, 0);
+        // 13 blocks forward and 10 evicted ones backward
+        assert_eq!(cursor.file.reads, 23);
+        for (at, offset) in [(95, 88), (100, 96), (500, 96), (3, 0), (90, 88)] {
+            assert!(cursor.seek(at));
+            assert_eq!(cursor.offset(), offset);
+        }
+        // blocks 11, 12 and 0 were cached
+        assert_eq!(cursor.into_inner().reads, 25);
+
+        let cursor = FileCursor::at(io::Cursor::new(haystack.as_bytes()), 70).unwrap();
+        assert_eq!(cursor.offset(), 0);
+        let cursor = FileCursor::with_cache(io::Cursor::new(haystack.as_bytes()), 70, 8, 1);
+        assert_eq!(cursor.unwrap().offset(), 64);
+
+        let mut cursor = FileCursor::new(io::Cursor::new(b"")).unwrap();
+        assert_eq!(cursor.chunk(), b"");
+        assert!(!cursor.advance());
+        assert!(cursor.seek(0));
+        assert_eq!(cursor.total_bytes(), Some(0));
+    }
+
+    #[test]
+    fn search() {
+        let haystack = "foo 123 Шерлок Холмс\n".repeat(100);
+        let re = Regex::new(r"\b\w+$|\d+").unwrap();
+        let expected: Vec<_> = re.find_iter(Input::new(haystack.as_str())).collect();
+        for (block_size, cache_blocks) in [(1, 1), (7, 2), (64, 4), (4096, 1)] {
+            let file = io::Cursor::new(haystack.as_bytes());
+            let cursor = FileCursor::with_cache(file, 0, block_size, cache_blocks).unwrap();
+            assert_eq!(re.find_iter(Input::new(cursor)).collect::<Vec<_>>(), expected);
+        }
+        let range = 1000..1500;
+        let expected: Vec<_> =
+            re.find_iter(Input::new(haystack.as_str()).range(range.clone())).collect();
+        let file = io::Cursor::new(haystack.as_bytes());
+        let cursor = FileCursor::with_cache(file, range.start, 16, 2).unwrap();
+        assert_eq!(re.find_iter(Input::new(cursor).range(range)).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn try_search() {
+        let haystack = "foo bar baz";
+        let re = Regex::new(r"ba[rz]").unwrap();
+        let file = CountReads { file: io::Cursor::new(haystack.as_bytes()), reads: 0, fail: false };
+        let mut cursor = FileCursor::with_cache(file, 0, 4, 1).unwrap();
+        let m = re.try_search(Input::new(&mut cursor)).unwrap().unwrap();
+        assert_eq!(m.range(), 4..7);
+
+        cursor.file.fail = true;
+        let err = re.try_search(Input::new(&mut cursor).range(m.end()..)).unwrap_err();
+        assert_eq!(err.io_error().unwrap().to_string(), "bad sector");
+        assert_eq!(err.to_string(), "cursor failed to read the haystack");
+        // backtracking to the start of the haystack fails too
+        let err = re.try_find_iter(Input::new(&mut cursor).range(..)).next().unwrap().unwrap_err();
+        assert!(err.io_error().is_some());
+
+        cursor.file.fail = false;
+        let matches: Result<Vec<_>, _> =
+            re.try_find_iter(Input::new(&mut cursor).range(..)).collect();
+        assert_eq!(matches.unwrap().len(), 2);
+    }
+}
+
+#[cfg(test)]
+mod chain_test {
+    use regex_automata::Span;
+
+    use crate::engines::meta::Regex;
+    use crate::test_rope::{RandomSlices, SingleByteChunks};
+    use crate::{Chain, Cursor, Input, SliceChunksCursor};
+
+    fn check_chunks<C: Cursor>(mut cursor: C, haystack: &[u8]) {
+        assert_eq!(cursor.total_bytes(), Some(haystack.len()));
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            assert!(!cursor.chunk().is_empty() || haystack.is_empty());
+            assert_eq!(cursor.chunk(), &haystack[offset..][..cursor.chunk().len()]);
+            offset += cursor.chunk().len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(offset, haystack.len());
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(offset, 0);
+    }
+
+    #[test]
+    fn smoke_test() {
+        let haystack = "foo Шерлок\nХолмс bar";
+        for split in (0..=haystack.len()).filter(|&i| haystack.is_char_boundary(i)) {
+            let (first, second) = haystack.split_at(split);
+            check_chunks(Chain::new(first, second), haystack.as_bytes());
+            let chain = Chain::new(
+                SingleByteChunks::new(first.as_bytes()),
+                RandomSlices::new(second.as_bytes()),
+            );
+            assert!(chain.utf8_aware());
+            check_chunks(chain, haystack.as_bytes());
+        }
+        let (first, second) = haystack.as_bytes().split_at(5);
+        assert!(!Chain::new(first, second).utf8_aware());
+    }
+
+    #[test]
+    fn seek() {
+        let haystack = "foo Шерлок\nХолмс bar";
+        for split in [0, 3, 6, haystack.len()] {
+            let (first, second) = haystack.as_bytes().split_at(split);
+            let first: SliceChunksCursor = first.chunks(3).collect();
+            let second: SliceChunksCursor = second.chunks(5).collect();
+            let mut chain = Chain::new(first, second);
+            for at in [20, 0, 3, 10, 30, 4, haystack.len()] {
+                assert!(chain.seek(at));
+                let (offset, len) = (chain.offset(), chain.chunk().len());
+                assert!(offset <= at && (at < offset + len || offset + len == haystack.len()));
+                // moving across the seam after seeking
+                let mut walk = chain.clone();
+                while walk.backtrack() {}
+                check_chunks(walk, haystack.as_bytes());
+            }
+        }
+    }
+
+    #[test]
+    fn search() {
+        let haystack = "foo Шерлок\nХолмс bar\nbaz";
+        let re = Regex::new(r"(?m)^\w+$|\b\w\b|\w+\s+\w+").unwrap();
+        let expected: Vec<_> = re.find_iter(Input::new(haystack)).collect();
+        for split in (0..=haystack.len()).filter(|&i| haystack.is_char_boundary(i)) {
+            let (first, second) = haystack.split_at(split);
+            let mut chain = Chain::new(RandomSlices::new(first.as_bytes()), second);
+            let matches: Vec<_> = re.find_iter(Input::new(&mut chain)).collect();
+            assert_eq!(matches, expected);
+            for m in matches {
+                let (a, b) = chain.map_span(m.span());
+                let mapped = a.map_or(&[][..], |a| &first.as_bytes()[a.range()]).iter();
+                let mapped = mapped.chain(b.map_or(&[][..], |b| &second.as_bytes()[b.range()]));
+                assert_eq!(mapped.copied().collect::<Vec<_>>(), haystack[m.range()].as_bytes());
+            }
+        }
+        let chain = Chain::new("ab", "cd");
+        assert_eq!(chain.map_span(Span::from(2..2)), (Some(Span::from(2..2)), None));
+        assert_eq!(chain.map_span(Span::from(3..3)), (None, Some(Span::from(1..1))));
+    }
+}
+
+#[cfg(test)]
+mod window_test {
+    use crate::engines::meta::Regex;
+    use crate::test_rope::{RandomSlices, SingleByteChunks};
+    use crate::{Cursor, Input, ReaderCursor, SliceChunksCursor, Window};
+
+    #[test]
+    fn smoke_test() {
+        let haystack = "foo Шерлок\nХолмс bar";
+        for start in 0..=haystack.len() {
+            for end in start..=haystack.len() {
+                let expected = &haystack.as_bytes()[start..end];
+                let mut window = Window::new(haystack, start..end);
+                assert_eq!(window.total_bytes(), Some(expected.len()));
+                assert_eq!(window.chunk(), expected);
+                assert!(!window.advance() && !window.backtrack());
+                if haystack.is_char_boundary(start) && haystack.is_char_boundary(end) {
+                    let mut window =
+                        Window::new(SingleByteChunks::new(haystack.as_bytes()), start..end);
+                    let mut offset = 0;
+                    loop {
+                        assert_eq!(window.offset(), offset);
+                        assert_eq!(window.chunk(), &expected[offset..][..window.chunk().len()]);
+                        offset += window.chunk().len();
+                        if !window.advance() {
+                            break;
+                        }
+                    }
+                    assert_eq!(offset, expected.len());
+                    while window.backtrack() {}
+                    assert_eq!(window.offset(), 0);
+                }
+                let chunks: SliceChunksCursor = haystack.as_bytes().chunks(3).collect();
+                let mut window = Window::new(chunks, start..end);
+                for at in (0..=expected.len()).rev() {
+                    assert!(window.seek(at));
+                    let (offset, len) = (window.offset(), window.chunk().len());
+                    assert!(offset <= at && (at < offset + len || offset + len == expected.len()));
+                }
+            }
+        }
+        let window = Window::new("foo", 1..=1);
+        assert_eq!(window.chunk(), b"o");
+        let window = Window::new("foo", 2..10);
+        assert_eq!(window.chunk(), b"o");
+        let window = Window::new("foo", 1..=usize::MAX);
+        assert_eq!(window.chunk(), b"oo");
+        assert_eq!(window.total_bytes(), Some(2));
+        // the haystack may end before the range if its length isn't known
+        let cursor = ReaderCursor::with_window(&b"foo bar"[..], 2, 4);
+        let mut window = Window::new(cursor, 1..10);
+        assert_eq!(window.total_bytes(), None);
+        while window.advance() {}
+        assert_eq!(window.offset() + window.chunk().len(), 6);
+        assert_eq!(window.total_bytes(), None);
+    }
+
+    #[test]
+    fn search() {
+        let haystack = "foo Шерлок\nХолмс bar\nbaz";
+        let re = Regex::new(r"(?m)^\w+$|\A\w|\b\w\b|\w+\s+\w+").unwrap();
+        for start in (0..=haystack.len()).filter(|&i| haystack.is_char_boundary(i)) {
+            let expected: Vec<_> = re.find_iter(Input::new(&haystack[start..])).collect();
+            let mut window = Window::new(RandomSlices::new(haystack.as_bytes()), start..);
+            let matches: Vec<_> = re.find_iter(Input::new(&mut window)).collect();
+            assert_eq!(matches, expected);
+            for m in matches {
+                assert_eq!(window.map_span(m.span()).range(), start + m.start()..start + m.end());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod reversed_test {
+    use crate::engines::meta::Regex;
+    use crate::test_rope::{RandomSlices, SingleByteChunks};
+    use crate::{Cursor, Input, Reversed, SliceChunksCursor};
+
+    const HAYSTACKS: &[&str] =
+        &["", "a", "foo bar", "Шерлок Холмс", "a\u{1F600}b😀 ß\nx", "let foo = bar.baz(1);"];
+
+    fn check_chunks<C: Cursor>(mut cursor: Reversed<C>, expected: &[u8]) {
+        assert_eq!(cursor.total_bytes(), Some(expected.len()));
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            assert_eq!(cursor.chunk(), &expected[offset..][..cursor.chunk().len()]);
+            offset += cursor.chunk().len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(offset, expected.len());
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(offset, 0);
+    }
+
+    #[test]
+    fn smoke_test() {
+        for haystack in HAYSTACKS {
+            let bytes: Vec<u8> = haystack.bytes().rev().collect();
+            let chars: String = haystack.chars().rev().collect();
+            check_chunks(Reversed::new(*haystack), &bytes);
+            check_chunks(Reversed::new(SingleByteChunks::new(haystack.as_bytes())), &bytes);
+            check_chunks(Reversed::new(RandomSlices::new(haystack.as_bytes())), &bytes);
+            check_chunks(Reversed::utf8(*haystack), chars.as_bytes());
+            check_chunks(
+                Reversed::utf8(SingleByteChunks::new(haystack.as_bytes())),
+                chars.as_bytes(),
+            );
+            check_chunks(Reversed::utf8(RandomSlices::new(haystack.as_bytes())), chars.as_bytes());
+            assert!(Reversed::utf8(RandomSlices::new(haystack.as_bytes())).utf8_aware());
+            assert!(!Reversed::new(RandomSlices::new(haystack.as_bytes())).utf8_aware());
+        }
+        // invalid UTF-8 is reversed byte by byte
+        let haystack = b"a\x80\x80\x80\x80b\xE2\x82c\xF0\x9F\x98\x80";
+        let expected = b"\xF0\x9F\x98\x80c\x82\xE2b\x80\x80\x80\x80a";
+        check_chunks(Reversed::utf8(&haystack[..]), expected);
+    }
+
+    #[test]
+    fn seek() {
+        // chunks of four bytes don't split these codepoints
+        let haystack = "ШерлокХолмс";
+        let chars: String = haystack.chars().rev().collect();
+        let chunks: SliceChunksCursor = haystack.as_bytes().chunks(4).collect();
+        let mut cursor = Reversed::utf8(chunks);
+        for at in (0..=haystack.len()).rev().chain([5, 0, 100]) {
+            assert!(cursor.seek(at));
+            let (offset, len) = (cursor.offset(), cursor.chunk().len());
+            assert!(offset <= at && (at < offset + len || offset + len == haystack.len()));
+            assert_eq!(cursor.chunk(), &chars.as_bytes()[offset..offset + len]);
+        }
+    }
+
+    #[test]
+    fn search() {
+        let re = Regex::new(r"(?m)^\w+|\b\w\b|\w+$|[^\w\s]+|").unwrap();
+        for haystack in HAYSTACKS {
+            let chars: String = haystack.chars().rev().collect();
+            let expected: Vec<_> = re.find_iter(Input::new(chars.as_str())).collect();
+            let mut single = Reversed::utf8(SingleByteChunks::new(haystack.as_bytes()));
+            let mut random = Reversed::utf8(RandomSlices::new(haystack.as_bytes()));
+            let matches: Vec<_> = re.find_iter(Input::new(&mut single)).collect();
+            assert_eq!(matches, expected);
+            assert_eq!(re.find_iter(Input::new(&mut random)).collect::<Vec<_>>(), expected);
+            for m in matches {
+                let original: String =
+                    haystack[single.map_span(m.span()).range()].chars().rev().collect();
+                assert_eq!(original, chars[m.range()]);
+            }
+        }
+
+        let re = Regex::new(r"[a-z]+|[\x00-\x7F]").unwrap();
+        for haystack in HAYSTACKS {
+            let bytes: Vec<u8> = haystack.bytes().rev().collect();
+            let expected: Vec<_> = re.find_iter(Input::new(&bytes[..])).collect();
+            let mut random = Reversed::new(RandomSlices::new(haystack.as_bytes()));
+            let matches: Vec<_> = re.find_iter(Input::new(&mut random)).collect();
+            assert_eq!(matches, expected);
+        }
+    }
+}
+
+#[cfg(test)]
+mod utf8_boundaries_test {
+    use crate::engines::meta::Regex;
+    use crate::util::utf8;
+    use crate::{Cursor, Input, SliceChunksCursor, Utf8Boundaries};
+
+    const HAYSTACKS: &[&str] =
+        &["", "a", "foo bar", "Шерлок Холмс", "a\u{1F600}b😀 ß\nx\u{10FFFF}", "ᛋᛏᚨᚱ  ᚹᚨᚱᛋ"];
+
+    fn check_chunks<C: Cursor + Clone>(mut cursor: Utf8Boundaries<C>, haystack: &[u8]) {
+        assert!(cursor.utf8_aware());
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            let chunk = cursor.chunk();
+            assert!(!chunk.is_empty() || haystack.is_empty());
+            assert_eq!(chunk, &haystack[offset..][..chunk.len()]);
+            if std::str::from_utf8(haystack).is_ok() {
+                assert!(utf8::is_boundary(haystack, offset));
+                assert!(utf8::is_boundary(chunk, 0));
+            }
+            offset += chunk.len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(offset, haystack.len());
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(offset, 0);
+        for at in (0..=haystack.len()).rev().chain(0..=haystack.len()) {
+            assert!(cursor.seek(at));
+            let (offset, len) = (cursor.offset(), cursor.chunk().len());
+            assert!(offset <= at && (at < offset + len || offset + len == haystack.len()));
+            assert_eq!(cursor.chunk(), &haystack[offset..offset + len]);
+            // the neighbours of the chunk are reached from there as well
+            let (mut forward, mut backward) = (cursor.clone(), cursor.clone());
+            let mut end = offset + len;
+            while forward.advance() {
+                assert_eq!(forward.offset(), end);
+                end += forward.chunk().len();
+            }
+            assert_eq!(end, haystack.len());
+            let mut start = offset;
+            while backward.backtrack() {
+                start -= backward.chunk().len();
+                assert_eq!(backward.offset(), start);
+            }
+            assert_eq!(start, 0);
+        }
+    }
+
+    #[test]
+    fn smoke_test() {
+        for haystack in HAYSTACKS {
+            for size in 1..=7 {
+                let chunks: SliceChunksCursor = haystack.as_bytes().chunks(size).collect();
+                check_chunks(Utf8Boundaries::new(chunks), haystack.as_bytes());
+            }
+        }
+        // empty chunks are skipped
+        let haystack = "a😀ß".as_bytes();
+        let chunks: SliceChunksCursor = haystack.chunks(1).flat_map(|chunk| [chunk, &[]]).collect();
+        check_chunks(Utf8Boundaries::new(chunks), haystack);
+        // invalid UTF-8 is passed through
+        let haystack = b"a\x80\x80\x80\x80b\xE2\x82c\xF0\x9F\x98\x80\xF0";
+        for size in 1..=7 {
+            let chunks: SliceChunksCursor = haystack.chunks(size).collect();
+            check_chunks(Utf8Boundaries::new(chunks), haystack);
+        }
+    }
+
+    #[test]
+    fn search() {
+        let re = Regex::new(r"\b\w+\b|\B|ß").unwrap();
+        for haystack in HAYSTACKS {
+            let expected: Vec<_> = re.find_iter(Input::new(*haystack)).collect();
+            for size in 1..=7 {
+                let chunks: SliceChunksCursor = haystack.as_bytes().chunks(size).collect();
+                let cursor = Utf8Boundaries::new(chunks);
+                assert_eq!(re.find_iter(Input::new(cursor)).collect::<Vec<_>>(), expected);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod transcoded_test {
+    use regex_automata::Span;
+
+    use crate::engines::meta::Regex;
+    use crate::{Cursor, Encoding, Input, SliceChunksCursor, Transcoded};
+
+    const HAYSTACKS: &[&str] =
+        &["", "a", "foo bar", "Шерлок Холмс", "a\u{1F600}b😀 ß\nx\u{10FFFF}", "ᛋᛏᚨᚱ  ᚹᚨᚱᛋ"];
+
+    fn utf16le(text: &str) -> Vec<u8> {
+        text.encode_utf16().flat_map(u16::to_le_bytes).collect()
+    }
+
+    fn check_chunks<C: Cursor>(cursor: &mut Transcoded<C>, text: &str) {
+        assert!(cursor.utf8_aware());
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            let chunk = cursor.chunk();
+            assert!(!chunk.is_empty() || text.is_empty());
+            assert_eq!(chunk, &text.as_bytes()[offset..][..chunk.len()]);
+            offset += chunk.len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(offset, text.len());
+        assert_eq!(cursor.total_bytes(), None);
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(offset, 0);
+        for at in (0..=text.len()).rev().chain(0..=text.len()) {
+            assert!(cursor.seek(at));
+            let (offset, len) = (cursor.offset(), cursor.chunk().len());
+            assert!(offset <= at && (at < offset + len || offset + len == text.len()));
+            assert_eq!(cursor.chunk(), &text.as_bytes()[offset..offset + len]);
+        }
+    }
+
+    #[test]
+    fn utf16() {
+        for text in HAYSTACKS {
+            let source = utf16le(text);
+            for size in 1..=7 {
+                let chunks: SliceChunksCursor = source.chunks(size).collect();
+                let mut cursor = Transcoded::new(chunks, Encoding::Utf16Le);
+                check_chunks(&mut cursor, text);
+                // every codepoint maps to its code units
+                for (i, c) in text.char_indices() {
+                    let start = text[..i].encode_utf16().count();
+                    let span = cursor.map_span(Span::from(i..i + c.len_utf8()));
+                    assert_eq!(span.range(), start..start + c.len_utf16());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn latin1() {
+        let source: Vec<u8> = (0..=255).collect();
+        let text: String = source.iter().map(|&b| char::from(b)).collect();
+        for size in 1..=7 {
+            let chunks: SliceChunksCursor = source.chunks(size).collect();
+            let mut cursor = Transcoded::new(chunks, Encoding::Latin1);
+            check_chunks(&mut cursor, &text);
+            assert_eq!(cursor.map_span(Span::from(127..131)).range(), 127..129);
+        }
+    }
+
+    #[test]
+    fn invalid_utf16() {
+        // an unpaired low surrogate, an unpaired high surrogate and an odd
+        // trailing byte
+        let source = [b'a', 0, 0x00, 0xDC, b'b', 0, 0x00, 0xD8, b'c', 0, b'd'];
+        let text = "a\u{FFFD}b\u{FFFD}c\u{FFFD}";
+        for size in 1..=7 {
+            let chunks: SliceChunksCursor = source.chunks(size).collect();
+            check_chunks(&mut Transcoded::new(chunks, Encoding::Utf16Le), text);
+        }
+        // the high surrogate at the end of the haystack
+        let chunks: SliceChunksCursor = [&[b'a', 0, 0x00][..], &[0xD8]].into_iter().collect();
+        check_chunks(&mut Transcoded::new(chunks, Encoding::Utf16Le), "a\u{FFFD}");
+    }
+
+    #[test]
+    fn search() {
+        let re = Regex::new(r"\b\w+\b|\B|ß").unwrap();
+        for text in HAYSTACKS {
+            let expected: Vec<_> = re.find_iter(Input::new(*text)).collect();
+            let source = utf16le(text);
+            for size in 1..=7 {
+                let chunks: SliceChunksCursor = source.chunks(size).collect();
+                let mut cursor = Transcoded::new(chunks, Encoding::Utf16Le);
+                let matches: Vec<_> = re.find_iter(Input::new(&mut cursor)).collect();
+                assert_eq!(matches, expected);
+                for m in matches {
+                    let start = text[..m.start()].encode_utf16().count();
+                    let len = text[m.range()].encode_utf16().count();
+                    assert_eq!(cursor.map_span(m.span()).range(), start..start + len);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn end_anchors() {
+        let latin1: Vec<u8> = (0..=255).collect();
+        let latin1_text: String = latin1.iter().map(|&b| char::from(b)).collect();
+        for needle in [r"$", r"\z", r"\w+$", r"(?m)\w$", r"[^a]\z"] {
+            let re = Regex::new(needle).unwrap();
+            for text in HAYSTACKS {
+                let expected: Vec<_> = re.find_iter(Input::new(*text)).collect();
+                let source = utf16le(text);
+                for size in [1, 3, 7] {
+                    let chunks: SliceChunksCursor = source.chunks(size).collect();
+                    let cursor = Transcoded::new(chunks, Encoding::Utf16Le);
+                    let matches: Vec<_> = re.find_iter(Input::new(cursor)).collect();
+                    assert_eq!(matches, expected, "{needle:?} {text:?}");
+                    let chunks: SliceChunksCursor = source.chunks(size).collect();
+                    let cursor = Transcoded::new(chunks, Encoding::Utf16Le);
+                    let m = re.try_search(Input::new(cursor)).unwrap();
+                    assert_eq!(m, expected.first().copied(), "{needle:?} {text:?}");
+                }
+            }
+            let expected: Vec<_> = re.find_iter(Input::new(latin1_text.as_str())).collect();
+            for size in [1, 3, 7] {
+                let chunks: SliceChunksCursor = latin1.chunks(size).collect();
+                let cursor = Transcoded::new(chunks, Encoding::Latin1);
+                let matches: Vec<_> = re.find_iter(Input::new(cursor)).collect();
+                assert_eq!(matches, expected, "{needle:?}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod normalize_newlines_test {
+    use regex_automata::Span;
+
+    use crate::engines::meta::Regex;
+    use crate::{Cursor, Input, NormalizeNewlines, SliceChunksCursor};
+
+    const HAYSTACKS: &[&str] = &[
+        "",
+        "\r",
+        "\r\n",
+        "\n\r",
+        "foo\r\nbar\r\n",
+        "a\rb\r\r\nc\n\r\n\r\r",
+        "Шерлок\r\nХолмс\r\n\r\n",
+    ];
+
+    fn check_chunks<C: Cursor>(cursor: &mut NormalizeNewlines<C>, text: &[u8]) {
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            let chunk = cursor.chunk();
+            assert!(!chunk.is_empty() || text.is_empty());
+            assert_eq!(chunk, &text[offset..][..chunk.len()]);
+            offset += chunk.len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(offset, text.len());
+        assert_eq!(cursor.total_bytes(), None);
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(offset, 0);
+        for at in (0..=text.len()).rev().chain(0..=text.len()) {
+            assert!(cursor.seek(at));
+            let (offset, len) = (cursor.offset(), cursor.chunk().len());
+            assert!(offset <= at && (at < offset + len || offset + len == text.len()));
+            assert_eq!(cursor.chunk(), &text[offset..offset + len]);
+        }
+    }
+
+    #[test]
+    fn smoke_test() {
+        for haystack in HAYSTACKS {
+            let normalized = haystack.replace("\r\n", "\n");
+            for size in 1..=7 {
+                let chunks: SliceChunksCursor = haystack.as_bytes().chunks(size).collect();
+                check_chunks(&mut NormalizeNewlines::new(chunks), normalized.as_bytes());
+            }
+            // line endings split by empty chunks
+            let chunks: SliceChunksCursor =
+                haystack.as_bytes().chunks(1).flat_map(|chunk| [chunk, &[]]).collect();
+            check_chunks(&mut NormalizeNewlines::new(chunks), normalized.as_bytes());
+        }
+    }
+
+    #[test]
+    fn map_span() {
+        let text = "a\r\n\r\nbc\r\r\nd";
+        let chunks: SliceChunksCursor = text.as_bytes().chunks(2).collect();
+        let mut cursor = NormalizeNewlines::new(chunks);
+        while cursor.advance() {}
+        let map = |range: std::ops::Range<usize>| cursor.map_span(Span::from(range)).range();
+        // "a\n\nbc\r\nd"
+        assert_eq!(map(0..1), 0..1);
+        assert_eq!(map(1..2), 1..3);
+        assert_eq!(map(1..1), 1..1);
+        assert_eq!(map(2..2), 3..3);
+        assert_eq!(map(0..3), 0..5);
+        assert_eq!(map(3..5), 5..7);
+        assert_eq!(map(5..7), 7..10);
+        assert_eq!(map(7..8), 10..11);
+        assert_eq!(map(8..8), 11..11);
+    }
+
+    #[test]
+    fn search() {
+        let re = Regex::new(r"(?m)^.*$|\n").unwrap();
+        for haystack in HAYSTACKS {
+            let normalized = haystack.replace("\r\n", "\n");
+            let expected: Vec<_> = re.find_iter(Input::new(normalized.as_str())).collect();
+            for size in 1..=7 {
+                let chunks: SliceChunksCursor = haystack.as_bytes().chunks(size).collect();
+                let mut cursor = NormalizeNewlines::new(chunks);
+                let matches: Vec<_> = re.find_iter(Input::new(&mut cursor)).collect();
+                assert_eq!(matches, expected);
+                for m in matches {
+                    let original = &haystack[cursor.map_span(m.span()).range()];
+                    assert_eq!(original.replace("\r\n", "\n"), &normalized[m.range()]);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn end_anchors() {
+        let haystacks = HAYSTACKS.iter().chain(&["foo\r\nfoo", "foo\r\n", "bar\r\n\r"]);
+        for needle in [r"$", r"\z", r"foo$", r"(?m)\w+$", r"\n\z", r"\r\z"] {
+            let re = Regex::new(needle).unwrap();
+            for haystack in haystacks.clone() {
+                let normalized = haystack.replace("\r\n", "\n");
+                let expected: Vec<_> = re.find_iter(Input::new(normalized.as_str())).collect();
+                for size in [1, 2, 5] {
+                    let chunks: SliceChunksCursor = haystack.as_bytes().chunks(size).collect();
+                    let cursor = NormalizeNewlines::new(chunks);
+                    let matches: Vec<_> = re.find_iter(Input::new(cursor)).collect();
+                    assert_eq!(matches, expected, "{needle:?} {haystack:?}");
+                    let chunks: SliceChunksCursor = haystack.as_bytes().chunks(size).collect();
+                    let cursor = NormalizeNewlines::new(chunks);
+                    let m = re.try_search(Input::new(cursor)).unwrap();
+                    assert_eq!(m, expected.first().copied(), "{needle:?} {haystack:?}");
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod concealed_test {
+    use std::ops::Range;
+
+    use regex_automata::Span;
+
+    use crate::engines::meta::Regex;
+    use crate::{Concealed, Cursor, Input, SliceChunksCursor};
+
+    type Inserted = &'static [(usize, &'static [u8])];
+
+    const CASES: &[(&str, &[Range<usize>], Inserted)] = &[
+        ("", &[], &[]),
+        ("", &[], &[(0, b"ab")]),
+        ("foo bar", &[], &[]),
+        ("foo bar", &[0..1, 1..2], &[]),
+        ("foo bar", &[0..3, 3..7], &[]),
+        ("foo bar", &[1..3, 3..4, 6..7], &[]),
+        ("foo bar", &[2..4, 5..100], &[(0, b"<"), (2, b"[x y]"), (3, b"hidden"), (5, b"z")]),
+        ("foo bar", &[4..5, 5..6], &[(4, b"a"), (4, b"b"), (6, b"c"), (7, b"end"), (9, b"past")]),
+        ("Шерлок Холмс", &[2..6, 13..15], &[(12, "😀 ".as_bytes())]),
+    ];
+
+    /// Returns the visible text, and the underlying offset of every visible
+    /// byte that isn't inserted.
+    fn expected(
+        text: &str,
+        hidden: &[Range<usize>],
+        inserted: Inserted,
+    ) -> (Vec<u8>, Vec<Option<usize>>) {
+        let is_hidden = |i: usize| hidden.iter().any(|range| range.contains(&i));
+        let (mut visible, mut offsets) = (Vec::new(), Vec::new());
+        for i in 0..=text.len() {
+            // texts inserted within a hidden range are hidden as well
+            if !hidden.iter().any(|range| range.start < i && i < range.end) {
+                for &(_, text) in inserted.iter().filter(|&&(at, _)| at == i) {
+                    visible.extend_from_slice(text);
+                    offsets.extend(text.iter().map(|_| None));
+                }
+            }
+            if i < text.len() && !is_hidden(i) {
+                visible.push(text.as_bytes()[i]);
+                offsets.push(Some(i));
+            }
+        }
+        (visible, offsets)
+    }
+
+    fn check_chunks<C: Cursor>(mut cursor: Concealed<'_, C>, text: &[u8]) {
+        let mut offset = 0;
+        loop {
+            assert_eq!(cursor.offset(), offset);
+            let chunk = cursor.chunk();
+            assert!(!chunk.is_empty() || text.is_empty());
+            assert_eq!(chunk, &text[offset..][..chunk.len()]);
+            offset += chunk.len();
+            if !cursor.advance() {
+                break;
+            }
+        }
+        assert_eq!(offset, text.len());
+        assert_eq!(cursor.total_bytes(), Some(text.len()));
+        loop {
+            offset -= cursor.chunk().len();
+            assert_eq!(cursor.offset(), offset);
+            if !cursor.backtrack() {
+                break;
+            }
+        }
+        assert_eq!(offset, 0);
+        for at in (0..=text.len()).rev().chain(0..=text.len()) {
+            assert!(cursor.seek(at));
+            let (offset, len) = (cursor.offset(), cursor.chunk().len());
+            assert!(offset <= at && (at < offset + len || offset + len == text.len()));
+            assert_eq!(cursor.chunk(), &text[offset..offset + len]);
+        }
+    }
+
+    #[test]
+    fn smoke_test() {
+        for &(text, hidden, inserted) in CASES {
+            let (visible, _) = expected(text, hidden, inserted);
+            for size in 1..=7 {
+                let chunks: SliceChunksCursor = text.as_bytes().chunks(size).collect();
+                check_chunks(Concealed::with_inserted(chunks, hidden, inserted), &visible);
+            }
+        }
+    }
+
+    #[test]
+    fn offset_map() {
+        for &(text, hidden, inserted) in CASES {
+            let (_, offsets) = expected(text, hidden, inserted);
+            let cursor = Concealed::with_inserted(text, hidden, inserted);
+            let map = cursor.offset_map();
+            for (visible, underlying) in offsets.iter().enumerate() {
+                let Some(underlying) = *underlying else { continue };
+                let span = map.to_underlying(Span::from(visible..visible + 1));
+                assert_eq!(span.range(), underlying..underlying + 1);
+                let span = map.to_visible(Span::from(underlying..underlying + 1));
+                assert_eq!(span.range(), visible..visible + 1);
+            }
+        }
+        let map =
+            Concealed::with_inserted("foo bar", &[2..3, 3..4], &[(5, b"xy")]).offset_map().clone();
+        let to_underlying = |range: Range<usize>| map.to_underlying(Span::from(range)).range();
+        // "fobxyar"
+        assert_eq!(to_underlying(0..2), 0..2);
+        assert_eq!(to_underlying(0..3), 0..5);
+        assert_eq!(to_underlying(2..2), 4..4);
+        assert_eq!(to_underlying(3..5), 5..5);
+        assert_eq!(to_underlying(2..7), 4..7);
+        let to_visible = |range: Range<usize>| map.to_visible(Span::from(range)).range();
+        assert_eq!(to_visible(1..3), 1..2);
+        assert_eq!(to_visible(3..7), 2..7);
+    }
+
+    #[test]
+    fn search() {
+        let re = Regex::new(r"(?<word>\w+)|\B").unwrap();
+        for &(text, hidden, inserted) in CASES {
+            let (visible, offsets) = expected(text, hidden, inserted);
+            let visible = String::from_utf8(visible).unwrap();
+            let expected: Vec<_> = re.find_iter(Input::new(visible.as_str())).collect();
+            for size in 1..=7 {
+                let chunks: SliceChunksCursor = text.as_bytes().chunks(size).collect();
+                let cursor = Concealed::with_inserted(chunks, hidden, inserted);
+                let matches: Vec<_> = re.find_iter(Input::new(cursor.clone())).collect();
+                assert_eq!(matches, expected);
+                let mut caps = re.create_captures();
+                for m in matches {
+                    let mut input = Input::new(cursor.clone());
+                    input.set_span(m.span());
+                    re.search_captures(input, &mut caps);
+                    let map = cursor.offset_map();
+                    map.map_captures(&mut caps);
+                    let mapped = map.map_match(m);
+                    assert_eq!(caps.get_match(), Some(mapped));
+                    // the match maps to the underlying text it shows
+                    let shown: Vec<_> = offsets[m.range()].iter().flatten().copied().collect();
+                    let underlying: Vec<_> = mapped
+                        .range()
+                        .filter(|&i| !hidden.iter().any(|range| range.contains(&i)))
+                        .collect();
+                    assert_eq!(shown, underlying);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(all(feature = "inflate", test))]
+mod inflate_test {
+    use std::io;
+
+    use miniz_oxide::deflate::compress_to_vec;
+
+    use crate::engines::meta::Regex;
+    use crate::{Compression, Cursor, InflateCursor, Input};
+
+    fn gzip_member(data: &[u8], level: u8) -> Vec<u8> {
+        let mut member = vec![0x1F, 0x8B, 8, 0, 0, 0, 0, 0, 0, 0xFF];
+        member.extend(compress_to_vec(data, level));
+        member.extend(crc32fast::hash(data).to_le_bytes());
+        member.extend((data.len() as u32).to_le_bytes());
+        member
+    }
+
+    /// `test_cases/syntax.rs`, compressed in different ways.
+    fn fixtures() -> [(Vec<u8>, Compression); 3] {
+        let text = std::fs::read("test_cases/syntax.rs").unwrap();
+        let (head, tail) = text.split_at(text.len() / 2);
+        // a member with stored blocks followed by a compressed one
+        let members = [gzip_member(head, 0), gzip_member(tail, 6)].concat();
+        [
+            (gzip_member(&text, 6), Compression::Gzip),
+            (members, Compression::Gzip),
+            (compress_to_vec(&text, 9), Compression::Deflate),
+        ]
+    }
+
+    fn open(
+        data: &[u8],
+        compression: Compression,
+        chunk_size: usize,
+    ) -> InflateCursor<io::Cursor<&[u8]>> {
+        InflateCursor::with_cache(io::Cursor::new(data), compression, chunk_size, 4, 2).unwrap()
+    }
+
+    #[test]
+    fn smoke_test() {
+        let text = std::fs::read("test_cases/syntax.rs").unwrap();
+        let fixtures = fixtures();
+        for (data, compression) in &fixtures {
+            let mut cursor = open(data, *compression, 1000);
+            assert_eq!(cursor.total_bytes(), None);
+            let mut offset = 0;
+            loop {
+                assert_eq!(cursor.offset(), offset);
+                assert_eq!(cursor.chunk(), &text[offset..][..cursor.chunk().len()]);
+                offset += cursor.chunk().len();
+                if !cursor.advance() {
+                    break;
+                }
+            }
+            assert_eq!(offset, text.len());
+            assert_eq!(cursor.total_bytes(), None);
+            assert_eq!(cursor.len, Some(text.len()));
+            assert_eq!(cursor.checkpoints.len(), 26);
+            loop {
+                offset -= cursor.chunk().len();
+                assert_eq!(cursor.offset(), offset);
+                assert_eq!(cursor.chunk(), &text[offset..][..cursor.chunk().len()]);
+                if !cursor.backtrack() {
+                    break;
+                }
+            }
+            assert_eq!(offset, 0);
+            for (at, offset) in
+                [(50_500, 50_000), (3, 0), (103_188, 103_000), (usize::MAX, 103_000)]
+            {
+                assert!(cursor.seek(at));
+                assert_eq!(cursor.offset(), offset);
+                assert_eq!(cursor.chunk(), &text[offset..][..cursor.chunk().len()]);
+            }
+            assert!(cursor.error().is_none());
+        }
+
+        // the end of the haystack is found while seeking past it
+        let mut cursor = open(&fixtures[0].0, Compression::Gzip, 4096);
+        assert!(cursor.seek(1_000_000));
+        assert_eq!(cursor.offset(), 102_400);
+        assert_eq!(cursor.len, Some(text.len()));
+        // a chunk size that divides the length of the haystack
+        let mut cursor = open(&fixtures[0].0, Compression::Gzip, 103_188 / 2);
+        assert!(cursor.advance());
+        assert!(!cursor.advance());
+        assert_eq!(cursor.offset(), 103_188 / 2);
+
+        let empty = [31, 139, 8, 0, 0, 0, 0, 0, 2, 3, 3, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+        let mut cursor = InflateCursor::new(io::Cursor::new(empty), Compression::Gzip).unwrap();
+        assert_eq!(cursor.chunk(), b"");
+        assert!(!cursor.advance());
+        assert!(cursor.seek(10));
+        assert_eq!(cursor.len, Some(0));
+    }
+
+    #[test]
+    fn search() {
+        let text = std::fs::read_to_string("test_cases/syntax.rs").unwrap();
+        let re = Regex::new(r"(?m)^\s*fn \w+|Шерлок|\d+\.\d+").unwrap();
+        let expected: Vec<_> = re.find_iter(Input::new(text.as_str())).collect();
+        let fixtures = fixtures();
+        for (data, compression) in &fixtures {
+            for chunk_size in [1, 777, 64 * 1024] {
+                let cursor = open(data, *compression, chunk_size);
+                assert_eq!(re.find_iter(Input::new(cursor)).collect::<Vec<_>>(), expected);
+            }
+        }
+        let range = 50_000..60_000;
+        let expected: Vec<_> =
+            re.find_iter(Input::new(text.as_str()).range(range.clone())).collect();
+        let mut cursor = open(&fixtures[0].0, Compression::Gzip, 256);
+        assert!(cursor.seek(range.start));
+        assert_eq!(re.find_iter(Input::new(cursor).range(range)).collect::<Vec<_>>(), expected);
+    }
+
+    #[test]
+    fn end_anchors() {
+        let text = std::fs::read_to_string("test_cases/syntax.rs").unwrap();
+        let fixtures = fixtures();
+        for needle in [r"$", r"\z", r"\}\s*\z", r"(?m)\}$"] {
+            let re = Regex::new(needle).unwrap();
+            let expected: Vec<_> = re.find_iter(Input::new(text.as_str())).collect();
+            for (data, compression) in &fixtures {
+                let cursor = open(data, *compression, 777);
+                let matches: Vec<_> = re.find_iter(Input::new(cursor)).collect();
+                assert_eq!(matches, expected, "{needle:?}");
+                let cursor = open(data, *compression, 777);
+                let m = re.try_search(Input::new(cursor)).unwrap();
+                assert_eq!(m, expected.first().copied(), "{needle:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn try_search() {
+        let re = Regex::new(r"fn \w+").unwrap();
+        let [(mut data, _), ..] = fixtures();
+        let len = data.len();
+        // the checksum of the member
+        data[len - 5] ^= 1;
+        let cursor = InflateCursor::new(io::Cursor::new(&data), Compression::Gzip).unwrap();
+        let matches: Result<Vec<_>, _> = re.try_find_iter(Input::new(cursor)).collect();
+        let err = matches.unwrap_err();
+        assert_eq!(err.io_error().unwrap().to_string(), "gzip checksum mismatch");
+
+        data.truncate(len / 2);
+        let mut cursor =
+            InflateCursor::with_cache(io::Cursor::new(&data), Compression::Gzip, 1000, 4, 2)
+                .unwrap();
+        let m = re.try_search(Input::new(&mut cursor)).unwrap().unwrap();
+        assert_eq!(m.range(), 752..772);
+        let err = re.try_search(Input::new(&mut cursor).range(60_000..)).unwrap_err();
+        assert_eq!(err.io_error().unwrap().kind(), io::ErrorKind::UnexpectedEof);
+        // earlier chunks can still be decompressed from a checkpoint
+        assert!(cursor.seek(0));
+        assert_eq!(re.try_search(Input::new(&mut cursor)).unwrap(), Some(m));
+
+        let err = InflateCursor::new(io::Cursor::new(b"fn main() {}"), Compression::Gzip);
+        assert_eq!(err.unwrap_err().to_string(), "invalid gzip header");
     }
 }
@@ -1,4 +1,6 @@
+pub mod backtrack;
 pub mod dfa;
 pub mod hybrid;
 pub mod meta;
+pub mod onepass;
 pub mod pikevm;
@@ -0,0 +1,600 @@
+pub use regex_automata::nfa::thompson::backtrack::{BoundedBacktracker, Builder, Config};
+use regex_automata::nfa::thompson::{State, NFA};
+use regex_automata::util::captures::Captures;
+use regex_automata::util::primitives::{NonMaxUsize, SmallIndex, StateID};
+use regex_automata::{Anchored, HalfMatch, Match, MatchError, PatternID};
+
+use crate::cursor::Cursor;
+use crate::util::{empty, iter};
+use crate::{literal, Input};
+
+#[cfg(test)]
+mod tests;
+
+/// Returns the minimum visited capacity for the given haystack.
+///
+/// This function can be used as the argument to [`Config::visited_capacity`]
+/// in order to guarantee that a backtracking search for the given `input`
+/// won't return an error when using a [`BoundedBacktracker`] built from the
+/// given `NFA`.
+///
+/// Only the length of the span of `input` is taken into account, so this
+/// works with any cursor whose total length is known up front. For cursors
+/// that don't report [`Cursor::total_bytes`], the span (and therefore the
+/// capacity returned here) is effectively unbounded.
+pub fn min_visited_capacity<C: Cursor>(nfa: &NFA, input: &Input<C>) -> usize {
+    div_ceil(nfa.states().len().saturating_mul(input.get_span().len().saturating_add(1)), 8)
+}
+
+/// Returns a fallible iterator over all non-overlapping leftmost matches in
+/// the given haystack. If no match exists, then the iterator yields no
+/// elements.
+///
+/// If the regex engine returns an error at any point, then the iterator
+/// will yield that error.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::engines::backtrack::{try_find_iter, BoundedBacktracker, Cache};
+/// use regex_cursor::regex_automata::Match;
+/// use regex_cursor::{Input, SliceChunksCursor};
+///
+/// let re = BoundedBacktracker::new("foo[0-9]+")?;
+/// let mut cache = Cache::new(&re);
+///
+/// let cursor: SliceChunksCursor = b"foo1 foo12 foo123".chunks(3).collect();
+/// let result: Result<Vec<Match>, _> =
+///     try_find_iter(&re, &mut cache, Input::new(cursor)).collect();
+/// let matches = result?;
+/// assert_eq!(matches, vec![
+///     Match::must(0, 0..4),
+///     Match::must(0, 5..10),
+///     Match::must(0, 11..17),
+/// ]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[inline]
+pub fn try_find_iter<'r, 'c, C: Cursor>(
+    re: &'r BoundedBacktracker,
+    cache: &'c mut Cache,
+    input: Input<C>,
+) -> TryFindMatches<'r, 'c, C> {
+    let caps = Captures::matches(re.get_nfa().group_info().clone());
+    let it = iter::Searcher::new(input);
+    TryFindMatches { re, cache, caps, it }
+}
+
+/// Returns true if and only if this regex matches the given haystack.
+///
+/// In the case of a backtracking regex engine, and unlike most other
+/// regex engines in this crate, short circuiting isn't practical. However,
+/// this routine may still be faster because it instructs backtracking to
+/// not keep track of any capturing groups.
+///
+/// # Errors
+///
+/// This routine only errors if the search could not complete. For this
+/// backtracking regex engine, this only occurs when the haystack length
+/// exceeds [`BoundedBacktracker::max_haystack_len`].
+#[inline]
+pub fn try_is_match<C: Cursor>(
+    re: &BoundedBacktracker,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+) -> Result<bool, MatchError> {
+    input.with(|input| {
+        let input = input.earliest(true);
+        try_search_slots(re, cache, input, &mut []).map(|pid| pid.is_some())
+    })
+}
+
+/// Executes a leftmost forward search and writes the spans of capturing
+/// groups that participated in a match into the provided [`Captures`]
+/// value. If no match was found, then [`Captures::is_match`] is guaranteed
+/// to return `false`.
+///
+/// # Errors
+///
+/// This routine only errors if the search could not complete. For this
+/// backtracking regex engine, this only occurs when the haystack length
+/// exceeds [`BoundedBacktracker::max_haystack_len`].
+#[inline]
+pub fn try_search<C: Cursor>(
+    re: &BoundedBacktracker,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    caps: &mut Captures,
+) -> Result<(), MatchError> {
+    caps.set_pattern(None);
+    let pid = try_search_slots(re, cache, input, caps.slots_mut())?;
+    caps.set_pattern(pid);
+    Ok(())
+}
+
+/// Executes a leftmost forward search and writes the spans of capturing
+/// groups that participated in a match into the provided `slots`, and
+/// returns the matching pattern ID. The contents of the slots for patterns
+/// other than the matching pattern are unspecified. If no match was found,
+/// then `None` is returned and the contents of all `slots` is unspecified.
+///
+/// This is like [`try_search`], but it accepts a raw slots slice instead of
+/// a `Captures` value. This is useful in contexts where you don't want or
+/// need to allocate a `Captures`.
+#[inline]
+pub fn try_search_slots<C: Cursor>(
+    re: &BoundedBacktracker,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    slots: &mut [Option<NonMaxUsize>],
+) -> Result<Option<PatternID>, MatchError> {
+    let utf8empty = re.get_nfa().has_empty() && re.get_nfa().is_utf8();
+    if !utf8empty {
+        let maybe_hm = try_search_slots_imp(re, cache, input, slots)?;
+        return Ok(maybe_hm.map(|hm| hm.pattern()));
+    }
+    // See pikevm::search_slots for why we do this.
+    let min = re.get_nfa().group_info().implicit_slot_len();
+    if slots.len() >= min {
+        let maybe_hm = try_search_slots_imp(re, cache, input, slots)?;
+        return Ok(maybe_hm.map(|hm| hm.pattern()));
+    }
+    if re.get_nfa().pattern_len() == 1 {
+        let mut enough = [None, None];
+        let got = try_search_slots_imp(re, cache, input, &mut enough)?;
+        // This is OK because we know `enough` is strictly bigger than
+        // `slots`, otherwise this special case isn't reached.
+        slots.copy_from_slice(&enough[..slots.len()]);
+        return Ok(got.map(|hm| hm.pattern()));
+    }
+    let mut enough = vec![None; min];
+    let got = try_search_slots_imp(re, cache, input, &mut enough)?;
+    // This is OK because we know `enough` is strictly bigger than `slots`,
+    // otherwise this special case isn't reached.
+    slots.copy_from_slice(&enough[..slots.len()]);
+    Ok(got.map(|hm| hm.pattern()))
+}
+
+/// This is the actual implementation of `try_search_slots` that doesn't
+/// account for the special case when 1) the NFA has UTF-8 mode enabled,
+/// 2) the NFA can match the empty string and 3) the caller has provided an
+/// insufficient number of slots to record match offsets.
+#[inline(never)]
+fn try_search_slots_imp<C: Cursor>(
+    re: &BoundedBacktracker,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    slots: &mut [Option<NonMaxUsize>],
+) -> Result<Option<HalfMatch>, MatchError> {
+    let utf8empty = re.get_nfa().has_empty() && re.get_nfa().is_utf8();
+    let result = search_imp(re, cache, input, slots);
+    let hm = match input.check_backtrack(result)? {
+        None => return Ok(None),
+        Some(hm) if !utf8empty => return Ok(Some(hm)),
+        Some(hm) => hm,
+    };
+    empty::skip_splits_fwd(input, hm, hm.offset(), |input| {
+        let result = search_imp(re, cache, input, slots);
+        Ok(input.check_backtrack(result)?.map(|hm| (hm, hm.offset())))
+    })
+}
+
+/// The implementation of standard leftmost backtracking search.
+///
+/// Capturing group spans are written to 'slots', but only if requested.
+/// 'slots' can be empty if no spans are needed.
+fn search_imp<C: Cursor>(
+    re: &BoundedBacktracker,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    slots: &mut [Option<NonMaxUsize>],
+) -> Result<Option<HalfMatch>, MatchError> {
+    // Unlike in the PikeVM, we write our capturing group spans directly
+    // into the caller's captures groups. So we have to make sure we're
+    // starting with a blank slate first.
+    for slot in slots.iter_mut() {
+        *slot = None;
+    }
+    cache.setup_search(re, input)?;
+    if input.is_done() {
+        return Ok(None);
+    }
+    let (anchored, start_id) = match input.get_anchored() {
+        // Only way we're unanchored is if both the caller asked for an
+        // unanchored search *and* the pattern is itself not anchored.
+        Anchored::No => (
+            re.get_nfa().is_always_start_anchored(),
+            // We always use the anchored starting state here, even if
+            // doing an unanchored search. The "unanchored" part of it is
+            // implemented in the loop below, by simply trying the next
+            // byte offset if the previous backtracking exploration failed.
+            re.get_nfa().start_anchored(),
+        ),
+        Anchored::Yes => (true, re.get_nfa().start_anchored()),
+        Anchored::Pattern(pid) => match re.get_nfa().start_pattern(pid) {
+            None => return Ok(None),
+            Some(sid) => (true, sid),
+        },
+    };
+    // Unlike the other engines, the backtracker jumps around in the haystack
+    // freely. So instead of streaming through the chunks we seek to every
+    // position we need to inspect and keep the look-behind buffer in sync
+    // with whatever chunk we end up in.
+    input.move_to(input.start());
+    input.sync_look_behind();
+    if anchored {
+        let at = input.start();
+        return Ok(backtrack(re, cache, input, at, start_id, slots));
+    }
+    let pre = re.get_config().get_prefilter();
+    let mut at = input.start();
+    while at <= input.end() {
+        if let Some(pre) = pre {
+            input.move_to_with_look_behind(at);
+            let chunk_offset = input.chunk_offset();
+            match literal::find(pre, input) {
+                None => break,
+                Some(ref span) => {
+                    at = span.start;
+                    if chunk_offset != input.chunk_offset() {
+                        input.sync_look_behind();
+                    }
+                }
+            }
+        }
+        if let Some(hm) = backtrack(re, cache, input, at, start_id, slots) {
+            return Ok(Some(hm));
+        }
+        at += 1;
+    }
+    Ok(None)
+}
+
+/// Look for a match starting at `at` in `input` and write the matching
+/// pattern ID and end offset to the caller's slots. If no match exists
+/// starting at `at`, then `None` is returned.
+///
+/// The given `start_id` must correspond to the anchored starting state of
+/// the NFA (or of a specific pattern).
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn backtrack<C: Cursor>(
+    re: &BoundedBacktracker,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    at: usize,
+    start_id: StateID,
+    slots: &mut [Option<NonMaxUsize>],
+) -> Option<HalfMatch> {
+    cache.stack.push(Frame::Step { sid: start_id, at });
+    while let Some(frame) = cache.stack.pop() {
+        match frame {
+            Frame::Step { sid, at } => {
+                if let Some(hm) = step(re, cache, input, sid, at, slots) {
+                    return Some(hm);
+                }
+            }
+            Frame::RestoreCapture { slot, offset } => {
+                slots[slot] = offset;
+            }
+        }
+    }
+    None
+}
+
+/// Execute a "step" in the backtracking algorithm starting at `sid` and
+/// position `at`. Any alternative paths that need to be explored later are
+/// pushed on to the cache's stack.
+///
+/// Every position that is read is first seeked to in `input`, which is
+/// cheap when moving within the current chunk (the common case, since most
+/// transitions only move forward by a single byte).
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn step<C: Cursor>(
+    re: &BoundedBacktracker,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    mut sid: StateID,
+    mut at: usize,
+    slots: &mut [Option<NonMaxUsize>],
+) -> Option<HalfMatch> {
+    loop {
+        if !cache.visited.insert(sid, at - input.start()) {
+            return None;
+        }
+        match *re.get_nfa().state(sid) {
+            State::ByteRange { ref trans } => {
+                // Unlike the PikeVM, the backtracker can steam roll ahead
+                // in the haystack outside of the main loop, so we need to
+                // check that we didn't leave the span of the search.
+                if at >= input.end() {
+                    return None;
+                }
+                input.move_to_with_look_behind(at);
+                if !trans.matches(input.chunk(), input.chunk_pos()) {
+                    return None;
+                }
+                sid = trans.next;
+                at += 1;
+            }
+            State::Sparse(ref sparse) => {
+                if at >= input.end() {
+                    return None;
+                }
+                input.move_to_with_look_behind(at);
+                sid = sparse.matches(input.chunk(), input.chunk_pos())?;
+                at += 1;
+            }
+            State::Dense(ref dense) => {
+                if at >= input.end() {
+                    return None;
+                }
+                input.move_to_with_look_behind(at);
+                sid = dense.matches(input.chunk(), input.chunk_pos())?;
+                at += 1;
+            }
+            State::Look { look, next } => {
+                // OK because we don't permit building a searcher with a
+                // Unicode word boundary if the requisite Unicode data is
+                // unavailable.
+                input.move_to_with_look_behind(at);
+                let (chunk, pos) = input.look_around();
+                if !re.get_nfa().look_matcher().matches(look, chunk, pos) {
+                    return None;
+                }
+                sid = next;
+            }
+            State::Union { ref alternates } => {
+                sid = match alternates.first() {
+                    None => return None,
+                    Some(&sid) => sid,
+                };
+                cache.stack.extend(
+                    alternates[1..].iter().copied().rev().map(|sid| Frame::Step { sid, at }),
+                );
+            }
+            State::BinaryUnion { alt1, alt2 } => {
+                sid = alt1;
+                cache.stack.push(Frame::Step { sid: alt2, at });
+            }
+            State::Capture { next, slot, .. } => {
+                if slot.as_usize() < slots.len() {
+                    cache.stack.push(Frame::RestoreCapture { slot, offset: slots[slot] });
+                    slots[slot] = NonMaxUsize::new(at);
+                }
+                sid = next;
+            }
+            State::Fail => return None,
+            State::Match { pattern_id } => {
+                return Some(HalfMatch::new(pattern_id, at));
+            }
+        }
+    }
+}
+
+/// An iterator over all non-overlapping matches for a fallible search.
+///
+/// The iterator yields a `Result<Match, MatchError>` value until no more
+/// matches could be found.
+///
+/// The lifetime parameters are as follows:
+///
+/// * `'r` represents the lifetime of the BoundedBacktracker.
+/// * `'c` represents the lifetime of the BoundedBacktracker's cache.
+///
+/// This iterator can be created with the [`try_find_iter`] function.
+#[derive(Debug)]
+pub struct TryFindMatches<'r, 'c, C: Cursor> {
+    re: &'r BoundedBacktracker,
+    cache: &'c mut Cache,
+    caps: Captures,
+    it: iter::Searcher<C>,
+}
+
+impl<'r, 'c, C: Cursor> Iterator for TryFindMatches<'r, 'c, C> {
+    type Item = Result<Match, MatchError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Result<Match, MatchError>> {
+        // Splitting 'self' apart seems necessary to appease borrowck.
+        let TryFindMatches { re, ref mut cache, ref mut caps, ref mut it } = *self;
+        it.try_advance(|input| {
+            try_search(re, cache, input, caps)?;
+            Ok(caps.get_match())
+        })
+        .transpose()
+    }
+}
+
+/// A cache represents mutable state that a [`BoundedBacktracker`] requires
+/// during a search.
+///
+/// A particular `Cache` is coupled with the [`BoundedBacktracker`] from which
+/// it was created. It may only be used with that `BoundedBacktracker`. A
+/// cache and its allocations may be re-purposed via [`Cache::reset`], in
+/// which case, it can only be used with the new `BoundedBacktracker` (and not
+/// the old one).
+#[derive(Clone, Debug)]
+pub struct Cache {
+    /// Stack used on the heap for doing backtracking instead of the
+    /// traditional recursive approach. We don't want recursion because then
+    /// we're likely to hit a stack overflow for bigger regexes.
+    stack: Vec<Frame>,
+    /// The set of (StateID, HaystackOffset) pairs that have been visited
+    /// by the backtracker within a single search. If such a pair has been
+    /// visited, then we avoid doing the work for that pair again. This is
+    /// what "bounds" the backtracking and prevents it from having worst case
+    /// exponential time.
+    visited: Visited,
+}
+
+impl Cache {
+    /// Create a new [`BoundedBacktracker`] cache.
+    ///
+    /// If you want to reuse the returned `Cache` with some other
+    /// `BoundedBacktracker`, then you must call [`Cache::reset`] with the
+    /// desired `BoundedBacktracker`.
+    pub fn new(re: &BoundedBacktracker) -> Cache {
+        Cache { stack: vec![], visited: Visited::new(re) }
+    }
+
+    /// Reset this cache such that it can be used for searching with a
+    /// different [`BoundedBacktracker`].
+    ///
+    /// A cache reset permits reusing memory already allocated in this cache
+    /// with a different `BoundedBacktracker`.
+    pub fn reset(&mut self, re: &BoundedBacktracker) {
+        self.visited.reset(re);
+    }
+
+    /// Returns the heap memory usage, in bytes, of this cache.
+    ///
+    /// This does **not** include the stack size used up by this cache. To
+    /// compute that, use `std::mem::size_of::<Cache>()`.
+    pub fn memory_usage(&self) -> usize {
+        self.stack.len() * core::mem::size_of::<Frame>() + self.visited.memory_usage()
+    }
+
+    /// Clears this cache. This should be called at the start of every search
+    /// to ensure we start with a clean slate.
+    ///
+    /// This also sizes the visited set for the span of `input`, which fails
+    /// if the span is too long for the configured visited capacity.
+    fn setup_search<C: Cursor>(
+        &mut self,
+        re: &BoundedBacktracker,
+        input: &Input<C>,
+    ) -> Result<(), MatchError> {
+        self.stack.clear();
+        self.visited.setup_search(re, input)?;
+        Ok(())
+    }
+}
+
+/// Represents a stack frame on the heap while doing backtracking.
+///
+/// Instead of using explicit recursion for backtracking, we use a stack on
+/// the heap to keep track of things that we want to explore if the current
+/// backtracking branch turns out to not lead to a match.
+#[derive(Clone, Debug)]
+enum Frame {
+    /// Look for a match starting at `sid` and the given position in the
+    /// haystack.
+    Step { sid: StateID, at: usize },
+    /// Reset the given `slot` to the given `offset` (which might be `None`).
+    /// This effectively gives a "scope" to capturing groups, such that an
+    /// offset for a particular group only gets returned if the match goes
+    /// through that capturing group. If backtracking ends up going down a
+    /// different branch that results in a different offset (or perhaps none
+    /// at all), then this "restore capture" frame will cause the offset to
+    /// get reset.
+    RestoreCapture { slot: SmallIndex, offset: Option<NonMaxUsize> },
+}
+
+/// A bitset that keeps track of whether a particular (StateID, offset) has
+/// been considered during backtracking. If it has already been visited, then
+/// backtracking skips it. This is what gives backtracking its "bound."
+#[derive(Clone, Debug)]
+struct Visited {
+    /// The actual underlying bitset. Each element in the bitset corresponds
+    /// to a particular (StateID, offset) pair. States correspond to the rows
+    /// and the offsets correspond to the columns.
+    ///
+    /// If our underlying NFA has N states and the haystack we're searching
+    /// has M bytes, then we have N*(M+1) entries in our bitset table. The
+    /// M+1 occurs because our matches are delayed by one byte (to support
+    /// look-around), and so we need to handle the end position itself rather
+    /// than stopping just before the end.
+    bitset: Vec<usize>,
+    /// The stride represents one plus length of the haystack we're searching
+    /// (as described above). The stride must be initialized for each search.
+    stride: usize,
+}
+
+impl Visited {
+    /// The size of each block, in bits.
+    const BLOCK_SIZE: usize = 8 * core::mem::size_of::<usize>();
+
+    /// Create a new visited set for the given backtracker.
+    ///
+    /// The set is ready to use, but must be setup at the beginning of each
+    /// search by calling `setup_search`.
+    fn new(re: &BoundedBacktracker) -> Visited {
+        let mut visited = Visited { bitset: vec![], stride: 0 };
+        visited.reset(re);
+        visited
+    }
+
+    /// Insert the given (StateID, offset) pair into this set. If it already
+    /// exists, then this is a no-op and it returns false. Otherwise this
+    /// returns true.
+    fn insert(&mut self, sid: StateID, at: usize) -> bool {
+        let table_index = sid.as_usize() * self.stride + at;
+        let block_index = table_index / Visited::BLOCK_SIZE;
+        let bit = table_index % Visited::BLOCK_SIZE;
+        let block_with_bit = 1 << bit;
+        if self.bitset[block_index] & block_with_bit != 0 {
+            return false;
+        }
+        self.bitset[block_index] |= block_with_bit;
+        true
+    }
+
+    /// Reset this visited set to work with the given bounded backtracker.
+    fn reset(&mut self, _: &BoundedBacktracker) {
+        self.bitset.truncate(0);
+    }
+
+    /// Setup this visited set to work for a search using the given NFA
+    /// and input configuration. The NFA must be the same NFA used by the
+    /// BoundedBacktracker given to Visited::reset. Failing to call this might
+    /// result in panics or silently incorrect search behavior.
+    ///
+    /// Only the span of the search determines the size of the set. Since the
+    /// end of the span is `usize::MAX` for cursors of unknown length, such
+    /// searches always exceed the visited capacity and report an error.
+    fn setup_search<C: Cursor>(
+        &mut self,
+        re: &BoundedBacktracker,
+        input: &Input<C>,
+    ) -> Result<(), MatchError> {
+        // Our haystack length is only the length of the span of the entire
+        // haystack that we'll be searching.
+        let haylen = input.get_span().len();
+        let err = || MatchError::haystack_too_long(haylen);
+        // Our stride is one more than the length of the input because our main
+        // search loop includes the position at input.end(). (And it does this
+        // because matches are delayed by one byte to account for look-around.)
+        self.stride = haylen.checked_add(1).ok_or_else(err)?;
+        let needed_capacity = match re.get_nfa().states().len().checked_mul(self.stride) {
+            None => return Err(err()),
+            Some(capacity) => capacity,
+        };
+        let max_capacity = 8 * re.get_config().get_visited_capacity();
+        if needed_capacity > max_capacity {
+            return Err(err());
+        }
+        let needed_blocks = div_ceil(needed_capacity, Visited::BLOCK_SIZE);
+        self.bitset.truncate(needed_blocks);
+        for block in self.bitset.iter_mut() {
+            *block = 0;
+        }
+        if needed_blocks > self.bitset.len() {
+            self.bitset.resize(needed_blocks, 0);
+        }
+        Ok(())
+    }
+
+    /// Return the heap memory usage, in bytes, of this visited set.
+    fn memory_usage(&self) -> usize {
+        self.bitset.len() * core::mem::size_of::<usize>()
+    }
+}
+
+/// Integer division, but rounds up instead of down.
+fn div_ceil(lhs: usize, rhs: usize) -> usize {
+    if lhs % rhs == 0 {
+        lhs / rhs
+    } else {
+        (lhs / rhs) + 1
+    }
+}
@@ -0,0 +1,112 @@
+use std::ops::RangeBounds;
+
+use proptest::{prop_assert_eq, proptest};
+use regex_automata::nfa::thompson::backtrack::BoundedBacktracker;
+use regex_automata::nfa::thompson::Config;
+use regex_automata::util::syntax::Config as SyntaxConfig;
+use regex_automata::MatchErrorKind;
+
+use crate::engines::backtrack::{try_find_iter, try_search};
+use crate::input::Input;
+use crate::test_rope::{RandomSlices, SingleByteChunks};
+
+use super::Cache;
+
+fn test(needle: &str, haystack: &[u8]) {
+    test_with_bounds(needle, haystack, ..)
+}
+
+fn test_with_bounds(needle: &str, haystack: &[u8], bounds: impl RangeBounds<usize> + Clone) {
+    for utf8 in [true, false] {
+        let regex = BoundedBacktracker::builder()
+            .syntax(SyntaxConfig::new().utf8(utf8))
+            .thompson(Config::new().utf8(utf8))
+            .build(needle)
+            .unwrap();
+        let mut cache1 = regex.create_cache();
+        let mut cache2 = Cache::new(&regex);
+        let input = regex_automata::Input::new(haystack).range(bounds.clone());
+        let iter1: Vec<_> = regex.try_find_iter(&mut cache1, input).collect();
+        let input = Input::new(SingleByteChunks::new(haystack)).range(bounds.clone());
+        let iter2: Vec<_> = try_find_iter(&regex, &mut cache2, input).collect();
+        assert_eq!(iter1, iter2);
+        let input = Input::new(RandomSlices::new(haystack)).range(bounds.clone());
+        let iter3: Vec<_> = try_find_iter(&regex, &mut cache2, input).collect();
+        assert_eq!(iter1, iter3);
+    }
+}
+
+#[test]
+fn any() {
+    test(".", b" ");
+}
+
+#[test]
+fn look_around() {
+    test(r"(?m)(?:^|a)+", b"a\naaa\n");
+    test_with_bounds(r"\b{end}", "𝛃".as_bytes(), 2..3);
+    let haystack: String =
+        (0..256).map(|i| format!("foöbar  foÖ{0}bar foö{0}bar", " ".repeat(i % 31))).collect();
+    let needle = r"\bfoö\b[ ]*\bbar\b";
+    test(needle, haystack.as_bytes())
+}
+
+#[test]
+fn maybe_empty() {
+    test(r"x*", b"x");
+    test(r"\bx*\b", b"x");
+}
+
+#[test]
+fn captures() {
+    let regex = BoundedBacktracker::new(r"(\w+)\s+(\w+)?(\d)").unwrap();
+    let haystack = b"foo  bar1 baz 2";
+    let mut cache1 = regex.create_cache();
+    let mut cache2 = Cache::new(&regex);
+    let mut caps1 = regex.create_captures();
+    let mut caps2 = regex.create_captures();
+    regex.try_search(&mut cache1, &regex_automata::Input::new(haystack), &mut caps1).unwrap();
+    let mut input = Input::new(SingleByteChunks::new(haystack));
+    try_search(&regex, &mut cache2, &mut input, &mut caps2).unwrap();
+    assert!(caps1.is_match());
+    assert_eq!(caps1.iter().collect::<Vec<_>>(), caps2.iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn haystack_too_long() {
+    let regex = BoundedBacktracker::builder()
+        .configure(BoundedBacktracker::config().visited_capacity(64))
+        .build(r"[a-z]+")
+        .unwrap();
+    let haystack = "a".repeat(regex.max_haystack_len() + 1);
+    let mut cache = Cache::new(&regex);
+    let mut caps = regex.create_captures();
+    let mut input = Input::new(SingleByteChunks::new(haystack.as_bytes()));
+    let err = try_search(&regex, &mut cache, &mut input, &mut caps).unwrap_err();
+    assert!(matches!(err.kind(), MatchErrorKind::HaystackTooLong { .. }));
+}
+
+proptest! {
+  #[test]
+  fn matches(haystack: String, needle: String) {
+    let Ok(regex) = BoundedBacktracker::builder().syntax(SyntaxConfig::new().case_insensitive(true)).build(&needle) else {
+        return Ok(())
+    };
+    let mut cache1 = regex.create_cache();
+    let mut cache2 = Cache::new(&regex);
+    let iter1: Vec<_> = regex.try_find_iter(&mut cache1, &haystack).collect();
+    let iter2: Vec<_> = try_find_iter(&regex, &mut cache2, Input::new(SingleByteChunks::new(haystack.as_bytes()))).collect();
+    prop_assert_eq!(iter1, iter2);
+  }
+  #[test]
+  fn matches_word(haystack: String, needle in r"\\b\PC+\\b") {
+    let Ok(regex) = BoundedBacktracker::builder().syntax(SyntaxConfig::new().case_insensitive(true)).build(&needle) else {
+        return Ok(())
+    };
+    let mut cache1 = regex.create_cache();
+    let mut cache2 = Cache::new(&regex);
+    let iter1: Vec<_> = regex.try_find_iter(&mut cache1, &haystack).collect();
+    let iter2: Vec<_> = try_find_iter(&regex, &mut cache2, Input::new(RandomSlices::new(haystack.as_bytes()))).collect();
+    prop_assert_eq!(iter1, iter2);
+  }
+}
@@ -6,10 +6,16 @@ use crate::cursor::Cursor;
 use crate::util::iter;
 use crate::Input;
 
-pub use crate::engines::dfa::search::{try_search_fwd, try_search_rev};
+pub use crate::engines::dfa::search::{
+    try_search_fwd, try_search_overlapping_fwd, try_search_overlapping_rev, try_search_rev,
+    try_which_overlapping_matches, OverlappingState,
+};
+pub use crate::engines::dfa::stream::StreamSearcher;
+pub use crate::util::stream::StreamMatch;
 
 mod accel;
 mod search;
+mod stream;
 #[cfg(test)]
 mod test;
 
@@ -1,7 +1,7 @@
 use regex_automata::{
     dfa::{Automaton, StartError},
     util::{prefilter::Prefilter, primitives::StateID, start},
-    Anchored, HalfMatch, MatchError,
+    Anchored, HalfMatch, MatchError, PatternSet,
 };
 
 use crate::{cursor::Cursor, engines::dfa::accel, literal, util::empty, Input};
@@ -344,6 +344,155 @@ pub fn try_search_rev<C: Cursor, A: Automaton>(
     })
 }
 
+/// Executes an overlapping forward search. Matches, if one exists, can be
+/// obtained via the [`OverlappingState::get_match`] method.
+///
+/// This routine is principally only useful when searching for multiple
+/// patterns on inputs where multiple patterns may match the same regions of
+/// text. In particular, callers must preserve the automaton's search state
+/// from prior calls so that the implementation knows where the last match
+/// occurred.
+///
+/// When using this routine to implement an iterator of overlapping matches,
+/// the `start` of the search should always be set to the end of the last
+/// match. The `OverlappingState` given keeps track of the actual position
+/// of the search, since multiple matches may be reported at the same
+/// position.
+///
+/// # Errors
+///
+/// This routine errors if the search could not complete. This can occur in
+/// the same circumstances as [`try_search_fwd`].
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::engines::dfa::{try_search_overlapping_fwd, OverlappingState};
+/// use regex_cursor::regex_automata::{dfa::dense, HalfMatch, MatchKind};
+/// use regex_cursor::Input;
+///
+/// let dfa = dense::Builder::new()
+///     .configure(dense::Config::new().match_kind(MatchKind::All))
+///     .build_many(&[r"\w+$", r"\S+$"])?;
+/// let mut input = Input::new("@foo");
+/// let mut state = OverlappingState::start();
+///
+/// let expected = Some(HalfMatch::must(1, 4));
+/// try_search_overlapping_fwd(&dfa, &mut input, &mut state)?;
+/// assert_eq!(expected, state.get_match());
+///
+/// let expected = Some(HalfMatch::must(0, 4));
+/// try_search_overlapping_fwd(&dfa, &mut input, &mut state)?;
+/// assert_eq!(expected, state.get_match());
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[inline]
+pub fn try_search_overlapping_fwd<C: Cursor, A: Automaton>(
+    dfa: &A,
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    let utf8empty = dfa.has_empty() && dfa.is_utf8();
+    find_overlapping_fwd(dfa, input, state)?;
+    match state.get_match() {
+        None => Ok(()),
+        Some(_) if !utf8empty => Ok(()),
+        Some(_) => skip_empty_utf8_splits_overlapping(input, state, |input, state| {
+            find_overlapping_fwd(dfa, input, state)
+        }),
+    }
+}
+
+/// Executes a reverse overlapping search. Matches, if one exists, can be
+/// obtained via the [`OverlappingState::get_match`] method. Each match
+/// reports the starting offset of a match that ends at the end of the
+/// search.
+///
+/// # Errors
+///
+/// This routine errors if the search could not complete. This can occur in
+/// the same circumstances as [`try_search_rev`].
+#[inline]
+pub fn try_search_overlapping_rev<C: Cursor, A: Automaton>(
+    dfa: &A,
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    let utf8empty = dfa.has_empty() && dfa.is_utf8();
+    find_overlapping_rev(dfa, input, state)?;
+    match state.get_match() {
+        None => Ok(()),
+        Some(_) if !utf8empty => Ok(()),
+        Some(_) => skip_empty_utf8_splits_overlapping(input, state, |input, state| {
+            find_overlapping_rev(dfa, input, state)
+        }),
+    }
+}
+
+/// Writes the set of patterns that match anywhere in the given search
+/// configuration to `patset`. If multiple patterns match at the same
+/// position and the underlying DFA supports overlapping matches, then all
+/// matching patterns are written to the given set.
+///
+/// This search routine *does not* clear the pattern set. If a pattern ID
+/// matched but the given `PatternSet` does not have sufficient capacity to
+/// store it, then it is not inserted and silently dropped.
+///
+/// # Errors
+///
+/// This routine errors if the search could not complete. This can occur in
+/// the same circumstances as [`try_search_fwd`].
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::engines::dfa::try_which_overlapping_matches;
+/// use regex_cursor::regex_automata::{dfa::{dense, Automaton}, MatchKind, PatternSet};
+/// use regex_cursor::Input;
+///
+/// let patterns = &[
+///     r"\w+", r"\d+", r"\pL+", r"foo", r"bar", r"barfoo", r"foobar",
+/// ];
+/// let dfa = dense::Builder::new()
+///     .configure(dense::Config::new().match_kind(MatchKind::All))
+///     .build_many(patterns)?;
+///
+/// let mut input = Input::new("foobar");
+/// let mut patset = PatternSet::new(dfa.pattern_len());
+/// try_which_overlapping_matches(&dfa, &mut input, &mut patset)?;
+/// let expected = vec![0, 2, 3, 4, 6];
+/// let got: Vec<usize> = patset.iter().map(|p| p.as_usize()).collect();
+/// assert_eq!(expected, got);
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[inline]
+pub fn try_which_overlapping_matches<C: Cursor, A: Automaton + ?Sized>(
+    dfa: &A,
+    input: &mut Input<C>,
+    patset: &mut PatternSet,
+) -> Result<(), MatchError> {
+    let mut state = OverlappingState::start();
+    while let Some(m) = {
+        find_overlapping_fwd(dfa, input, &mut state)?;
+        if dfa.has_empty() && dfa.is_utf8() {
+            skip_empty_utf8_splits_overlapping(input, &mut state, |input, state| {
+                find_overlapping_fwd(dfa, input, state)
+            })?;
+        }
+        state.get_match()
+    } {
+        let _ = patset.try_insert(m.pattern());
+        // There's nothing left to find, so we can stop. Or the caller
+        // asked us to.
+        if patset.is_full() || input.get_earliest() {
+            break;
+        }
+    }
+    Ok(())
+}
+
 #[inline(never)]
 pub fn find_fwd<A: Automaton + ?Sized, C: Cursor>(
     dfa: &A,
@@ -356,7 +505,7 @@ pub fn find_fwd<A: Automaton + ?Sized, C: Cursor>(
     // Searching with a pattern ID is always anchored, so we should never use
     // a prefilter.
     let pre = if input.get_anchored().is_anchored() { None } else { dfa.get_prefilter() };
-    if pre.is_some() {
+    let result = if pre.is_some() {
         if input.get_earliest() {
             find_fwd_imp(dfa, input, pre, true)
         } else {
@@ -366,7 +515,8 @@ pub fn find_fwd<A: Automaton + ?Sized, C: Cursor>(
         find_fwd_imp(dfa, input, None, true)
     } else {
         find_fwd_imp(dfa, input, None, false)
-    }
+    };
+    input.check_backtrack(result)
 }
 
 #[cfg_attr(feature = "perf-inline", inline(always))]
@@ -535,11 +685,12 @@ pub fn find_rev<A: Automaton + ?Sized, C: Cursor>(
     if input.is_done() {
         return Ok(None);
     }
-    if input.get_earliest() {
+    let result = if input.get_earliest() {
         find_rev_imp(dfa, input, true)
     } else {
         find_rev_imp(dfa, input, false)
-    }
+    };
+    input.check_backtrack(result)
 }
 
 #[cfg_attr(feature = "perf-inline", inline(always))]
@@ -666,6 +817,250 @@ fn find_rev_imp<A: Automaton + ?Sized, C: Cursor>(
     Ok(mat)
 }
 
+#[inline(never)]
+pub(crate) fn find_overlapping_fwd<A: Automaton + ?Sized, C: Cursor>(
+    dfa: &A,
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    state.mat = None;
+    if input.is_done() {
+        return Ok(());
+    }
+    let pre = if input.get_anchored().is_anchored() { None } else { dfa.get_prefilter() };
+    if pre.is_some() {
+        find_overlapping_fwd_imp(dfa, input, pre, state)
+    } else {
+        find_overlapping_fwd_imp(dfa, input, None, state)
+    }
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn find_overlapping_fwd_imp<A: Automaton + ?Sized, C: Cursor>(
+    dfa: &A,
+    input: &mut Input<C>,
+    pre: Option<&'_ Prefilter>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    // See 'prefilter_restart' docs for explanation.
+    let universal_start = dfa.universal_start_state(Anchored::No).is_some();
+    let mut sid = match state.id {
+        None => {
+            state.at = input.start();
+            input.move_to(state.at);
+            init_fwd(dfa, input)?
+        }
+        Some(sid) => {
+            if let Some(match_index) = state.next_match_index {
+                let match_len = dfa.match_len(sid);
+                if match_index < match_len {
+                    state.next_match_index = Some(match_index + 1);
+                    let pattern = dfa.match_pattern(sid, match_index);
+                    state.mat = Some(HalfMatch::new(pattern, state.at));
+                    return Ok(());
+                }
+            }
+            // Once we've reported all matches at a given position, we need to
+            // advance the search to the next position.
+            state.at += 1;
+            if state.at > input.end() {
+                return Ok(());
+            }
+            input.move_to(state.at);
+            sid
+        }
+    };
+
+    // NOTE: We don't optimize the crap out of this routine primarily because
+    // it seems like most find_overlapping searches will have higher match
+    // counts, and thus, throughput is perhaps not as important. Accelerated
+    // states are simply stepped through one byte at a time.
+    while state.at < input.end() {
+        if input.chunk_pos() >= input.chunk().len() && !input.advance() {
+            break;
+        }
+        sid = dfa.next_state(sid, input.chunk()[input.chunk_pos]);
+        if dfa.is_special_state(sid) {
+            state.id = Some(sid);
+            if dfa.is_start_state(sid) {
+                if let Some(pre) = pre {
+                    match literal::find(pre, input) {
+                        None => return Ok(()),
+                        Some(ref span) => {
+                            if span.start > state.at {
+                                state.at = span.start;
+                                input.move_to(state.at);
+                                if !universal_start {
+                                    sid = prefilter_restart(dfa, input)?;
+                                }
+                                continue;
+                            }
+                            // the prefilter may need to do some scan ahead
+                            input.move_to(state.at);
+                        }
+                    }
+                }
+            } else if dfa.is_match_state(sid) {
+                state.next_match_index = Some(1);
+                let pattern = dfa.match_pattern(sid, 0);
+                state.mat = Some(HalfMatch::new(pattern, state.at));
+                return Ok(());
+            } else if dfa.is_dead_state(sid) {
+                return Ok(());
+            } else if dfa.is_quit_state(sid) {
+                return Err(MatchError::quit(input.chunk()[input.chunk_pos], state.at));
+            } else {
+                debug_assert!(dfa.is_accel_state(sid));
+            }
+        }
+        state.at += 1;
+        input.chunk_pos += 1;
+    }
+
+    let result = eoi_fwd(dfa, input, &mut sid, &mut state.mat);
+    state.id = Some(sid);
+    if state.mat.is_some() {
+        // '1' is always correct here since if we get to this point, this
+        // always corresponds to the first (index '0') match discovered at
+        // this position. So the next match to report at this position (if
+        // it exists) is at index '1'.
+        state.next_match_index = Some(1);
+    }
+    result
+}
+
+#[inline(never)]
+pub(crate) fn find_overlapping_rev<A: Automaton + ?Sized, C: Cursor>(
+    dfa: &A,
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    state.mat = None;
+    if input.is_done() {
+        return Ok(());
+    }
+    let mut sid = match state.id {
+        None => {
+            input.move_to(input.end());
+            let sid = init_rev(dfa, input)?;
+            state.id = Some(sid);
+            if input.start() == input.end() {
+                state.rev_eoi = true;
+            } else {
+                state.at = input.end() - 1;
+            }
+            sid
+        }
+        Some(sid) => {
+            if let Some(match_index) = state.next_match_index {
+                let match_len = dfa.match_len(sid);
+                if match_index < match_len {
+                    state.next_match_index = Some(match_index + 1);
+                    let pattern = dfa.match_pattern(sid, match_index);
+                    state.mat = Some(HalfMatch::new(pattern, state.at));
+                    return Ok(());
+                }
+            }
+            // Once we've reported all matches at a given position, we need
+            // to advance the search to the next position. However, if we've
+            // already followed the EOI transition, then we know we're done
+            // with the search and there cannot be any more matches to report.
+            if state.rev_eoi {
+                return Ok(());
+            } else if state.at == input.start() {
+                // At this point, we should follow the EOI transition. This
+                // will cause us the skip the main loop below and fall through
+                // to the final 'eoi_rev' transition.
+                state.rev_eoi = true;
+            } else {
+                // We haven't hit the end of the search yet, so move on.
+                state.at -= 1;
+            }
+            sid
+        }
+    };
+    if !state.rev_eoi {
+        input.move_to(state.at);
+    }
+    while !state.rev_eoi {
+        sid = dfa.next_state(sid, input.chunk()[input.chunk_pos]);
+        if dfa.is_special_state(sid) {
+            state.id = Some(sid);
+            if dfa.is_start_state(sid) || dfa.is_accel_state(sid) {
+                // do nothing
+            } else if dfa.is_match_state(sid) {
+                state.next_match_index = Some(1);
+                let pattern = dfa.match_pattern(sid, 0);
+                state.mat = Some(HalfMatch::new(pattern, state.at + 1));
+                return Ok(());
+            } else if dfa.is_dead_state(sid) {
+                return Ok(());
+            } else {
+                debug_assert!(dfa.is_quit_state(sid));
+                return Err(MatchError::quit(input.chunk()[input.chunk_pos], state.at));
+            }
+        }
+        if state.at == input.start() {
+            break;
+        }
+        state.at -= 1;
+        while input.chunk_pos == 0 {
+            input.backtrack();
+        }
+        input.chunk_pos -= 1;
+    }
+
+    let result = eoi_rev(dfa, input, &mut sid, &mut state.mat);
+    state.rev_eoi = true;
+    state.id = Some(sid);
+    if state.mat.is_some() {
+        // '1' is always correct here since if we get to this point, this
+        // always corresponds to the first (index '0') match discovered at
+        // this position. So the next match to report at this position (if
+        // it exists) is at index '1'.
+        state.next_match_index = Some(1);
+    }
+    result
+}
+
+/// Runs the given overlapping `search` function (forwards or backwards) until
+/// a match is found whose offset does not split a codepoint.
+///
+/// The function with the same name in hybrid/search.rs has a bit more docs.
+#[cold]
+#[inline(never)]
+fn skip_empty_utf8_splits_overlapping<C: Cursor, F>(
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+    mut search: F,
+) -> Result<(), MatchError>
+where
+    F: FnMut(&mut Input<C>, &mut OverlappingState) -> Result<(), MatchError>,
+{
+    let mut hm = match state.get_match() {
+        None => return Ok(()),
+        Some(hm) => hm,
+    };
+    if input.get_anchored().is_anchored() {
+        input.move_to(hm.offset());
+        if !input.is_char_boundary() {
+            state.mat = None;
+        }
+        return Ok(());
+    }
+    loop {
+        input.move_to(hm.offset());
+        if input.is_char_boundary() {
+            return Ok(());
+        }
+        search(input, state)?;
+        hm = match state.get_match() {
+            None => return Ok(()),
+            Some(hm) => hm,
+        };
+    }
+}
+
 #[cfg_attr(feature = "perf-inline", inline(always))]
 fn init_fwd<A: Automaton + ?Sized, C: Cursor>(
     dfa: &A,
@@ -784,3 +1179,51 @@ fn prefilter_restart<A: Automaton + ?Sized, C: Cursor>(
 ) -> Result<StateID, MatchError> {
     init_fwd(dfa, input)
 }
+
+/// Represents the current state of an overlapping search.
+///
+/// This is used for overlapping searches since they need to know something
+/// about the previous search. For example, when multiple patterns match at the
+/// same position, this state tracks the last reported pattern so that the next
+/// search knows whether to report another matching pattern or continue with
+/// the search at the next position. Additionally, it also tracks which state
+/// the last search call terminated in.
+///
+/// Callers should always provide a fresh state constructed via
+/// [`OverlappingState::start`] when starting a new search. Reusing state from
+/// a previous search may result in incorrect results.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OverlappingState {
+    /// The match reported by the most recent overlapping search to use this
+    /// state.
+    mat: Option<HalfMatch>,
+    /// The state ID of the state at which the search was in when the call
+    /// terminated. A `None` value indicates the start state of the
+    /// corresponding automaton.
+    id: Option<StateID>,
+    /// The position of the search.
+    at: usize,
+    /// The index into the matching patterns of the next match to report if the
+    /// current state is a match state.
+    next_match_index: Option<usize>,
+    /// This is set to true when a reverse overlapping search has entered its
+    /// EOI transitions.
+    rev_eoi: bool,
+}
+
+impl OverlappingState {
+    /// Create a new overlapping state that begins at the start state of any
+    /// automaton.
+    pub fn start() -> OverlappingState {
+        OverlappingState { mat: None, id: None, at: 0, next_match_index: None, rev_eoi: false }
+    }
+
+    /// Return the match result of the most recent search to execute with this
+    /// state.
+    ///
+    /// A searches will clear this result automatically, such that if no
+    /// match is found, this will correctly report `None`.
+    pub fn get_match(&self) -> Option<HalfMatch> {
+        self.mat
+    }
+}
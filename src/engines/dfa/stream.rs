@@ -0,0 +1,364 @@
+use std::mem;
+
+use regex_automata::dfa::regex::Regex;
+use regex_automata::dfa::{Automaton, StartError};
+use regex_automata::util::primitives::StateID;
+use regex_automata::util::start;
+use regex_automata::{Anchored, HalfMatch, MatchError};
+
+use crate::util::stream::StreamMatch;
+use crate::util::utf8;
+
+/// A push-based searcher for fully unbuffered streams.
+///
+/// This is like [`hybrid::StreamSearcher`](crate::engines::hybrid::StreamSearcher),
+/// but for fully compiled DFAs. See its documentation for more details.
+///
+/// # Errors
+///
+/// `feed` and `finish` return an error when the DFA quits. The searcher
+/// can't continue after an error and must be [reset](StreamSearcher::reset)
+/// before it can be used again.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::engines::dfa::{Regex, StreamSearcher};
+/// use regex_cursor::regex_automata::HalfMatch;
+///
+/// let re = Regex::new(r"[0-9]+(?-u:\b)")?;
+/// let mut searcher = StreamSearcher::new(&re);
+/// let mut matches = vec![];
+/// for chunk in ["12 3", "45 6", "78"] {
+///     matches.extend(searcher.feed(chunk.as_bytes())?);
+/// }
+/// matches.extend(searcher.finish()?);
+/// let ends: Vec<HalfMatch> = matches.iter().map(|m| m.as_half_match()).collect();
+/// assert_eq!(ends, vec![
+///     HalfMatch::must(0, 2),
+///     HalfMatch::must(0, 6),
+///     HalfMatch::must(0, 10),
+/// ]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct StreamSearcher<'r> {
+    re: &'r Regex,
+    /// The current state of the forward DFA. `None` if the search has to be
+    /// (re)started at `at`.
+    sid: Option<StateID>,
+    /// The offset of the next byte the forward DFA consumes.
+    at: usize,
+    /// The offset at which the current search started.
+    search_start: usize,
+    /// The byte before `at`, if any.
+    look_behind: Option<u8>,
+    /// The most recent match of the current search, and the byte before the
+    /// end of that match.
+    mat: Option<(HalfMatch, Option<u8>)>,
+    /// The bytes of earlier chunks that were consumed since the end of
+    /// `mat`. These need to be searched again when a new search starts at
+    /// the end of `mat`.
+    lookahead: Vec<u8>,
+    last_match_end: Option<usize>,
+    /// The number of bytes retained for reverse searches.
+    window_len: usize,
+    window: Vec<u8>,
+    /// The offset of the first byte in `window`.
+    window_start: usize,
+    /// The number of bytes pushed into the searcher.
+    offset: usize,
+    /// Set once no more matches can be found.
+    done: bool,
+}
+
+impl<'r> StreamSearcher<'r> {
+    /// Creates a new searcher for a stream that reports the end offsets of
+    /// the matches of the given regex.
+    pub fn new(re: &'r Regex) -> StreamSearcher<'r> {
+        StreamSearcher {
+            re,
+            sid: None,
+            at: 0,
+            search_start: 0,
+            look_behind: None,
+            mat: None,
+            lookahead: Vec::new(),
+            last_match_end: None,
+            window_len: 0,
+            window: Vec::new(),
+            window_start: 0,
+            offset: 0,
+            done: false,
+        }
+    }
+
+    /// Keeps the last `len` bytes that were pushed into the searcher (in
+    /// addition to the chunk currently being searched) so that the start of
+    /// matches can be found with a reverse search.
+    ///
+    /// This must be configured before any bytes are pushed.
+    pub fn reverse_window(mut self, len: usize) -> StreamSearcher<'r> {
+        assert_eq!(self.offset, 0, "reverse window must be configured before searching");
+        self.window_len = len;
+        self
+    }
+
+    /// Returns the number of bytes pushed into this searcher so far.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Resets this searcher so that it can be used to search a new stream.
+    pub fn reset(&mut self) {
+        self.sid = None;
+        self.at = 0;
+        self.search_start = 0;
+        self.look_behind = None;
+        self.mat = None;
+        self.lookahead.clear();
+        self.last_match_end = None;
+        self.window.clear();
+        self.window_start = 0;
+        self.offset = 0;
+        self.done = false;
+    }
+
+    /// Searches the next chunk of the stream and returns all matches that
+    /// were completed by it.
+    pub fn feed(&mut self, chunk: &[u8]) -> Result<Vec<StreamMatch>, MatchError> {
+        let mut matches = Vec::new();
+        if self.window_len != 0 {
+            self.window.extend_from_slice(chunk);
+        }
+        self.offset += chunk.len();
+        if !self.done {
+            self.search(chunk, &mut matches)?;
+        }
+        if self.window.len() > self.window_len {
+            let excess = self.window.len() - self.window_len;
+            self.window.drain(..excess);
+            self.window_start += excess;
+        }
+        Ok(matches)
+    }
+
+    /// Marks the end of the stream and returns the remaining matches. This
+    /// is where look-around assertions that match at the end of the haystack
+    /// (like `$` or `\b`) are resolved.
+    ///
+    /// No more bytes may be pushed after calling this (until the searcher is
+    /// [reset](StreamSearcher::reset)).
+    pub fn finish(&mut self) -> Result<Vec<StreamMatch>, MatchError> {
+        let mut matches = Vec::new();
+        let dfa = self.re.forward();
+        while !self.done {
+            let sid = match self.sid {
+                Some(sid) => sid,
+                None => self.init_fwd()?,
+            };
+            let sid = dfa.next_eoi_state(sid);
+            self.sid = Some(sid);
+            if dfa.is_match_state(sid) {
+                let pattern = dfa.match_pattern(sid, 0);
+                self.mat = Some((HalfMatch::new(pattern, self.at), self.look_behind));
+                self.lookahead.clear();
+            }
+            // N.B. We don't have to check 'is_quit' here because the EOI
+            // transition can never lead to a quit state.
+            debug_assert!(!dfa.is_quit_state(sid));
+            let Some((hm, _)) = self.mat else {
+                break;
+            };
+            if !self.report(self.lookahead.first().copied(), &mut matches)? {
+                break;
+            }
+            let replay = mem::take(&mut self.lookahead);
+            self.search(&replay[self.at - hm.offset()..], &mut matches)?;
+        }
+        self.done = true;
+        Ok(matches)
+    }
+
+    /// Runs the forward DFA over `haystack`, which starts at `self.at`.
+    ///
+    /// Once a match is reported, a new search starts at its end. If the
+    /// match ended in an earlier chunk, the bytes since then are searched
+    /// again from `lookahead` before continuing with `haystack`. The bytes
+    /// after the end of a match that isn't reported yet are buffered in
+    /// `lookahead` when the end of `haystack` is reached.
+    fn search(
+        &mut self,
+        haystack: &[u8],
+        matches: &mut Vec<StreamMatch>,
+    ) -> Result<(), MatchError> {
+        let start = self.at;
+        let end = start + haystack.len();
+        let dfa = self.re.forward();
+        while self.at < end {
+            if self.done {
+                return Ok(());
+            }
+            let mut sid = match self.sid {
+                Some(sid) => sid,
+                None => self.init_fwd()?,
+            };
+            let mut at = self.at;
+            while at < end {
+                sid = dfa.next_state(sid, haystack[at - start]);
+                if dfa.is_special_state(sid) {
+                    break;
+                }
+                at += 1;
+            }
+            self.sid = Some(sid);
+            if at > self.at {
+                self.look_behind = Some(haystack[at - start - 1]);
+                self.at = at;
+            }
+            if at == end {
+                break;
+            }
+            let byte = haystack[at - start];
+            if dfa.is_match_state(sid) {
+                let pattern = dfa.match_pattern(sid, 0);
+                self.mat = Some((HalfMatch::new(pattern, at), self.look_behind));
+                self.lookahead.clear();
+            } else if dfa.is_dead_state(sid) {
+                let Some((hm, _)) = self.mat else {
+                    self.done = true;
+                    return Ok(());
+                };
+                let next = match hm.offset().checked_sub(start) {
+                    Some(i) => haystack[i],
+                    None => self.lookahead[0],
+                };
+                self.report(Some(next), matches)?;
+                if hm.offset() < start {
+                    let replay = mem::take(&mut self.lookahead);
+                    self.search(&replay[self.at - hm.offset()..], matches)?;
+                }
+                continue;
+            } else if dfa.is_quit_state(sid) {
+                return Err(MatchError::quit(byte, at));
+            }
+            // Start and accelerated states are fine to step through one byte
+            // at a time.
+            self.look_behind = Some(byte);
+            self.at += 1;
+        }
+        if let Some((hm, _)) = self.mat {
+            let from = hm.offset().max(start) - start;
+            self.lookahead.extend_from_slice(&haystack[from..]);
+        }
+        Ok(())
+    }
+
+    /// Reports the match of the current search (unless it's an empty match
+    /// that must be skipped) and prepares a new search after it. `next` is
+    /// the byte at the end of the match, or `None` at the end of the stream.
+    /// Returns false if no more matches can be found.
+    fn report(
+        &mut self,
+        next: Option<u8>,
+        matches: &mut Vec<StreamMatch>,
+    ) -> Result<bool, MatchError> {
+        let (hm, mut look_behind) = self.mat.take().unwrap();
+        let mut at = hm.offset();
+        // See 'Searcher::handle_overlapping_empty_match' and
+        // 'util::empty::skip_splits_fwd'. Since the search can't go back,
+        // the next search starts right after the end of the match.
+        let dfa = self.re.forward();
+        let split =
+            dfa.has_empty() && dfa.is_utf8() && next.map_or(false, |b| !utf8::is_boundary(&[b], 0));
+        if self.last_match_end == Some(at) || split {
+            let Some(next) = next else {
+                return Ok(false);
+            };
+            look_behind = Some(next);
+            at += 1;
+        } else {
+            let start = self.find_start(hm)?;
+            matches.push(StreamMatch::new(hm.pattern(), start, hm.offset()));
+            self.last_match_end = Some(hm.offset());
+        }
+        self.sid = None;
+        self.at = at;
+        self.search_start = at;
+        self.look_behind = look_behind;
+        Ok(true)
+    }
+
+    /// Returns the start of the given match, if it can be determined.
+    fn find_start(&mut self, hm: HalfMatch) -> Result<Option<usize>, MatchError> {
+        let end = hm.offset();
+        if end == self.search_start || self.re.forward().is_always_start_anchored() {
+            return Ok(Some(self.search_start));
+        }
+        if end < self.window_start || self.window.is_empty() {
+            return Ok(None);
+        }
+        let dfa = self.re.reverse();
+        let window = &self.window[..];
+        let end = end - self.window_start;
+        let min_start = self.search_start.max(self.window_start) - self.window_start;
+        let look_ahead = window.get(end).copied();
+        let start_config = start::Config::new().look_behind(look_ahead).anchored(Anchored::Yes);
+        let mut sid = dfa.start_state(&start_config).map_err(|err| match err {
+            StartError::Quit { byte } => MatchError::quit(byte, hm.offset()),
+            StartError::UnsupportedAnchored { mode } => MatchError::unsupported_anchored(mode),
+            _ => panic!("damm forward compatability"),
+        })?;
+        let mut start = None;
+        for at in (min_start..end).rev() {
+            let byte = window[at];
+            sid = dfa.next_state(sid, byte);
+            if dfa.is_special_state(sid) {
+                if dfa.is_match_state(sid) {
+                    // Reverse searches report the beginning of a match one
+                    // byte late, so the start is the previous position.
+                    start = Some(at + 1);
+                } else if dfa.is_dead_state(sid) {
+                    return Ok(start.map(|start| start + self.window_start));
+                } else if dfa.is_quit_state(sid) {
+                    return Err(MatchError::quit(byte, at + self.window_start));
+                }
+            }
+        }
+        if min_start > 0 {
+            let byte = window[min_start - 1];
+            sid = dfa.next_state(sid, byte);
+            if dfa.is_match_state(sid) {
+                start = Some(min_start);
+            } else if dfa.is_quit_state(sid) {
+                return Err(MatchError::quit(byte, min_start - 1 + self.window_start));
+            }
+        } else if self.window_start == 0 {
+            sid = dfa.next_eoi_state(sid);
+            if dfa.is_match_state(sid) {
+                start = Some(0);
+            }
+        } else {
+            // The match may extend further back than the window reaches (or
+            // depend on the byte before it), so its start is unknown.
+            return Ok(None);
+        }
+        Ok(start.map(|start| start + self.window_start))
+    }
+
+    fn init_fwd(&mut self) -> Result<StateID, MatchError> {
+        let start_config =
+            start::Config::new().look_behind(self.look_behind).anchored(Anchored::No);
+        let sid = self.re.forward().start_state(&start_config).map_err(|err| match err {
+            StartError::Quit { byte } => {
+                let offset = self.at.checked_sub(1).expect("no quit in start without look-behind");
+                MatchError::quit(byte, offset)
+            }
+            StartError::UnsupportedAnchored { mode } => MatchError::unsupported_anchored(mode),
+            _ => panic!("damm forward compatability"),
+        })?;
+        self.sid = Some(sid);
+        Ok(sid)
+    }
+}
@@ -1,7 +1,10 @@
 use proptest::proptest;
+use regex_automata::MatchError;
 
 use crate::engines::dfa::find_iter;
 use crate::input::Input;
+use crate::util::stream::test::{check_stream, StreamSearch};
+use crate::util::stream::StreamMatch;
 
 #[test]
 fn searcher() {
@@ -62,3 +65,22 @@ proptest! {
     crate::util::iter::prop_assert_eq(iter1, iter2)?;
   }
 }
+
+impl StreamSearch for super::StreamSearcher<'_> {
+    fn feed(&mut self, chunk: &[u8]) -> Result<Vec<StreamMatch>, MatchError> {
+        super::StreamSearcher::feed(self, chunk)
+    }
+
+    fn finish(&mut self) -> Result<Vec<StreamMatch>, MatchError> {
+        super::StreamSearcher::finish(self)
+    }
+}
+
+#[test]
+fn stream() {
+    check_stream(
+        |needle| super::Regex::new(needle).unwrap(),
+        |regex, haystack| regex.find_iter(haystack).collect(),
+        |regex, window| Box::new(super::StreamSearcher::new(regex).reverse_window(window)),
+    );
+}
@@ -5,9 +5,15 @@ use crate::cursor::Cursor;
 use crate::input::Input;
 use crate::util::iter;
 
-pub use crate::engines::hybrid::search::{try_search_fwd, try_search_rev};
+pub use crate::engines::hybrid::search::{
+    try_search_fwd, try_search_overlapping_fwd, try_search_overlapping_rev, try_search_rev,
+    try_which_overlapping_matches, OverlappingState,
+};
+pub use crate::engines::hybrid::stream::StreamSearcher;
+pub use crate::util::stream::StreamMatch;
 
 mod search;
+mod stream;
 #[cfg(test)]
 mod test;
 
@@ -2,7 +2,7 @@ use regex_automata::hybrid::dfa::{Cache, DFA};
 use regex_automata::hybrid::{LazyStateID, StartError};
 use regex_automata::util::prefilter::Prefilter;
 use regex_automata::util::start;
-use regex_automata::{HalfMatch, MatchError};
+use regex_automata::{HalfMatch, MatchError, PatternSet};
 
 use crate::cursor::Cursor;
 use crate::input::Input;
@@ -358,6 +358,185 @@ pub fn try_search_rev<C: Cursor>(
     })
 }
 
+/// Executes an overlapping forward search. Matches, if one exists, can be
+/// obtained via the [`OverlappingState::get_match`] method.
+///
+/// This routine is principally only useful when searching for multiple
+/// patterns on inputs where multiple patterns may match the same regions
+/// of text. In particular, callers must preserve the automaton's search
+/// state from prior calls so that the implementation knows where the last
+/// match occurred.
+///
+/// When using this routine to implement an iterator of overlapping
+/// matches, the `start` of the search should remain invariant throughout
+/// iteration. The `OverlappingState` given to the search will keep track
+/// of the current position of the search. (This is because multiple
+/// matches may be reported at the same position, so only the search
+/// implementation itself knows when to advance the position.)
+///
+/// If for some reason you want the search to forget about its previous
+/// state and restart the search at a particular position, then setting the
+/// state to [`OverlappingState::start`] will accomplish that.
+///
+/// # Errors
+///
+/// This routine errors if the search could not complete. This can occur
+/// in the same circumstances as [`try_search_fwd`].
+///
+/// # Example
+///
+/// This example shows how to run an overlapping search with multiple
+/// regexes.
+///
+/// ```
+/// use regex_cursor::engines::hybrid::{try_search_overlapping_fwd, OverlappingState};
+/// use regex_cursor::regex_automata::{hybrid::dfa::DFA, HalfMatch, MatchKind};
+/// use regex_cursor::Input;
+///
+/// let dfa = DFA::builder()
+///     .configure(DFA::config().match_kind(MatchKind::All))
+///     .build_many(&[r"\w+$", r"\S+$"])?;
+/// let mut cache = dfa.create_cache();
+///
+/// let mut input = Input::new("@foo");
+/// let mut state = OverlappingState::start();
+///
+/// let expected = Some(HalfMatch::must(1, 4));
+/// try_search_overlapping_fwd(&dfa, &mut cache, &mut input, &mut state)?;
+/// assert_eq!(expected, state.get_match());
+///
+/// // The first pattern also matches at the same position, so re-running
+/// // the search will yield another match. Notice also that the first
+/// // pattern is returned after the second. This is because the second
+/// // pattern begins its match before the first, is therefore an earlier
+/// // match and is thus reported first.
+/// let expected = Some(HalfMatch::must(0, 4));
+/// try_search_overlapping_fwd(&dfa, &mut cache, &mut input, &mut state)?;
+/// assert_eq!(expected, state.get_match());
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[inline]
+pub fn try_search_overlapping_fwd<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    let utf8empty = dfa.get_nfa().has_empty() && dfa.get_nfa().is_utf8();
+    find_overlapping_fwd(dfa, cache, input, state)?;
+    match state.get_match() {
+        None => Ok(()),
+        Some(_) if !utf8empty => Ok(()),
+        Some(_) => skip_empty_utf8_splits_overlapping(input, state, |input, state| {
+            find_overlapping_fwd(dfa, cache, input, state)
+        }),
+    }
+}
+
+/// Executes a reverse overlapping search. Matches, if one exists, can be
+/// obtained via the [`OverlappingState::get_match`] method. Each match
+/// reports the starting offset of a match that ends at the end of the
+/// search.
+///
+/// The same invariants as for [`try_search_overlapping_fwd`] apply: the
+/// span of the search must not change while the same `OverlappingState` is
+/// used.
+///
+/// # Errors
+///
+/// This routine errors if the search could not complete. This can occur
+/// in the same circumstances as [`try_search_rev`].
+#[inline]
+pub fn try_search_overlapping_rev<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    let utf8empty = dfa.get_nfa().has_empty() && dfa.get_nfa().is_utf8();
+    find_overlapping_rev(dfa, cache, input, state)?;
+    match state.get_match() {
+        None => Ok(()),
+        Some(_) if !utf8empty => Ok(()),
+        Some(_) => skip_empty_utf8_splits_overlapping(input, state, |input, state| {
+            find_overlapping_rev(dfa, cache, input, state)
+        }),
+    }
+}
+
+/// Writes the set of patterns that match anywhere in the given search
+/// configuration to `patset`. If multiple patterns match at the same
+/// position and the underlying DFA supports overlapping matches, then all
+/// matching patterns are written to the given set.
+///
+/// Unless all of the patterns in this DFA are anchored, then generally
+/// speaking, this will visit every byte in the haystack.
+///
+/// This search routine *does not* clear the pattern set. This gives some
+/// flexibility to the caller (e.g., running multiple searches with the
+/// same pattern set), but does make the API bug-prone if you're reusing
+/// the same pattern set for multiple searches but intended them to be
+/// independent.
+///
+/// If a pattern ID matched but the given `PatternSet` does not have
+/// sufficient capacity to store it, then it is not inserted and silently
+/// dropped.
+///
+/// # Errors
+///
+/// This routine errors if the search could not complete. This can occur
+/// in the same circumstances as [`try_search_fwd`].
+///
+/// # Example
+///
+/// This example shows how to find all matching patterns in a haystack,
+/// even when some patterns match at the same position as other patterns.
+///
+/// ```
+/// use regex_cursor::engines::hybrid::try_which_overlapping_matches;
+/// use regex_cursor::regex_automata::{hybrid::dfa::DFA, MatchKind, PatternSet};
+/// use regex_cursor::Input;
+///
+/// let patterns = &[
+///     r"\w+", r"\d+", r"\pL+", r"foo", r"bar", r"barfoo", r"foobar",
+/// ];
+/// let dfa = DFA::builder()
+///     .configure(DFA::config().match_kind(MatchKind::All))
+///     .build_many(patterns)?;
+/// let mut cache = dfa.create_cache();
+///
+/// let mut input = Input::new("foobar");
+/// let mut patset = PatternSet::new(dfa.pattern_len());
+/// try_which_overlapping_matches(&dfa, &mut cache, &mut input, &mut patset)?;
+/// let expected = vec![0, 2, 3, 4, 6];
+/// let got: Vec<usize> = patset.iter().map(|p| p.as_usize()).collect();
+/// assert_eq!(expected, got);
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[inline]
+pub fn try_which_overlapping_matches<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    patset: &mut PatternSet,
+) -> Result<(), MatchError> {
+    let mut state = OverlappingState::start();
+    while let Some(m) = {
+        try_search_overlapping_fwd(dfa, cache, input, &mut state)?;
+        state.get_match()
+    } {
+        let _ = patset.try_insert(m.pattern());
+        // There's nothing left to find, so we can stop. Or the caller
+        // asked us to.
+        if patset.is_full() || input.get_earliest() {
+            break;
+        }
+    }
+    Ok(())
+}
+
 #[inline(never)]
 pub(crate) fn find_fwd<C: Cursor>(
     dfa: &DFA,
@@ -378,7 +557,7 @@ pub(crate) fn find_fwd<C: Cursor>(
     // beneficial in ad hoc benchmarks. To see these differences, you often
     // need a query with a high match count. In other words, specializing these
     // four routines *tends* to help latency more than throughput.
-    if pre.is_some() {
+    let result = if pre.is_some() {
         if input.get_earliest() {
             find_fwd_imp(dfa, cache, input, pre, true)
         } else {
@@ -388,7 +567,8 @@ pub(crate) fn find_fwd<C: Cursor>(
         find_fwd_imp(dfa, cache, input, None, true)
     } else {
         find_fwd_imp(dfa, cache, input, None, false)
-    }
+    };
+    input.check_backtrack(result)
 }
 
 #[cfg_attr(feature = "perf-inline", inline(always))]
@@ -666,11 +846,12 @@ pub(crate) fn find_rev<C: Cursor>(
     if input.is_done() {
         return Ok(None);
     }
-    if input.get_earliest() {
+    let result = if input.get_earliest() {
         find_rev_imp(dfa, cache, input, true)
     } else {
         find_rev_imp(dfa, cache, input, false)
-    }
+    };
+    input.check_backtrack(result)
 }
 
 #[cfg_attr(feature = "perf-inline", inline(always))]
@@ -819,6 +1000,282 @@ fn find_rev_imp<C: Cursor>(
     Ok(mat)
 }
 
+#[inline(never)]
+pub(crate) fn find_overlapping_fwd<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    state.mat = None;
+    if input.is_done() {
+        return Ok(());
+    }
+    let pre =
+        if input.get_anchored().is_anchored() { None } else { dfa.get_config().get_prefilter() };
+    if pre.is_some() {
+        find_overlapping_fwd_imp(dfa, cache, input, pre, state)
+    } else {
+        find_overlapping_fwd_imp(dfa, cache, input, None, state)
+    }
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn find_overlapping_fwd_imp<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    pre: Option<&'_ Prefilter>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    // See 'prefilter_restart' docs for explanation.
+    let universal_start = dfa.get_nfa().look_set_prefix_any().is_empty();
+    let mut sid = match state.id {
+        None => {
+            state.at = input.start();
+            input.move_to(state.at);
+            init_fwd(dfa, cache, input)?
+        }
+        Some(sid) => {
+            if let Some(match_index) = state.next_match_index {
+                let match_len = dfa.match_len(cache, sid);
+                if match_index < match_len {
+                    state.next_match_index = Some(match_index + 1);
+                    let pattern = dfa.match_pattern(cache, sid, match_index);
+                    state.mat = Some(HalfMatch::new(pattern, state.at));
+                    return Ok(());
+                }
+            }
+            // Once we've reported all matches at a given position, we need to
+            // advance the search to the next position.
+            state.at += 1;
+            if state.at > input.end() {
+                return Ok(());
+            }
+            input.move_to(state.at);
+            sid
+        }
+    };
+
+    // NOTE: We don't optimize the crap out of this routine primarily because
+    // it seems like most overlapping searches will have higher match counts,
+    // and thus, throughput is perhaps not as important. But if you have a use
+    // case for something faster, feel free to file an issue.
+    cache.search_start(state.at);
+    while state.at < input.end() {
+        if input.chunk_pos() >= input.chunk().len() && !input.advance() {
+            break;
+        }
+        sid = dfa
+            .next_state(cache, sid, input.chunk()[input.chunk_pos])
+            .map_err(|_| gave_up(state.at))?;
+        if sid.is_tagged() {
+            state.id = Some(sid);
+            if sid.is_start() {
+                if let Some(pre) = pre {
+                    match literal::find(pre, input) {
+                        None => return Ok(()),
+                        Some(ref span) => {
+                            if span.start > state.at {
+                                state.at = span.start;
+                                input.move_to(state.at);
+                                if !universal_start {
+                                    sid = prefilter_restart(dfa, cache, input)?;
+                                }
+                                continue;
+                            }
+                            // the prefilter may need to do some scan ahead
+                            input.move_to(state.at);
+                        }
+                    }
+                }
+            } else if sid.is_match() {
+                state.next_match_index = Some(1);
+                let pattern = dfa.match_pattern(cache, sid, 0);
+                state.mat = Some(HalfMatch::new(pattern, state.at));
+                cache.search_finish(state.at);
+                return Ok(());
+            } else if sid.is_dead() {
+                cache.search_finish(state.at);
+                return Ok(());
+            } else if sid.is_quit() {
+                cache.search_finish(state.at);
+                return Err(MatchError::quit(input.chunk()[input.chunk_pos], state.at));
+            } else {
+                debug_assert!(sid.is_unknown());
+                unreachable!("sid being unknown is a bug");
+            }
+        }
+        state.at += 1;
+        input.chunk_pos += 1;
+        cache.search_update(state.at);
+    }
+
+    let result = eoi_fwd(dfa, cache, input, &mut sid, &mut state.mat);
+    state.id = Some(sid);
+    if state.mat.is_some() {
+        // '1' is always correct here since if we get to this point, this
+        // always corresponds to the first (index '0') match discovered at
+        // this position. So the next match to report at this position (if
+        // it exists) is at index '1'.
+        state.next_match_index = Some(1);
+    }
+    cache.search_finish(input.end());
+    result
+}
+
+#[inline(never)]
+pub(crate) fn find_overlapping_rev<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+) -> Result<(), MatchError> {
+    state.mat = None;
+    if input.is_done() {
+        return Ok(());
+    }
+    let mut sid = match state.id {
+        None => {
+            input.move_to(input.end());
+            let sid = init_rev(dfa, cache, input)?;
+            state.id = Some(sid);
+            if input.start() == input.end() {
+                state.rev_eoi = true;
+            } else {
+                state.at = input.end() - 1;
+            }
+            sid
+        }
+        Some(sid) => {
+            if let Some(match_index) = state.next_match_index {
+                let match_len = dfa.match_len(cache, sid);
+                if match_index < match_len {
+                    state.next_match_index = Some(match_index + 1);
+                    let pattern = dfa.match_pattern(cache, sid, match_index);
+                    state.mat = Some(HalfMatch::new(pattern, state.at));
+                    return Ok(());
+                }
+            }
+            // Once we've reported all matches at a given position, we need
+            // to advance the search to the next position. However, if we've
+            // already followed the EOI transition, then we know we're done
+            // with the search and there cannot be any more matches to report.
+            if state.rev_eoi {
+                return Ok(());
+            } else if state.at == input.start() {
+                // At this point, we should follow the EOI transition. This
+                // will cause us the skip the main loop below and fall through
+                // to the final 'eoi_rev' transition.
+                state.rev_eoi = true;
+            } else {
+                // We haven't hit the end of the search yet, so move on.
+                state.at -= 1;
+            }
+            sid
+        }
+    };
+    if !state.rev_eoi {
+        input.move_to(state.at);
+    }
+    cache.search_start(state.at);
+    while !state.rev_eoi {
+        sid = dfa
+            .next_state(cache, sid, input.chunk()[input.chunk_pos])
+            .map_err(|_| gave_up(state.at))?;
+        if sid.is_tagged() {
+            state.id = Some(sid);
+            if sid.is_start() {
+                // do nothing
+            } else if sid.is_match() {
+                state.next_match_index = Some(1);
+                let pattern = dfa.match_pattern(cache, sid, 0);
+                state.mat = Some(HalfMatch::new(pattern, state.at + 1));
+                cache.search_finish(state.at);
+                return Ok(());
+            } else if sid.is_dead() {
+                cache.search_finish(state.at);
+                return Ok(());
+            } else if sid.is_quit() {
+                cache.search_finish(state.at);
+                return Err(MatchError::quit(input.chunk()[input.chunk_pos], state.at));
+            } else {
+                debug_assert!(sid.is_unknown());
+                unreachable!("sid being unknown is a bug");
+            }
+        }
+        if state.at == input.start() {
+            break;
+        }
+        state.at -= 1;
+        while input.chunk_pos == 0 {
+            input.backtrack();
+        }
+        input.chunk_pos -= 1;
+        cache.search_update(state.at);
+    }
+
+    let result = eoi_rev(dfa, cache, input, &mut sid, &mut state.mat);
+    state.rev_eoi = true;
+    state.id = Some(sid);
+    if state.mat.is_some() {
+        // '1' is always correct here since if we get to this point, this
+        // always corresponds to the first (index '0') match discovered at
+        // this position. So the next match to report at this position (if
+        // it exists) is at index '1'.
+        state.next_match_index = Some(1);
+    }
+    cache.search_finish(input.start());
+    result
+}
+
+/// Runs the given overlapping `search` function (forwards or backwards) until
+/// a match is found whose offset does not split a codepoint.
+///
+/// This is *not* always correct to call. It should only be called when the
+/// underlying NFA has UTF-8 mode enabled *and* it can produce zero-width
+/// matches. Calling this when both of those things aren't true might result
+/// in legitimate matches getting skipped.
+#[cold]
+#[inline(never)]
+fn skip_empty_utf8_splits_overlapping<C: Cursor, F>(
+    input: &mut Input<C>,
+    state: &mut OverlappingState,
+    mut search: F,
+) -> Result<(), MatchError>
+where
+    F: FnMut(&mut Input<C>, &mut OverlappingState) -> Result<(), MatchError>,
+{
+    // Note that this routine works for forwards and reverse searches
+    // even though there's no code here to handle those cases. That's
+    // because overlapping searches drive themselves to completion via
+    // `OverlappingState`. So all we have to do is push it until no matches are
+    // found.
+
+    let mut hm = match state.get_match() {
+        None => return Ok(()),
+        Some(hm) => hm,
+    };
+    if input.get_anchored().is_anchored() {
+        input.move_to(hm.offset());
+        if !input.is_char_boundary() {
+            state.mat = None;
+        }
+        return Ok(());
+    }
+    loop {
+        input.move_to(hm.offset());
+        if input.is_char_boundary() {
+            return Ok(());
+        }
+        search(input, state)?;
+        hm = match state.get_match() {
+            None => return Ok(()),
+            Some(hm) => hm,
+        };
+    }
+}
+
 #[cfg_attr(feature = "perf-inline", inline(always))]
 fn init_fwd<C: Cursor>(
     dfa: &DFA,
@@ -987,3 +1444,78 @@ fn prefilter_restart<C: Cursor>(
 ) -> Result<LazyStateID, MatchError> {
     init_fwd(dfa, cache, input)
 }
+
+/// Represents the current state of an overlapping search.
+///
+/// This is used for overlapping searches since they need to know something
+/// about the previous search. For example, when multiple patterns match at the
+/// same position, this state tracks the last reported pattern so that the next
+/// search knows whether to report another matching pattern or continue with
+/// the search at the next position. Additionally, it also tracks which state
+/// the last search call terminated in.
+///
+/// This type provides little introspection capabilities. The only thing a
+/// caller can do is construct it and pass it around to permit search routines
+/// to use it to track state, and also ask whether a match has been found.
+///
+/// Callers should always provide a fresh state constructed via
+/// [`OverlappingState::start`] when starting a new search. Reusing state from
+/// a previous search may result in incorrect results.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct OverlappingState {
+    /// The match reported by the most recent overlapping search to use this
+    /// state.
+    ///
+    /// If a search does not find any matches, then it is expected to clear
+    /// this value.
+    mat: Option<HalfMatch>,
+    /// The state ID of the state at which the search was in when the call
+    /// terminated. When this is a match state, `last_match` must be set to a
+    /// non-None value.
+    ///
+    /// A `None` value indicates the start state of the corresponding
+    /// automaton. We cannot use the actual ID, since any one automaton may
+    /// have many start states, and which one is in use depends on several
+    /// search-time factors.
+    id: Option<LazyStateID>,
+    /// The position of the search.
+    ///
+    /// When `id` is None (i.e., we are starting a search), this is set to
+    /// the beginning of the search as given by the caller regardless of its
+    /// current value. Subsequent calls to an overlapping search pick up at
+    /// this offset.
+    at: usize,
+    /// The index into the matching patterns of the next match to report if the
+    /// current state is a match state. Note that this may be 1 greater than
+    /// the total number of matches to report for the current match state. (In
+    /// which case, no more matches should be reported at the current position
+    /// and the search should advance to the next position.)
+    next_match_index: Option<usize>,
+    /// This is set to true when a reverse overlapping search has entered its
+    /// EOI transitions.
+    ///
+    /// This isn't used in a forward search because it knows to stop once the
+    /// position exceeds the end of the search range. In a reverse search,
+    /// since we use unsigned offsets, we don't "know" once we've gone past
+    /// `0`. So the only way to detect it is with this extra flag. The reverse
+    /// overlapping search knows to terminate specifically after it has
+    /// reported all matches after following the EOI transition.
+    rev_eoi: bool,
+}
+
+impl OverlappingState {
+    /// Create a new overlapping state that begins at the start state of any
+    /// automaton.
+    pub fn start() -> OverlappingState {
+        OverlappingState { mat: None, id: None, at: 0, next_match_index: None, rev_eoi: false }
+    }
+
+    /// Return the match result of the most recent search to execute with this
+    /// state.
+    ///
+    /// A searches will clear this result automatically, such that if no
+    /// match is found, this will correctly report `None`.
+    pub fn get_match(&self) -> Option<HalfMatch> {
+        self.mat
+    }
+}
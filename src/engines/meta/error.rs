@@ -1,3 +1,5 @@
+use std::io;
+
 use regex_automata::{nfa, MatchError, MatchErrorKind, PatternID};
 use regex_syntax::{ast, hir};
 
@@ -112,6 +114,151 @@ impl core::fmt::Display for BuildError {
     }
 }
 
+/// An error that occurs when the cursor of a fallible search (like
+/// [`Regex::try_search`](crate::engines::meta::Regex::try_search)) fails.
+///
+/// Unlike the errors of the regex engines, which are handled by retrying the
+/// search with a different engine, a failing cursor ends the search. There
+/// are two ways a cursor can fail:
+///
+/// * Reading the haystack failed, for example, because a
+///   [`ReaderCursor`](crate::ReaderCursor) got an I/O error. The underlying
+///   error is available with [`CursorError::io_error`] (see
+///   [`Cursor::take_error`](crate::Cursor::take_error)).
+/// * The cursor could not move back to data that the search needed again,
+///   for example, because it was dropped from the window of a
+///   [`ReaderCursor`](crate::ReaderCursor). The offset of the chunk the
+///   cursor could not backtrack from is available with
+///   [`CursorError::backtrack_offset`]. The cursor may explain why with an
+///   I/O error, as a `ReaderCursor` does.
+#[derive(Debug)]
+pub struct CursorError {
+    io: Option<io::Error>,
+    backtrack_offset: Option<usize>,
+}
+
+impl CursorError {
+    /// Returns the I/O error that made the cursor fail, if any.
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.io.as_ref()
+    }
+
+    /// If the cursor failed to backtrack, returns the offset of the chunk it
+    /// could not backtrack from. A cursor may also report why it failed with
+    /// an I/O error.
+    pub fn backtrack_offset(&self) -> Option<usize> {
+        self.backtrack_offset
+    }
+
+    /// Creates the error of a search whose cursor returned `err` from
+    /// [`Cursor::take_error`](crate::Cursor::take_error) or failed to
+    /// backtrack at `backtrack_offset`.
+    pub(crate) fn new(
+        err: Option<io::Error>,
+        backtrack_offset: Option<usize>,
+    ) -> Option<CursorError> {
+        if err.is_none() && backtrack_offset.is_none() {
+            return None;
+        }
+        Some(CursorError { io: err, backtrack_offset })
+    }
+}
+
+impl std::error::Error for CursorError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.io.as_ref().map(|err| err as &(dyn std::error::Error + 'static))
+    }
+}
+
+impl core::fmt::Display for CursorError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        // An I/O error takes precedence, since it is usually why
+        // backtracking failed.
+        match (&self.io, self.backtrack_offset) {
+            (Some(_), _) => write!(f, "cursor failed to read the haystack"),
+            (None, Some(offset)) => write!(f, "cursor failed to backtrack from offset {}", offset),
+            (None, None) => unreachable!(),
+        }
+    }
+}
+
+impl From<CursorError> for io::Error {
+    fn from(err: CursorError) -> io::Error {
+        match err.io {
+            Some(io) => io,
+            None => io::Error::new(io::ErrorKind::Other, err),
+        }
+    }
+}
+
+/// An error that occurs when a search should be retried.
+///
+/// This retry error distinguishes between two different failure modes.
+///
+/// The first is one where potential quadratic behavior has been detected.
+/// In this case, whatever optimization that led to this behavior should be
+/// stopped, and the next best strategy should be used.
+///
+/// The second indicates that the underlying regex engine has failed for some
+/// reason. This usually occurs because either a lazy DFA's cache has become
+/// ineffective or because a non-ASCII byte has been seen *and* a Unicode word
+/// boundary was used in one of the patterns. In this failure case, a different
+/// regex engine that won't fail in these ways (PikeVM, backtracker or the
+/// one-pass DFA) should be used.
+///
+/// This is an internal error only and should never bleed into the public
+/// API.
+#[derive(Debug)]
+pub(crate) enum RetryError {
+    Quadratic(RetryQuadraticError),
+    Fail(RetryFailError),
+}
+
+impl std::error::Error for RetryError {}
+
+impl core::fmt::Display for RetryError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match *self {
+            RetryError::Quadratic(ref err) => err.fmt(f),
+            RetryError::Fail(ref err) => err.fmt(f),
+        }
+    }
+}
+
+impl From<MatchError> for RetryError {
+    fn from(merr: MatchError) -> RetryError {
+        RetryError::Fail(RetryFailError::from(merr))
+    }
+}
+
+/// An error that occurs when potential quadratic behavior has been detected
+/// when applying either the "reverse suffix" or "reverse inner" optimizations.
+///
+/// When this error occurs, callers should abandon the "reverse" optimization
+/// and use a normal forward search.
+#[derive(Debug)]
+pub(crate) struct RetryQuadraticError(());
+
+impl RetryQuadraticError {
+    pub(crate) fn new() -> RetryQuadraticError {
+        RetryQuadraticError(())
+    }
+}
+
+impl std::error::Error for RetryQuadraticError {}
+
+impl core::fmt::Display for RetryQuadraticError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        write!(f, "regex engine gave up to avoid quadratic behavior")
+    }
+}
+
+impl From<RetryQuadraticError> for RetryError {
+    fn from(err: RetryQuadraticError) -> RetryError {
+        RetryError::Quadratic(err)
+    }
+}
+
 /// An error that occurs when a regex engine "gives up" for some reason before
 /// finishing a search. Usually this occurs because of heuristic Unicode word
 /// boundary support or because of ineffective cache usage in the lazy DFA.
@@ -160,3 +307,9 @@ impl From<MatchError> for RetryFailError {
         }
     }
 }
+
+impl From<RetryFailError> for RetryError {
+    fn from(err: RetryFailError) -> RetryError {
+        RetryError::Fail(err)
+    }
+}
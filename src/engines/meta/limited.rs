@@ -0,0 +1,298 @@
+/*!
+This module defines two bespoke reverse DFA searching routines. (One for the
+lazy DFA and one for the fully compiled DFA.) These routines differ from the
+usual ones by permitting the caller to specify a minimum starting position.
+That is, the search will begin at `input.end()` and will usually stop at
+`input.start()`, unless `min_start > input.start()`, in which case, the search
+will stop at `min_start`.
+
+In other words, this lets you say, "no, the search must not extend past this
+point, even if it's within the bounds of the given `Input`." And if the search
+*does* want to go past that point, it stops and returns a "may be quadratic"
+error, which indicates that the caller should retry using some other technique.
+
+These routines specifically exist to protect against quadratic behavior when
+employing the "reverse suffix" and "reverse inner" optimizations. Without the
+backstop these routines provide, it is possible for parts of the haystack to
+get re-scanned over and over again. The backstop not only prevents this, but
+*tells you when it is happening* so that you can change the strategy.
+
+Why can't we just use the normal search routines? We could use the normal
+search routines and just set the start bound on the provided `Input` to our
+`min_start` position. The problem here is that it's impossible to distinguish
+between "no match because we reached the end of input" and "determined there
+was no match well before the end of input." The former case is what we care
+about with respect to quadratic behavior. The latter case is totally fine.
+
+Unlike upstream, the haystack is walked through the cursor one byte at a time
+(backtracking into the previous chunk whenever the current one is exhausted)
+instead of indexing into a contiguous slice. The reverse scans started from a
+literal candidate are usually short, so this doesn't bother with the unrolled
+loop of the general purpose reverse search.
+*/
+
+use log::trace;
+use regex_automata::dfa::{Automaton, StartError};
+use regex_automata::hybrid::dfa::{Cache, DFA};
+use regex_automata::hybrid::{LazyStateID, StartError as LazyStartError};
+use regex_automata::util::primitives::StateID;
+use regex_automata::util::start;
+use regex_automata::{HalfMatch, MatchError};
+
+use crate::cursor::Cursor;
+use crate::engines::meta::error::{RetryError, RetryQuadraticError};
+use crate::Input;
+
+pub(crate) fn dfa_try_search_half_rev<A: Automaton + ?Sized, C: Cursor>(
+    dfa: &A,
+    input: &mut Input<C>,
+    min_start: usize,
+) -> Result<Option<HalfMatch>, RetryError> {
+    let mut mat = None;
+    input.move_to(input.end());
+    let mut sid = dfa_init_rev(dfa, input)?;
+    if input.start() == input.end() {
+        dfa_eoi_rev(dfa, input, &mut sid, &mut mat)?;
+        return Ok(mat);
+    }
+    step_back(input);
+    loop {
+        let byte = input.chunk()[input.chunk_pos];
+        sid = dfa.next_state(sid, byte);
+        if dfa.is_special_state(sid) {
+            if dfa.is_match_state(sid) {
+                let pattern = dfa.match_pattern(sid, 0);
+                // Since reverse searches report the beginning of a
+                // match and the beginning is inclusive (not exclusive
+                // like the end of a match), we add 1 to make it
+                // inclusive.
+                mat = Some(HalfMatch::new(pattern, input.at() + 1));
+            } else if dfa.is_dead_state(sid) {
+                return Ok(mat);
+            } else if dfa.is_quit_state(sid) {
+                return Err(MatchError::quit(byte, input.at()).into());
+            }
+        }
+        if input.at() == input.start() {
+            break;
+        }
+        step_back(input);
+        if input.at() < min_start {
+            trace!(
+                "reached position {} which is before the previous literal \
+				 match, quitting to avoid quadratic behavior",
+                input.at(),
+            );
+            return Err(RetryError::Quadratic(RetryQuadraticError::new()));
+        }
+    }
+    let was_dead = dfa.is_dead_state(sid);
+    dfa_eoi_rev(dfa, input, &mut sid, &mut mat)?;
+    // If we reach the beginning of the search and we could otherwise still
+    // potentially keep matching if there was more to match, then we actually
+    // return an error to indicate giving up on this optimization. Why? Because
+    // we can't prove that the real match begins at where we would report it.
+    //
+    // This only happens when all of the following are true:
+    //
+    // 1) We reach the starting point of our search span.
+    // 2) The match we found is before the starting point.
+    // 3) The FSM reports we could possibly find a longer match.
+    //
+    // We need (1) because otherwise the search stopped before the starting
+    // point and there is no possible way to find a more leftmost position.
+    //
+    // We need (2) because if the match found has an offset equal to the minimum
+    // possible offset, then there is no possible more leftmost match.
+    //
+    // We need (3) because if the FSM couldn't continue anyway (i.e., it's in
+    // a dead state), then we know we couldn't find anything more leftmost
+    // than what we have. (We have to check the state we were in prior to the
+    // EOI transition since the EOI transition will usually bring us to a dead
+    // state by virtue of it represents the end-of-input.)
+    if mat.map_or(false, |m| m.offset() > input.start()) && !was_dead {
+        trace!(
+            "reached beginning of search at offset {} without hitting \
+             a dead state, quitting to avoid potential false positive match",
+            input.start(),
+        );
+        return Err(RetryError::Quadratic(RetryQuadraticError::new()));
+    }
+    Ok(mat)
+}
+
+pub(crate) fn hybrid_try_search_half_rev<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    min_start: usize,
+) -> Result<Option<HalfMatch>, RetryError> {
+    let mut mat = None;
+    input.move_to(input.end());
+    let mut sid = hybrid_init_rev(dfa, cache, input)?;
+    if input.start() == input.end() {
+        hybrid_eoi_rev(dfa, cache, input, &mut sid, &mut mat)?;
+        return Ok(mat);
+    }
+    step_back(input);
+    loop {
+        let byte = input.chunk()[input.chunk_pos];
+        sid = dfa.next_state(cache, sid, byte).map_err(|_| MatchError::gave_up(input.at()))?;
+        if sid.is_tagged() {
+            if sid.is_match() {
+                let pattern = dfa.match_pattern(cache, sid, 0);
+                // Since reverse searches report the beginning of a
+                // match and the beginning is inclusive (not exclusive
+                // like the end of a match), we add 1 to make it
+                // inclusive.
+                mat = Some(HalfMatch::new(pattern, input.at() + 1));
+            } else if sid.is_dead() {
+                return Ok(mat);
+            } else if sid.is_quit() {
+                return Err(MatchError::quit(byte, input.at()).into());
+            }
+        }
+        if input.at() == input.start() {
+            break;
+        }
+        step_back(input);
+        if input.at() < min_start {
+            trace!(
+                "reached position {} which is before the previous literal \
+				 match, quitting to avoid quadratic behavior",
+                input.at(),
+            );
+            return Err(RetryError::Quadratic(RetryQuadraticError::new()));
+        }
+    }
+    let was_dead = sid.is_dead();
+    hybrid_eoi_rev(dfa, cache, input, &mut sid, &mut mat)?;
+    // See the comments in the full DFA routine above for why we need this.
+    if mat.map_or(false, |m| m.offset() > input.start()) && !was_dead {
+        trace!(
+            "reached beginning of search at offset {} without hitting \
+             a dead state, quitting to avoid potential false positive match",
+            input.start(),
+        );
+        return Err(RetryError::Quadratic(RetryQuadraticError::new()));
+    }
+    Ok(mat)
+}
+
+/// Moves the cursor one byte backwards, backtracking into the previous chunk
+/// if the current one has been exhausted. Callers must ensure that there is
+/// a byte before the current position.
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn step_back<C: Cursor>(input: &mut Input<C>) {
+    while input.chunk_pos == 0 {
+        let backtracked = input.backtrack();
+        assert!(backtracked, "reverse search moved past the start of the haystack");
+    }
+    input.chunk_pos -= 1;
+}
+
+/// Returns the byte immediately following the end of the search, which acts
+/// as the look-behind of a reverse search. The cursor must be positioned at
+/// `input.end()`.
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn look_ahead<C: Cursor>(input: &mut Input<C>) -> Option<u8> {
+    let mut look_ahead = input.chunk().get(input.chunk_pos()).copied();
+    if look_ahead.is_none() && input.advance() {
+        look_ahead = input.chunk().first().copied();
+        input.backtrack();
+    }
+    look_ahead
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn dfa_init_rev<A: Automaton + ?Sized, C: Cursor>(
+    dfa: &A,
+    input: &mut Input<C>,
+) -> Result<StateID, MatchError> {
+    let look_ahead = look_ahead(input);
+    let start_config = start::Config::new().look_behind(look_ahead).anchored(input.get_anchored());
+    dfa.start_state(&start_config).map_err(|err| match err {
+        StartError::Quit { byte } => MatchError::quit(byte, input.end()),
+        StartError::UnsupportedAnchored { mode } => MatchError::unsupported_anchored(mode),
+        _ => panic!("damm forward compatability"),
+    })
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn hybrid_init_rev<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+) -> Result<LazyStateID, MatchError> {
+    let look_ahead = look_ahead(input);
+    let start_config = start::Config::new().look_behind(look_ahead).anchored(input.get_anchored());
+    dfa.start_state(cache, &start_config).map_err(|err| match err {
+        LazyStartError::Quit { byte } => MatchError::quit(byte, input.end()),
+        LazyStartError::UnsupportedAnchored { mode } => MatchError::unsupported_anchored(mode),
+        LazyStartError::Cache { .. } => MatchError::gave_up(input.end()),
+        _ => panic!("damm forward compatability"),
+    })
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn dfa_eoi_rev<A: Automaton + ?Sized, C: Cursor>(
+    dfa: &A,
+    input: &mut Input<C>,
+    sid: &mut StateID,
+    mat: &mut Option<HalfMatch>,
+) -> Result<(), MatchError> {
+    let sp = input.get_span();
+    if sp.start > 0 {
+        input.move_to(sp.start - 1);
+        let byte = input.chunk()[sp.start - input.chunk_offset() - 1];
+        *sid = dfa.next_state(*sid, byte);
+        if dfa.is_match_state(*sid) {
+            let pattern = dfa.match_pattern(*sid, 0);
+            *mat = Some(HalfMatch::new(pattern, sp.start));
+        } else if dfa.is_quit_state(*sid) {
+            return Err(MatchError::quit(byte, sp.start - 1));
+        }
+    } else {
+        *sid = dfa.next_eoi_state(*sid);
+        if dfa.is_match_state(*sid) {
+            let pattern = dfa.match_pattern(*sid, 0);
+            *mat = Some(HalfMatch::new(pattern, 0));
+        }
+        // N.B. We don't have to check 'is_quit' here because the EOI
+        // transition can never lead to a quit state.
+        debug_assert!(!dfa.is_quit_state(*sid));
+    }
+    Ok(())
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn hybrid_eoi_rev<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    sid: &mut LazyStateID,
+    mat: &mut Option<HalfMatch>,
+) -> Result<(), MatchError> {
+    let sp = input.get_span();
+    if sp.start > 0 {
+        input.move_to(sp.start - 1);
+        let byte = input.chunk()[sp.start - input.chunk_offset() - 1];
+        *sid = dfa.next_state(cache, *sid, byte).map_err(|_| MatchError::gave_up(sp.start))?;
+        if sid.is_match() {
+            let pattern = dfa.match_pattern(cache, *sid, 0);
+            *mat = Some(HalfMatch::new(pattern, sp.start));
+        } else if sid.is_quit() {
+            return Err(MatchError::quit(byte, sp.start - 1));
+        }
+    } else {
+        *sid = dfa.next_eoi_state(cache, *sid).map_err(|_| MatchError::gave_up(sp.start))?;
+        if sid.is_match() {
+            let pattern = dfa.match_pattern(cache, *sid, 0);
+            *mat = Some(HalfMatch::new(pattern, 0));
+        }
+        // N.B. We don't have to check 'is_quit' here because the EOI
+        // transition can never lead to a quit state.
+        debug_assert!(!sid.is_quit());
+    }
+    Ok(())
+}
@@ -42,14 +42,19 @@ level routines such as [`Regex::search_with`].
 
 */
 
-pub use self::regex::{Builder, Cache, CapturesMatches, Config, FindMatches, Regex, Split, SplitN};
+pub use self::error::CursorError;
+pub use self::regex::{
+    AsyncFindMatches, Builder, Cache, CapturesMatches, Config, FindMatches, FindOverlappingMatches,
+    RFindMatches, Regex, Split, SplitN, TryFindMatches,
+};
 pub use regex_automata::meta::BuildError;
 
 mod error;
-// mod limited;
+mod limited;
 mod literal;
+mod poll;
 mod regex;
-// mod reverse_inner;
-// mod stopat;
+mod reverse_inner;
+mod stopat;
 mod strategy;
 mod wrappers;
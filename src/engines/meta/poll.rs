@@ -0,0 +1,460 @@
+/*!
+This module defines the search routines used by the async searches of the meta
+regex. Each one works on an [`AsyncCursor`] and keeps all of its progress in a
+state value, so that it can return `Poll::Pending` whenever the cursor has to
+wait for a chunk and pick up where it left off when it's polled again.
+
+There are forward and reverse routines for the lazy DFA and for the fully
+compiled DFA, which the meta regex uses to find the end and then the start of a
+match, just like its regular searches do. When neither DFA is available (or
+when one of them fails), the PikeVM is run one chunk at a time instead. The
+prefilter strategy, which has no regex engine at all, gets its own literal
+scan.
+
+None of these routines support anchored searches or look at the span of an
+`Input`: a search always runs from its starting position to the end of the
+haystack.
+*/
+
+use core::task::{ready, Context, Poll};
+
+use regex_automata::dfa::{Automaton, StartError};
+use regex_automata::hybrid::dfa::{Cache, DFA};
+use regex_automata::hybrid::{LazyStateID, StartError as LazyStartError};
+use regex_automata::nfa::thompson::{pikevm::PikeVM, NFA};
+use regex_automata::util::prefilter::Prefilter;
+use regex_automata::util::primitives::{NonMaxUsize, StateID};
+use regex_automata::util::start;
+use regex_automata::{Anchored, HalfMatch, Match, MatchError, Span};
+
+use crate::cursor::AsyncCursor;
+use crate::engines::meta::error::RetryFailError;
+use crate::engines::pikevm::{self, ChunkSearch};
+use crate::util::utf8;
+
+/// The number of bytes before the start of a search that the PikeVM needs to
+/// evaluate look-behind assertions.
+const LOOK_BEHIND_LEN: usize = 4;
+
+/// The progress of a forward or reverse DFA search.
+#[derive(Clone, Debug)]
+pub(crate) struct DfaPoll<S> {
+    /// The current state, or `None` if the start state hasn't been computed
+    /// yet.
+    sid: Option<S>,
+    /// The position of the next byte to search (for forward searches) or one
+    /// past it (for reverse searches).
+    at: usize,
+    mat: Option<HalfMatch>,
+}
+
+impl<S> DfaPoll<S> {
+    /// Creates the state of a search starting at `at`. For reverse searches
+    /// this is the end of the searched span.
+    pub(crate) fn new(at: usize) -> DfaPoll<S> {
+        DfaPoll { sid: None, at, mat: None }
+    }
+}
+
+/// The progress of a PikeVM search.
+#[derive(Clone, Debug)]
+pub(crate) struct PikeVMPoll {
+    at: usize,
+    earliest: bool,
+    /// The bytes before the start of the search collected so far. Once all
+    /// of them are available, they're moved into `search`.
+    look_behind: Vec<u8>,
+    search: Option<ChunkSearch>,
+    slots: Vec<Option<NonMaxUsize>>,
+}
+
+impl PikeVMPoll {
+    pub(crate) fn new(nfa: &NFA, start: usize, earliest: bool) -> PikeVMPoll {
+        let slots = vec![None; nfa.group_info().implicit_slot_len()];
+        PikeVMPoll { at: start, earliest, look_behind: Vec::new(), search: None, slots }
+    }
+}
+
+/// The progress of a prefilter scan.
+#[derive(Clone, Debug)]
+pub(crate) struct PrePoll {
+    at: usize,
+    /// The bytes right before `at` that may still contain the start of a
+    /// match, because not enough bytes following them have been seen to tell.
+    window: Vec<u8>,
+}
+
+impl PrePoll {
+    pub(crate) fn new(start: usize) -> PrePoll {
+        PrePoll { at: start, window: Vec::new() }
+    }
+}
+
+/// Moves the cursor to the chunk containing `at`. Returns false if `at` is the
+/// end of the haystack.
+pub(crate) fn poll_seek<C: AsyncCursor>(
+    cursor: &mut C,
+    cx: &mut Context<'_>,
+    at: usize,
+) -> Poll<bool> {
+    while at < cursor.offset() {
+        let moved = ready!(cursor.poll_backtrack(cx));
+        assert!(moved, "cursor failed to backtrack to offset {at}");
+    }
+    while at >= cursor.offset() + cursor.chunk().len() {
+        if !ready!(cursor.poll_advance(cx)) {
+            debug_assert_eq!(at, cursor.offset() + cursor.chunk().len());
+            return Poll::Ready(false);
+        }
+    }
+    Poll::Ready(true)
+}
+
+/// Returns the byte at `at`, or `None` if `at` is the end of the haystack.
+fn poll_byte<C: AsyncCursor>(cursor: &mut C, cx: &mut Context<'_>, at: usize) -> Poll<Option<u8>> {
+    if !ready!(poll_seek(cursor, cx, at)) {
+        return Poll::Ready(None);
+    }
+    Poll::Ready(Some(cursor.chunk()[at - cursor.offset()]))
+}
+
+/// Returns true if `at` is a UTF-8 codepoint boundary (see
+/// `utf8::is_boundary`).
+pub(crate) fn poll_is_boundary<C: AsyncCursor>(
+    cursor: &mut C,
+    cx: &mut Context<'_>,
+    at: usize,
+) -> Poll<bool> {
+    match ready!(poll_byte(cursor, cx, at)) {
+        Some(byte) => Poll::Ready(utf8::is_boundary(&[byte], 0)),
+        None => Poll::Ready(true),
+    }
+}
+
+pub(crate) fn dfa_poll_search_half_fwd<A: Automaton + ?Sized, C: AsyncCursor>(
+    dfa: &A,
+    state: &mut DfaPoll<StateID>,
+    earliest: bool,
+    cursor: &mut C,
+    cx: &mut Context<'_>,
+) -> Poll<Result<Option<HalfMatch>, RetryFailError>> {
+    let mut sid = match state.sid {
+        Some(sid) => sid,
+        None => {
+            let look_behind = match state.at.checked_sub(1) {
+                Some(at) => ready!(poll_byte(cursor, cx, at)),
+                None => None,
+            };
+            let start_config = start::Config::new().look_behind(look_behind).anchored(Anchored::No);
+            let sid = dfa.start_state(&start_config).map_err(|err| match err {
+                StartError::Quit { byte } => MatchError::quit(byte, state.at - 1),
+                _ => panic!("damm forward compatability"),
+            })?;
+            *state.sid.insert(sid)
+        }
+    };
+    while ready!(poll_seek(cursor, cx, state.at)) {
+        let (chunk, offset) = (cursor.chunk(), cursor.offset());
+        for (at, &byte) in (offset..).zip(chunk).skip(state.at - offset) {
+            sid = dfa.next_state(sid, byte);
+            if dfa.is_special_state(sid) {
+                if dfa.is_match_state(sid) {
+                    let pattern = dfa.match_pattern(sid, 0);
+                    state.mat = Some(HalfMatch::new(pattern, at));
+                    if earliest {
+                        return Poll::Ready(Ok(state.mat));
+                    }
+                } else if dfa.is_dead_state(sid) {
+                    return Poll::Ready(Ok(state.mat));
+                } else if dfa.is_quit_state(sid) {
+                    return Poll::Ready(Err(MatchError::quit(byte, at).into()));
+                }
+            }
+        }
+        state.at = offset + chunk.len();
+        state.sid = Some(sid);
+    }
+    sid = dfa.next_eoi_state(sid);
+    if dfa.is_match_state(sid) {
+        let pattern = dfa.match_pattern(sid, 0);
+        state.mat = Some(HalfMatch::new(pattern, state.at));
+    }
+    Poll::Ready(Ok(state.mat))
+}
+
+pub(crate) fn hybrid_poll_search_half_fwd<C: AsyncCursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    state: &mut DfaPoll<LazyStateID>,
+    earliest: bool,
+    cursor: &mut C,
+    cx: &mut Context<'_>,
+) -> Poll<Result<Option<HalfMatch>, RetryFailError>> {
+    let mut sid = match state.sid {
+        Some(sid) => sid,
+        None => {
+            let look_behind = match state.at.checked_sub(1) {
+                Some(at) => ready!(poll_byte(cursor, cx, at)),
+                None => None,
+            };
+            let start_config = start::Config::new().look_behind(look_behind).anchored(Anchored::No);
+            let sid = dfa.start_state(cache, &start_config).map_err(|err| match err {
+                LazyStartError::Quit { byte } => MatchError::quit(byte, state.at - 1),
+                LazyStartError::Cache { .. } => MatchError::gave_up(state.at),
+                _ => panic!("damm forward compatability"),
+            })?;
+            *state.sid.insert(sid)
+        }
+    };
+    while ready!(poll_seek(cursor, cx, state.at)) {
+        let (chunk, offset) = (cursor.chunk(), cursor.offset());
+        for (at, &byte) in (offset..).zip(chunk).skip(state.at - offset) {
+            sid = dfa.next_state(cache, sid, byte).map_err(|_| MatchError::gave_up(at))?;
+            if sid.is_tagged() {
+                if sid.is_match() {
+                    let pattern = dfa.match_pattern(cache, sid, 0);
+                    state.mat = Some(HalfMatch::new(pattern, at));
+                    if earliest {
+                        return Poll::Ready(Ok(state.mat));
+                    }
+                } else if sid.is_dead() {
+                    return Poll::Ready(Ok(state.mat));
+                } else if sid.is_quit() {
+                    return Poll::Ready(Err(MatchError::quit(byte, at).into()));
+                }
+            }
+        }
+        state.at = offset + chunk.len();
+        state.sid = Some(sid);
+    }
+    sid = dfa.next_eoi_state(cache, sid).map_err(|_| MatchError::gave_up(state.at))?;
+    if sid.is_match() {
+        let pattern = dfa.match_pattern(cache, sid, 0);
+        state.mat = Some(HalfMatch::new(pattern, state.at));
+    }
+    Poll::Ready(Ok(state.mat))
+}
+
+/// Runs an anchored reverse search from the position the state was created
+/// with back to `start`.
+pub(crate) fn dfa_poll_search_half_rev<A: Automaton + ?Sized, C: AsyncCursor>(
+    dfa: &A,
+    state: &mut DfaPoll<StateID>,
+    start: usize,
+    cursor: &mut C,
+    cx: &mut Context<'_>,
+) -> Poll<Result<Option<HalfMatch>, RetryFailError>> {
+    let mut sid = match state.sid {
+        Some(sid) => sid,
+        None => {
+            let look_ahead = ready!(poll_byte(cursor, cx, state.at));
+            let start_config = start::Config::new().look_behind(look_ahead).anchored(Anchored::Yes);
+            let sid = dfa.start_state(&start_config).map_err(|err| match err {
+                StartError::Quit { byte } => MatchError::quit(byte, state.at),
+                _ => panic!("damm forward compatability"),
+            })?;
+            *state.sid.insert(sid)
+        }
+    };
+    while state.at > start {
+        ready!(poll_seek(cursor, cx, state.at - 1));
+        let (chunk, offset) = (cursor.chunk(), cursor.offset());
+        let lo = offset.max(start);
+        for at in (lo..state.at).rev() {
+            let byte = chunk[at - offset];
+            sid = dfa.next_state(sid, byte);
+            if dfa.is_special_state(sid) {
+                if dfa.is_match_state(sid) {
+                    let pattern = dfa.match_pattern(sid, 0);
+                    state.mat = Some(HalfMatch::new(pattern, at + 1));
+                } else if dfa.is_dead_state(sid) {
+                    return Poll::Ready(Ok(state.mat));
+                } else if dfa.is_quit_state(sid) {
+                    return Poll::Ready(Err(MatchError::quit(byte, at).into()));
+                }
+            }
+        }
+        state.at = lo;
+        state.sid = Some(sid);
+    }
+    match start.checked_sub(1) {
+        Some(at) => {
+            let byte = ready!(poll_byte(cursor, cx, at)).unwrap();
+            sid = dfa.next_state(sid, byte);
+            if dfa.is_match_state(sid) {
+                let pattern = dfa.match_pattern(sid, 0);
+                state.mat = Some(HalfMatch::new(pattern, start));
+            } else if dfa.is_quit_state(sid) {
+                return Poll::Ready(Err(MatchError::quit(byte, at).into()));
+            }
+        }
+        None => {
+            sid = dfa.next_eoi_state(sid);
+            if dfa.is_match_state(sid) {
+                let pattern = dfa.match_pattern(sid, 0);
+                state.mat = Some(HalfMatch::new(pattern, start));
+            }
+        }
+    }
+    Poll::Ready(Ok(state.mat))
+}
+
+/// Runs an anchored reverse search from the position the state was created
+/// with back to `start`.
+pub(crate) fn hybrid_poll_search_half_rev<C: AsyncCursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    state: &mut DfaPoll<LazyStateID>,
+    start: usize,
+    cursor: &mut C,
+    cx: &mut Context<'_>,
+) -> Poll<Result<Option<HalfMatch>, RetryFailError>> {
+    let mut sid = match state.sid {
+        Some(sid) => sid,
+        None => {
+            let look_ahead = ready!(poll_byte(cursor, cx, state.at));
+            let start_config = start::Config::new().look_behind(look_ahead).anchored(Anchored::Yes);
+            let sid = dfa.start_state(cache, &start_config).map_err(|err| match err {
+                LazyStartError::Quit { byte } => MatchError::quit(byte, state.at),
+                LazyStartError::Cache { .. } => MatchError::gave_up(state.at),
+                _ => panic!("damm forward compatability"),
+            })?;
+            *state.sid.insert(sid)
+        }
+    };
+    while state.at > start {
+        ready!(poll_seek(cursor, cx, state.at - 1));
+        let (chunk, offset) = (cursor.chunk(), cursor.offset());
+        let lo = offset.max(start);
+        for at in (lo..state.at).rev() {
+            let byte = chunk[at - offset];
+            sid = dfa.next_state(cache, sid, byte).map_err(|_| MatchError::gave_up(at))?;
+            if sid.is_tagged() {
+                if sid.is_match() {
+                    let pattern = dfa.match_pattern(cache, sid, 0);
+                    state.mat = Some(HalfMatch::new(pattern, at + 1));
+                } else if sid.is_dead() {
+                    return Poll::Ready(Ok(state.mat));
+                } else if sid.is_quit() {
+                    return Poll::Ready(Err(MatchError::quit(byte, at).into()));
+                }
+            }
+        }
+        state.at = lo;
+        state.sid = Some(sid);
+    }
+    match start.checked_sub(1) {
+        Some(at) => {
+            let byte = ready!(poll_byte(cursor, cx, at)).unwrap();
+            sid = dfa.next_state(cache, sid, byte).map_err(|_| MatchError::gave_up(at))?;
+            if sid.is_match() {
+                let pattern = dfa.match_pattern(cache, sid, 0);
+                state.mat = Some(HalfMatch::new(pattern, start));
+            } else if sid.is_quit() {
+                return Poll::Ready(Err(MatchError::quit(byte, at).into()));
+            }
+        }
+        None => {
+            sid = dfa.next_eoi_state(cache, sid).map_err(|_| MatchError::gave_up(start))?;
+            if sid.is_match() {
+                let pattern = dfa.match_pattern(cache, sid, 0);
+                state.mat = Some(HalfMatch::new(pattern, start));
+            }
+        }
+    }
+    Poll::Ready(Ok(state.mat))
+}
+
+/// Runs the PikeVM over one chunk at a time, starting with collecting the
+/// bytes before the start of the search that look-behind assertions need.
+pub(crate) fn pikevm_poll_search<C: AsyncCursor>(
+    vm: &PikeVM,
+    cache: &mut pikevm::Cache,
+    state: &mut PikeVMPoll,
+    cursor: &mut C,
+    cx: &mut Context<'_>,
+) -> Poll<Option<Match>> {
+    let PikeVMPoll { ref mut at, earliest, ref mut look_behind, ref mut search, ref mut slots } =
+        *state;
+    let search = match search {
+        Some(search) => search,
+        None => {
+            let lo = at.saturating_sub(LOOK_BEHIND_LEN);
+            while lo + look_behind.len() < *at {
+                let byte = ready!(poll_byte(cursor, cx, lo + look_behind.len()));
+                look_behind.push(byte.unwrap());
+            }
+            search.insert(ChunkSearch::new(core::mem::take(look_behind), earliest))
+        }
+    };
+    loop {
+        let chunk = match ready!(poll_seek(cursor, cx, *at)) {
+            true => &cursor.chunk()[*at - cursor.offset()..],
+            false => &[],
+        };
+        if search.search(vm, cache, slots, chunk, *at) {
+            let hm = match search.half_match() {
+                Some(hm) => hm,
+                None => return Poll::Ready(None),
+            };
+            let (start, end) = (hm.pattern().as_usize() * 2, hm.pattern().as_usize() * 2 + 1);
+            let start = slots[start].expect("PikeVM match without a start").get();
+            let end = slots[end].expect("PikeVM match without an end").get();
+            return Poll::Ready(Some(Match::new(hm.pattern(), start..end)));
+        }
+        *at += chunk.len();
+    }
+}
+
+/// Finds the leftmost-first match of the prefilter's literals, which must be
+/// exact.
+///
+/// A match found close to the end of a chunk is only reported once the bytes
+/// after it are known, since a longer literal of higher priority might match
+/// at the same position. Likewise, the last bytes of each chunk are kept
+/// around in case a match starts in them and crosses into the next chunk.
+pub(crate) fn pre_poll_search<C: AsyncCursor>(
+    pre: &Prefilter,
+    state: &mut PrePoll,
+    cursor: &mut C,
+    cx: &mut Context<'_>,
+) -> Poll<Option<Span>> {
+    // The number of bytes after the start of a match that are needed to know
+    // every literal that could match there.
+    let look_ahead = pre.max_needle_len().saturating_sub(1);
+    let shift = |span: Span, offset: usize| Span::from(span.start + offset..span.end + offset);
+    loop {
+        let window_start = state.at - state.window.len();
+        if !ready!(poll_seek(cursor, cx, state.at)) {
+            let span = pre.find(&state.window, Span::from(0..state.window.len()));
+            return Poll::Ready(span.map(|span| shift(span, window_start)));
+        }
+        let chunk = &cursor.chunk()[state.at - cursor.offset()..];
+        if !state.window.is_empty() {
+            let len = state.window.len();
+            state.window.extend_from_slice(&chunk[..chunk.len().min(look_ahead)]);
+            let resolved = state.window.len() >= len + look_ahead;
+            match pre.find(&state.window, Span::from(0..state.window.len())) {
+                Some(span) if span.start < len && resolved => {
+                    return Poll::Ready(Some(shift(span, window_start)));
+                }
+                _ if resolved => state.window.clear(),
+                // The whole chunk was appended to the window, which still
+                // needs more bytes.
+                _ => {
+                    state.at += chunk.len();
+                    continue;
+                }
+            }
+        }
+        match pre.find(chunk, Span::from(0..chunk.len())) {
+            Some(span) if span.start + look_ahead < chunk.len() => {
+                return Poll::Ready(Some(shift(span, state.at)));
+            }
+            _ => {}
+        }
+        let tail = chunk.len().saturating_sub(look_ahead);
+        state.window.extend_from_slice(&chunk[tail..]);
+        state.at += chunk.len();
+    }
+}
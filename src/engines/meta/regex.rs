@@ -1,6 +1,8 @@
 use core::{
     borrow::Borrow,
+    future::poll_fn,
     panic::{RefUnwindSafe, UnwindSafe},
+    task::{ready, Context, Poll},
 };
 
 use std::{boxed::Box, sync::Arc, vec, vec::Vec};
@@ -13,7 +15,7 @@ use regex_automata::{
         prefilter::Prefilter,
         primitives::NonMaxUsize,
     },
-    HalfMatch, Match, MatchKind, PatternID, Span,
+    Anchored, HalfMatch, Match, MatchKind, PatternID, PatternSet, Span,
 };
 use regex_syntax::{
     ast,
@@ -21,8 +23,12 @@ use regex_syntax::{
 };
 
 use crate::{
-    cursor::Cursor,
-    engines::meta::{error::BuildError, strategy::Strategy, wrappers},
+    cursor::{AsyncCursor, Cursor},
+    engines::meta::{
+        error::{BuildError, CursorError},
+        strategy::{OverlappingState, PollState, Strategy},
+        wrappers,
+    },
     util::iter,
     Input,
 };
@@ -533,7 +539,7 @@ impl Regex {
         let result = self.imp.strat.is_match(&mut guard, &mut input);
         // See 'Regex::search' for why we put the guard back explicitly.
         PoolGuard::put(guard);
-        result
+        input.panic_if_failed(result)
     }
 
     /// Executes a leftmost search and returns the first match that is found,
@@ -557,6 +563,36 @@ impl Regex {
         self.search(input)
     }
 
+    /// Returns the last match that [`Regex::find_iter`] would report for the
+    /// given haystack, if one exists.
+    ///
+    /// Unlike running `find_iter` and keeping the last match, this usually
+    /// only looks at the end of the haystack. See [`Regex::rfind_iter`] for
+    /// how this works and for the cases in which it has to search the
+    /// haystack from the start.
+    ///
+    /// # Example
+    ///
+    /// This shows how to find the previous match before some position, for
+    /// example, the caret in an editor:
+    ///
+    /// ```
+    /// use regex_cursor::{engines::meta::Regex, Input};
+    /// use regex_cursor::regex_automata::Match;
+    ///
+    /// let re = Regex::new("foo[0-9]+")?;
+    /// let haystack = "foo1 foo12 foo123";
+    /// let caret = 12;
+    /// let input = Input::new(haystack).range(..caret);
+    /// assert_eq!(Some(Match::must(0, 5..10)), re.rfind(input));
+    ///
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn rfind<C: Cursor>(&self, input: Input<C>) -> Option<Match> {
+        self.rfind_iter(input).next()
+    }
+
     /// Executes a leftmost forward search and writes the spans of capturing
     /// groups that participated in a match into the provided [`Captures`]
     /// value. If no match was found, then [`Captures::is_match`] is guaranteed
@@ -648,6 +684,229 @@ impl Regex {
         CapturesMatches { re: self, cache, caps, it }
     }
 
+    /// Returns an iterator over all overlapping matches in the given
+    /// haystack. Unlike [`Regex::find_iter`], every match of every pattern is
+    /// reported, even if it overlaps with (or shares an end offset with)
+    /// another match. If no match exists, then the iterator yields no
+    /// elements.
+    ///
+    /// This is only useful when this `Regex` was configured with
+    /// [`MatchKind::All`] semantics. With any other match kind, the matches
+    /// reported are whatever the underlying engines find when asked to keep
+    /// searching past the first match, which is usually not what you want.
+    ///
+    /// The order in which matches are reported is unspecified. When a full
+    /// or lazy DFA is available, matches are reported in order of their end
+    /// offsets. Otherwise, the PikeVM is run once per starting position,
+    /// which takes time quadratic in the size of the haystack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_cursor::{engines::meta::Regex, Input};
+    /// use regex_cursor::regex_automata::{Match, MatchKind};
+    ///
+    /// let re = Regex::builder()
+    ///     .configure(Regex::config().match_kind(MatchKind::All))
+    ///     .build_many(&[r"\w+", r"\d+"])?;
+    /// let mut matches: Vec<Match> = re.find_overlapping_iter(Input::new("a12")).collect();
+    /// matches.sort_by_key(|m| (m.start(), m.end(), m.pattern()));
+    /// assert_eq!(matches, vec![
+    ///     Match::must(0, 0..1),
+    ///     Match::must(0, 0..2),
+    ///     Match::must(0, 0..3),
+    ///     Match::must(0, 1..2),
+    ///     Match::must(1, 1..2),
+    ///     Match::must(0, 1..3),
+    ///     Match::must(1, 1..3),
+    ///     Match::must(0, 2..3),
+    ///     Match::must(1, 2..3),
+    /// ]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn find_overlapping_iter<C: Cursor>(
+        &self,
+        input: Input<C>,
+    ) -> FindOverlappingMatches<'_, C> {
+        let cache = self.pool.get();
+        let state = OverlappingState::start();
+        FindOverlappingMatches { re: self, cache, input, state }
+    }
+
+    /// Returns an iterator over all non-overlapping leftmost matches in the
+    /// given haystack, starting with the last one. If no match exists, then
+    /// the iterator yields no elements.
+    ///
+    /// This yields the same matches as [`Regex::find_iter`] in reverse order,
+    /// but it walks the haystack backwards from the end of the span instead
+    /// of scanning it from the start. This makes it possible to find the
+    /// match preceding some position without searching everything before it.
+    ///
+    /// Each step searches a window before the current position, growing the
+    /// window until a match is found. The reverse DFA then finds the leftmost
+    /// start of a match ending where the first match in the window ends, and
+    /// a forward leftmost search determines the matches reported.
+    ///
+    /// The matches reported by `find_iter` depend on everything before them,
+    /// since every search starts where the previous match ended. For example,
+    /// `aa` on `aaa` matches `0..2` with `find_iter`, so `1..3` must not be
+    /// reported even though a search starting at `1` finds it. The forward
+    /// search therefore starts at a position that no match can cross: one
+    /// that follows a byte no match can contain or, for regexes whose matches
+    /// are short, one where the matches starting shortly before it end
+    /// before it. If there is no such position (or no reverse DFA is
+    /// available), the haystack is searched forwards from the start of the
+    /// span, so this can be as slow as `find_iter` for some regexes.
+    ///
+    /// The end of the haystack must be known, so if the cursor doesn't
+    /// report its length, the entire haystack is read before the first match
+    /// is reported.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_cursor::{engines::meta::Regex, Input};
+    /// use regex_cursor::regex_automata::Match;
+    ///
+    /// let re = Regex::new("foo[0-9]+")?;
+    /// let haystack = "foo1 foo12 foo123";
+    /// let matches: Vec<Match> = re.rfind_iter(Input::new(haystack)).collect();
+    /// assert_eq!(matches, vec![
+    ///     Match::must(0, 11..17),
+    ///     Match::must(0, 5..10),
+    ///     Match::must(0, 0..4),
+    /// ]);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn rfind_iter<C: Cursor>(&self, input: Input<C>) -> RFindMatches<'_, C> {
+        let cache = self.pool.get();
+        RFindMatches { re: self, cache, input, pending: Vec::new(), sync: None, done: false }
+    }
+
+    /// Returns the start and end offset of the leftmost match, like
+    /// [`Regex::find`], or an error if the cursor failed.
+    ///
+    /// A cursor fails if it can't read the haystack (see
+    /// [`Cursor::take_error`]) or if it can't backtrack to data the search
+    /// needs again. Since the search can't tell what the rest of the haystack
+    /// would have contained, the result of a search whose cursor failed is
+    /// discarded. Infallible searches like [`Regex::find`] panic if the
+    /// cursor failed to backtrack, while this returns the error.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_cursor::{engines::meta::Regex, Input, ReaderCursor};
+    ///
+    /// // the window of the reader is too small to find the start of the match
+    /// let re = Regex::new(r"a+!")?;
+    /// let haystack = "a".repeat(100) + "!";
+    /// let cursor = ReaderCursor::with_window(haystack.as_bytes(), 8, 2);
+    /// let err = re.try_search(Input::new(cursor)).unwrap_err();
+    /// assert!(err.backtrack_offset().is_some());
+    ///
+    /// let cursor = ReaderCursor::with_window(haystack.as_bytes(), 8, 16);
+    /// assert_eq!(re.try_search(Input::new(cursor))?.unwrap().range(), 0..101);
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn try_search<C: Cursor>(&self, mut input: Input<C>) -> Result<Option<Match>, CursorError> {
+        let mut guard = self.pool.get();
+        let result = self.search_imp(&mut guard, &mut input);
+        PoolGuard::put(guard);
+        let (err, backtrack_offset) = input.take_cursor_error();
+        match CursorError::new(err, backtrack_offset) {
+            Some(err) => Err(err),
+            None => Ok(result),
+        }
+    }
+
+    /// Returns an iterator over all non-overlapping leftmost matches, like
+    /// [`Regex::find_iter`], that yields an error (and then stops) if the
+    /// cursor fails. See [`Regex::try_search`] for how a cursor can fail.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::io::{self, Read};
+    /// use regex_cursor::{engines::meta::Regex, Input, ReaderCursor};
+    ///
+    /// let reader = b"foo1 foo12".chain(FailingReader);
+    /// let re = Regex::new("foo[0-9]+")?;
+    /// let mut it = re.try_find_iter(Input::new(ReaderCursor::with_window(reader, 4, 4)));
+    /// assert_eq!(it.next().unwrap()?.range(), 0..4);
+    /// let err = it.next().unwrap().unwrap_err();
+    /// assert_eq!(err.io_error().unwrap().kind(), io::ErrorKind::BrokenPipe);
+    /// assert!(it.next().is_none());
+    ///
+    /// struct FailingReader;
+    ///
+    /// impl Read for FailingReader {
+    ///     fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+    ///         Err(io::ErrorKind::BrokenPipe.into())
+    ///     }
+    /// }
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn try_find_iter<C: Cursor>(&self, input: Input<C>) -> TryFindMatches<'_, C> {
+        let cache = self.pool.get();
+        let it = iter::Searcher::new(input);
+        TryFindMatches { re: self, cache, it, done: false }
+    }
+
+    /// Returns true if and only if this regex matches the haystack of the
+    /// given async cursor, starting at its current offset.
+    ///
+    /// This is the async counterpart of [`Regex::is_match`]. Whenever the
+    /// cursor has to wait for a chunk, the search is suspended at the chunk
+    /// boundary instead of blocking. Like [`Regex::find_iter_async`], it
+    /// always searches up to the end of the haystack and doesn't support the
+    /// configuration options of an [`Input`].
+    pub async fn is_match_async<A: AsyncCursor>(&self, mut cursor: A) -> bool {
+        let mut cache = self.pool.get();
+        let mut state = PollState::start(cursor.offset(), true);
+        let strat = &self.imp.strat;
+        let m = poll_fn(|cx| strat.poll_search(&mut cache, &mut state, &mut cursor, cx)).await;
+        m.is_some()
+    }
+
+    /// Returns the start and end offset of the leftmost match in the
+    /// haystack of the given async cursor, starting at its current offset.
+    ///
+    /// This is the async counterpart of [`Regex::find`]. See
+    /// [`Regex::find_iter_async`] for details.
+    pub async fn find_async<A: AsyncCursor>(&self, cursor: A) -> Option<Match> {
+        self.find_iter_async(cursor).next().await
+    }
+
+    /// Returns an async iterator over all non-overlapping leftmost matches in
+    /// the haystack of the given async cursor, starting at its current
+    /// offset.
+    ///
+    /// This is the async counterpart of [`Regex::find_iter`] and reports the
+    /// same matches. The search loops of the lazy DFA, the fully compiled DFA
+    /// and the PikeVM (which is used if neither DFA is available or if one of
+    /// them fails) are suspended at chunk boundaries whenever
+    /// [`AsyncCursor::poll_advance`] or [`AsyncCursor::poll_backtrack`]
+    /// returns `Poll::Pending`, and resumed when the returned iterator is
+    /// polled again.
+    ///
+    /// Finding the start of a match requires moving the cursor backwards, and
+    /// so does starting the next search at the end of the previous match.
+    ///
+    /// Since the searches don't take an [`Input`], they are always
+    /// unanchored (unless the regex itself is anchored) and run up to the
+    /// end of the haystack. Prefilters are only used by regexes that consist
+    /// entirely of literals.
+    pub fn find_iter_async<A: AsyncCursor>(&self, cursor: A) -> AsyncFindMatches<'_, A> {
+        let cache = self.pool.get();
+        let state = PollState::start(cursor.offset(), false);
+        AsyncFindMatches { re: self, cache, cursor, state, last_match_end: None, done: false }
+    }
+
     /// Returns an iterator of spans of the haystack given, delimited by a
     /// match of the regex. Namely, each element of the iterator corresponds to
     /// a part of the haystack that *isn't* matched by the regular expression.
@@ -924,7 +1183,7 @@ impl Regex {
         // pool), or callers can use the lower level APIs that accept a 'Cache'
         // directly and do their own handling.
         PoolGuard::put(guard);
-        result
+        input.panic_if_failed(result)
     }
 
     /// Returns the end offset of the leftmost match. If no match exists, then
@@ -963,7 +1222,7 @@ impl Regex {
         let result = self.imp.strat.search_half(&mut guard, &mut input);
         // See 'Regex::search' for why we put the guard back explicitly.
         PoolGuard::put(guard);
-        result
+        input.panic_if_failed(result)
     }
 
     /// Executes a leftmost forward search and writes the spans of capturing
@@ -1118,65 +1377,69 @@ impl Regex {
         let result = self.imp.strat.search_slots(&mut guard, &mut input, slots);
         // See 'Regex::search' for why we put the guard back explicitly.
         PoolGuard::put(guard);
-        result
-    }
-
-    // /// Writes the set of patterns that match anywhere in the given search
-    // /// configuration to `patset`. If multiple patterns match at the same
-    // /// position and this `Regex` was configured with [`MatchKind::All`]
-    // /// semantics, then all matching patterns are written to the given set.
-    // ///
-    // /// Unless all of the patterns in this `Regex` are anchored, then generally
-    // /// speaking, this will scan the entire haystack.
-    // ///
-    // /// This search routine *does not* clear the pattern set. This gives some
-    // /// flexibility to the caller (e.g., running multiple searches with the
-    // /// same pattern set), but does make the API bug-prone if you're reusing
-    // /// the same pattern set for multiple searches but intended them to be
-    // /// independent.
-    // ///
-    // /// If a pattern ID matched but the given `PatternSet` does not have
-    // /// sufficient capacity to store it, then it is not inserted and silently
-    // /// dropped.
-    // ///
-    // /// # Example
-    // ///
-    // /// This example shows how to find all matching patterns in a haystack,
-    // /// even when some patterns match at the same position as other patterns.
-    // /// It is important that we configure the `Regex` with [`MatchKind::All`]
-    // /// semantics here, or else overlapping matches will not be reported.
-    // ///
-    // /// ```
-    // /// # if cfg!(miri) { return Ok(()); } // miri takes too long
-    // /// use regex_automata::{meta::Regex, Input, MatchKind, PatternSet};
-    // ///
-    // /// let patterns = &[
-    // ///     r"\w+", r"\d+", r"\pL+", r"foo", r"bar", r"barfoo", r"foobar",
-    // /// ];
-    // /// let re = Regex::builder()
-    // ///     .configure(Regex::config().match_kind(MatchKind::All))
-    // ///     .build_many(patterns)?;
-    // ///
-    // /// let input = Input::new("foobar");
-    // /// let mut patset = PatternSet::new(re.pattern_len());
-    // /// re.which_overlapping_matches(&input, &mut patset);
-    // /// let expected = vec![0, 2, 3, 4, 6];
-    // /// let got: Vec<usize> = patset.iter().map(|p| p.as_usize()).collect();
-    // /// assert_eq!(expected, got);
-    // ///
-    // /// # Ok::<(), Box<dyn std::error::Error>>(())
-    // /// ```
-    // #[inline]
-    // pub fn which_overlapping_matches<C: Cursor>(&self, mut input: Input<C>, patset: &mut PatternSet) {
-    //     if self.imp.info.is_impossible(input) {
-    //         return;
-    //     }
-    //     let mut guard = self.pool.get();
-    //     let result = self.imp.strat.which_overlapping_matches(&mut guard, input, patset);
-    //     // See 'Regex::search' for why we put the guard back explicitly.
-    //     PoolGuard::put(guard);
-    //     result
-    // }
+        input.panic_if_failed(result)
+    }
+
+    /// Writes the set of patterns that match anywhere in the given search
+    /// configuration to `patset`. If multiple patterns match at the same
+    /// position and this `Regex` was configured with [`MatchKind::All`]
+    /// semantics, then all matching patterns are written to the given set.
+    ///
+    /// Unless all of the patterns in this `Regex` are anchored, then generally
+    /// speaking, this will scan the entire haystack.
+    ///
+    /// This search routine *does not* clear the pattern set. This gives some
+    /// flexibility to the caller (e.g., running multiple searches with the
+    /// same pattern set), but does make the API bug-prone if you're reusing
+    /// the same pattern set for multiple searches but intended them to be
+    /// independent.
+    ///
+    /// If a pattern ID matched but the given `PatternSet` does not have
+    /// sufficient capacity to store it, then it is not inserted and silently
+    /// dropped.
+    ///
+    /// # Example
+    ///
+    /// This example shows how to find all matching patterns in a haystack,
+    /// even when some patterns match at the same position as other patterns.
+    /// It is important that we configure the `Regex` with [`MatchKind::All`]
+    /// semantics here, or else overlapping matches will not be reported.
+    ///
+    /// ```
+    /// use regex_cursor::{engines::meta::Regex, Input};
+    /// use regex_cursor::regex_automata::{MatchKind, PatternSet};
+    ///
+    /// let patterns = &[
+    ///     r"\w+", r"\d+", r"\pL+", r"foo", r"bar", r"barfoo", r"foobar",
+    /// ];
+    /// let re = Regex::builder()
+    ///     .configure(Regex::config().match_kind(MatchKind::All))
+    ///     .build_many(patterns)?;
+    ///
+    /// let input = Input::new("foobar");
+    /// let mut patset = PatternSet::new(re.pattern_len());
+    /// re.which_overlapping_matches(input, &mut patset);
+    /// let expected = vec![0, 2, 3, 4, 6];
+    /// let got: Vec<usize> = patset.iter().map(|p| p.as_usize()).collect();
+    /// assert_eq!(expected, got);
+    ///
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn which_overlapping_matches<C: Cursor>(
+        &self,
+        mut input: Input<C>,
+        patset: &mut PatternSet,
+    ) {
+        if self.imp.info.is_impossible(&input) {
+            return;
+        }
+        let mut guard = self.pool.get();
+        self.imp.strat.which_overlapping_matches(&mut guard, &mut input, patset);
+        // See 'Regex::search' for why we put the guard back explicitly.
+        PoolGuard::put(guard);
+        input.panic_if_failed(())
+    }
 }
 
 /// Lower level search routines that give more control, and require the caller
@@ -1214,6 +1477,14 @@ impl Regex {
     /// ```
     #[inline]
     pub fn search_with<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<Match> {
+        let result = self.search_imp(cache, input);
+        input.panic_if_failed(result)
+    }
+
+    /// Like [`Regex::search_with`], but returns the result of the search even
+    /// if the cursor failed, for the callers that check for it themselves.
+    #[inline]
+    fn search_imp<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<Match> {
         if self.imp.info.is_impossible(input) {
             return None;
         }
@@ -1259,7 +1530,8 @@ impl Regex {
         if self.imp.info.is_impossible(input) {
             return None;
         }
-        self.imp.strat.search_half(cache, input)
+        let result = self.imp.strat.search_half(cache, input);
+        input.panic_if_failed(result)
     }
 
     /// This is like [`Regex::search_captures`], but requires the caller to
@@ -1412,7 +1684,56 @@ impl Regex {
         if self.imp.info.is_impossible(input) {
             return None;
         }
-        self.imp.strat.search_slots(cache, input, slots)
+        let result = self.imp.strat.search_slots(cache, input, slots);
+        input.panic_if_failed(result)
+    }
+
+    /// This is like [`Regex::which_overlapping_matches`], but requires the
+    /// caller to explicitly pass a [`Cache`].
+    ///
+    /// Passing a `Cache` explicitly will bypass the use of an internal memory
+    /// pool used by `Regex` to get a `Cache` for a search. The use of this
+    /// pool can be slower in some cases when a `Regex` is used from multiple
+    /// threads simultaneously. Typically, performance only becomes an issue
+    /// when there is heavy contention, which in turn usually only occurs
+    /// when each thread's primary unit of work is a regex search on a small
+    /// haystack.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use regex_cursor::{engines::meta::Regex, Input};
+    /// use regex_cursor::regex_automata::{MatchKind, PatternSet};
+    ///
+    /// let patterns = &[
+    ///     r"\w+", r"\d+", r"\pL+", r"foo", r"bar", r"barfoo", r"foobar",
+    /// ];
+    /// let re = Regex::builder()
+    ///     .configure(Regex::config().match_kind(MatchKind::All))
+    ///     .build_many(patterns)?;
+    /// let mut cache = re.create_cache();
+    ///
+    /// let mut input = Input::new("foobar");
+    /// let mut patset = PatternSet::new(re.pattern_len());
+    /// re.which_overlapping_matches_with(&mut cache, &mut input, &mut patset);
+    /// let expected = vec![0, 2, 3, 4, 6];
+    /// let got: Vec<usize> = patset.iter().map(|p| p.as_usize()).collect();
+    /// assert_eq!(expected, got);
+    ///
+    /// # Ok::<(), Box<dyn std::error::Error>>(())
+    /// ```
+    #[inline]
+    pub fn which_overlapping_matches_with<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        patset: &mut PatternSet,
+    ) {
+        if self.imp.info.is_impossible(input) {
+            return;
+        }
+        self.imp.strat.which_overlapping_matches(cache, input, patset);
+        input.panic_if_failed(())
     }
 }
 
@@ -1831,6 +2152,51 @@ struct RegexInfoI {
     config: Config,
     props: Vec<hir::Properties>,
     props_union: hir::Properties,
+    /// The bytes that may occur in a match of any pattern. No match can
+    /// contain a byte that isn't in this set.
+    match_bytes: [bool; 256],
+}
+
+/// Returns the set of bytes that may occur in a match of any of the given
+/// HIRs. This is an over-approximation: all bytes of a non-ASCII codepoint
+/// are assumed to be in the set.
+fn match_bytes(hirs: &[&Hir]) -> [bool; 256] {
+    use regex_syntax::hir::{Class, HirKind};
+
+    let mut set = [false; 256];
+    let mut stack: Vec<&Hir> = hirs.to_vec();
+    while let Some(hir) = stack.pop() {
+        match hir.kind() {
+            HirKind::Empty | HirKind::Look(_) => {}
+            HirKind::Literal(lit) => {
+                for &byte in lit.0.iter() {
+                    set[usize::from(byte)] = true;
+                }
+            }
+            HirKind::Class(Class::Unicode(class)) => {
+                for range in class.iter() {
+                    let (start, end) = (u32::from(range.start()), u32::from(range.end()));
+                    for byte in start..=end.min(0x7F) {
+                        set[byte as usize] = true;
+                    }
+                    if end > 0x7F {
+                        set[0x80..].fill(true);
+                    }
+                }
+            }
+            HirKind::Class(Class::Bytes(class)) => {
+                for range in class.iter() {
+                    for byte in range.start()..=range.end() {
+                        set[usize::from(byte)] = true;
+                    }
+                }
+            }
+            HirKind::Repetition(rep) => stack.push(&rep.sub),
+            HirKind::Capture(cap) => stack.push(&cap.sub),
+            HirKind::Concat(subs) | HirKind::Alternation(subs) => stack.extend(subs),
+        }
+    }
+    set
 }
 
 impl RegexInfo {
@@ -1843,8 +2209,9 @@ impl RegexInfo {
             props.push(hir.properties().clone());
         }
         let props_union = hir::Properties::union(&props);
+        let match_bytes = match_bytes(hirs);
 
-        RegexInfo(Arc::new(RegexInfoI { config, props, props_union }))
+        RegexInfo(Arc::new(RegexInfoI { config, props, props_union, match_bytes }))
     }
 
     pub(crate) fn config(&self) -> &Config {
@@ -1863,6 +2230,11 @@ impl RegexInfo {
         self.props().len()
     }
 
+    /// Returns false if no match of the regex can contain the given byte.
+    pub(crate) fn may_match_byte(&self, byte: u8) -> bool {
+        self.0.match_bytes[usize::from(byte)]
+    }
+
     pub(crate) fn memory_usage(&self) -> usize {
         self.props().iter().map(|p| p.memory_usage()).sum::<usize>()
             + self.props_union().memory_usage()
@@ -1952,7 +2324,8 @@ impl RegexInfo {
 /// An iterator over all non-overlapping matches for an infallible search.
 ///
 /// The iterator yields a [`Match`] value until no more matches could be found.
-/// If the underlying regex engine returns an error, then a panic occurs.
+/// If the underlying regex engine returns an error or the cursor fails to
+/// backtrack, then a panic occurs.
 ///
 /// This iterator can be created with the [`Regex::find_iter`] method.
 #[derive(Debug)]
@@ -2003,6 +2376,349 @@ impl<'r, C: Cursor> Iterator for FindMatches<'r, C> {
 
 impl<'r, C: Cursor> core::iter::FusedIterator for FindMatches<'r, C> {}
 
+/// An iterator over all non-overlapping matches for a search whose cursor
+/// may fail.
+///
+/// The iterator yields a [`Match`] value until no more matches could be
+/// found, or a [`CursorError`] if the cursor failed, after which it yields
+/// nothing.
+///
+/// This iterator can be created with the [`Regex::try_find_iter`] method.
+#[derive(Debug)]
+pub struct TryFindMatches<'r, C: Cursor> {
+    re: &'r Regex,
+    cache: CachePoolGuard<'r>,
+    it: iter::Searcher<C>,
+    done: bool,
+}
+
+impl<'r, C: Cursor> TryFindMatches<'r, C> {
+    /// Returns the `Regex` value that created this iterator.
+    #[inline]
+    pub fn regex(&self) -> &'r Regex {
+        self.re
+    }
+
+    /// Returns the current `Input` associated with this iterator.
+    ///
+    /// The `start` position on the given `Input` may change during iteration,
+    /// but all other values are guaranteed to remain invariant.
+    #[inline]
+    pub fn input(&mut self) -> &mut Input<C> {
+        self.it.input()
+    }
+}
+
+impl<'r, C: Cursor> Iterator for TryFindMatches<'r, C> {
+    type Item = Result<Match, CursorError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Result<Match, CursorError>> {
+        if self.done {
+            return None;
+        }
+        let TryFindMatches { re, ref mut cache, ref mut it, .. } = *self;
+        let m = it.advance(|input| Ok(re.search_imp(cache, input)));
+        let (err, backtrack_offset) = it.input().take_cursor_error();
+        if let Some(err) = CursorError::new(err, backtrack_offset) {
+            self.done = true;
+            return Some(Err(err));
+        }
+        m.map(Ok)
+    }
+}
+
+impl<'r, C: Cursor> core::iter::FusedIterator for TryFindMatches<'r, C> {}
+
+/// An iterator over all overlapping matches for an infallible search.
+///
+/// The iterator yields a [`Match`] value until no more matches could be found.
+/// See [`Regex::find_overlapping_iter`] for the order in which matches are
+/// reported.
+///
+/// This iterator can be created with the [`Regex::find_overlapping_iter`]
+/// method.
+#[derive(Debug)]
+pub struct FindOverlappingMatches<'r, C: Cursor> {
+    re: &'r Regex,
+    cache: CachePoolGuard<'r>,
+    input: Input<C>,
+    state: OverlappingState,
+}
+
+impl<'r, C: Cursor> FindOverlappingMatches<'r, C> {
+    /// Returns the `Regex` value that created this iterator.
+    #[inline]
+    pub fn regex(&self) -> &'r Regex {
+        self.re
+    }
+}
+
+impl<'r, C: Cursor> Iterator for FindOverlappingMatches<'r, C> {
+    type Item = Match;
+
+    #[inline]
+    fn next(&mut self) -> Option<Match> {
+        let FindOverlappingMatches { re, ref mut cache, ref mut input, ref mut state } = *self;
+        if re.imp.info.is_impossible(input) {
+            return None;
+        }
+        let result = re.imp.strat.search_overlapping(cache, input, state);
+        input.panic_if_failed(result)
+    }
+}
+
+impl<'r, C: Cursor> core::iter::FusedIterator for FindOverlappingMatches<'r, C> {}
+
+/// The size of the first window searched by [`RFindMatches`] for the last
+/// match end. The window doubles in size every time it comes up empty.
+const RFIND_WINDOW: usize = 4 * 1024;
+
+/// The maximum length of the matches of a regex for which [`RFindMatches`]
+/// checks the matches around a potential starting point directly.
+const RFIND_MAX_CHECKED_LEN: usize = 64;
+
+/// An iterator over all non-overlapping matches for an infallible search,
+/// starting with the last one.
+///
+/// The iterator yields a [`Match`] value until no more matches could be found.
+/// If the underlying regex engine returns an error or the cursor fails to
+/// backtrack, then a panic occurs.
+///
+/// This iterator can be created with the [`Regex::rfind_iter`] method.
+#[derive(Debug)]
+pub struct RFindMatches<'r, C: Cursor> {
+    re: &'r Regex,
+    cache: CachePoolGuard<'r>,
+    input: Input<C>,
+    /// The matches found by the most recent scan, in forward order.
+    pending: Vec<Match>,
+    /// The position at which the most recent scan started. It bounds the
+    /// next scan, and an empty match at this position (which `find_iter`
+    /// wouldn't report) must not be reported by the next scan.
+    sync: Option<usize>,
+    done: bool,
+}
+
+impl<'r, C: Cursor> RFindMatches<'r, C> {
+    /// Returns the `Regex` value that created this iterator.
+    #[inline]
+    pub fn regex(&self) -> &'r Regex {
+        self.re
+    }
+
+    /// Searches backwards from the end of the previous scan for a position
+    /// from which a forward search finds the same matches as `find_iter`
+    /// and pushes all matches from there on to `pending`.
+    fn scan(&mut self) {
+        let start = self.input.start();
+        let end = match self.sync {
+            Some(end) => end,
+            None => {
+                // Walking to the end of the haystack makes sure that the end
+                // of the span is known even if the cursor doesn't report the
+                // length of the haystack.
+                self.input.move_to(self.input.end());
+                self.input.end()
+            }
+        };
+        if self.re.imp.info.is_impossible(&self.input) {
+            self.done = true;
+            return;
+        }
+        // Anchored searches can't start anywhere but at the start of the
+        // span, so there is nothing to gain from looking at a window.
+        let mut window =
+            if self.input.get_anchored().is_anchored() { usize::MAX } else { RFIND_WINDOW };
+        let (sync, matches) = loop {
+            let lo = end.saturating_sub(window).max(start);
+            let matches = self.forward_matches(lo..end);
+            if lo == start {
+                break (start, matches);
+            }
+            if let Some(first) = matches.first() {
+                let (re, cache) = (self.re, &mut *self.cache);
+                let candidate = self
+                    .input
+                    .with(|input| {
+                        input.span(start..first.end()).anchored(Anchored::Yes).earliest(false);
+                        re.imp.strat.search_half_rev(cache, input)
+                    })
+                    .map_or(start, |hm| hm.offset());
+                let sync = self.barrier(candidate);
+                break (sync, self.forward_matches(sync..end));
+            }
+            window = window.saturating_mul(2);
+        };
+        self.pending = matches;
+        if sync == start {
+            self.done = true;
+        } else {
+            self.sync = Some(sync);
+        }
+    }
+
+    /// Returns the closest position at or before `at` that no match reported
+    /// by `find_iter` can contain. `find_iter` resumes its search at or
+    /// before such a position without reporting a match that crosses it, so
+    /// a forward search from there finds the same matches.
+    fn barrier(&mut self, mut at: usize) -> usize {
+        let start = self.input.start();
+        while at > start && !self.is_barrier(at) {
+            at -= 1;
+        }
+        at.max(start)
+    }
+
+    fn is_barrier(&mut self, at: usize) -> bool {
+        let RFindMatches { re, ref mut cache, ref mut input, .. } = *self;
+        let info = &re.imp.info;
+        // No match contains the byte before `at`, so none crosses `at` (and
+        // no non-empty match ends there).
+        input.move_to(at);
+        while input.chunk_pos() == 0 {
+            if !input.backtrack() {
+                return false;
+            }
+        }
+        if !info.may_match_byte(input.chunk()[input.chunk_pos() - 1]) {
+            return true;
+        }
+        // Matches that are never longer than a few bytes can be checked
+        // directly: every match starting shortly before `at` must end before
+        // it. If the regex can match the empty string, a match ending at
+        // `at` would also hide an empty match at `at` from `find_iter`.
+        let Some(max_len) = info.props_union().maximum_len() else {
+            return false;
+        };
+        if max_len > RFIND_MAX_CHECKED_LEN {
+            return false;
+        }
+        let can_be_empty = info.props_union().minimum_len() == Some(0);
+        let end = input.end();
+        (at.saturating_sub(max_len).max(input.start())..at).all(|s| {
+            let m = input.with(|input| {
+                input.span(s..end).anchored(Anchored::Yes).earliest(false);
+                re.search_with(cache, input)
+            });
+            m.map_or(true, |m| m.end() < at || (m.end() == at && !can_be_empty))
+        })
+    }
+
+    /// Returns the matches `find_iter` reports when searching `span`. An
+    /// empty match at the end of the previous scan is never included.
+    fn forward_matches(&mut self, span: core::ops::Range<usize>) -> Vec<Match> {
+        let RFindMatches { re, ref mut cache, ref mut input, sync, .. } = *self;
+        let end = span.end;
+        input.with(|input| {
+            input.span(span);
+            let mut matches: Vec<Match> = Vec::new();
+            while let Some(mut m) = re.search_with(cache, input) {
+                if m.is_empty() && matches.last().map(|m| m.end()) == Some(m.end()) {
+                    // See 'Searcher::handle_overlapping_empty_match'.
+                    if m.end() == end {
+                        break;
+                    }
+                    input.set_start(m.end() + 1);
+                    m = match re.search_with(cache, input) {
+                        None => break,
+                        Some(m) => m,
+                    };
+                }
+                if m.is_empty() && Some(m.end()) == sync {
+                    break;
+                }
+                input.set_start(m.end());
+                matches.push(m);
+            }
+            matches
+        })
+    }
+}
+
+impl<'r, C: Cursor> Iterator for RFindMatches<'r, C> {
+    type Item = Match;
+
+    #[inline]
+    fn next(&mut self) -> Option<Match> {
+        loop {
+            if let Some(m) = self.pending.pop() {
+                return Some(m);
+            }
+            if self.done {
+                return None;
+            }
+            self.scan();
+        }
+    }
+}
+
+impl<'r, C: Cursor> core::iter::FusedIterator for RFindMatches<'r, C> {}
+
+/// An async iterator over all non-overlapping matches in the haystack of an
+/// [`AsyncCursor`].
+///
+/// Matches are retrieved with [`AsyncFindMatches::next`] or
+/// [`AsyncFindMatches::poll_next`]. This crate doesn't depend on the `futures`
+/// crate, so this type doesn't implement its `Stream` trait. But `poll_next`
+/// works just like `Stream::poll_next`, so wrapping it into a `Stream` is
+/// straightforward.
+///
+/// This iterator can be created with the [`Regex::find_iter_async`] method.
+#[derive(Debug)]
+pub struct AsyncFindMatches<'r, A> {
+    re: &'r Regex,
+    cache: CachePoolGuard<'r>,
+    cursor: A,
+    state: PollState,
+    last_match_end: Option<usize>,
+    done: bool,
+}
+
+impl<'r, A: AsyncCursor> AsyncFindMatches<'r, A> {
+    /// Returns the `Regex` value that created this iterator.
+    #[inline]
+    pub fn regex(&self) -> &'r Regex {
+        self.re
+    }
+
+    /// Returns the cursor searched by this iterator.
+    #[inline]
+    pub fn cursor(&mut self) -> &mut A {
+        &mut self.cursor
+    }
+
+    /// Attempts to find the next match. Returns `Poll::Pending` if the cursor
+    /// has to wait for a chunk, in which case the current task is woken once
+    /// it's available. Returns `Poll::Ready(None)` once there are no more
+    /// matches.
+    pub fn poll_next(&mut self, cx: &mut Context<'_>) -> Poll<Option<Match>> {
+        let AsyncFindMatches { re, ref mut cache, ref mut cursor, ref mut state, .. } = *self;
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+            let Some(m) = ready!(re.imp.strat.poll_search(cache, state, cursor, cx)) else {
+                self.done = true;
+                return Poll::Ready(None);
+            };
+            // See 'Searcher::handle_overlapping_empty_match'.
+            if m.is_empty() && Some(m.end()) == self.last_match_end {
+                *state = PollState::start(m.end() + 1, false);
+                continue;
+            }
+            self.last_match_end = Some(m.end());
+            *state = PollState::start(m.end(), false);
+            return Poll::Ready(Some(m));
+        }
+    }
+
+    /// Returns the next match, or `None` once there are no more matches.
+    pub async fn next(&mut self) -> Option<Match> {
+        poll_fn(|cx| self.poll_next(cx)).await
+    }
+}
+
 /// An iterator over all non-overlapping leftmost matches with their capturing
 /// groups.
 ///
@@ -2230,10 +2946,10 @@ impl<'r, C: Cursor> core::iter::FusedIterator for SplitN<'r, C> {}
 pub struct Cache {
     pub(crate) capmatches: Captures,
     pub(crate) pikevm: wrappers::PikeVMCache,
-    // pub(crate) backtrack: wrappers::BoundedBacktrackerCache,
-    // pub(crate) onepass: wrappers::OnePassCache,
+    pub(crate) backtrack: wrappers::BoundedBacktrackerCache,
+    pub(crate) onepass: wrappers::OnePassCache,
     pub(crate) hybrid: wrappers::HybridCache,
-    // pub(crate) revhybrid: wrappers::ReverseHybridCache,
+    pub(crate) revhybrid: wrappers::ReverseHybridCache,
 }
 
 impl Cache {
@@ -2294,10 +3010,10 @@ impl Cache {
     pub fn memory_usage(&self) -> usize {
         let mut bytes = 0;
         bytes += self.pikevm.memory_usage();
-        // bytes += self.backtrack.memory_usage();
-        // bytes += self.onepass.memory_usage();
+        bytes += self.backtrack.memory_usage();
+        bytes += self.onepass.memory_usage();
         bytes += self.hybrid.memory_usage();
-        // bytes += self.revhybrid.memory_usage();
+        bytes += self.revhybrid.memory_usage();
         bytes
     }
 }
@@ -2347,7 +3063,7 @@ pub struct Config {
     dfa: Option<bool>,
     dfa_size_limit: Option<Option<usize>>,
     dfa_state_limit: Option<Option<usize>>,
-    // onepass: Option<bool>,
+    onepass: Option<bool>,
     backtrack: Option<bool>,
     byte_classes: Option<bool>,
     line_terminator: Option<u8>,
@@ -2903,21 +3619,20 @@ impl Config {
         Config { dfa: Some(yes), ..self }
     }
 
-    // /// Toggle whether a one-pass DFA should be available for use by the meta
-    // /// regex engine.
-    // ///
-    // /// Enabling this does not necessarily mean that a one-pass DFA will
-    // /// definitely be used. It just means that it will be _available_ for
-    // /// use if the meta regex engine thinks it will be useful. (Indeed, a
-    // /// one-pass DFA can only be used when the regex is one-pass. See the
-    // /// [`dfa::onepass`](crate::dfa::onepass) module for more details.)
-    // ///
-    // /// When the `dfa-onepass` crate feature is enabled, then this is enabled
-    // /// by default. Otherwise, if the crate feature is disabled, then this is
-    // /// always disabled, regardless of its setting by the caller.
-    // pub fn onepass(self, yes: bool) -> Config {
-    //     Config { onepass: Some(yes), ..self }
-    // }
+    /// Toggle whether a one-pass DFA should be available for use by the meta
+    /// regex engine.
+    ///
+    /// Enabling this does not necessarily mean that a one-pass DFA will
+    /// definitely be used. It just means that it will be _available_ for
+    /// use if the meta regex engine thinks it will be useful. (Indeed, a
+    /// one-pass DFA can only be used when the regex is one-pass and the
+    /// search is anchored. See the [`onepass`](crate::engines::onepass)
+    /// module for more details.)
+    ///
+    /// This is enabled by default.
+    pub fn onepass(self, yes: bool) -> Config {
+        Config { onepass: Some(yes), ..self }
+    }
 
     /// Toggle whether a bounded backtracking regex engine should be available
     /// for use by the meta regex engine.
@@ -2926,9 +3641,10 @@ impl Config {
     /// definitely be used. It just means that it will be _available_ for use
     /// if the meta regex engine thinks it will be useful.
     ///
-    /// When the `nfa-backtrack` crate feature is enabled, then this is enabled
-    /// by default. Otherwise, if the crate feature is disabled, then this is
-    /// always disabled, regardless of its setting by the caller.
+    /// This is enabled by default. The backtracker is only ever used for
+    /// searches whose span is short enough to fit into its visited set, which
+    /// in particular means it is never used with cursors that don't report
+    /// their [`total_bytes`](crate::Cursor::total_bytes).
     pub fn backtrack(self, yes: bool) -> Config {
         Config { backtrack: Some(yes), ..self }
     }
@@ -2980,13 +3696,13 @@ impl Config {
         self.nfa_size_limit.unwrap_or(Some(10 * (1 << 20)))
     }
 
-    // /// Returns one-pass DFA size limit, as set by
-    // /// [`Config::onepass_size_limit`].
-    // ///
-    // /// If it was not explicitly set, then a default value is returned.
-    // pub fn get_onepass_size_limit(&self) -> Option<usize> {
-    //     self.onepass_size_limit.unwrap_or(Some((1 << 20)))
-    // }
+    /// Returns one-pass DFA size limit, as set by
+    /// [`Config::onepass_size_limit`].
+    ///
+    /// If it was not explicitly set, then a default value is returned.
+    pub fn get_onepass_size_limit(&self) -> Option<usize> {
+        self.onepass_size_limit.unwrap_or(Some(1 << 20))
+    }
 
     /// Returns hybrid NFA/DFA cache capacity, as set by
     /// [`Config::hybrid_cache_capacity`].
@@ -3062,28 +3778,21 @@ impl Config {
         self.dfa.unwrap_or(true)
     }
 
-    // /// Returns whether the one-pass DFA regex engine may be used, as set by
-    // /// [`Config::onepass`].
-    // ///
-    // /// If it was not explicitly set, then a default value is returned.
-    // pub fn get_onepass(&self) -> bool {
-    //     self.onepass.unwrap_or(true)
-    // }
-
-    // /// Returns whether the bounded backtracking regex engine may be used, as
-    // /// set by [`Config::backtrack`].
-    // ///
-    // /// If it was not explicitly set, then a default value is returned.
-    // pub fn get_backtrack(&self) -> bool {
-    //     #[cfg(feature = "nfa-backtrack")]
-    //     {
-    //         self.backtrack.unwrap_or(true)
-    //     }
-    //     #[cfg(not(feature = "nfa-backtrack"))]
-    //     {
-    //         false
-    //     }
-    // }
+    /// Returns whether the one-pass DFA regex engine may be used, as set by
+    /// [`Config::onepass`].
+    ///
+    /// If it was not explicitly set, then a default value is returned.
+    pub fn get_onepass(&self) -> bool {
+        self.onepass.unwrap_or(true)
+    }
+
+    /// Returns whether the bounded backtracking regex engine may be used, as
+    /// set by [`Config::backtrack`].
+    ///
+    /// If it was not explicitly set, then a default value is returned.
+    pub fn get_backtrack(&self) -> bool {
+        self.backtrack.unwrap_or(true)
+    }
 
     /// Overwrite the default configuration such that the options in `o` are
     /// always used. If an option in `o` is not set, then the corresponding
@@ -3103,7 +3812,7 @@ impl Config {
             dfa: o.dfa.or(self.dfa),
             dfa_size_limit: o.dfa_size_limit.or(self.dfa_size_limit),
             dfa_state_limit: o.dfa_state_limit.or(self.dfa_state_limit),
-            // onepass: o.onepass.or(self.onepass),
+            onepass: o.onepass.or(self.onepass),
             backtrack: o.backtrack.or(self.backtrack),
             byte_classes: o.byte_classes.or(self.byte_classes),
             line_terminator: o.line_terminator.or(self.line_terminator),
@@ -0,0 +1,251 @@
+/*!
+This module defines two bespoke forward DFA search routines. One for the lazy
+DFA and one for the fully compiled DFA. These routines differ from the normal
+ones by reporting the position at which the search terminates when a match
+*isn't* found.
+
+This position at which a search terminates is useful in contexts where the meta
+regex engine runs optimizations that could go quadratic if we aren't careful.
+Namely, a regex search *could* scan to the end of the haystack only to report a
+non-match. If the caller doesn't know that the search scanned to the end of the
+haystack, it might restart the search at the next literal candidate it finds
+and repeat the process.
+
+Providing the caller with the position at which the search stopped provides a
+way for the caller to determine the point at which subsequent scans should not
+pass. This is principally used in the "reverse inner" optimization, which works
+like this:
+
+1. Look for a match of an inner literal. Say, 'Z' in '\w+Z\d+'.
+2. At the spot where 'Z' matches, do a reverse anchored search from there for
+   '\w+'.
+3. If the reverse search matches, it corresponds to the start position of a
+   (possible) match. At this point, do a forward anchored search to find the
+   end position. If an end position is found, then we have a match and we know
+   its bounds.
+
+If the forward anchored search in (3) searches the entire rest of the haystack
+but reports a non-match, then a naive implementation of the above will continue
+back at step 1 looking for more candidates. There might still be a match to be
+found! It's possible. But we already scanned the whole haystack. So if we keep
+repeating the process, then we might wind up taking quadratic time in the size
+of the haystack, which is not great.
+
+So if the forward anchored search in (3) reports the position at which it
+stops, then we can detect whether quadratic behavior might be occurring in
+steps (1) and (2). For (1), it occurs if the literal candidate found occurs
+*before* the end of the previous search in (3), since that means we're now
+going to look for another match in a place where the forward search has already
+scanned. It is *correct* to do so, but our technique has become inefficient.
+For (2), quadratic behavior occurs similarly when its reverse search extends
+past the point where the previous forward search in (3) terminated. Indeed, to
+implement (2), we use the sibling 'limited' module for ensuring our reverse
+scan doesn't go further than we want.
+
+For cursors, the end of the haystack may not be known up front. The searches
+below simply walk the cursor until it is exhausted, at which point
+`input.end()` reflects the real end of the haystack.
+*/
+
+use regex_automata::dfa::{Automaton, StartError};
+use regex_automata::hybrid::dfa::{Cache, DFA};
+use regex_automata::hybrid::{LazyStateID, StartError as LazyStartError};
+use regex_automata::util::primitives::StateID;
+use regex_automata::util::start;
+use regex_automata::{HalfMatch, MatchError};
+
+use crate::cursor::Cursor;
+use crate::engines::meta::error::RetryFailError;
+use crate::Input;
+
+pub(crate) fn dfa_try_search_half_fwd<A: Automaton + ?Sized, C: Cursor>(
+    dfa: &A,
+    input: &mut Input<C>,
+) -> Result<Result<HalfMatch, usize>, RetryFailError> {
+    let mut mat = None;
+    input.move_to(input.start());
+    let mut sid = dfa_init_fwd(dfa, input)?;
+    while input.at() < input.end() {
+        if input.chunk_pos == input.chunk().len() {
+            if !input.advance() {
+                break;
+            }
+            continue;
+        }
+        let byte = input.chunk()[input.chunk_pos];
+        sid = dfa.next_state(sid, byte);
+        if dfa.is_special_state(sid) {
+            if dfa.is_match_state(sid) {
+                let pattern = dfa.match_pattern(sid, 0);
+                mat = Some(HalfMatch::new(pattern, input.at()));
+                if input.get_earliest() {
+                    return Ok(mat.ok_or(input.at()));
+                }
+            } else if dfa.is_dead_state(sid) {
+                return Ok(mat.ok_or(input.at()));
+            } else if dfa.is_quit_state(sid) {
+                return Err(MatchError::quit(byte, input.at()).into());
+            } else {
+                // Ideally we wouldn't use a DFA that specialized start states
+                // and thus 'is_start_state()' could never be true here, but in
+                // practice we reuse the DFA created for the full regex which
+                // will specialize start states whenever there is a prefilter.
+                // Accelerated states are fine to step through one byte at a
+                // time.
+                debug_assert!(dfa.is_start_state(sid) || dfa.is_accel_state(sid));
+            }
+        }
+        input.chunk_pos += 1;
+    }
+    dfa_eoi_fwd(dfa, input, &mut sid, &mut mat)?;
+    Ok(mat.ok_or(input.end()))
+}
+
+pub(crate) fn hybrid_try_search_half_fwd<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+) -> Result<Result<HalfMatch, usize>, RetryFailError> {
+    let mut mat = None;
+    input.move_to(input.start());
+    let mut sid = hybrid_init_fwd(dfa, cache, input)?;
+    while input.at() < input.end() {
+        if input.chunk_pos == input.chunk().len() {
+            if !input.advance() {
+                break;
+            }
+            continue;
+        }
+        let byte = input.chunk()[input.chunk_pos];
+        sid = dfa.next_state(cache, sid, byte).map_err(|_| MatchError::gave_up(input.at()))?;
+        if sid.is_tagged() {
+            if sid.is_match() {
+                let pattern = dfa.match_pattern(cache, sid, 0);
+                mat = Some(HalfMatch::new(pattern, input.at()));
+                if input.get_earliest() {
+                    return Ok(mat.ok_or(input.at()));
+                }
+            } else if sid.is_dead() {
+                return Ok(mat.ok_or(input.at()));
+            } else if sid.is_quit() {
+                return Err(MatchError::quit(byte, input.at()).into());
+            } else {
+                // We should NEVER get an unknown state ID back from
+                // dfa.next_state().
+                debug_assert!(!sid.is_unknown());
+                // Ideally we wouldn't use a lazy DFA that specialized start
+                // states and thus 'sid.is_start()' could never be true here,
+                // but in practice we reuse the lazy DFA created for the full
+                // regex which will specialize start states whenever there is
+                // a prefilter.
+                debug_assert!(sid.is_start());
+            }
+        }
+        input.chunk_pos += 1;
+    }
+    hybrid_eoi_fwd(dfa, cache, input, &mut sid, &mut mat)?;
+    Ok(mat.ok_or(input.end()))
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn dfa_init_fwd<A: Automaton + ?Sized, C: Cursor>(
+    dfa: &A,
+    input: &mut Input<C>,
+) -> Result<StateID, MatchError> {
+    let look_behind = input.ensure_look_behind();
+    let start_config = start::Config::new().look_behind(look_behind).anchored(input.get_anchored());
+    dfa.start_state(&start_config).map_err(|err| match err {
+        StartError::Quit { byte } => {
+            let offset = input.at().checked_sub(1).expect("no quit in start without look-behind");
+            MatchError::quit(byte, offset)
+        }
+        StartError::UnsupportedAnchored { mode } => MatchError::unsupported_anchored(mode),
+        _ => panic!("damm forward compatability"),
+    })
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn hybrid_init_fwd<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+) -> Result<LazyStateID, MatchError> {
+    let look_behind = input.ensure_look_behind();
+    let start_config = start::Config::new().look_behind(look_behind).anchored(input.get_anchored());
+    dfa.start_state(cache, &start_config).map_err(|err| match err {
+        LazyStartError::Quit { byte } => {
+            let offset = input.at().checked_sub(1).expect("no quit in start without look-behind");
+            MatchError::quit(byte, offset)
+        }
+        LazyStartError::UnsupportedAnchored { mode } => MatchError::unsupported_anchored(mode),
+        LazyStartError::Cache { .. } => MatchError::gave_up(input.end()),
+        _ => panic!("damm forward compatability"),
+    })
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn dfa_eoi_fwd<A: Automaton + ?Sized, C: Cursor>(
+    dfa: &A,
+    input: &mut Input<C>,
+    sid: &mut StateID,
+    mat: &mut Option<HalfMatch>,
+) -> Result<(), MatchError> {
+    let sp = input.get_span();
+    input.move_to(sp.end);
+    match input.chunk().get(sp.end - input.chunk_offset()) {
+        Some(&b) => {
+            *sid = dfa.next_state(*sid, b);
+            if dfa.is_match_state(*sid) {
+                let pattern = dfa.match_pattern(*sid, 0);
+                *mat = Some(HalfMatch::new(pattern, sp.end));
+            } else if dfa.is_quit_state(*sid) {
+                return Err(MatchError::quit(b, sp.end));
+            }
+        }
+        None => {
+            *sid = dfa.next_eoi_state(*sid);
+            if dfa.is_match_state(*sid) {
+                let pattern = dfa.match_pattern(*sid, 0);
+                *mat = Some(HalfMatch::new(pattern, sp.end));
+            }
+            // N.B. We don't have to check 'is_quit' here because the EOI
+            // transition can never lead to a quit state.
+            debug_assert!(!dfa.is_quit_state(*sid));
+        }
+    }
+    Ok(())
+}
+
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn hybrid_eoi_fwd<C: Cursor>(
+    dfa: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    sid: &mut LazyStateID,
+    mat: &mut Option<HalfMatch>,
+) -> Result<(), MatchError> {
+    let sp = input.get_span();
+    input.move_to(sp.end);
+    match input.chunk().get(sp.end - input.chunk_offset()) {
+        Some(&b) => {
+            *sid = dfa.next_state(cache, *sid, b).map_err(|_| MatchError::gave_up(sp.end))?;
+            if sid.is_match() {
+                let pattern = dfa.match_pattern(cache, *sid, 0);
+                *mat = Some(HalfMatch::new(pattern, sp.end));
+            } else if sid.is_quit() {
+                return Err(MatchError::quit(b, sp.end));
+            }
+        }
+        None => {
+            *sid = dfa.next_eoi_state(cache, *sid).map_err(|_| MatchError::gave_up(sp.end))?;
+            if sid.is_match() {
+                let pattern = dfa.match_pattern(cache, *sid, 0);
+                *mat = Some(HalfMatch::new(pattern, sp.end));
+            }
+            // N.B. We don't have to check 'is_quit' here because the EOI
+            // transition can never lead to a quit state.
+            debug_assert!(!sid.is_quit());
+        }
+    }
+    Ok(())
+}
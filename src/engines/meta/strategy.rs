@@ -1,4 +1,5 @@
 use core::fmt::Debug;
+use core::task::{ready, Context, Poll};
 
 use std::sync::Arc;
 
@@ -6,23 +7,25 @@ use log::{debug, trace};
 use regex_syntax::hir::{literal, Hir};
 
 use crate::{
-    cursor::Cursor,
+    cursor::{AsyncCursor, Cursor},
     engines::meta::{
-        error::{BuildError, RetryFailError},
+        error::{BuildError, RetryError, RetryFailError, RetryQuadraticError},
+        poll::{self, DfaPoll, PikeVMPoll, PrePoll},
         regex::{Cache, RegexInfo},
-        wrappers,
+        reverse_inner, wrappers,
     },
     Input,
 };
 use regex_automata::{
+    hybrid::LazyStateID,
     nfa::thompson::{self, WhichCaptures, NFA},
     util::{
         captures::{Captures, GroupInfo},
         look::LookMatcher,
         prefilter::{self, Prefilter},
-        primitives::{NonMaxUsize, PatternID},
+        primitives::{NonMaxUsize, PatternID, StateID},
     },
-    Anchored, HalfMatch, Match, MatchKind,
+    Anchored, HalfMatch, Match, MatchKind, PatternSet,
 };
 
 /// A trait that represents a single meta strategy. Its main utility is in
@@ -95,6 +98,49 @@ impl Strategy {
     ) -> Option<PatternID> {
         self.0.search_slots(cache, input, slots)
     }
+
+    pub(super) fn which_overlapping_matches<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        patset: &mut PatternSet,
+    ) {
+        self.0.which_overlapping_matches(cache, input, patset)
+    }
+
+    pub(super) fn search_overlapping<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        state: &mut OverlappingState,
+    ) -> Option<Match> {
+        self.0.search_overlapping(cache, input, state)
+    }
+
+    pub(super) fn search_half_rev<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+    ) -> Option<HalfMatch> {
+        self.0.search_half_rev(cache, input)
+    }
+
+    pub(super) fn poll_search<A: AsyncCursor>(
+        &self,
+        cache: &mut Cache,
+        state: &mut PollState,
+        cursor: &mut A,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Match>> {
+        // Skipping an empty match moves the start of the next search one
+        // past it, which may be past the end of the haystack.
+        if let PollEngine::Start = state.engine {
+            if state.start > 0 && !ready!(poll::poll_seek(cursor, cx, state.start - 1)) {
+                return Poll::Ready(None);
+            }
+        }
+        self.0.poll_search(cache, state, cursor, cx)
+    }
 }
 
 #[derive(Debug)]
@@ -102,6 +148,8 @@ enum StrategyI {
     Core(Core),
     Pre(Pre),
     ReverseAnchored(ReverseAnchored),
+    ReverseSuffix(ReverseSuffix),
+    ReverseInner(ReverseInner),
 }
 
 impl StrategyI {
@@ -188,20 +236,20 @@ impl StrategyI {
                 return Ok(Self::ReverseAnchored(ra));
             }
         };
-        // core = match ReverseSuffix::new(core, hirs) {
-        //     Err(core) => core,
-        //     Ok(rs) => {
-        //         debug!("using reverse suffix strategy");
-        //         return Ok(Arc::new(rs));
-        //     }
-        // };
-        // core = match ReverseInner::new(core, hirs) {
-        //     Err(core) => core,
-        //     Ok(ri) => {
-        //         debug!("using reverse inner strategy");
-        //         return Ok(Arc::new(ri));
-        //     }
-        // };
+        core = match ReverseSuffix::new(core, hirs) {
+            Err(core) => core,
+            Ok(rs) => {
+                debug!("using reverse suffix strategy");
+                return Ok(Self::ReverseSuffix(rs));
+            }
+        };
+        core = match ReverseInner::new(core, hirs) {
+            Err(core) => core,
+            Ok(ri) => {
+                debug!("using reverse inner strategy");
+                return Ok(Self::ReverseInner(ri));
+            }
+        };
         debug!("using core strategy");
         Ok(Self::Core(core))
     }
@@ -211,6 +259,8 @@ impl StrategyI {
             Self::Core(core) => core.group_info(),
             Self::Pre(pre) => pre.group_info(),
             Self::ReverseAnchored(rev_anchored) => rev_anchored.group_info(),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.group_info(),
+            Self::ReverseInner(rev_inner) => rev_inner.group_info(),
         }
     }
 
@@ -219,6 +269,8 @@ impl StrategyI {
             Self::Core(core) => core.create_cache(),
             Self::Pre(pre) => pre.create_cache(),
             Self::ReverseAnchored(rev_anchored) => rev_anchored.create_cache(),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.create_cache(),
+            Self::ReverseInner(rev_inner) => rev_inner.create_cache(),
         }
     }
 
@@ -227,6 +279,8 @@ impl StrategyI {
             Self::Core(core) => core.reset_cache(cache),
             Self::Pre(pre) => pre.reset_cache(cache),
             Self::ReverseAnchored(rev_anchored) => rev_anchored.reset_cache(cache),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.reset_cache(cache),
+            Self::ReverseInner(rev_inner) => rev_inner.reset_cache(cache),
         }
     }
 
@@ -235,6 +289,8 @@ impl StrategyI {
             Self::Core(core) => core.is_accelerated(),
             Self::Pre(pre) => pre.is_accelerated(),
             Self::ReverseAnchored(rev_anchored) => rev_anchored.is_accelerated(),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.is_accelerated(),
+            Self::ReverseInner(rev_inner) => rev_inner.is_accelerated(),
         }
     }
 
@@ -243,6 +299,8 @@ impl StrategyI {
             Self::Core(core) => core.memory_usage(),
             Self::Pre(pre) => pre.memory_usage(),
             Self::ReverseAnchored(rev_anchored) => rev_anchored.memory_usage(),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.memory_usage(),
+            Self::ReverseInner(rev_inner) => rev_inner.memory_usage(),
         }
     }
 
@@ -255,6 +313,8 @@ impl StrategyI {
             Self::Core(core) => core.search(cache, input),
             Self::Pre(pre) => pre.search(cache, input),
             Self::ReverseAnchored(rev_anchored) => rev_anchored.search(cache, input),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.search(cache, input),
+            Self::ReverseInner(rev_inner) => rev_inner.search(cache, input),
         }
     }
 
@@ -267,6 +327,8 @@ impl StrategyI {
             Self::Core(core) => core.search_half(cache, input),
             Self::Pre(pre) => pre.search_half(cache, input),
             Self::ReverseAnchored(rev_anchored) => rev_anchored.search_half(cache, input),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.search_half(cache, input),
+            Self::ReverseInner(rev_inner) => rev_inner.search_half(cache, input),
         }
     }
 
@@ -275,6 +337,8 @@ impl StrategyI {
             Self::Core(core) => core.is_match(cache, input),
             Self::Pre(pre) => pre.is_match(cache, input),
             Self::ReverseAnchored(rev_anchored) => rev_anchored.is_match(cache, input),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.is_match(cache, input),
+            Self::ReverseInner(rev_inner) => rev_inner.is_match(cache, input),
         }
     }
 
@@ -288,6 +352,80 @@ impl StrategyI {
             Self::Core(core) => core.search_slots(cache, input, slots),
             Self::Pre(pre) => pre.search_slots(cache, input, slots),
             Self::ReverseAnchored(rev_anchored) => rev_anchored.search_slots(cache, input, slots),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.search_slots(cache, input, slots),
+            Self::ReverseInner(rev_inner) => rev_inner.search_slots(cache, input, slots),
+        }
+    }
+
+    pub(super) fn which_overlapping_matches<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        patset: &mut PatternSet,
+    ) {
+        match self {
+            Self::Core(core) => core.which_overlapping_matches(cache, input, patset),
+            Self::Pre(pre) => pre.which_overlapping_matches(cache, input, patset),
+            Self::ReverseAnchored(rev_anchored) => {
+                rev_anchored.which_overlapping_matches(cache, input, patset)
+            }
+            Self::ReverseSuffix(rev_suffix) => {
+                rev_suffix.which_overlapping_matches(cache, input, patset)
+            }
+            Self::ReverseInner(rev_inner) => {
+                rev_inner.which_overlapping_matches(cache, input, patset)
+            }
+        }
+    }
+
+    pub(super) fn search_overlapping<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        state: &mut OverlappingState,
+    ) -> Option<Match> {
+        match self {
+            Self::Core(core) => core.search_overlapping(cache, input, state),
+            Self::Pre(pre) => pre.search_overlapping(cache, input, state),
+            Self::ReverseAnchored(rev_anchored) => {
+                rev_anchored.search_overlapping(cache, input, state)
+            }
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.search_overlapping(cache, input, state),
+            Self::ReverseInner(rev_inner) => rev_inner.search_overlapping(cache, input, state),
+        }
+    }
+
+    pub(super) fn search_half_rev<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+    ) -> Option<HalfMatch> {
+        match self {
+            Self::Core(core) => core.search_half_rev(cache, input),
+            Self::Pre(pre) => pre.search_half_rev(cache, input),
+            Self::ReverseAnchored(rev_anchored) => rev_anchored.core.search_half_rev(cache, input),
+            Self::ReverseSuffix(rev_suffix) => rev_suffix.core.search_half_rev(cache, input),
+            Self::ReverseInner(rev_inner) => rev_inner.core.search_half_rev(cache, input),
+        }
+    }
+
+    pub(super) fn poll_search<A: AsyncCursor>(
+        &self,
+        cache: &mut Cache,
+        state: &mut PollState,
+        cursor: &mut A,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Match>> {
+        match self {
+            Self::Core(core) => core.poll_search(cache, state, cursor, cx),
+            Self::Pre(pre) => pre.poll_search(cache, state, cursor, cx),
+            Self::ReverseAnchored(rev_anchored) => {
+                rev_anchored.core.poll_search(cache, state, cursor, cx)
+            }
+            Self::ReverseSuffix(rev_suffix) => {
+                rev_suffix.core.poll_search(cache, state, cursor, cx)
+            }
+            Self::ReverseInner(rev_inner) => rev_inner.core.poll_search(cache, state, cursor, cx),
         }
     }
 }
@@ -446,10 +584,10 @@ impl Pre {
         Cache {
             capmatches: Captures::all(self.group_info().clone()),
             pikevm: wrappers::PikeVMCache::none(),
-            // backtrack: wrappers::BoundedBacktrackerCache::none(),
-            // onepass: wrappers::OnePassCache::none(),
+            backtrack: wrappers::BoundedBacktrackerCache::none(),
+            onepass: wrappers::OnePassCache::none(),
             hybrid: wrappers::HybridCache::none(),
-            // revhybrid: wrappers::ReverseHybridCache::none(),
+            revhybrid: wrappers::ReverseHybridCache::none(),
         }
     }
 
@@ -468,6 +606,7 @@ impl Pre {
         if input.is_done() {
             return None;
         }
+        input.move_to(input.start());
         if input.get_anchored().is_anchored() {
             return crate::literal::prefix(&self.pre, input)
                 .map(|sp| Match::new(PatternID::ZERO, sp));
@@ -501,6 +640,87 @@ impl Pre {
         }
         Some(m.pattern())
     }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn which_overlapping_matches<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        patset: &mut PatternSet,
+    ) {
+        if self.search(cache, input).is_some() {
+            patset.insert(PatternID::ZERO);
+        }
+    }
+
+    fn search_overlapping<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        state: &mut OverlappingState,
+    ) -> Option<Match> {
+        // The prefilter can only report the leftmost-first match starting at
+        // or after a given position, so the best we can do is to report one
+        // match per starting position. In practice this strategy is never
+        // selected for `MatchKind::All`, which is the only match kind where
+        // overlapping searches are meaningful.
+        let at = match state.engine {
+            OverlappingEngine::Start => input.start(),
+            OverlappingEngine::Fallback { at } => at,
+            _ => return None,
+        };
+        input.move_to(at);
+        if at > input.end() || (input.get_anchored().is_anchored() && at > input.start()) {
+            state.engine = OverlappingEngine::Done;
+            return None;
+        }
+        let m = input.with(|input| {
+            input.span(at..input.end());
+            self.search(cache, input)
+        });
+        state.engine = match m {
+            Some(m) => OverlappingEngine::Fallback { at: m.start() + 1 },
+            None => OverlappingEngine::Done,
+        };
+        m
+    }
+
+    fn search_half_rev<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+    ) -> Option<HalfMatch> {
+        // Every match is one of the literals, so the leftmost start of a
+        // match ending at 'end' can't be more than the longest literal away
+        // from it. Just try each of those starting positions in turn.
+        let (start, end) = (input.start(), input.end());
+        let min_start = end.saturating_sub(self.pre.max_needle_len()).max(start);
+        input.with(|input| {
+            input.anchored(Anchored::Yes);
+            (min_start..=end).find_map(|at| {
+                input.span(at..end);
+                let m = self.search(cache, input)?;
+                (m.end() == end).then(|| HalfMatch::new(m.pattern(), at))
+            })
+        })
+    }
+
+    fn poll_search<A: AsyncCursor>(
+        &self,
+        _cache: &mut Cache,
+        state: &mut PollState,
+        cursor: &mut A,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Match>> {
+        if let PollEngine::Start = state.engine {
+            state.engine = PollEngine::Pre(PrePoll::new(state.start));
+        }
+        let PollEngine::Pre(ref mut pre) = state.engine else {
+            unreachable!("prefilter strategy used another engine");
+        };
+        let span = ready!(poll::pre_poll_search(&self.pre, pre, cursor, cx));
+        Poll::Ready(span.map(|span| Match::new(PatternID::ZERO, span)))
+    }
 }
 
 #[derive(Debug)]
@@ -510,8 +730,8 @@ struct Core {
     nfa: NFA,
     nfarev: Option<NFA>,
     pikevm: wrappers::PikeVM,
-    // backtrack: wrappers::BoundedBacktracker,
-    // onepass: wrappers::OnePass,
+    backtrack: wrappers::BoundedBacktracker,
+    onepass: wrappers::OnePass,
     hybrid: wrappers::Hybrid,
     dfa: wrappers::DFA,
 }
@@ -537,12 +757,12 @@ impl Core {
         // match. (Construction can also fail if the NFA was compiled without
         // captures, but we always enable that above.)
         let pikevm = wrappers::PikeVM::new(&info, pre.clone(), &nfa)?;
-        // let backtrack = wrappers::BoundedBacktracker::new(&info, pre.clone(), &nfa)?;
+        let backtrack = wrappers::BoundedBacktracker::new(&info, pre.clone(), &nfa)?;
         // The onepass engine can of course fail to build, but we expect it to
         // fail in many cases because it is an optimization that doesn't apply
         // to all regexes. The 'OnePass' wrapper encapsulates this failure (and
         // logs a message if it occurs).
-        // let onepass = wrappers::OnePass::new(&info, &nfa);
+        let onepass = wrappers::OnePass::new(&info, &nfa);
         // We try to encapsulate whether a particular regex engine should be
         // used within each respective wrapper, but the DFAs need a reverse NFA
         // to build itself, and we really do not want to build a reverse NFA if
@@ -588,7 +808,7 @@ impl Core {
             };
             (Some(nfarev), hybrid, dfa)
         };
-        Ok(Core { info, pre, nfa, nfarev, pikevm, /*backtrack, onepass,*/ hybrid, dfa })
+        Ok(Core { info, pre, nfa, nfarev, pikevm, backtrack, onepass, hybrid, dfa })
     }
 
     #[cfg_attr(feature = "perf-inline", inline(always))]
@@ -617,13 +837,13 @@ impl Core {
         // classic example of how the borrow checker inhibits decomposition.
         // There are of course work-arounds (more types and/or interior
         // mutability), but that's more annoying than this IMO.
-        let pid = /*if let Some(ref e) = self.onepass.get(input) {
+        let pid = if let Some(e) = self.onepass.get(input) {
             trace!("using OnePass for search at {:?}", input.get_span());
             e.search_slots(&mut cache.onepass, input, caps.slots_mut())
-        } else if let Some(ref e) = self.backtrack.get(input) {
+        } else if let Some(e) = self.backtrack.get(input) {
             trace!("using BoundedBacktracker for search at {:?}", input.get_span());
             e.search_slots(&mut cache.backtrack, input, caps.slots_mut())
-        } else */{
+        } else {
             trace!("using PikeVM for search at {:?}", input.get_span());
             let e = self.pikevm.get();
             e.search_slots(&mut cache.pikevm, input, caps.slots_mut())
@@ -651,14 +871,13 @@ impl Core {
         input: &mut Input<C>,
         slots: &mut [Option<NonMaxUsize>],
     ) -> Option<PatternID> {
-        /* if let Some(ref e) = self.onepass.get(input) {
+        if let Some(e) = self.onepass.get(input) {
             trace!("using OnePass for capture search at {:?}", input.get_span());
             e.search_slots(&mut cache.onepass, input, slots)
-        } else if let Some(ref e) = self.backtrack.get(input) {
+        } else if let Some(e) = self.backtrack.get(input) {
             trace!("using BoundedBacktracker for capture search at {:?}", input.get_span());
             e.search_slots(&mut cache.backtrack, input, slots)
-        } else*/
-        {
+        } else {
             trace!("using PikeVM for capture search at {:?}", input.get_span());
             let e = self.pikevm.get();
             e.search_slots(&mut cache.pikevm, input, slots)
@@ -666,14 +885,13 @@ impl Core {
     }
 
     fn is_match_nofail<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> bool {
-        /*if let Some(ref e) = self.onepass.get(input) {
+        if let Some(e) = self.onepass.get(input) {
             trace!("using OnePass for is-match search at {:?}", input.get_span());
             e.search_slots(&mut cache.onepass, input, &mut []).is_some()
-        } else if let Some(ref e) = self.backtrack.get(input) {
+        } else if let Some(e) = self.backtrack.get(input) {
             trace!("using BoundedBacktracker for is-match search at {:?}", input.get_span());
             e.is_match(&mut cache.backtrack, input)
-        } else*/
-        {
+        } else {
             trace!("using PikeVM for is-match search at {:?}", input.get_span());
             let e = self.pikevm.get();
             e.is_match(&mut cache.pikevm, input)
@@ -683,6 +901,117 @@ impl Core {
     fn is_capture_search_needed(&self, slots_len: usize) -> bool {
         slots_len > self.nfa.group_info().implicit_slot_len()
     }
+
+    /// Finds the next end position of an overlapping match with the full DFA
+    /// and pushes every match ending there (for that pattern) to `pending`.
+    /// Nothing is pushed if this fails, so the caller can switch to another
+    /// engine without reporting duplicates.
+    fn search_overlapping_dfa<C: Cursor>(
+        &self,
+        e: &wrappers::DFAEngine,
+        input: &mut Input<C>,
+        fwd: &mut crate::engines::dfa::OverlappingState,
+        pending: &mut Vec<Match>,
+    ) -> Result<Option<HalfMatch>, RetryFailError> {
+        e.try_search_overlapping_fwd(input, fwd)?;
+        let Some(hm) = fwd.get_match() else {
+            return Ok(None);
+        };
+        let (start, anchored) = (input.start(), input.get_anchored());
+        let mut rev = crate::engines::dfa::OverlappingState::start();
+        let mut found = Vec::new();
+        input.with(|input| {
+            input.span(start..hm.offset()).anchored(Anchored::Pattern(hm.pattern()));
+            input.earliest(false);
+            loop {
+                e.try_search_overlapping_rev(input, &mut rev)?;
+                let Some(hm_start) = rev.get_match() else {
+                    return Ok::<(), RetryFailError>(());
+                };
+                if !anchored.is_anchored() || hm_start.offset() == start {
+                    found.push(Match::new(hm.pattern(), hm_start.offset()..hm.offset()));
+                }
+            }
+        })?;
+        pending.extend(found.into_iter().rev());
+        Ok(Some(hm))
+    }
+
+    /// Like 'search_overlapping_dfa', but for the lazy DFA.
+    fn search_overlapping_hybrid<C: Cursor>(
+        &self,
+        e: &wrappers::HybridEngine,
+        cache: &mut wrappers::HybridCache,
+        input: &mut Input<C>,
+        fwd: &mut crate::engines::hybrid::OverlappingState,
+        pending: &mut Vec<Match>,
+    ) -> Result<Option<HalfMatch>, RetryFailError> {
+        e.try_search_overlapping_fwd(cache, input, fwd)?;
+        let Some(hm) = fwd.get_match() else {
+            return Ok(None);
+        };
+        let (start, anchored) = (input.start(), input.get_anchored());
+        let mut rev = crate::engines::hybrid::OverlappingState::start();
+        let mut found = Vec::new();
+        input.with(|input| {
+            input.span(start..hm.offset()).anchored(Anchored::Pattern(hm.pattern()));
+            input.earliest(false);
+            loop {
+                e.try_search_overlapping_rev(cache, input, &mut rev)?;
+                let Some(hm_start) = rev.get_match() else {
+                    return Ok::<(), RetryFailError>(());
+                };
+                if !anchored.is_anchored() || hm_start.offset() == start {
+                    found.push(Match::new(hm.pattern(), hm_start.offset()..hm.offset()));
+                }
+            }
+        })?;
+        pending.extend(found.into_iter().rev());
+        Ok(Some(hm))
+    }
+
+    /// Pushes every match starting at `at` to `pending` using an anchored
+    /// PikeVM search and moves `at` to the next starting position. Returns
+    /// false once there are no starting positions left.
+    ///
+    /// This is quadratic in the size of the haystack, but it never fails.
+    /// Matches that a DFA has already reported (as recorded in `reported`)
+    /// are skipped.
+    fn search_overlapping_nofail<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        at: &mut usize,
+        reported: Option<&(usize, Vec<PatternID>)>,
+        pending: &mut Vec<Match>,
+    ) -> bool {
+        let start = *at;
+        input.move_to(start);
+        let anchored = input.get_anchored();
+        if start > input.end() || (anchored.is_anchored() && start > input.start()) {
+            return false;
+        }
+        *at += 1;
+        let mut found = Vec::new();
+        input.with(|input| {
+            input.span(start..input.end()).earliest(false);
+            if !anchored.is_anchored() {
+                input.anchored(Anchored::Yes);
+            }
+            let e = self.pikevm.get();
+            e.search_overlapping(&mut cache.pikevm, input, |pid, end| {
+                let seen = reported.map_or(false, |&(last_end, ref pids)| {
+                    end < last_end || (end == last_end && pids.contains(&pid))
+                });
+                if !seen {
+                    found.push(Match::new(pid, start..end));
+                }
+                false
+            });
+        });
+        pending.extend(found.into_iter().rev());
+        true
+    }
 }
 
 impl Core {
@@ -696,18 +1025,18 @@ impl Core {
         Cache {
             capmatches: Captures::all(self.group_info().clone()),
             pikevm: self.pikevm.create_cache(),
-            // backtrack: self.backtrack.create_cache(),
-            // onepass: self.onepass.create_cache(),
+            backtrack: self.backtrack.create_cache(),
+            onepass: self.onepass.create_cache(),
             hybrid: self.hybrid.create_cache(),
-            // revhybrid: wrappers::ReverseHybridCache::none(),
+            revhybrid: wrappers::ReverseHybridCache::none(),
         }
     }
 
     #[cfg_attr(feature = "perf-inline", inline(always))]
     fn reset_cache(&self, cache: &mut Cache) {
         cache.pikevm.reset(&self.pikevm);
-        // cache.backtrack.reset(&self.backtrack);
-        // cache.onepass.reset(&self.onepass);
+        cache.backtrack.reset(&self.backtrack);
+        cache.onepass.reset(&self.onepass);
         cache.hybrid.reset(&self.hybrid);
     }
 
@@ -720,7 +1049,7 @@ impl Core {
             + self.pre.as_ref().map_or(0, |pre| pre.memory_usage())
             + self.nfa.memory_usage()
             + self.nfarev.as_ref().map_or(0, |nfa| nfa.memory_usage())
-            // + self.onepass.memory_usage()
+            + self.onepass.memory_usage()
             + self.dfa.memory_usage()
     }
 
@@ -835,9 +1164,9 @@ impl Core {
         // in that case, the line always matches. So the lazy DFA scan is
         // usually just wasted work. But, the lazy DFA is usually quite fast
         // and doesn't cost too much here.
-        // if self.onepass.get(&mut input).is_some() {
-        //     return self.search_slots_nofail(cache, &mut input, slots);
-        // }
+        if self.onepass.get(input).is_some() {
+            return self.search_slots_nofail(cache, input, slots);
+        }
         let m = match self.try_search_mayfail(cache, input) {
             Some(Ok(Some(m))) => m,
             Some(Ok(None)) => return None,
@@ -865,6 +1194,241 @@ impl Core {
         });
         Some(res)
     }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn which_overlapping_matches<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        patset: &mut PatternSet,
+    ) {
+        if let Some(e) = self.dfa.get(input) {
+            trace!("using full DFA for overlapping search at {:?}", input.get_span());
+            let _err = match e.try_which_overlapping_matches(input, patset) {
+                Ok(()) => return,
+                Err(err) => err,
+            };
+            trace!("fast overlapping search failed: {}", _err);
+        } else if let Some(e) = self.hybrid.get(input) {
+            trace!("using lazy DFA for overlapping search at {:?}", input.get_span());
+            let _err = match e.try_which_overlapping_matches(&mut cache.hybrid, input, patset) {
+                Ok(()) => {
+                    return;
+                }
+                Err(err) => err,
+            };
+            trace!("fast overlapping search failed: {}", _err);
+        }
+        trace!("using PikeVM for overlapping search at {:?}", input.get_span());
+        let e = self.pikevm.get();
+        e.which_overlapping_matches(&mut cache.pikevm, input, patset)
+    }
+
+    fn search_overlapping<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        state: &mut OverlappingState,
+    ) -> Option<Match> {
+        loop {
+            if let Some(m) = state.pending.pop() {
+                return Some(m);
+            }
+            let result = match state.engine {
+                OverlappingEngine::Start => {
+                    state.engine = if self.dfa.get(input).is_some() {
+                        trace!("using full DFA for overlapping search at {:?}", input.get_span());
+                        OverlappingEngine::DFA(crate::engines::dfa::OverlappingState::start())
+                    } else if self.hybrid.get(input).is_some() {
+                        trace!("using lazy DFA for overlapping search at {:?}", input.get_span());
+                        OverlappingEngine::Hybrid(crate::engines::hybrid::OverlappingState::start())
+                    } else {
+                        trace!("using PikeVM for overlapping search at {:?}", input.get_span());
+                        OverlappingEngine::Fallback { at: input.start() }
+                    };
+                    continue;
+                }
+                OverlappingEngine::DFA(ref mut fwd) => {
+                    let e = self.dfa.get(input).unwrap();
+                    self.search_overlapping_dfa(e, input, fwd, &mut state.pending)
+                }
+                OverlappingEngine::Hybrid(ref mut fwd) => {
+                    let e = self.hybrid.get(input).unwrap();
+                    let hybrid_cache = &mut cache.hybrid;
+                    self.search_overlapping_hybrid(e, hybrid_cache, input, fwd, &mut state.pending)
+                }
+                OverlappingEngine::Fallback { ref mut at } => {
+                    let reported = state.reported.as_ref();
+                    let pending = &mut state.pending;
+                    if !self.search_overlapping_nofail(cache, input, at, reported, pending) {
+                        state.engine = OverlappingEngine::Done;
+                    }
+                    continue;
+                }
+                OverlappingEngine::Done => return None,
+            };
+            match result {
+                Ok(Some(hm)) => match state.reported {
+                    Some((end, ref mut pids)) if end == hm.offset() => pids.push(hm.pattern()),
+                    _ => state.reported = Some((hm.offset(), vec![hm.pattern()])),
+                },
+                Ok(None) => state.engine = OverlappingEngine::Done,
+                Err(_err) => {
+                    trace!("fast overlapping search failed: {}", _err);
+                    state.engine = OverlappingEngine::Fallback { at: input.start() };
+                }
+            }
+        }
+    }
+
+    fn search_half_rev<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+    ) -> Option<HalfMatch> {
+        // None of the infallible engines can search in reverse, so there's
+        // nothing to fall back to if the DFAs fail. The caller is expected to
+        // use a forward search instead.
+        let result = if let Some(e) = self.dfa.get(input) {
+            trace!("using full DFA for reverse search at {:?}", input.get_span());
+            e.try_search_half_rev(input)
+        } else if let Some(e) = self.hybrid.get(input) {
+            trace!("using lazy DFA for reverse search at {:?}", input.get_span());
+            e.try_search_half_rev(&mut cache.hybrid, input)
+        } else {
+            return None;
+        };
+        match result {
+            Ok(hm) => hm,
+            Err(_err) => {
+                trace!("reverse search failed: {}", _err);
+                None
+            }
+        }
+    }
+
+    fn poll_search<A: AsyncCursor>(
+        &self,
+        cache: &mut Cache,
+        state: &mut PollState,
+        cursor: &mut A,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Match>> {
+        loop {
+            let result = match state.engine {
+                PollEngine::Start => {
+                    state.engine = if self.dfa.get_async().is_some() {
+                        trace!("using full DFA for async search at {}", state.start);
+                        PollEngine::Dfa(DfaPoll::new(state.start))
+                    } else if self.hybrid.get_async().is_some() {
+                        trace!("using lazy DFA for async search at {}", state.start);
+                        PollEngine::Hybrid(DfaPoll::new(state.start))
+                    } else {
+                        trace!("using PikeVM for async search at {}", state.start);
+                        self.poll_fallback(state)
+                    };
+                    continue;
+                }
+                PollEngine::Dfa(ref mut fwd) => {
+                    let e = self.dfa.get_async().unwrap();
+                    match ready!(e.poll_search_half_fwd(fwd, state.earliest, cursor, cx)) {
+                        Ok(Some(hm)) if !state.earliest => {
+                            state.engine = PollEngine::DfaRev(hm, DfaPoll::new(hm.offset()));
+                            continue;
+                        }
+                        result => result,
+                    }
+                }
+                PollEngine::DfaRev(end, ref mut rev) => {
+                    let e = self.dfa.get_async().unwrap();
+                    match ready!(e.poll_search_half_rev(rev, state.start, cursor, cx)) {
+                        Ok(start) => {
+                            let start =
+                                start.expect("reverse search must match if forward search does");
+                            let m = Match::new(end.pattern(), start.offset()..end.offset());
+                            state.engine = PollEngine::Split(m);
+                            continue;
+                        }
+                        Err(err) => Err(err),
+                    }
+                }
+                PollEngine::Hybrid(ref mut fwd) => {
+                    let e = self.hybrid.get_async().unwrap();
+                    let hybrid_cache = &mut cache.hybrid;
+                    match ready!(e.poll_search_half_fwd(
+                        hybrid_cache,
+                        fwd,
+                        state.earliest,
+                        cursor,
+                        cx
+                    )) {
+                        Ok(Some(hm)) if !state.earliest => {
+                            state.engine = PollEngine::HybridRev(hm, DfaPoll::new(hm.offset()));
+                            continue;
+                        }
+                        result => result,
+                    }
+                }
+                PollEngine::HybridRev(end, ref mut rev) => {
+                    let e = self.hybrid.get_async().unwrap();
+                    let hybrid_cache = &mut cache.hybrid;
+                    match ready!(e.poll_search_half_rev(hybrid_cache, rev, state.start, cursor, cx))
+                    {
+                        Ok(start) => {
+                            let start =
+                                start.expect("reverse search must match if forward search does");
+                            let m = Match::new(end.pattern(), start.offset()..end.offset());
+                            state.engine = PollEngine::Split(m);
+                            continue;
+                        }
+                        Err(err) => Err(err),
+                    }
+                }
+                PollEngine::PikeVM(ref mut vm) => {
+                    let e = self.pikevm.get();
+                    match ready!(e.poll_search(&mut cache.pikevm, vm, cursor, cx)) {
+                        Some(m) if !state.earliest => {
+                            state.engine = PollEngine::Split(m);
+                            continue;
+                        }
+                        m => return Poll::Ready(m),
+                    }
+                }
+                PollEngine::Split(m) => {
+                    // See 'util::empty::skip_splits_fwd'.
+                    let utf8empty = self.nfa.has_empty() && self.nfa.is_utf8();
+                    if utf8empty
+                        && m.is_empty()
+                        && !ready!(poll::poll_is_boundary(cursor, cx, m.end()))
+                    {
+                        state.start = m.end() + 1;
+                        state.engine = PollEngine::Start;
+                        continue;
+                    }
+                    return Poll::Ready(Some(m));
+                }
+                PollEngine::Pre(_) => unreachable!("core strategy used a prefilter scan"),
+            };
+            match result {
+                // The start of the match isn't searched for when looking for
+                // the earliest match.
+                Ok(hm) => {
+                    let m = hm.map(|hm| Match::new(hm.pattern(), hm.offset()..hm.offset()));
+                    return Poll::Ready(m);
+                }
+                Err(_err) => {
+                    trace!("async DFA search failed: {}", _err);
+                    state.engine = self.poll_fallback(state);
+                }
+            }
+        }
+    }
+
+    /// Returns the state of a PikeVM search starting over at the start of
+    /// the given search.
+    fn poll_fallback(&self, state: &PollState) -> PollEngine {
+        PollEngine::PikeVM(PikeVMPoll::new(&self.nfa, state.start, state.earliest))
+    }
 }
 
 #[derive(Debug)]
@@ -941,6 +1505,10 @@ impl ReverseAnchored {
 // here because the 'Regex' wrapper actually does that for us in all cases.
 // Thus, in this impl, we can actually assume that the end position in 'input'
 // is equivalent to the length of the haystack.
+//
+// That doesn't hold for cursors that don't know the length of their haystack
+// (like a 'ReaderCursor'). The end of 'input' is 'usize::MAX' then, which a
+// reverse search can't start at, so those searches are handed to 'Core'.
 impl ReverseAnchored {
     #[cfg_attr(feature = "perf-inline", inline(always))]
     fn group_info(&self) -> &GroupInfo {
@@ -970,7 +1538,7 @@ impl ReverseAnchored {
 
     #[cfg_attr(feature = "perf-inline", inline(always))]
     fn search<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<Match> {
-        if input.get_anchored().is_anchored() {
+        if input.get_anchored().is_anchored() || input.haystack_len().is_none() {
             return self.core.search(cache, input);
         }
         match self.try_search_half_anchored_rev(cache, input) {
@@ -985,7 +1553,7 @@ impl ReverseAnchored {
 
     #[cfg_attr(feature = "perf-inline", inline(always))]
     fn search_half<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<HalfMatch> {
-        if input.get_anchored().is_anchored() {
+        if input.get_anchored().is_anchored() || input.haystack_len().is_none() {
             return self.core.search_half(cache, input);
         }
         match self.try_search_half_anchored_rev(cache, input) {
@@ -1008,7 +1576,7 @@ impl ReverseAnchored {
 
     #[cfg_attr(feature = "perf-inline", inline(always))]
     fn is_match<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> bool {
-        if input.get_anchored().is_anchored() {
+        if input.get_anchored().is_anchored() || input.haystack_len().is_none() {
             return self.core.is_match(cache, input);
         }
         match self.try_search_half_anchored_rev(cache, input) {
@@ -1028,7 +1596,7 @@ impl ReverseAnchored {
         input: &mut Input<C>,
         slots: &mut [Option<NonMaxUsize>],
     ) -> Option<PatternID> {
-        if input.get_anchored().is_anchored() {
+        if input.get_anchored().is_anchored() || input.haystack_len().is_none() {
             return self.core.search_slots(cache, input, slots);
         }
         match self.try_search_half_anchored_rev(cache, input) {
@@ -1053,744 +1621,884 @@ impl ReverseAnchored {
         }
     }
 
-    // #[cfg_attr(feature = "perf-inline", inline(always))]
-    // fn which_overlapping_matches(
-    //     &self,
-    //     cache: &mut Cache,
-    //     input: &mut Input<C>,
-    //     patset: &mut PatternSet,
-    // ) {
-    //     // It seems like this could probably benefit from a reverse anchored
-    //     // optimization, perhaps by doing an overlapping reverse search (which
-    //     // the DFAs do support). I haven't given it much thought though, and
-    //     // I'm currently focus more on the single pattern case.
-    //     self.core.which_overlapping_matches(cache, input, patset)
-    // }
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn which_overlapping_matches<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        patset: &mut PatternSet,
+    ) {
+        // It seems like this could probably benefit from a reverse anchored
+        // optimization, perhaps by doing an overlapping reverse search (which
+        // the DFAs do support). I haven't given it much thought though, and
+        // I'm currently focus more on the single pattern case.
+        self.core.which_overlapping_matches(cache, input, patset)
+    }
+
+    fn search_overlapping<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        state: &mut OverlappingState,
+    ) -> Option<Match> {
+        self.core.search_overlapping(cache, input, state)
+    }
 }
 
-// #[derive(Debug)]
-// struct ReverseSuffix {
-//     core: Core,
-//     pre: Prefilter,
-// }
-
-// impl ReverseSuffix {
-//     fn new(core: Core, hirs: &[&Hir]) -> Result<ReverseSuffix, Core> {
-//         if !core.info.config().get_auto_prefilter() {
-//             debug!(
-//                 "skipping reverse suffix optimization because \
-//                  automatic prefilters are disabled"
-//             );
-//             return Err(core);
-//         }
-//         // Like the reverse inner optimization, we don't do this for regexes
-//         // that are always anchored. It could lead to scanning too much, but
-//         // could say "no match" much more quickly than running the regex
-//         // engine if the initial literal scan doesn't match. With that said,
-//         // the reverse suffix optimization has lower overhead, since it only
-//         // requires a reverse scan after a literal match to confirm or reject
-//         // the match. (Although, in the case of confirmation, it then needs to
-//         // do another forward scan to find the end position.)
-//         //
-//         // Note that the caller can still request an anchored search even
-//         // when the regex isn't anchored. We detect that case in the search
-//         // routines below and just fallback to the core engine. Currently this
-//         // optimization assumes all searches are unanchored, so if we do want
-//         // to enable this optimization for anchored searches, it will need a
-//         // little work to support it.
-//         if core.info.is_always_anchored_start() {
-//             debug!(
-//                 "skipping reverse suffix optimization because \
-// 				 the regex is always anchored at the start",
-//             );
-//             return Err(core);
-//         }
-//         // Only DFAs can do reverse searches (currently), so we need one of
-//         // them in order to do this optimization. It's possible (although
-//         // pretty unlikely) that we have neither and need to give up.
-//         if !core.hybrid.is_some() && !core.dfa.is_some() {
-//             debug!(
-//                 "skipping reverse suffix optimization because \
-// 				 we don't have a lazy DFA or a full DFA"
-//             );
-//             return Err(core);
-//         }
-//         if core.pre.as_ref().map_or(false, |p| p.is_fast()) {
-//             debug!(
-//                 "skipping reverse suffix optimization because \
-// 				 we already have a prefilter that we think is fast"
-//             );
-//             return Err(core);
-//         }
-//         let kind = core.info.config().get_match_kind();
-//         let suffixseq = crate::util::prefilter::suffixes(kind, hirs);
-//         let Some(suffixes) = suffixseq.literals() else {
-//             debug!(
-//                 "skipping reverse suffix optimization because \
-//                  the extract suffix sequence is not finite",
-//             );
-//             return Err(core);
-//         };
-//         let Some(pre) = Prefilter::new(kind, suffixes) else {
-//             debug!(
-//                 "skipping reverse suffix optimization because \
-//                      a prefilter could not be constructed from the \
-//                      longest common suffix",
-//             );
-//             return Err(core);
-//         };
-//         if !pre.is_fast() {
-//             debug!(
-//                 "skipping reverse suffix optimization because \
-// 				 while we have a suffix prefilter, it is not \
-// 				 believed to be 'fast'"
-//             );
-//             return Err(core);
-//         }
-//         Ok(ReverseSuffix { core, pre })
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn try_search_half_start(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//     ) -> Result<Option<HalfMatch>, RetryError> {
-//         let mut span = input.get_span();
-//         let mut min_start = 0;
-//         loop {
-//             let litmatch = match self.pre.find(input.haystack(), span) {
-//                 None => return Ok(None),
-//                 Some(span) => span,
-//             };
-//             trace!("reverse suffix scan found suffix match at {:?}", litmatch);
-//             let revinput = input.clone().anchored(Anchored::Yes).span(input.start()..litmatch.end);
-//             match self.try_search_half_rev_limited(cache, &revinput, min_start)? {
-//                 None => {
-//                     if span.start >= span.end {
-//                         break;
-//                     }
-//                     span.start = litmatch.start.checked_add(1).unwrap();
-//                 }
-//                 Some(hm) => return Ok(Some(hm)),
-//             }
-//             min_start = litmatch.end;
-//         }
-//         Ok(None)
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn try_search_half_fwd(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//     ) -> Result<Option<HalfMatch>, RetryFailError> {
-//         if let Some(e) = self.core.dfa.get(&mut input) {
-//             trace!("using full DFA for forward reverse suffix search at {:?}", input.get_span());
-//             e.try_search_half_fwd(&mut input)
-//         } else if let Some(e) = self.core.hybrid.get(&mut input) {
-//             trace!("using lazy DFA for forward reverse suffix search at {:?}", input.get_span());
-//             e.try_search_half_fwd(&mut cache.hybrid, &mut input)
-//         } else {
-//             unreachable!("ReverseSuffix always has a DFA")
-//         }
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn try_search_half_rev_limited(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//         min_start: usize,
-//     ) -> Result<Option<HalfMatch>, RetryError> {
-//         if let Some(e) = self.core.dfa.get(&mut input) {
-//             trace!(
-//                 "using full DFA for reverse suffix search at {:?}, \
-//                  but will be stopped at {} to avoid quadratic behavior",
-//                 input.get_span(),
-//                 min_start,
-//             );
-//             e.try_search_half_rev_limited(&mut input, min_start)
-//         } else if let Some(e) = self.core.hybrid.get(&mut input) {
-//             trace!(
-//                 "using lazy DFA for reverse inner search at {:?}, \
-//                  but will be stopped at {} to avoid quadratic behavior",
-//                 input.get_span(),
-//                 min_start,
-//             );
-//             e.try_search_half_rev_limited(&mut cache.hybrid, &mut input, min_start)
-//         } else {
-//             unreachable!("ReverseSuffix always has a DFA")
-//         }
-//     }
-// }
-
-// impl Strategy for ReverseSuffix {
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn group_info(&self) -> &GroupInfo {
-//         self.core.group_info()
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn create_cache(&self) -> Cache {
-//         self.core.create_cache()
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn reset_cache(&self, cache: &mut Cache) {
-//         self.core.reset_cache(cache);
-//     }
-
-//     fn is_accelerated(&self) -> bool {
-//         self.pre.is_fast()
-//     }
-
-//     fn memory_usage(&self) -> usize {
-//         self.core.memory_usage() + self.pre.memory_usage()
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn search(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<Match> {
-//         if input.get_anchored().is_anchored() {
-//             return self.core.search(cache, input);
-//         }
-//         match self.try_search_half_start(cache, input) {
-//             Err(RetryError::Quadratic(_err)) => {
-//                 trace!("reverse suffix optimization failed: {}", _err);
-//                 self.core.search(cache, input)
-//             }
-//             Err(RetryError::Fail(_err)) => {
-//                 trace!("reverse suffix reverse fast search failed: {}", _err);
-//                 self.core.search_nofail(cache, input)
-//             }
-//             Ok(None) => None,
-//             Ok(Some(hm_start)) => {
-//                 let fwdinput = input
-//                     .clone()
-//                     .anchored(Anchored::Pattern(hm_start.pattern()))
-//                     .span(hm_start.offset()..input.end());
-//                 match self.try_search_half_fwd(cache, &fwdinput) {
-//                     Err(_err) => {
-//                         trace!("reverse suffix forward fast search failed: {}", _err);
-//                         self.core.search_nofail(cache, input)
-//                     }
-//                     Ok(None) => {
-//                         unreachable!(
-//                             "suffix match plus reverse match implies \
-// 						     there must be a match",
-//                         )
-//                     }
-//                     Ok(Some(hm_end)) => {
-//                         Some(Match::new(hm_start.pattern(), hm_start.offset()..hm_end.offset()))
-//                     }
-//                 }
-//             }
-//         }
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn search_half(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<HalfMatch> {
-//         if input.get_anchored().is_anchored() {
-//             return self.core.search_half(cache, input);
-//         }
-//         match self.try_search_half_start(cache, input) {
-//             Err(RetryError::Quadratic(_err)) => {
-//                 trace!("reverse suffix half optimization failed: {}", _err);
-//                 self.core.search_half(cache, input)
-//             }
-//             Err(RetryError::Fail(_err)) => {
-//                 trace!("reverse suffix reverse fast half search failed: {}", _err);
-//                 self.core.search_half_nofail(cache, input)
-//             }
-//             Ok(None) => None,
-//             Ok(Some(hm_start)) => {
-//                 // This is a bit subtle. It is tempting to just stop searching
-//                 // at this point and return a half-match with an offset
-//                 // corresponding to where the suffix was found. But the suffix
-//                 // match does not necessarily correspond to the end of the
-//                 // proper leftmost-first match. Consider /[a-z]+ing/ against
-//                 // 'tingling'. The first suffix match is the first 'ing', and
-//                 // the /[a-z]+/ matches the 't'. So if we stopped here, then
-//                 // we'd report 'ting' as the match. But 'tingling' is the
-//                 // correct match because of greediness.
-//                 let fwdinput = input
-//                     .clone()
-//                     .anchored(Anchored::Pattern(hm_start.pattern()))
-//                     .span(hm_start.offset()..input.end());
-//                 match self.try_search_half_fwd(cache, &fwdinput) {
-//                     Err(_err) => {
-//                         trace!("reverse suffix forward fast search failed: {}", _err);
-//                         self.core.search_half_nofail(cache, input)
-//                     }
-//                     Ok(None) => {
-//                         unreachable!(
-//                             "suffix match plus reverse match implies \
-// 						     there must be a match",
-//                         )
-//                     }
-//                     Ok(Some(hm_end)) => Some(hm_end),
-//                 }
-//             }
-//         }
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn is_match(&self, cache: &mut Cache, input: &mut Input<C>) -> bool {
-//         if input.get_anchored().is_anchored() {
-//             return self.core.is_match(cache, input);
-//         }
-//         match self.try_search_half_start(cache, input) {
-//             Err(RetryError::Quadratic(_err)) => {
-//                 trace!("reverse suffix half optimization failed: {}", _err);
-//                 self.core.is_match_nofail(cache, input)
-//             }
-//             Err(RetryError::Fail(_err)) => {
-//                 trace!("reverse suffix reverse fast half search failed: {}", _err);
-//                 self.core.is_match_nofail(cache, input)
-//             }
-//             Ok(None) => false,
-//             Ok(Some(_)) => true,
-//         }
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn search_slots(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//         slots: &mut [Option<NonMaxUsize>],
-//     ) -> Option<PatternID> {
-//         if input.get_anchored().is_anchored() {
-//             return self.core.search_slots(cache, input, slots);
-//         }
-//         if !self.core.is_capture_search_needed(slots.len()) {
-//             trace!("asked for slots unnecessarily, trying fast path");
-//             let m = self.search(cache, input)?;
-//             copy_match_to_slots(m, slots);
-//             return Some(m.pattern());
-//         }
-//         let hm_start = match self.try_search_half_start(cache, input) {
-//             Err(RetryError::Quadratic(_err)) => {
-//                 trace!("reverse suffix captures optimization failed: {}", _err);
-//                 return self.core.search_slots(cache, input, slots);
-//             }
-//             Err(RetryError::Fail(_err)) => {
-//                 trace!("reverse suffix reverse fast captures search failed: {}", _err);
-//                 return self.core.search_slots_nofail(cache, input, slots);
-//             }
-//             Ok(None) => return None,
-//             Ok(Some(hm_start)) => hm_start,
-//         };
-//         trace!(
-//             "match found at {}..{} in capture search, \
-// 		  	 using another engine to find captures",
-//             hm_start.offset(),
-//             input.end(),
-//         );
-//         let start = hm_start.offset();
-//         let input =
-//             input.clone().span(start..input.end()).anchored(Anchored::Pattern(hm_start.pattern()));
-//         self.core.search_slots_nofail(cache, &mut input, slots)
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn which_overlapping_matches(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//         patset: &mut PatternSet,
-//     ) {
-//         self.core.which_overlapping_matches(cache, input, patset)
-//     }
-// }
-
-// #[derive(Debug)]
-// struct ReverseInner {
-//     core: Core,
-//     preinner: Prefilter,
-//     nfarev: NFA,
-//     hybrid: wrappers::ReverseHybrid,
-//     dfa: wrappers::ReverseDFA,
-// }
-
-// impl ReverseInner {
-//     fn new(core: Core, hirs: &[&Hir]) -> Result<ReverseInner, Core> {
-//         if !core.info.config().get_auto_prefilter() {
-//             debug!(
-//                 "skipping reverse inner optimization because \
-//                  automatic prefilters are disabled"
-//             );
-//             return Err(core);
-//         }
-//         // Currently we hard-code the assumption of leftmost-first match
-//         // semantics. This isn't a huge deal because 'all' semantics tend to
-//         // only be used for forward overlapping searches with multiple regexes,
-//         // and this optimization only supports a single pattern at the moment.
-//         if core.info.config().get_match_kind() != MatchKind::LeftmostFirst {
-//             debug!(
-//                 "skipping reverse inner optimization because \
-// 				 match kind is {:?} but this only supports leftmost-first",
-//                 core.info.config().get_match_kind(),
-//             );
-//             return Err(core);
-//         }
-//         // It's likely that a reverse inner scan has too much overhead for it
-//         // to be worth it when the regex is anchored at the start. It is
-//         // possible for it to be quite a bit faster if the initial literal
-//         // scan fails to detect a match, in which case, we can say "no match"
-//         // very quickly. But this could be undesirable, e.g., scanning too far
-//         // or when the literal scan matches. If it matches, then confirming the
-//         // match requires a reverse scan followed by a forward scan to confirm
-//         // or reject, which is a fair bit of work.
-//         //
-//         // Note that the caller can still request an anchored search even
-//         // when the regex isn't anchored. We detect that case in the search
-//         // routines below and just fallback to the core engine. Currently this
-//         // optimization assumes all searches are unanchored, so if we do want
-//         // to enable this optimization for anchored searches, it will need a
-//         // little work to support it.
-//         if core.info.is_always_anchored_start() {
-//             debug!(
-//                 "skipping reverse inner optimization because \
-// 				 the regex is always anchored at the start",
-//             );
-//             return Err(core);
-//         }
-//         // Only DFAs can do reverse searches (currently), so we need one of
-//         // them in order to do this optimization. It's possible (although
-//         // pretty unlikely) that we have neither and need to give up.
-//         if !core.hybrid.is_some() && !core.dfa.is_some() {
-//             debug!(
-//                 "skipping reverse inner optimization because \
-// 				 we don't have a lazy DFA or a full DFA"
-//             );
-//             return Err(core);
-//         }
-//         if core.pre.as_ref().map_or(false, |p| p.is_fast()) {
-//             debug!(
-//                 "skipping reverse inner optimization because \
-// 				 we already have a prefilter that we think is fast"
-//             );
-//             return Err(core);
-//         } else if core.pre.is_some() {
-//             debug!(
-//                 "core engine has a prefix prefilter, but it is \
-//                  probably not fast, so continuing with attempt to \
-//                  use reverse inner prefilter"
-//             );
-//         }
-//         let (concat_prefix, preinner) = match reverse_inner::extract(hirs) {
-//             Some(x) => x,
-//             // N.B. the 'extract' function emits debug messages explaining
-//             // why we bailed out here.
-//             None => return Err(core),
-//         };
-//         debug!("building reverse NFA for prefix before inner literal");
-//         let mut lookm = LookMatcher::new();
-//         lookm.set_line_terminator(core.info.config().get_line_terminator());
-//         let thompson_config = thompson::Config::new()
-//             .reverse(true)
-//             .utf8(core.info.config().get_utf8_empty())
-//             .nfa_size_limit(core.info.config().get_nfa_size_limit())
-//             .shrink(false)
-//             .which_captures(WhichCaptures::None)
-//             .look_matcher(lookm);
-//         let result =
-//             thompson::Compiler::new().configure(thompson_config).build_from_hir(&concat_prefix);
-//         let nfarev = match result {
-//             Ok(nfarev) => nfarev,
-//             Err(_err) => {
-//                 debug!(
-//                     "skipping reverse inner optimization because the \
-// 					 reverse NFA failed to build: {}",
-//                     _err,
-//                 );
-//                 return Err(core);
-//             }
-//         };
-//         debug!("building reverse DFA for prefix before inner literal");
-//         let dfa = if !core.info.config().get_dfa() {
-//             wrappers::ReverseDFA::none()
-//         } else {
-//             wrappers::ReverseDFA::new(&core.info, &nfarev)
-//         };
-//         let hybrid = if !core.info.config().get_hybrid() {
-//             wrappers::ReverseHybrid::none()
-//         } else if dfa.is_some() {
-//             debug!(
-//                 "skipping lazy DFA for reverse inner optimization \
-// 				 because we have a full DFA"
-//             );
-//             wrappers::ReverseHybrid::none()
-//         } else {
-//             wrappers::ReverseHybrid::new(&core.info, &nfarev)
-//         };
-//         Ok(ReverseInner { core, preinner, nfarev, hybrid, dfa })
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn try_search_full(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//     ) -> Result<Option<Match>, RetryError> {
-//         let mut span = input.get_span();
-//         let mut min_match_start = 0;
-//         let mut min_pre_start = 0;
-//         loop {
-//             let litmatch = match self.preinner.find(input.haystack(), span) {
-//                 None => return Ok(None),
-//                 Some(span) => span,
-//             };
-//             if litmatch.start < min_pre_start {
-//                 trace!(
-//                     "found inner prefilter match at {:?}, which starts \
-// 					 before the end of the last forward scan at {}, \
-// 					 quitting to avoid quadratic behavior",
-//                     litmatch,
-//                     min_pre_start,
-//                 );
-//                 return Err(RetryError::Quadratic(RetryQuadraticError::new()));
-//             }
-//             trace!("reverse inner scan found inner match at {:?}", litmatch);
-//             let revinput =
-//                 input.clone().anchored(Anchored::Yes).span(input.start()..litmatch.start);
-//             // Note that in addition to the literal search above scanning past
-//             // our minimum start point, this routine can also return an error
-//             // as a result of detecting possible quadratic behavior if the
-//             // reverse scan goes past the minimum start point. That is, the
-//             // literal search might not, but the reverse regex search for the
-//             // prefix might!
-//             match self.try_search_half_rev_limited(cache, &revinput, min_match_start)? {
-//                 None => {
-//                     if span.start >= span.end {
-//                         break;
-//                     }
-//                     span.start = litmatch.start.checked_add(1).unwrap();
-//                 }
-//                 Some(hm_start) => {
-//                     let fwdinput = input
-//                         .clone()
-//                         .anchored(Anchored::Pattern(hm_start.pattern()))
-//                         .span(hm_start.offset()..input.end());
-//                     match self.try_search_half_fwd_stopat(cache, &fwdinput)? {
-//                         Err(stopat) => {
-//                             min_pre_start = stopat;
-//                             span.start = litmatch.start.checked_add(1).unwrap();
-//                         }
-//                         Ok(hm_end) => {
-//                             return Ok(Some(Match::new(
-//                                 hm_start.pattern(),
-//                                 hm_start.offset()..hm_end.offset(),
-//                             )))
-//                         }
-//                     }
-//                 }
-//             }
-//             min_match_start = litmatch.end;
-//         }
-//         Ok(None)
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn try_search_half_fwd_stopat(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//     ) -> Result<Result<HalfMatch, usize>, RetryFailError> {
-//         if let Some(e) = self.core.dfa.get(&mut input) {
-//             trace!("using full DFA for forward reverse inner search at {:?}", input.get_span());
-//             e.try_search_half_fwd_stopat(&mut input)
-//         } else if let Some(e) = self.core.hybrid.get(&mut input) {
-//             trace!("using lazy DFA for forward reverse inner search at {:?}", input.get_span());
-//             e.try_search_half_fwd_stopat(&mut cache.hybrid, &mut input)
-//         } else {
-//             unreachable!("ReverseInner always has a DFA")
-//         }
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn try_search_half_rev_limited(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//         min_start: usize,
-//     ) -> Result<Option<HalfMatch>, RetryError> {
-//         if let Some(e) = self.dfa.get(&mut input) {
-//             trace!(
-//                 "using full DFA for reverse inner search at {:?}, \
-//                  but will be stopped at {} to avoid quadratic behavior",
-//                 input.get_span(),
-//                 min_start,
-//             );
-//             e.try_search_half_rev_limited(&mut input, min_start)
-//         } else if let Some(e) = self.hybrid.get(&mut input) {
-//             trace!(
-//                 "using lazy DFA for reverse inner search at {:?}, \
-//                  but will be stopped at {} to avoid quadratic behavior",
-//                 input.get_span(),
-//                 min_start,
-//             );
-//             e.try_search_half_rev_limited(&mut cache.revhybrid, &mut input, min_start)
-//         } else {
-//             unreachable!("ReverseInner always has a DFA")
-//         }
-//     }
-// }
-
-// impl Strategy for ReverseInner {
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn group_info(&self) -> &GroupInfo {
-//         self.core.group_info()
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn create_cache(&self) -> Cache {
-//         let mut cache = self.core.create_cache();
-//         cache.revhybrid = self.hybrid.create_cache();
-//         cache
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn reset_cache(&self, cache: &mut Cache) {
-//         self.core.reset_cache(cache);
-//         cache.revhybrid.reset(&self.hybrid);
-//     }
-
-//     fn is_accelerated(&self) -> bool {
-//         self.preinner.is_fast()
-//     }
-
-//     fn memory_usage(&self) -> usize {
-//         self.core.memory_usage()
-//             + self.preinner.memory_usage()
-//             + self.nfarev.memory_usage()
-//             + self.dfa.memory_usage()
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn search(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<Match> {
-//         if input.get_anchored().is_anchored() {
-//             return self.core.search(cache, input);
-//         }
-//         match self.try_search_full(cache, input) {
-//             Err(RetryError::Quadratic(_err)) => {
-//                 trace!("reverse inner optimization failed: {}", _err);
-//                 self.core.search(cache, input)
-//             }
-//             Err(RetryError::Fail(_err)) => {
-//                 trace!("reverse inner fast search failed: {}", _err);
-//                 self.core.search_nofail(cache, input)
-//             }
-//             Ok(matornot) => matornot,
-//         }
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn search_half(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<HalfMatch> {
-//         if input.get_anchored().is_anchored() {
-//             return self.core.search_half(cache, input);
-//         }
-//         match self.try_search_full(cache, input) {
-//             Err(RetryError::Quadratic(_err)) => {
-//                 trace!("reverse inner half optimization failed: {}", _err);
-//                 self.core.search_half(cache, input)
-//             }
-//             Err(RetryError::Fail(_err)) => {
-//                 trace!("reverse inner fast half search failed: {}", _err);
-//                 self.core.search_half_nofail(cache, input)
-//             }
-//             Ok(None) => None,
-//             Ok(Some(m)) => Some(HalfMatch::new(m.pattern(), m.end())),
-//         }
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn is_match(&self, cache: &mut Cache, input: &mut Input<C>) -> bool {
-//         if input.get_anchored().is_anchored() {
-//             return self.core.is_match(cache, input);
-//         }
-//         match self.try_search_full(cache, input) {
-//             Err(RetryError::Quadratic(_err)) => {
-//                 trace!("reverse inner half optimization failed: {}", _err);
-//                 self.core.is_match_nofail(cache, input)
-//             }
-//             Err(RetryError::Fail(_err)) => {
-//                 trace!("reverse inner fast half search failed: {}", _err);
-//                 self.core.is_match_nofail(cache, input)
-//             }
-//             Ok(None) => false,
-//             Ok(Some(_)) => true,
-//         }
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn search_slots(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//         slots: &mut [Option<NonMaxUsize>],
-//     ) -> Option<PatternID> {
-//         if input.get_anchored().is_anchored() {
-//             return self.core.search_slots(cache, input, slots);
-//         }
-//         if !self.core.is_capture_search_needed(slots.len()) {
-//             trace!("asked for slots unnecessarily, trying fast path");
-//             let m = self.search(cache, input)?;
-//             copy_match_to_slots(m, slots);
-//             return Some(m.pattern());
-//         }
-//         let m = match self.try_search_full(cache, input) {
-//             Err(RetryError::Quadratic(_err)) => {
-//                 trace!("reverse inner captures optimization failed: {}", _err);
-//                 return self.core.search_slots(cache, input, slots);
-//             }
-//             Err(RetryError::Fail(_err)) => {
-//                 trace!("reverse inner fast captures search failed: {}", _err);
-//                 return self.core.search_slots_nofail(cache, input, slots);
-//             }
-//             Ok(None) => return None,
-//             Ok(Some(m)) => m,
-//         };
-//         trace!(
-//             "match found at {}..{} in capture search, \
-// 		  	 using another engine to find captures",
-//             m.start(),
-//             m.end(),
-//         );
-//         let input = input.clone().span(m.start()..m.end()).anchored(Anchored::Pattern(m.pattern()));
-//         self.core.search_slots_nofail(cache, &mut input, slots)
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     fn which_overlapping_matches(
-//         &self,
-//         cache: &mut Cache,
-//         input: &mut Input<C>,
-//         patset: &mut PatternSet,
-//     ) {
-//         self.core.which_overlapping_matches(cache, input, patset)
-//     }
-// }
+#[derive(Debug)]
+struct ReverseSuffix {
+    core: Core,
+    pre: Prefilter,
+}
 
-/// Copies the offsets in the given match to the corresponding positions in
-/// `slots`.
-///
-/// In effect, this sets the slots corresponding to the implicit group for the
-/// pattern in the given match. If the indices for the corresponding slots do
-/// not exist, then no slots are set.
-///
-/// This is useful when the caller provides slots (or captures), but you use a
-/// regex engine that doesn't operate on slots (like a lazy DFA). This function
-/// lets you map the match you get back to the slots provided by the caller.
-#[cfg_attr(feature = "perf-inline", inline(always))]
-fn copy_match_to_slots(m: Match, slots: &mut [Option<NonMaxUsize>]) {
-    let slot_start = m.pattern().as_usize() * 2;
-    let slot_end = slot_start + 1;
-    if let Some(slot) = slots.get_mut(slot_start) {
-        *slot = NonMaxUsize::new(m.start());
-    }
-    if let Some(slot) = slots.get_mut(slot_end) {
-        *slot = NonMaxUsize::new(m.end());
-    }
+impl ReverseSuffix {
+    fn new(core: Core, hirs: &[&Hir]) -> Result<ReverseSuffix, Core> {
+        if !core.info.config().get_auto_prefilter() {
+            debug!(
+                "skipping reverse suffix optimization because \
+                 automatic prefilters are disabled"
+            );
+            return Err(core);
+        }
+        // Like the reverse inner optimization, we don't do this for regexes
+        // that are always anchored. It could lead to scanning too much, but
+        // could say "no match" much more quickly than running the regex
+        // engine if the initial literal scan doesn't match. With that said,
+        // the reverse suffix optimization has lower overhead, since it only
+        // requires a reverse scan after a literal match to confirm or reject
+        // the match. (Although, in the case of confirmation, it then needs to
+        // do another forward scan to find the end position.)
+        //
+        // Note that the caller can still request an anchored search even
+        // when the regex isn't anchored. We detect that case in the search
+        // routines below and just fallback to the core engine. Currently this
+        // optimization assumes all searches are unanchored, so if we do want
+        // to enable this optimization for anchored searches, it will need a
+        // little work to support it.
+        if core.info.is_always_anchored_start() {
+            debug!(
+                "skipping reverse suffix optimization because \
+				 the regex is always anchored at the start",
+            );
+            return Err(core);
+        }
+        // Only DFAs can do reverse searches (currently), so we need one of
+        // them in order to do this optimization. It's possible (although
+        // pretty unlikely) that we have neither and need to give up.
+        if !core.hybrid.is_some() && !core.dfa.is_some() {
+            debug!(
+                "skipping reverse suffix optimization because \
+				 we don't have a lazy DFA or a full DFA"
+            );
+            return Err(core);
+        }
+        if core.pre.as_ref().map_or(false, |p| p.is_fast()) {
+            debug!(
+                "skipping reverse suffix optimization because \
+				 we already have a prefilter that we think is fast"
+            );
+            return Err(core);
+        }
+        let kind = core.info.config().get_match_kind();
+        let suffixes = crate::util::prefilter::suffixes(kind, hirs);
+        // Using the full set of suffixes as a prefilter would be incorrect
+        // here: a candidate for one alternate could be found before the end
+        // of a match of another alternate that starts further left. The
+        // longest common suffix is a suffix of every match, so the first
+        // candidate always belongs to the leftmost match.
+        let lcs = match suffixes.longest_common_suffix() {
+            None => {
+                debug!(
+                    "skipping reverse suffix optimization because \
+                     a longest common suffix could not be found",
+                );
+                return Err(core);
+            }
+            Some([]) => {
+                debug!(
+                    "skipping reverse suffix optimization because \
+                     the longest common suffix is the empty string",
+                );
+                return Err(core);
+            }
+            Some(lcs) => lcs,
+        };
+        let Some(pre) = Prefilter::new(kind, &[lcs]) else {
+            debug!(
+                "skipping reverse suffix optimization because \
+                 a prefilter could not be constructed from the \
+                 longest common suffix",
+            );
+            return Err(core);
+        };
+        if !pre.is_fast() {
+            debug!(
+                "skipping reverse suffix optimization because \
+				 while we have a suffix prefilter, it is not \
+				 believed to be 'fast'"
+            );
+            return Err(core);
+        }
+        Ok(ReverseSuffix { core, pre })
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn try_search_half_start<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+    ) -> Result<Option<HalfMatch>, RetryError> {
+        let mut span = input.get_span();
+        let mut min_start = 0;
+        loop {
+            // The reverse scan below leaves the cursor somewhere before the
+            // suffix candidate, so the literal scan has to be explicitly
+            // resumed at the start of the remaining span.
+            input.move_to(span.start);
+            let litmatch = match crate::literal::find(&self.pre, input) {
+                None => return Ok(None),
+                Some(span) => span,
+            };
+            trace!("reverse suffix scan found suffix match at {:?}", litmatch);
+            let hm = input.with(|input| {
+                let start = input.start();
+                input.anchored(Anchored::Yes).set_span(start..litmatch.end);
+                self.try_search_half_rev_limited(cache, input, min_start)
+            })?;
+            match hm {
+                None => {
+                    if span.start >= span.end {
+                        break;
+                    }
+                    span.start = litmatch.start.checked_add(1).unwrap();
+                }
+                Some(hm) => return Ok(Some(hm)),
+            }
+            min_start = litmatch.end;
+        }
+        Ok(None)
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn try_search_half_fwd<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+    ) -> Result<Option<HalfMatch>, RetryFailError> {
+        if let Some(e) = self.core.dfa.get(input) {
+            trace!("using full DFA for forward reverse suffix search at {:?}", input.get_span());
+            e.try_search_half_fwd(input)
+        } else if let Some(e) = self.core.hybrid.get(input) {
+            trace!("using lazy DFA for forward reverse suffix search at {:?}", input.get_span());
+            e.try_search_half_fwd(&mut cache.hybrid, input)
+        } else {
+            unreachable!("ReverseSuffix always has a DFA")
+        }
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn try_search_half_rev_limited<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        min_start: usize,
+    ) -> Result<Option<HalfMatch>, RetryError> {
+        if let Some(e) = self.core.dfa.get(input) {
+            trace!(
+                "using full DFA for reverse suffix search at {:?}, \
+                 but will be stopped at {} to avoid quadratic behavior",
+                input.get_span(),
+                min_start,
+            );
+            e.try_search_half_rev_limited(input, min_start)
+        } else if let Some(e) = self.core.hybrid.get(input) {
+            trace!(
+                "using lazy DFA for reverse suffix search at {:?}, \
+                 but will be stopped at {} to avoid quadratic behavior",
+                input.get_span(),
+                min_start,
+            );
+            e.try_search_half_rev_limited(&mut cache.hybrid, input, min_start)
+        } else {
+            unreachable!("ReverseSuffix always has a DFA")
+        }
+    }
+
+    /// Runs a forward search from the start of a match found by the reverse
+    /// scan in order to find where that match ends.
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn try_search_half_end<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        hm_start: HalfMatch,
+    ) -> Result<Option<HalfMatch>, RetryFailError> {
+        input.with(|input| {
+            let end = input.end();
+            input.anchored(Anchored::Pattern(hm_start.pattern())).set_span(hm_start.offset()..end);
+            self.try_search_half_fwd(cache, input)
+        })
+    }
+}
+
+impl ReverseSuffix {
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn group_info(&self) -> &GroupInfo {
+        self.core.group_info()
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn create_cache(&self) -> Cache {
+        self.core.create_cache()
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn reset_cache(&self, cache: &mut Cache) {
+        self.core.reset_cache(cache);
+    }
+
+    fn is_accelerated(&self) -> bool {
+        self.pre.is_fast()
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.core.memory_usage() + self.pre.memory_usage()
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn search<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<Match> {
+        if input.get_anchored().is_anchored() {
+            return self.core.search(cache, input);
+        }
+        match self.try_search_half_start(cache, input) {
+            Err(RetryError::Quadratic(_err)) => {
+                trace!("reverse suffix optimization failed: {}", _err);
+                self.core.search(cache, input)
+            }
+            Err(RetryError::Fail(_err)) => {
+                trace!("reverse suffix reverse fast search failed: {}", _err);
+                self.core.search_nofail(cache, input)
+            }
+            Ok(None) => None,
+            Ok(Some(hm_start)) => match self.try_search_half_end(cache, input, hm_start) {
+                Err(_err) => {
+                    trace!("reverse suffix forward fast search failed: {}", _err);
+                    self.core.search_nofail(cache, input)
+                }
+                Ok(None) => {
+                    unreachable!(
+                        "suffix match plus reverse match implies \
+						 there must be a match",
+                    )
+                }
+                Ok(Some(hm_end)) => {
+                    Some(Match::new(hm_start.pattern(), hm_start.offset()..hm_end.offset()))
+                }
+            },
+        }
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn search_half<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<HalfMatch> {
+        if input.get_anchored().is_anchored() {
+            return self.core.search_half(cache, input);
+        }
+        match self.try_search_half_start(cache, input) {
+            Err(RetryError::Quadratic(_err)) => {
+                trace!("reverse suffix half optimization failed: {}", _err);
+                self.core.search_half(cache, input)
+            }
+            Err(RetryError::Fail(_err)) => {
+                trace!("reverse suffix reverse fast half search failed: {}", _err);
+                self.core.search_half_nofail(cache, input)
+            }
+            Ok(None) => None,
+            Ok(Some(hm_start)) => {
+                // This is a bit subtle. It is tempting to just stop searching
+                // at this point and return a half-match with an offset
+                // corresponding to where the suffix was found. But the suffix
+                // match does not necessarily correspond to the end of the
+                // proper leftmost-first match. Consider /[a-z]+ing/ against
+                // 'tingling'. The first suffix match is the first 'ing', and
+                // the /[a-z]+/ matches the 't'. So if we stopped here, then
+                // we'd report 'ting' as the match. But 'tingling' is the
+                // correct match because of greediness.
+                match self.try_search_half_end(cache, input, hm_start) {
+                    Err(_err) => {
+                        trace!("reverse suffix forward fast search failed: {}", _err);
+                        self.core.search_half_nofail(cache, input)
+                    }
+                    Ok(None) => {
+                        unreachable!(
+                            "suffix match plus reverse match implies \
+						     there must be a match",
+                        )
+                    }
+                    Ok(Some(hm_end)) => Some(hm_end),
+                }
+            }
+        }
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn is_match<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> bool {
+        if input.get_anchored().is_anchored() {
+            return self.core.is_match(cache, input);
+        }
+        match self.try_search_half_start(cache, input) {
+            Err(RetryError::Quadratic(_err)) => {
+                trace!("reverse suffix half optimization failed: {}", _err);
+                self.core.is_match_nofail(cache, input)
+            }
+            Err(RetryError::Fail(_err)) => {
+                trace!("reverse suffix reverse fast half search failed: {}", _err);
+                self.core.is_match_nofail(cache, input)
+            }
+            Ok(None) => false,
+            Ok(Some(_)) => true,
+        }
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn search_slots<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        slots: &mut [Option<NonMaxUsize>],
+    ) -> Option<PatternID> {
+        if input.get_anchored().is_anchored() {
+            return self.core.search_slots(cache, input, slots);
+        }
+        if !self.core.is_capture_search_needed(slots.len()) {
+            trace!("asked for slots unnecessarily, trying fast path");
+            let m = self.search(cache, input)?;
+            copy_match_to_slots(m, slots);
+            return Some(m.pattern());
+        }
+        let hm_start = match self.try_search_half_start(cache, input) {
+            Err(RetryError::Quadratic(_err)) => {
+                trace!("reverse suffix captures optimization failed: {}", _err);
+                return self.core.search_slots(cache, input, slots);
+            }
+            Err(RetryError::Fail(_err)) => {
+                trace!("reverse suffix reverse fast captures search failed: {}", _err);
+                return self.core.search_slots_nofail(cache, input, slots);
+            }
+            Ok(None) => return None,
+            Ok(Some(hm_start)) => hm_start,
+        };
+        trace!(
+            "match found at {}..{} in capture search, \
+		  	 using another engine to find captures",
+            hm_start.offset(),
+            input.end(),
+        );
+        let start = hm_start.offset();
+        input.with(|input| {
+            input.span(start..input.end()).anchored(Anchored::Pattern(hm_start.pattern()));
+            self.core.search_slots_nofail(cache, input, slots)
+        })
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn which_overlapping_matches<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        patset: &mut PatternSet,
+    ) {
+        self.core.which_overlapping_matches(cache, input, patset)
+    }
+
+    fn search_overlapping<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        state: &mut OverlappingState,
+    ) -> Option<Match> {
+        self.core.search_overlapping(cache, input, state)
+    }
+}
+
+#[derive(Debug)]
+struct ReverseInner {
+    core: Core,
+    preinner: Prefilter,
+    nfarev: NFA,
+    hybrid: wrappers::ReverseHybrid,
+    dfa: wrappers::ReverseDFA,
+}
+
+impl ReverseInner {
+    fn new(core: Core, hirs: &[&Hir]) -> Result<ReverseInner, Core> {
+        if !core.info.config().get_auto_prefilter() {
+            debug!(
+                "skipping reverse inner optimization because \
+                 automatic prefilters are disabled"
+            );
+            return Err(core);
+        }
+        // Currently we hard-code the assumption of leftmost-first match
+        // semantics. This isn't a huge deal because 'all' semantics tend to
+        // only be used for forward overlapping searches with multiple regexes,
+        // and this optimization only supports a single pattern at the moment.
+        if core.info.config().get_match_kind() != MatchKind::LeftmostFirst {
+            debug!(
+                "skipping reverse inner optimization because \
+				 match kind is {:?} but this only supports leftmost-first",
+                core.info.config().get_match_kind(),
+            );
+            return Err(core);
+        }
+        // It's likely that a reverse inner scan has too much overhead for it
+        // to be worth it when the regex is anchored at the start. It is
+        // possible for it to be quite a bit faster if the initial literal
+        // scan fails to detect a match, in which case, we can say "no match"
+        // very quickly. But this could be undesirable, e.g., scanning too far
+        // or when the literal scan matches. If it matches, then confirming the
+        // match requires a reverse scan followed by a forward scan to confirm
+        // or reject, which is a fair bit of work.
+        //
+        // Note that the caller can still request an anchored search even
+        // when the regex isn't anchored. We detect that case in the search
+        // routines below and just fallback to the core engine. Currently this
+        // optimization assumes all searches are unanchored, so if we do want
+        // to enable this optimization for anchored searches, it will need a
+        // little work to support it.
+        if core.info.is_always_anchored_start() {
+            debug!(
+                "skipping reverse inner optimization because \
+				 the regex is always anchored at the start",
+            );
+            return Err(core);
+        }
+        // Only DFAs can do reverse searches (currently), so we need one of
+        // them in order to do this optimization. It's possible (although
+        // pretty unlikely) that we have neither and need to give up.
+        if !core.hybrid.is_some() && !core.dfa.is_some() {
+            debug!(
+                "skipping reverse inner optimization because \
+				 we don't have a lazy DFA or a full DFA"
+            );
+            return Err(core);
+        }
+        if core.pre.as_ref().map_or(false, |p| p.is_fast()) {
+            debug!(
+                "skipping reverse inner optimization because \
+				 we already have a prefilter that we think is fast"
+            );
+            return Err(core);
+        } else if core.pre.is_some() {
+            debug!(
+                "core engine has a prefix prefilter, but it is \
+                 probably not fast, so continuing with attempt to \
+                 use reverse inner prefilter"
+            );
+        }
+        let (concat_prefix, preinner) = match reverse_inner::extract(hirs) {
+            Some(x) => x,
+            // N.B. the 'extract' function emits debug messages explaining
+            // why we bailed out here.
+            None => return Err(core),
+        };
+        debug!("building reverse NFA for prefix before inner literal");
+        let mut lookm = LookMatcher::new();
+        lookm.set_line_terminator(core.info.config().get_line_terminator());
+        let thompson_config = thompson::Config::new()
+            .reverse(true)
+            .utf8(core.info.config().get_utf8_empty())
+            .nfa_size_limit(core.info.config().get_nfa_size_limit())
+            .shrink(false)
+            .which_captures(WhichCaptures::None)
+            .look_matcher(lookm);
+        let result =
+            thompson::Compiler::new().configure(thompson_config).build_from_hir(&concat_prefix);
+        let nfarev = match result {
+            Ok(nfarev) => nfarev,
+            Err(_err) => {
+                debug!(
+                    "skipping reverse inner optimization because the \
+					 reverse NFA failed to build: {}",
+                    _err,
+                );
+                return Err(core);
+            }
+        };
+        debug!("building reverse DFA for prefix before inner literal");
+        let dfa = if !core.info.config().get_dfa() {
+            wrappers::ReverseDFA::none()
+        } else {
+            wrappers::ReverseDFA::new(&core.info, &nfarev)
+        };
+        let hybrid = if !core.info.config().get_hybrid() {
+            wrappers::ReverseHybrid::none()
+        } else if dfa.is_some() {
+            debug!(
+                "skipping lazy DFA for reverse inner optimization \
+				 because we have a full DFA"
+            );
+            wrappers::ReverseHybrid::none()
+        } else {
+            wrappers::ReverseHybrid::new(&core.info, &nfarev)
+        };
+        Ok(ReverseInner { core, preinner, nfarev, hybrid, dfa })
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn try_search_full<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+    ) -> Result<Option<Match>, RetryError> {
+        let mut span = input.get_span();
+        let mut min_match_start = 0;
+        let mut min_pre_start = 0;
+        loop {
+            // Both the reverse and the forward scan below move the cursor away
+            // from the end of the last literal candidate, so the literal scan
+            // is explicitly resumed at the start of the remaining span. The
+            // prefilter stitches chunks together itself, so an inner literal
+            // that straddles a chunk boundary is still found.
+            input.move_to(span.start);
+            let litmatch = match crate::literal::find(&self.preinner, input) {
+                None => return Ok(None),
+                Some(span) => span,
+            };
+            if litmatch.start < min_pre_start {
+                trace!(
+                    "found inner prefilter match at {:?}, which starts \
+					 before the end of the last forward scan at {}, \
+					 quitting to avoid quadratic behavior",
+                    litmatch,
+                    min_pre_start,
+                );
+                return Err(RetryError::Quadratic(RetryQuadraticError::new()));
+            }
+            trace!("reverse inner scan found inner match at {:?}", litmatch);
+            // Note that in addition to the literal search above scanning past
+            // our minimum start point, this routine can also return an error
+            // as a result of detecting possible quadratic behavior if the
+            // reverse scan goes past the minimum start point. That is, the
+            // literal search might not, but the reverse regex search for the
+            // prefix might!
+            let hm_start = input.with(|input| {
+                let start = input.start();
+                input.anchored(Anchored::Yes).set_span(start..litmatch.start);
+                self.try_search_half_rev_limited(cache, input, min_match_start)
+            })?;
+            match hm_start {
+                None => {
+                    if span.start >= span.end {
+                        break;
+                    }
+                    span.start = litmatch.start.checked_add(1).unwrap();
+                }
+                Some(hm_start) => {
+                    let hm_end = input.with(|input| {
+                        let end = input.end();
+                        input
+                            .anchored(Anchored::Pattern(hm_start.pattern()))
+                            .set_span(hm_start.offset()..end);
+                        self.try_search_half_fwd_stopat(cache, input)
+                    })?;
+                    match hm_end {
+                        Err(stopat) => {
+                            min_pre_start = stopat;
+                            span.start = litmatch.start.checked_add(1).unwrap();
+                        }
+                        Ok(hm_end) => {
+                            return Ok(Some(Match::new(
+                                hm_start.pattern(),
+                                hm_start.offset()..hm_end.offset(),
+                            )))
+                        }
+                    }
+                }
+            }
+            min_match_start = litmatch.end;
+        }
+        Ok(None)
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn try_search_half_fwd_stopat<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+    ) -> Result<Result<HalfMatch, usize>, RetryFailError> {
+        if let Some(e) = self.core.dfa.get(input) {
+            trace!("using full DFA for forward reverse inner search at {:?}", input.get_span());
+            e.try_search_half_fwd_stopat(input)
+        } else if let Some(e) = self.core.hybrid.get(input) {
+            trace!("using lazy DFA for forward reverse inner search at {:?}", input.get_span());
+            e.try_search_half_fwd_stopat(&mut cache.hybrid, input)
+        } else {
+            unreachable!("ReverseInner always has a DFA")
+        }
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn try_search_half_rev_limited<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        min_start: usize,
+    ) -> Result<Option<HalfMatch>, RetryError> {
+        if let Some(e) = self.dfa.get(input) {
+            trace!(
+                "using full DFA for reverse inner search at {:?}, \
+                 but will be stopped at {} to avoid quadratic behavior",
+                input.get_span(),
+                min_start,
+            );
+            e.try_search_half_rev_limited(input, min_start)
+        } else if let Some(e) = self.hybrid.get(input) {
+            trace!(
+                "using lazy DFA for reverse inner search at {:?}, \
+                 but will be stopped at {} to avoid quadratic behavior",
+                input.get_span(),
+                min_start,
+            );
+            e.try_search_half_rev_limited(&mut cache.revhybrid, input, min_start)
+        } else {
+            unreachable!("ReverseInner always has a DFA")
+        }
+    }
+}
+
+impl ReverseInner {
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn group_info(&self) -> &GroupInfo {
+        self.core.group_info()
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn create_cache(&self) -> Cache {
+        let mut cache = self.core.create_cache();
+        cache.revhybrid = self.hybrid.create_cache();
+        cache
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn reset_cache(&self, cache: &mut Cache) {
+        self.core.reset_cache(cache);
+        cache.revhybrid.reset(&self.hybrid);
+    }
+
+    fn is_accelerated(&self) -> bool {
+        self.preinner.is_fast()
+    }
+
+    fn memory_usage(&self) -> usize {
+        self.core.memory_usage()
+            + self.preinner.memory_usage()
+            + self.nfarev.memory_usage()
+            + self.dfa.memory_usage()
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn search<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<Match> {
+        if input.get_anchored().is_anchored() {
+            return self.core.search(cache, input);
+        }
+        match self.try_search_full(cache, input) {
+            Err(RetryError::Quadratic(_err)) => {
+                trace!("reverse inner optimization failed: {}", _err);
+                self.core.search(cache, input)
+            }
+            Err(RetryError::Fail(_err)) => {
+                trace!("reverse inner fast search failed: {}", _err);
+                self.core.search_nofail(cache, input)
+            }
+            Ok(matornot) => matornot,
+        }
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn search_half<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> Option<HalfMatch> {
+        if input.get_anchored().is_anchored() {
+            return self.core.search_half(cache, input);
+        }
+        match self.try_search_full(cache, input) {
+            Err(RetryError::Quadratic(_err)) => {
+                trace!("reverse inner half optimization failed: {}", _err);
+                self.core.search_half(cache, input)
+            }
+            Err(RetryError::Fail(_err)) => {
+                trace!("reverse inner fast half search failed: {}", _err);
+                self.core.search_half_nofail(cache, input)
+            }
+            Ok(None) => None,
+            Ok(Some(m)) => Some(HalfMatch::new(m.pattern(), m.end())),
+        }
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn is_match<C: Cursor>(&self, cache: &mut Cache, input: &mut Input<C>) -> bool {
+        if input.get_anchored().is_anchored() {
+            return self.core.is_match(cache, input);
+        }
+        match self.try_search_full(cache, input) {
+            Err(RetryError::Quadratic(_err)) => {
+                trace!("reverse inner half optimization failed: {}", _err);
+                self.core.is_match_nofail(cache, input)
+            }
+            Err(RetryError::Fail(_err)) => {
+                trace!("reverse inner fast half search failed: {}", _err);
+                self.core.is_match_nofail(cache, input)
+            }
+            Ok(None) => false,
+            Ok(Some(_)) => true,
+        }
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn search_slots<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        slots: &mut [Option<NonMaxUsize>],
+    ) -> Option<PatternID> {
+        if input.get_anchored().is_anchored() {
+            return self.core.search_slots(cache, input, slots);
+        }
+        if !self.core.is_capture_search_needed(slots.len()) {
+            trace!("asked for slots unnecessarily, trying fast path");
+            let m = self.search(cache, input)?;
+            copy_match_to_slots(m, slots);
+            return Some(m.pattern());
+        }
+        let m = match self.try_search_full(cache, input) {
+            Err(RetryError::Quadratic(_err)) => {
+                trace!("reverse inner captures optimization failed: {}", _err);
+                return self.core.search_slots(cache, input, slots);
+            }
+            Err(RetryError::Fail(_err)) => {
+                trace!("reverse inner fast captures search failed: {}", _err);
+                return self.core.search_slots_nofail(cache, input, slots);
+            }
+            Ok(None) => return None,
+            Ok(Some(m)) => m,
+        };
+        trace!(
+            "match found at {}..{} in capture search, \
+		  	 using another engine to find captures",
+            m.start(),
+            m.end(),
+        );
+        input.with(|input| {
+            input.span(m.start()..m.end()).anchored(Anchored::Pattern(m.pattern()));
+            self.core.search_slots_nofail(cache, input, slots)
+        })
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn which_overlapping_matches<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        patset: &mut PatternSet,
+    ) {
+        self.core.which_overlapping_matches(cache, input, patset)
+    }
+
+    fn search_overlapping<C: Cursor>(
+        &self,
+        cache: &mut Cache,
+        input: &mut Input<C>,
+        state: &mut OverlappingState,
+    ) -> Option<Match> {
+        self.core.search_overlapping(cache, input, state)
+    }
+}
+
+/// Copies the offsets in the given match to the corresponding positions in
+/// `slots`.
+///
+/// In effect, this sets the slots corresponding to the implicit group for the
+/// pattern in the given match. If the indices for the corresponding slots do
+/// not exist, then no slots are set.
+///
+/// This is useful when the caller provides slots (or captures), but you use a
+/// regex engine that doesn't operate on slots (like a lazy DFA). This function
+/// lets you map the match you get back to the slots provided by the caller.
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn copy_match_to_slots(m: Match, slots: &mut [Option<NonMaxUsize>]) {
+    let slot_start = m.pattern().as_usize() * 2;
+    let slot_end = slot_start + 1;
+    if let Some(slot) = slots.get_mut(slot_start) {
+        *slot = NonMaxUsize::new(m.start());
+    }
+    if let Some(slot) = slots.get_mut(slot_end) {
+        *slot = NonMaxUsize::new(m.end());
+    }
+}
+
+/// The state of an overlapping search through the meta regex engine, which
+/// is carried between calls of `Strategy::search_overlapping`.
+///
+/// The full and lazy DFAs find the end of every overlapping match with a
+/// forward scan, after which a reverse overlapping scan anchored to that end
+/// finds all of its starting positions. If either DFA fails, the search falls
+/// back to running an anchored PikeVM at every starting position.
+#[derive(Clone, Debug)]
+pub(super) struct OverlappingState {
+    engine: OverlappingEngine,
+    /// Matches that have been found but not reported yet, in reverse order.
+    pending: Vec<Match>,
+    /// The last end offset (and the patterns matching there) found by a
+    /// DFA. Every match ending before it has been reported, so the fallback
+    /// can skip them.
+    reported: Option<(usize, Vec<PatternID>)>,
+}
+
+impl OverlappingState {
+    pub(super) fn start() -> OverlappingState {
+        OverlappingState { engine: OverlappingEngine::Start, pending: Vec::new(), reported: None }
+    }
+}
+
+/// The progress of an async search, which is suspended whenever the cursor
+/// has to wait for a chunk.
+///
+/// Like a regular search, the full and lazy DFAs find the end of a match with
+/// a forward scan and then its start with a reverse scan anchored at that end.
+/// If neither DFA is available, or if one of them fails, the search starts
+/// over with the PikeVM.
+#[derive(Clone, Debug)]
+pub(super) struct PollState {
+    start: usize,
+    earliest: bool,
+    engine: PollEngine,
+}
+
+impl PollState {
+    /// Starts a search at `start`. If `earliest` is set, the search stops as
+    /// soon as it knows that there is a match, and the match it reports is
+    /// empty and located at the position where the search stopped.
+    pub(super) fn start(start: usize, earliest: bool) -> PollState {
+        PollState { start, earliest, engine: PollEngine::Start }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum PollEngine {
+    Start,
+    Dfa(DfaPoll<StateID>),
+    DfaRev(HalfMatch, DfaPoll<StateID>),
+    Hybrid(DfaPoll<LazyStateID>),
+    HybridRev(HalfMatch, DfaPoll<LazyStateID>),
+    PikeVM(PikeVMPoll),
+    Pre(PrePoll),
+    /// A match that still has to be checked for splitting a codepoint.
+    Split(Match),
+}
+
+#[derive(Clone, Debug)]
+enum OverlappingEngine {
+    Start,
+    DFA(crate::engines::dfa::OverlappingState),
+    Hybrid(crate::engines::hybrid::OverlappingState),
+    Fallback { at: usize },
+    Done,
 }
@@ -24,16 +24,20 @@ regex engine that needs them. If an engine is unavailable or not used, then a
 cache for it will *not* actually be allocated.
 */
 
+use core::task::{Context, Poll};
+
 use log::debug;
+use regex_automata::hybrid::LazyStateID;
 use regex_automata::nfa::thompson::NFA;
 use regex_automata::util::prefilter::Prefilter;
-use regex_automata::util::primitives::NonMaxUsize;
-use regex_automata::{dfa, hybrid, HalfMatch, Match, MatchKind, PatternID};
+use regex_automata::util::primitives::{NonMaxUsize, StateID};
+use regex_automata::{dfa, hybrid, HalfMatch, Match, MatchKind, PatternID, PatternSet};
 
-use crate::cursor::Cursor;
-use crate::engines::meta::error::{BuildError, RetryFailError};
+use crate::cursor::{AsyncCursor, Cursor};
+use crate::engines::meta::error::{BuildError, RetryError, RetryFailError};
+use crate::engines::meta::poll::{DfaPoll, PikeVMPoll};
 use crate::engines::meta::regex::RegexInfo;
-use crate::engines::pikevm;
+use crate::engines::{backtrack, onepass, pikevm};
 use crate::Input;
 
 #[derive(Debug)]
@@ -92,15 +96,38 @@ impl PikeVMEngine {
         crate::engines::pikevm::search_slots(&self.0, cache.0.as_mut().unwrap(), input, slots)
     }
 
-    // #[cfg_attr(feature = "perf-inline", inline(always))]
-    // pub(crate) fn which_overlapping_matches(
-    //     &self,
-    //     cache: &mut PikeVMCache,
-    //     input: &mut Input<impl Cursor>,
-    //     patset: &mut PatternSet,
-    // ) {
-    //     self.0.which_overlapping_matches(cache.0.as_mut().unwrap(), input, patset)
-    // }
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn which_overlapping_matches(
+        &self,
+        cache: &mut PikeVMCache,
+        input: &mut Input<impl Cursor>,
+        patset: &mut PatternSet,
+    ) {
+        let cache = cache.0.as_mut().unwrap();
+        crate::engines::pikevm::which_overlapping_matches(&self.0, cache, input, patset)
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn search_overlapping(
+        &self,
+        cache: &mut PikeVMCache,
+        input: &mut Input<impl Cursor>,
+        report: impl FnMut(PatternID, usize) -> bool,
+    ) {
+        let cache = cache.0.as_mut().unwrap();
+        crate::engines::pikevm::search_overlapping(&self.0, cache, input, report)
+    }
+
+    pub(crate) fn poll_search<C: AsyncCursor>(
+        &self,
+        cache: &mut PikeVMCache,
+        state: &mut PikeVMPoll,
+        cursor: &mut C,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Match>> {
+        let cache = cache.0.as_mut().unwrap();
+        crate::engines::meta::poll::pikevm_poll_search(&self.0, cache, state, cursor, cx)
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -124,6 +151,243 @@ impl PikeVMCache {
     }
 }
 
+#[derive(Debug)]
+pub(crate) struct BoundedBacktracker(Option<BoundedBacktrackerEngine>);
+
+impl BoundedBacktracker {
+    pub(crate) fn new(
+        info: &RegexInfo,
+        pre: Option<Prefilter>,
+        nfa: &NFA,
+    ) -> Result<BoundedBacktracker, BuildError> {
+        BoundedBacktrackerEngine::new(info, pre, nfa).map(BoundedBacktracker)
+    }
+
+    pub(crate) fn create_cache(&self) -> BoundedBacktrackerCache {
+        BoundedBacktrackerCache::new(self)
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn get(&self, input: &mut Input<impl Cursor>) -> Option<&BoundedBacktrackerEngine> {
+        let engine = self.0.as_ref()?;
+        // It is difficult to make the backtracker give up early if it is
+        // guaranteed to eventually wind up in a match state. This is because
+        // of the greedy nature of a backtracker: it just blindly mushes
+        // forward. Every other regex engine is able to give up more quickly,
+        // so even if the backtracker might be able to zip through faster than
+        // (say) the PikeVM, we prefer the theoretical benefit that some other
+        // engine might be able to scan much less of the haystack than the
+        // backtracker.
+        //
+        // Now, if the span is really short already, then we allow the
+        // backtracker to run. We can't cheaply look at the length of the
+        // whole haystack here (it may not even be known), so the span is
+        // used instead.
+        if input.get_earliest() && input.get_span().len() > 128 {
+            return None;
+        }
+        // If the backtracker is just going to return an error because the
+        // span is too long, then obviously do not use it. This also rules out
+        // cursors of unknown length since their span ends at usize::MAX.
+        if input.get_span().len() > engine.max_haystack_len() {
+            return None;
+        }
+        Some(engine)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct BoundedBacktrackerEngine(backtrack::BoundedBacktracker);
+
+impl BoundedBacktrackerEngine {
+    pub(crate) fn new(
+        info: &RegexInfo,
+        pre: Option<Prefilter>,
+        nfa: &NFA,
+    ) -> Result<Option<BoundedBacktrackerEngine>, BuildError> {
+        if !info.config().get_backtrack()
+            || info.config().get_match_kind() != MatchKind::LeftmostFirst
+        {
+            return Ok(None);
+        }
+        let backtrack_config = backtrack::Config::new().prefilter(pre);
+        let engine = backtrack::Builder::new()
+            .configure(backtrack_config)
+            .build_from_nfa(nfa.clone())
+            .map_err(BuildError::nfa)?;
+        debug!("BoundedBacktracker built (max haystack length: {:?})", engine.max_haystack_len());
+        Ok(Some(BoundedBacktrackerEngine(engine)))
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn is_match(
+        &self,
+        cache: &mut BoundedBacktrackerCache,
+        input: &mut Input<impl Cursor>,
+    ) -> bool {
+        // OK because we only permit access to this engine when we know
+        // the span is short enough for the backtracker to run without
+        // reporting an error. The only other error is a cursor that failed
+        // to backtrack, and the result of such a search is discarded.
+        backtrack::try_is_match(&self.0, cache.0.as_mut().unwrap(), input).unwrap_or_default()
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn search_slots(
+        &self,
+        cache: &mut BoundedBacktrackerCache,
+        input: &mut Input<impl Cursor>,
+        slots: &mut [Option<NonMaxUsize>],
+    ) -> Option<PatternID> {
+        // OK because we only permit access to this engine when we know
+        // the span is short enough for the backtracker to run without
+        // reporting an error. The only other error is a cursor that failed
+        // to backtrack, and the result of such a search is discarded.
+        backtrack::try_search_slots(&self.0, cache.0.as_mut().unwrap(), input, slots)
+            .unwrap_or_default()
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn max_haystack_len(&self) -> usize {
+        self.0.max_haystack_len()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct BoundedBacktrackerCache(Option<backtrack::Cache>);
+
+impl BoundedBacktrackerCache {
+    pub(crate) fn none() -> BoundedBacktrackerCache {
+        BoundedBacktrackerCache(None)
+    }
+
+    pub(crate) fn new(builder: &BoundedBacktracker) -> BoundedBacktrackerCache {
+        BoundedBacktrackerCache(builder.0.as_ref().map(|e| backtrack::Cache::new(&e.0)))
+    }
+
+    pub(crate) fn reset(&mut self, builder: &BoundedBacktracker) {
+        if let Some(ref e) = builder.0 {
+            self.0.as_mut().unwrap().reset(&e.0);
+        }
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.0.as_ref().map_or(0, |c| c.memory_usage())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct OnePass(Option<OnePassEngine>);
+
+impl OnePass {
+    pub(crate) fn new(info: &RegexInfo, nfa: &NFA) -> OnePass {
+        OnePass(OnePassEngine::new(info, nfa))
+    }
+
+    pub(crate) fn create_cache(&self) -> OnePassCache {
+        OnePassCache::new(self)
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn get(&self, input: &Input<impl Cursor>) -> Option<&OnePassEngine> {
+        let engine = self.0.as_ref()?;
+        if !input.get_anchored().is_anchored() && !engine.get_nfa().is_always_start_anchored() {
+            return None;
+        }
+        Some(engine)
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.0.as_ref().map_or(0, |e| e.memory_usage())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct OnePassEngine(onepass::DFA);
+
+impl OnePassEngine {
+    pub(crate) fn new(info: &RegexInfo, nfa: &NFA) -> Option<OnePassEngine> {
+        if !info.config().get_onepass() {
+            return None;
+        }
+        // In order to even attempt building a one-pass DFA, we require
+        // that we either have at least one explicit capturing group or
+        // there's a Unicode word boundary somewhere. If we don't have
+        // either of these things, then the lazy DFA will almost certainly
+        // be useable and be much faster.
+        if info.props_union().explicit_captures_len() == 0
+            && !info.props_union().look_set().contains_word_unicode()
+        {
+            debug!("not building OnePass because it isn't worth it");
+            return None;
+        }
+        let onepass_config = onepass::Config::new()
+            .match_kind(info.config().get_match_kind())
+            // Like for the lazy DFA, we unconditionally enable this
+            // because it doesn't cost much and makes the API more
+            // flexible.
+            .starts_for_each_pattern(true)
+            .byte_classes(info.config().get_byte_classes())
+            .size_limit(info.config().get_onepass_size_limit());
+        let result = onepass::Builder::new().configure(onepass_config).build_from_nfa(nfa.clone());
+        let engine = match result {
+            Ok(engine) => engine,
+            Err(_err) => {
+                debug!("OnePass failed to build: {}", _err);
+                return None;
+            }
+        };
+        debug!("OnePass built, {} bytes", engine.memory_usage());
+        Some(OnePassEngine(engine))
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn search_slots(
+        &self,
+        cache: &mut OnePassCache,
+        input: &mut Input<impl Cursor>,
+        slots: &mut [Option<NonMaxUsize>],
+    ) -> Option<PatternID> {
+        // OK because we only permit getting a OnePassEngine when we know
+        // the search is anchored and thus an error cannot occur (except for
+        // a cursor that failed to backtrack, whose result is discarded).
+        onepass::try_search_slots(&self.0, cache.0.as_mut().unwrap(), input, slots)
+            .unwrap_or_default()
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.0.memory_usage()
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn get_nfa(&self) -> &NFA {
+        self.0.get_nfa()
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct OnePassCache(Option<onepass::Cache>);
+
+impl OnePassCache {
+    pub(crate) fn none() -> OnePassCache {
+        OnePassCache(None)
+    }
+
+    pub(crate) fn new(builder: &OnePass) -> OnePassCache {
+        OnePassCache(builder.0.as_ref().map(|e| e.0.create_cache()))
+    }
+
+    pub(crate) fn reset(&mut self, builder: &OnePass) {
+        if let Some(ref e) = builder.0 {
+            self.0.as_mut().unwrap().reset(&e.0);
+        }
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.0.as_ref().map_or(0, |c| c.memory_usage())
+    }
+}
+
 #[derive(Debug)]
 pub(crate) struct Hybrid(Option<HybridEngine>);
 
@@ -146,6 +410,11 @@ impl Hybrid {
         Some(engine)
     }
 
+    /// Like `get`, but for async searches, which don't have an `Input`.
+    pub(crate) fn get_async(&self) -> Option<&HybridEngine> {
+        self.0.as_ref()
+    }
+
     pub(crate) fn is_some(&self) -> bool {
         self.0.is_some()
     }
@@ -248,16 +517,16 @@ impl HybridEngine {
         crate::engines::hybrid::try_search_fwd(fwd, fwdcache, input).map_err(|e| e.into())
     }
 
-    // #[cfg_attr(feature = "perf-inline", inline(always))]
-    // pub(crate) fn try_search_half_fwd_stopat(
-    //     &self,
-    //     cache: &mut HybridCache,
-    //     input: &mut Input<impl Cursor>,
-    // ) -> Result<Result<HalfMatch, usize>, RetryFailError> {
-    //     let dfa = self.0.forward();
-    //     let mut cache = cache.0.as_mut().unwrap().as_parts_mut().0;
-    //     crate::meta::stopat::hybrid_try_search_half_fwd(dfa, &mut cache, input)
-    // }
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_half_fwd_stopat(
+        &self,
+        cache: &mut HybridCache,
+        input: &mut Input<impl Cursor>,
+    ) -> Result<Result<HalfMatch, usize>, RetryFailError> {
+        let dfa = self.0.forward();
+        let fwdcache = cache.0.as_mut().unwrap().as_parts_mut().0;
+        crate::engines::meta::stopat::hybrid_try_search_half_fwd(dfa, fwdcache, input)
+    }
 
     #[cfg_attr(feature = "perf-inline", inline(always))]
     pub(crate) fn try_search_half_rev(
@@ -270,29 +539,86 @@ impl HybridEngine {
         crate::engines::hybrid::try_search_rev(rev, revcache, input).map_err(|e| e.into())
     }
 
-    // #[cfg_attr(feature = "perf-inline", inline(always))]
-    // pub(crate) fn try_search_half_rev_limited(
-    //     &self,
-    //     cache: &mut HybridCache,
-    //     input: &mut Input<impl Cursor>,
-    //     min_start: usize,
-    // ) -> Result<Option<HalfMatch>, RetryError> {
-    //     let dfa = self.0.reverse();
-    //     let mut cache = cache.0.as_mut().unwrap().as_parts_mut().1;
-    //     crate::meta::limited::hybrid_try_search_half_rev(dfa, &mut cache, input, min_start)
-    // }
-
-    // #[inline]
-    // pub(crate) fn try_which_overlapping_matches(
-    //     &self,
-    //     cache: &mut HybridCache,
-    //     input: &mut Input<impl Cursor>,
-    //     patset: &mut PatternSet,
-    // ) -> Result<(), RetryFailError> {
-    //         let fwd = self.0.forward();
-    //         let mut fwdcache = cache.0.as_mut().unwrap().as_parts_mut().0;
-    //         fwd.try_which_overlapping_matches(&mut fwdcache, input, patset).map_err(|e| e.into())
-    // }
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_half_rev_limited(
+        &self,
+        cache: &mut HybridCache,
+        input: &mut Input<impl Cursor>,
+        min_start: usize,
+    ) -> Result<Option<HalfMatch>, RetryError> {
+        let dfa = self.0.reverse();
+        let revcache = cache.0.as_mut().unwrap().as_parts_mut().1;
+        crate::engines::meta::limited::hybrid_try_search_half_rev(dfa, revcache, input, min_start)
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_overlapping_fwd(
+        &self,
+        cache: &mut HybridCache,
+        input: &mut Input<impl Cursor>,
+        state: &mut crate::engines::hybrid::OverlappingState,
+    ) -> Result<(), RetryFailError> {
+        let fwd = self.0.forward();
+        let fwdcache = cache.0.as_mut().unwrap().as_parts_mut().0;
+        crate::engines::hybrid::try_search_overlapping_fwd(fwd, fwdcache, input, state)
+            .map_err(|e| e.into())
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_overlapping_rev(
+        &self,
+        cache: &mut HybridCache,
+        input: &mut Input<impl Cursor>,
+        state: &mut crate::engines::hybrid::OverlappingState,
+    ) -> Result<(), RetryFailError> {
+        let rev = self.0.reverse();
+        let revcache = cache.0.as_mut().unwrap().as_parts_mut().1;
+        crate::engines::hybrid::try_search_overlapping_rev(rev, revcache, input, state)
+            .map_err(|e| e.into())
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_which_overlapping_matches(
+        &self,
+        cache: &mut HybridCache,
+        input: &mut Input<impl Cursor>,
+        patset: &mut PatternSet,
+    ) -> Result<(), RetryFailError> {
+        let fwd = self.0.forward();
+        let fwdcache = cache.0.as_mut().unwrap().as_parts_mut().0;
+        crate::engines::hybrid::try_which_overlapping_matches(fwd, fwdcache, input, patset)
+            .map_err(|e| e.into())
+    }
+
+    pub(crate) fn poll_search_half_fwd<C: AsyncCursor>(
+        &self,
+        cache: &mut HybridCache,
+        state: &mut DfaPoll<LazyStateID>,
+        earliest: bool,
+        cursor: &mut C,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HalfMatch>, RetryFailError>> {
+        let fwd = self.0.forward();
+        let fwdcache = cache.0.as_mut().unwrap().as_parts_mut().0;
+        crate::engines::meta::poll::hybrid_poll_search_half_fwd(
+            fwd, fwdcache, state, earliest, cursor, cx,
+        )
+    }
+
+    pub(crate) fn poll_search_half_rev<C: AsyncCursor>(
+        &self,
+        cache: &mut HybridCache,
+        state: &mut DfaPoll<LazyStateID>,
+        start: usize,
+        cursor: &mut C,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HalfMatch>, RetryFailError>> {
+        let rev = self.0.reverse();
+        let revcache = cache.0.as_mut().unwrap().as_parts_mut().1;
+        crate::engines::meta::poll::hybrid_poll_search_half_rev(
+            rev, revcache, state, start, cursor, cx,
+        )
+    }
 }
 
 #[derive(Clone, Debug)]
@@ -338,6 +664,11 @@ impl DFA {
         Some(engine)
     }
 
+    /// Like `get`, but for async searches, which don't have an `Input`.
+    pub(crate) fn get_async(&self) -> Option<&DFAEngine> {
+        self.0.as_ref()
+    }
+
     pub(crate) fn is_some(&self) -> bool {
         self.0.is_some()
     }
@@ -451,14 +782,14 @@ impl DFAEngine {
         crate::engines::dfa::try_search_fwd(self.0.forward(), input).map_err(|e| e.into())
     }
 
-    // #[cfg_attr(feature = "perf-inline", inline(always))]
-    // pub(crate) fn try_search_half_fwd_stopat(
-    //     &self,
-    //     input: &mut Input<impl Cursor>,
-    // ) -> Result<Result<HalfMatch, usize>, RetryFailError> {
-    //         let dfa = self.0.forward();
-    //         crate::meta::stopat::dfa_try_search_half_fwd(dfa, input)
-    // }
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_half_fwd_stopat(
+        &self,
+        input: &mut Input<impl Cursor>,
+    ) -> Result<Result<HalfMatch, usize>, RetryFailError> {
+        let dfa = self.0.forward();
+        crate::engines::meta::stopat::dfa_try_search_half_fwd(dfa, input)
+    }
 
     #[cfg_attr(feature = "perf-inline", inline(always))]
     pub(crate) fn try_search_half_rev(
@@ -468,269 +799,260 @@ impl DFAEngine {
         crate::engines::dfa::try_search_rev(self.0.reverse(), input).map_err(|e| e.into())
     }
 
-    // #[cfg_attr(feature = "perf-inline", inline(always))]
-    // pub(crate) fn try_search_half_rev_limited(
-    //     &self,
-    //     input: &mut Input<impl Cursor>,
-    //     min_start: usize,
-    // ) -> Result<Option<HalfMatch>, RetryError> {
-    //     let dfa = self.0.reverse();
-    //     crate::meta::limited::dfa_try_search_half_rev(dfa, input, min_start)
-    // }
-
-    // #[inline]
-    // pub(crate) fn try_which_overlapping_matches(
-    //     &self,
-    //     input: &mut Input<impl Cursor>,
-    //     patset: &mut PatternSet,
-    // ) -> Result<(), RetryFailError> {
-    //         use crate::dfa::Automaton;
-    //         self.0.forward().try_which_overlapping_matches(input, patset).map_err(|e| e.into())
-    // }
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_half_rev_limited(
+        &self,
+        input: &mut Input<impl Cursor>,
+        min_start: usize,
+    ) -> Result<Option<HalfMatch>, RetryError> {
+        let dfa = self.0.reverse();
+        crate::engines::meta::limited::dfa_try_search_half_rev(dfa, input, min_start)
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_overlapping_fwd(
+        &self,
+        input: &mut Input<impl Cursor>,
+        state: &mut crate::engines::dfa::OverlappingState,
+    ) -> Result<(), RetryFailError> {
+        crate::engines::dfa::try_search_overlapping_fwd(self.0.forward(), input, state)
+            .map_err(|e| e.into())
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_overlapping_rev(
+        &self,
+        input: &mut Input<impl Cursor>,
+        state: &mut crate::engines::dfa::OverlappingState,
+    ) -> Result<(), RetryFailError> {
+        crate::engines::dfa::try_search_overlapping_rev(self.0.reverse(), input, state)
+            .map_err(|e| e.into())
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_which_overlapping_matches(
+        &self,
+        input: &mut Input<impl Cursor>,
+        patset: &mut PatternSet,
+    ) -> Result<(), RetryFailError> {
+        crate::engines::dfa::try_which_overlapping_matches(self.0.forward(), input, patset)
+            .map_err(|e| e.into())
+    }
+
+    pub(crate) fn poll_search_half_fwd<C: AsyncCursor>(
+        &self,
+        state: &mut DfaPoll<StateID>,
+        earliest: bool,
+        cursor: &mut C,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HalfMatch>, RetryFailError>> {
+        let dfa = self.0.forward();
+        crate::engines::meta::poll::dfa_poll_search_half_fwd(dfa, state, earliest, cursor, cx)
+    }
+
+    pub(crate) fn poll_search_half_rev<C: AsyncCursor>(
+        &self,
+        state: &mut DfaPoll<StateID>,
+        start: usize,
+        cursor: &mut C,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HalfMatch>, RetryFailError>> {
+        let dfa = self.0.reverse();
+        crate::engines::meta::poll::dfa_poll_search_half_rev(dfa, state, start, cursor, cx)
+    }
 
     pub(crate) fn memory_usage(&self) -> usize {
         self.0.forward().memory_usage() + self.0.reverse().memory_usage()
     }
 }
 
-// #[derive(Debug)]
-// pub(crate) struct ReverseHybrid(Option<ReverseHybridEngine>);
-
-// impl ReverseHybrid {
-//     pub(crate) fn none() -> ReverseHybrid {
-//         ReverseHybrid(None)
-//     }
-
-//     pub(crate) fn new(info: &RegexInfo, nfarev: &NFA) -> ReverseHybrid {
-//         ReverseHybrid(ReverseHybridEngine::new(info, nfarev))
-//     }
-
-//     pub(crate) fn create_cache(&self) -> ReverseHybridCache {
-//         ReverseHybridCache::new(self)
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     pub(crate) fn get(&self, _input: &mut Input<impl Cursor>) -> Option<&ReverseHybridEngine> {
-//         let engine = self.0.as_ref()?;
-//         Some(engine)
-//     }
-// }
-
-// #[derive(Debug)]
-// pub(crate) struct ReverseHybridEngine(hybrid::dfa::DFA);
-
-// impl ReverseHybridEngine {
-//     pub(crate) fn new(info: &RegexInfo, nfarev: &NFA) -> Option<ReverseHybridEngine> {
-//         if !info.config().get_hybrid() {
-//             return None;
-//         }
-//         // Since we only use this for reverse searches, we can hard-code
-//         // a number of things like match semantics, prefilters, starts
-//         // for each pattern and so on.
-//         let dfa_config = hybrid::dfa::Config::new()
-//             .match_kind(MatchKind::All)
-//             .prefilter(None)
-//             .starts_for_each_pattern(false)
-//             .byte_classes(info.config().get_byte_classes())
-//             .unicode_word_boundary(true)
-//             .specialize_start_states(false)
-//             .cache_capacity(info.config().get_hybrid_cache_capacity())
-//             .skip_cache_capacity_check(false)
-//             .minimum_cache_clear_count(Some(3))
-//             .minimum_bytes_per_state(Some(10));
-//         let result =
-//             hybrid::dfa::Builder::new().configure(dfa_config).build_from_nfa(nfarev.clone());
-//         let rev = match result {
-//             Ok(rev) => rev,
-//             Err(_err) => {
-//                 debug!("lazy reverse DFA failed to build: {}", _err);
-//                 return None;
-//             }
-//         };
-//         debug!("lazy reverse DFA built");
-//         Some(ReverseHybridEngine(rev))
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     pub(crate) fn try_search_half_rev_limited(
-//         &self,
-//         cache: &mut ReverseHybridCache,
-//         input: &mut Input<impl Cursor>,
-//         min_start: usize,
-//     ) -> Result<Option<HalfMatch>, RetryError> {
-//         let dfa = &self.0;
-//         let mut cache = cache.0.as_mut().unwrap();
-//         crate::meta::limited::hybrid_try_search_half_rev(dfa, &mut cache, input, min_start)
-//     }
-// }
-
-// #[derive(Clone, Debug)]
-// pub(crate) struct ReverseHybridCache(
-//     #[cfg(feature = "hybrid")] Option<hybrid::dfa::Cache>,
-//     #[cfg(not(feature = "hybrid"))] (),
-// );
-
-// impl ReverseHybridCache {
-//     pub(crate) fn none() -> ReverseHybridCache {
-//         #[cfg(feature = "hybrid")]
-//         {
-//             ReverseHybridCache(None)
-//         }
-//         #[cfg(not(feature = "hybrid"))]
-//         {
-//             ReverseHybridCache(())
-//         }
-//     }
-
-//     pub(crate) fn new(builder: &ReverseHybrid) -> ReverseHybridCache {
-//         #[cfg(feature = "hybrid")]
-//         {
-//             ReverseHybridCache(builder.0.as_ref().map(|e| e.0.create_cache()))
-//         }
-//         #[cfg(not(feature = "hybrid"))]
-//         {
-//             ReverseHybridCache(())
-//         }
-//     }
-
-//     pub(crate) fn reset(&mut self, builder: &ReverseHybrid) {
-//         #[cfg(feature = "hybrid")]
-//         if let Some(ref e) = builder.0 {
-//             self.0.as_mut().unwrap().reset(&e.0);
-//         }
-//     }
-
-//     pub(crate) fn memory_usage(&self) -> usize {
-//         #[cfg(feature = "hybrid")]
-//         {
-//             self.0.as_ref().map_or(0, |c| c.memory_usage())
-//         }
-//         #[cfg(not(feature = "hybrid"))]
-//         {
-//             0
-//         }
-//     }
-// }
-
-// #[derive(Debug)]
-// pub(crate) struct ReverseDFA(Option<ReverseDFAEngine>);
-
-// impl ReverseDFA {
-//     pub(crate) fn none() -> ReverseDFA {
-//         ReverseDFA(None)
-//     }
-
-//     pub(crate) fn new(info: &RegexInfo, nfarev: &NFA) -> ReverseDFA {
-//         ReverseDFA(ReverseDFAEngine::new(info, nfarev))
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     pub(crate) fn get(&self, _input: &mut Input<impl Cursor>) -> Option<&ReverseDFAEngine> {
-//         let engine = self.0.as_ref()?;
-//         Some(engine)
-//     }
-
-//     pub(crate) fn is_some(&self) -> bool {
-//         self.0.is_some()
-//     }
-
-//     pub(crate) fn memory_usage(&self) -> usize {
-//         self.0.as_ref().map_or(0, |e| e.memory_usage())
-//     }
-// }
-
-// #[derive(Debug)]
-// pub(crate) struct ReverseDFAEngine(
-//     #[cfg(feature = "dfa-build")] dfa::dense::DFA<Vec<u32>>,
-//     #[cfg(not(feature = "dfa-build"))] (),
-// );
-
-// impl ReverseDFAEngine {
-//     pub(crate) fn new(info: &RegexInfo, nfarev: &NFA) -> Option<ReverseDFAEngine> {
-//         #[cfg(feature = "dfa-build")]
-//         {
-//             if !info.config().get_dfa() {
-//                 return None;
-//             }
-//             // If our NFA is anything but small, don't even bother with a DFA.
-//             if let Some(state_limit) = info.config().get_dfa_state_limit() {
-//                 if nfarev.states().len() > state_limit {
-//                     debug!(
-//                         "skipping full reverse DFA because NFA has {} states, \
-//                          which exceeds the heuristic limit of {}",
-//                         nfarev.states().len(),
-//                         state_limit,
-//                     );
-//                     return None;
-//                 }
-//             }
-//             // We cut the size limit in two because the total heap used by DFA
-//             // construction is determinization aux memory and the DFA itself,
-//             // and those things are configured independently in the lower level
-//             // DFA builder API.
-//             let size_limit = info.config().get_dfa_size_limit().map(|n| n / 2);
-//             // Since we only use this for reverse searches, we can hard-code
-//             // a number of things like match semantics, prefilters, starts
-//             // for each pattern and so on. We also disable acceleration since
-//             // it's incompatible with limited searches (which is the only
-//             // operation we support for this kind of engine at the moment).
-//             let dfa_config = dfa::dense::Config::new()
-//                 .match_kind(MatchKind::All)
-//                 .prefilter(None)
-//                 .accelerate(false)
-//                 .start_kind(dfa::StartKind::Anchored)
-//                 .starts_for_each_pattern(false)
-//                 .byte_classes(info.config().get_byte_classes())
-//                 .unicode_word_boundary(true)
-//                 .specialize_start_states(false)
-//                 .determinize_size_limit(size_limit)
-//                 .dfa_size_limit(size_limit);
-//             let result = dfa::dense::Builder::new().configure(dfa_config).build_from_nfa(&nfarev);
-//             let rev = match result {
-//                 Ok(rev) => rev,
-//                 Err(_err) => {
-//                     debug!("full reverse DFA failed to build: {}", _err);
-//                     return None;
-//                 }
-//             };
-//             debug!("fully compiled reverse DFA built, {} bytes", rev.memory_usage());
-//             Some(ReverseDFAEngine(rev))
-//         }
-//         #[cfg(not(feature = "dfa-build"))]
-//         {
-//             None
-//         }
-//     }
-
-//     #[cfg_attr(feature = "perf-inline", inline(always))]
-//     pub(crate) fn try_search_half_rev_limited(
-//         &self,
-//         input: &mut Input<impl Cursor>,
-//         min_start: usize,
-//     ) -> Result<Option<HalfMatch>, RetryError> {
-//         #[cfg(feature = "dfa-build")]
-//         {
-//             let dfa = &self.0;
-//             crate::meta::limited::dfa_try_search_half_rev(dfa, input, min_start)
-//         }
-//         #[cfg(not(feature = "dfa-build"))]
-//         {
-//             // Impossible to reach because this engine is never constructed
-//             // if the requisite features aren't enabled.
-//             unreachable!()
-//         }
-//     }
-
-//     pub(crate) fn memory_usage(&self) -> usize {
-//         #[cfg(feature = "dfa-build")]
-//         {
-//             self.0.memory_usage()
-//         }
-//         #[cfg(not(feature = "dfa-build"))]
-//         {
-//             // Impossible to reach because this engine is never constructed
-//             // if the requisite features aren't enabled.
-//             unreachable!()
-//         }
-//     }
-// }
+#[derive(Debug)]
+pub(crate) struct ReverseHybrid(Option<ReverseHybridEngine>);
+
+impl ReverseHybrid {
+    pub(crate) fn none() -> ReverseHybrid {
+        ReverseHybrid(None)
+    }
+
+    pub(crate) fn new(info: &RegexInfo, nfarev: &NFA) -> ReverseHybrid {
+        ReverseHybrid(ReverseHybridEngine::new(info, nfarev))
+    }
+
+    pub(crate) fn create_cache(&self) -> ReverseHybridCache {
+        ReverseHybridCache::new(self)
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn get(&self, _input: &mut Input<impl Cursor>) -> Option<&ReverseHybridEngine> {
+        let engine = self.0.as_ref()?;
+        Some(engine)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ReverseHybridEngine(hybrid::dfa::DFA);
+
+impl ReverseHybridEngine {
+    pub(crate) fn new(info: &RegexInfo, nfarev: &NFA) -> Option<ReverseHybridEngine> {
+        if !info.config().get_hybrid() {
+            return None;
+        }
+        // Since we only use this for reverse searches, we can hard-code
+        // a number of things like match semantics, prefilters, starts
+        // for each pattern and so on.
+        let dfa_config = hybrid::dfa::Config::new()
+            .match_kind(MatchKind::All)
+            .prefilter(None)
+            .starts_for_each_pattern(false)
+            .byte_classes(info.config().get_byte_classes())
+            .unicode_word_boundary(true)
+            .specialize_start_states(false)
+            .cache_capacity(info.config().get_hybrid_cache_capacity())
+            .skip_cache_capacity_check(false)
+            .minimum_cache_clear_count(Some(3))
+            .minimum_bytes_per_state(Some(10));
+        let result =
+            hybrid::dfa::Builder::new().configure(dfa_config).build_from_nfa(nfarev.clone());
+        let rev = match result {
+            Ok(rev) => rev,
+            Err(_err) => {
+                debug!("lazy reverse DFA failed to build: {}", _err);
+                return None;
+            }
+        };
+        debug!("lazy reverse DFA built");
+        Some(ReverseHybridEngine(rev))
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_half_rev_limited(
+        &self,
+        cache: &mut ReverseHybridCache,
+        input: &mut Input<impl Cursor>,
+        min_start: usize,
+    ) -> Result<Option<HalfMatch>, RetryError> {
+        let dfa = &self.0;
+        let cache = cache.0.as_mut().unwrap();
+        crate::engines::meta::limited::hybrid_try_search_half_rev(dfa, cache, input, min_start)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct ReverseHybridCache(Option<hybrid::dfa::Cache>);
+
+impl ReverseHybridCache {
+    pub(crate) fn none() -> ReverseHybridCache {
+        ReverseHybridCache(None)
+    }
+
+    pub(crate) fn new(builder: &ReverseHybrid) -> ReverseHybridCache {
+        ReverseHybridCache(builder.0.as_ref().map(|e| e.0.create_cache()))
+    }
+
+    pub(crate) fn reset(&mut self, builder: &ReverseHybrid) {
+        if let Some(ref e) = builder.0 {
+            self.0.as_mut().unwrap().reset(&e.0);
+        }
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.0.as_ref().map_or(0, |c| c.memory_usage())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ReverseDFA(Option<ReverseDFAEngine>);
+
+impl ReverseDFA {
+    pub(crate) fn none() -> ReverseDFA {
+        ReverseDFA(None)
+    }
+
+    pub(crate) fn new(info: &RegexInfo, nfarev: &NFA) -> ReverseDFA {
+        ReverseDFA(ReverseDFAEngine::new(info, nfarev))
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn get(&self, _input: &mut Input<impl Cursor>) -> Option<&ReverseDFAEngine> {
+        let engine = self.0.as_ref()?;
+        Some(engine)
+    }
+
+    pub(crate) fn is_some(&self) -> bool {
+        self.0.is_some()
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.0.as_ref().map_or(0, |e| e.memory_usage())
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct ReverseDFAEngine(dfa::dense::DFA<Vec<u32>>);
+
+impl ReverseDFAEngine {
+    pub(crate) fn new(info: &RegexInfo, nfarev: &NFA) -> Option<ReverseDFAEngine> {
+        {
+            if !info.config().get_dfa() {
+                return None;
+            }
+            // If our NFA is anything but small, don't even bother with a DFA.
+            if let Some(state_limit) = info.config().get_dfa_state_limit() {
+                if nfarev.states().len() > state_limit {
+                    debug!(
+                        "skipping full reverse DFA because NFA has {} states, \
+                         which exceeds the heuristic limit of {}",
+                        nfarev.states().len(),
+                        state_limit,
+                    );
+                    return None;
+                }
+            }
+            // We cut the size limit in two because the total heap used by DFA
+            // construction is determinization aux memory and the DFA itself,
+            // and those things are configured independently in the lower level
+            // DFA builder API.
+            let size_limit = info.config().get_dfa_size_limit().map(|n| n / 2);
+            // Since we only use this for reverse searches, we can hard-code
+            // a number of things like match semantics, prefilters, starts
+            // for each pattern and so on. We also disable acceleration since
+            // it's incompatible with limited searches (which is the only
+            // operation we support for this kind of engine at the moment).
+            let dfa_config = dfa::dense::Config::new()
+                .match_kind(MatchKind::All)
+                .prefilter(None)
+                .accelerate(false)
+                .start_kind(dfa::StartKind::Anchored)
+                .starts_for_each_pattern(false)
+                .byte_classes(info.config().get_byte_classes())
+                .unicode_word_boundary(true)
+                .specialize_start_states(false)
+                .determinize_size_limit(size_limit)
+                .dfa_size_limit(size_limit);
+            let result = dfa::dense::Builder::new().configure(dfa_config).build_from_nfa(nfarev);
+            let rev = match result {
+                Ok(rev) => rev,
+                Err(_err) => {
+                    debug!("full reverse DFA failed to build: {}", _err);
+                    return None;
+                }
+            };
+            debug!("fully compiled reverse DFA built, {} bytes", rev.memory_usage());
+            Some(ReverseDFAEngine(rev))
+        }
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn try_search_half_rev_limited(
+        &self,
+        input: &mut Input<impl Cursor>,
+        min_start: usize,
+    ) -> Result<Option<HalfMatch>, RetryError> {
+        let dfa = &self.0;
+        crate::engines::meta::limited::dfa_try_search_half_rev(dfa, input, min_start)
+    }
+
+    pub(crate) fn memory_usage(&self) -> usize {
+        self.0.memory_usage()
+    }
+}
@@ -0,0 +1,1191 @@
+/*!
+A DFA that can return spans for matching capturing groups.
+
+This is a port of [`regex_automata::dfa::onepass`] to cursors. The
+construction of the DFA itself is identical to the upstream implementation
+(which unfortunately keeps its transition table private) while the search
+routines walk the haystack chunk by chunk and evaluate look-around
+assertions through [`Input::look_around`], so a match may freely span chunk
+boundaries.
+
+Like upstream, a one-pass DFA only supports anchored searches.
+*/
+
+pub use regex_automata::dfa::onepass::Config;
+use regex_automata::nfa::thompson::{self, NFA};
+use regex_automata::util::alphabet::ByteClasses;
+use regex_automata::util::captures::Captures;
+use regex_automata::util::look::{Look, LookSet, UnicodeWordBoundaryError};
+use regex_automata::util::primitives::{NonMaxUsize, PatternID, StateID};
+use regex_automata::{Anchored, MatchError, MatchKind};
+
+use crate::cursor::Cursor;
+use crate::util::sparse_set::SparseSet;
+use crate::Input;
+
+#[cfg(test)]
+mod tests;
+
+/// The identifier of the dead state. It is always the first state in the
+/// transition table and every transition that isn't defined points to it.
+const DEAD: StateID = StateID::ZERO;
+
+/// A builder for a [one-pass DFA](DFA).
+///
+/// This builder mirrors [`regex_automata::dfa::onepass::Builder`]. It permits
+/// configuring options for the syntax of a pattern, the NFA construction and
+/// the DFA construction. Since the upstream configuration can't be merged
+/// from outside of `regex-automata`, [`Builder::configure`] replaces the
+/// current configuration wholesale.
+#[derive(Clone, Debug)]
+pub struct Builder {
+    config: Config,
+    thompson: thompson::Compiler,
+}
+
+impl Builder {
+    /// Create a new one-pass DFA builder with the default configuration.
+    pub fn new() -> Builder {
+        Builder { config: Config::default(), thompson: thompson::Compiler::new() }
+    }
+
+    /// Build a one-pass DFA from the given pattern.
+    ///
+    /// If there was a problem parsing or compiling the pattern, then an error
+    /// is returned.
+    pub fn build(&self, pattern: &str) -> Result<DFA, BuildError> {
+        self.build_many(&[pattern])
+    }
+
+    /// Build a one-pass DFA from the given patterns.
+    ///
+    /// When matches are returned, the pattern ID corresponds to the index of
+    /// the pattern in the slice given.
+    pub fn build_many<P: AsRef<str>>(&self, patterns: &[P]) -> Result<DFA, BuildError> {
+        let nfa = self.thompson.build_many(patterns).map_err(BuildError::nfa)?;
+        self.build_from_nfa(nfa)
+    }
+
+    /// Build a DFA from the given NFA.
+    ///
+    /// This returns an error if the NFA is not one-pass or if one of the
+    /// configured (or hard-coded) limits is exceeded.
+    pub fn build_from_nfa(&self, nfa: NFA) -> Result<DFA, BuildError> {
+        InternalBuilder::new(self.config.clone(), &nfa).build()
+    }
+
+    /// Apply the given one-pass DFA configuration options to this builder.
+    pub fn configure(&mut self, config: Config) -> &mut Builder {
+        self.config = config;
+        self
+    }
+
+    /// Set the syntax configuration for this builder using
+    /// [`syntax::Config`](regex_automata::util::syntax::Config).
+    ///
+    /// This permits setting things like case insensitivity, Unicode and multi
+    /// line mode.
+    pub fn syntax(&mut self, config: regex_automata::util::syntax::Config) -> &mut Builder {
+        self.thompson.syntax(config);
+        self
+    }
+
+    /// Set the Thompson NFA configuration for this builder using
+    /// [`nfa::thompson::Config`](thompson::Config).
+    ///
+    /// This permits setting things like whether additional time should be
+    /// spent shrinking the size of the NFA.
+    pub fn thompson(&mut self, config: thompson::Config) -> &mut Builder {
+        self.thompson.configure(config);
+        self
+    }
+}
+
+impl Default for Builder {
+    fn default() -> Builder {
+        Builder::new()
+    }
+}
+
+/// An internal builder for a one-pass DFA.
+///
+/// Each DFA state corresponds to a single NFA state (and all states reachable
+/// from it via epsilon transitions). If following epsilon transitions ever
+/// leads to the same NFA state twice, or if two transitions for the same
+/// byte disagree, then the NFA isn't one-pass and construction fails.
+#[derive(Debug)]
+struct InternalBuilder<'a> {
+    /// The DFA we're building.
+    dfa: DFA,
+    /// NFA state IDs that have been assigned a DFA state but whose epsilon
+    /// closure hasn't been compiled into that DFA state yet.
+    uncompiled_nfa_ids: Vec<StateID>,
+    /// A map from NFA state ID to DFA state ID. An NFA state that hasn't been
+    /// used as the starting point of a DFA state yet maps to `DEAD`.
+    nfa_to_dfa_id: Vec<StateID>,
+    /// A stack used to traverse the NFA states that make up a single DFA
+    /// state.
+    stack: Vec<(StateID, Epsilons)>,
+    /// The set of NFA states that we've visited via `stack`.
+    seen: SparseSet,
+    /// Whether a match NFA state has been observed while constructing the
+    /// current DFA state. Transitions added after that point don't win over
+    /// the match (under leftmost-first semantics).
+    matched: bool,
+    /// The config passed to the builder (duplicated in `dfa.config`).
+    config: Config,
+    /// The NFA we're building a one-pass DFA from (duplicated in `dfa.nfa`).
+    nfa: &'a NFA,
+    /// The equivalence classes that make up the alphabet for this DFA
+    /// (duplicated in `dfa.classes`).
+    classes: ByteClasses,
+}
+
+impl<'a> InternalBuilder<'a> {
+    /// Create a new builder with an initial empty DFA.
+    fn new(config: Config, nfa: &'a NFA) -> InternalBuilder<'a> {
+        let classes = if !config.get_byte_classes() {
+            ByteClasses::singletons()
+        } else {
+            *nfa.byte_classes()
+        };
+        // We don't need an EOI transition since look-around is handled
+        // explicitly during the search. Its slot in each state is reused to
+        // store the `PatternEpsilons` for that state instead.
+        let alphabet_len = classes.alphabet_len().checked_sub(1).unwrap();
+        let stride2 = classes.stride2();
+        let dfa = DFA {
+            config: config.clone(),
+            nfa: nfa.clone(),
+            table: vec![],
+            starts: vec![],
+            // No state ID can be bigger than StateID::MAX, so this sentinel
+            // is kept when there are no match states at all.
+            min_match_id: StateID::MAX,
+            classes,
+            alphabet_len,
+            stride2,
+            pateps_offset: alphabet_len,
+            // OK because PatternID::MAX*2 is guaranteed not to overflow.
+            explicit_slot_start: nfa.pattern_len().checked_mul(2).unwrap(),
+        };
+        InternalBuilder {
+            dfa,
+            uncompiled_nfa_ids: vec![],
+            nfa_to_dfa_id: vec![DEAD; nfa.states().len()],
+            stack: vec![],
+            seen: SparseSet::new(nfa.states().len()),
+            matched: false,
+            config,
+            nfa,
+            classes,
+        }
+    }
+
+    /// Build the DFA from the NFA given to this builder. If the NFA is not
+    /// one-pass, then return an error. An error may also be returned if a
+    /// particular limit is exceeded.
+    fn build(mut self) -> Result<DFA, BuildError> {
+        self.nfa.look_set_any().available().map_err(BuildError::word)?;
+        for look in self.nfa.look_set_any().iter() {
+            // Only ten assertions fit into the look-around bits of a
+            // transition. Newer assertions are rejected.
+            if look.as_repr() > Look::WordUnicodeNegate.as_repr() {
+                return Err(BuildError::unsupported_look(look));
+            }
+        }
+        if self.nfa.pattern_len() as u64 > PatternEpsilons::PATTERN_ID_LIMIT {
+            return Err(BuildError::too_many_patterns(PatternEpsilons::PATTERN_ID_LIMIT));
+        }
+        if self.nfa.group_info().explicit_slot_len() > Slots::LIMIT {
+            return Err(BuildError::not_one_pass("too many explicit capturing groups (max is 16)"));
+        }
+        assert_eq!(DEAD, self.add_empty_state()?);
+
+        // Only explicit slots are tracked by the DFA. The implicit slots (two
+        // for each pattern) are handled by the search routine itself.
+        let explicit_slot_start = self.nfa.pattern_len() * 2;
+        self.add_start_state(None, self.nfa.start_anchored())?;
+        if self.config.get_starts_for_each_pattern() {
+            for pid in self.nfa.patterns() {
+                self.add_start_state(Some(pid), self.nfa.start_pattern(pid).unwrap())?;
+            }
+        }
+        while let Some(nfa_id) = self.uncompiled_nfa_ids.pop() {
+            let dfa_id = self.nfa_to_dfa_id[nfa_id];
+            // Once we see a match, we keep going (to verify that the regex
+            // is actually one-pass) but new transitions don't win anymore.
+            self.matched = false;
+            self.seen.clear();
+            self.stack_push(nfa_id, Epsilons::empty())?;
+            while let Some((id, epsilons)) = self.stack.pop() {
+                match *self.nfa.state(id) {
+                    thompson::State::ByteRange { ref trans } => {
+                        self.compile_transition(dfa_id, trans, epsilons)?;
+                    }
+                    thompson::State::Sparse(ref sparse) => {
+                        for trans in sparse.transitions.iter() {
+                            self.compile_transition(dfa_id, trans, epsilons)?;
+                        }
+                    }
+                    thompson::State::Dense(ref dense) => {
+                        let transitions = dense.transitions.iter().enumerate();
+                        for (byte, &next) in transitions.filter(|&(_, &next)| next != DEAD) {
+                            let byte = byte as u8;
+                            let trans = thompson::Transition { start: byte, end: byte, next };
+                            self.compile_transition(dfa_id, &trans, epsilons)?;
+                        }
+                    }
+                    thompson::State::Look { look, next } => {
+                        let looks = epsilons.looks().insert(look);
+                        self.stack_push(next, epsilons.set_looks(looks))?;
+                    }
+                    thompson::State::Union { ref alternates } => {
+                        for &sid in alternates.iter().rev() {
+                            self.stack_push(sid, epsilons)?;
+                        }
+                    }
+                    thompson::State::BinaryUnion { alt1, alt2 } => {
+                        self.stack_push(alt2, epsilons)?;
+                        self.stack_push(alt1, epsilons)?;
+                    }
+                    thompson::State::Capture { next, slot, .. } => {
+                        let slot = slot.as_usize();
+                        let epsilons = if slot < explicit_slot_start {
+                            epsilons
+                        } else {
+                            // Offset our explicit slots so that they start
+                            // at index 0.
+                            let offset = slot - explicit_slot_start;
+                            epsilons.set_slots(epsilons.slots().insert(offset))
+                        };
+                        self.stack_push(next, epsilons)?;
+                    }
+                    thompson::State::Fail => {
+                        continue;
+                    }
+                    thompson::State::Match { pattern_id } => {
+                        // Two different paths to a match state for the same
+                        // DFA state are ambiguous.
+                        if self.matched {
+                            return Err(BuildError::not_one_pass(
+                                "multiple epsilon transitions to match state",
+                            ));
+                        }
+                        self.matched = true;
+                        self.dfa.set_pattern_epsilons(
+                            dfa_id,
+                            PatternEpsilons::empty()
+                                .set_pattern_id(pattern_id)
+                                .set_epsilons(epsilons),
+                        );
+                    }
+                }
+            }
+        }
+        self.shuffle_states();
+        Ok(self.dfa)
+    }
+
+    /// Shuffle all match states to the end of the transition table and set
+    /// `min_match_id` to the ID of the first such match state.
+    ///
+    /// This permits checking whether a state is a match state with a single
+    /// comparison during a search.
+    fn shuffle_states(&mut self) {
+        // `map[i]` is the original ID of the state now stored at index `i`.
+        let mut map: Vec<StateID> = (0..self.dfa.state_len()).map(StateID::new_unchecked).collect();
+        let mut next_dest = self.dfa.last_state_id();
+        for i in (0..self.dfa.state_len()).rev() {
+            let id = StateID::new_unchecked(i);
+            if self.dfa.pattern_epsilons(id).pattern_id().is_none() {
+                continue;
+            }
+            if id != next_dest {
+                self.dfa.swap_states(next_dest, id);
+                map.swap(next_dest.as_usize(), id.as_usize());
+            }
+            self.dfa.min_match_id = next_dest;
+            next_dest = self
+                .dfa
+                .prev_state_id(next_dest)
+                .expect("match states should be a proper subset of all states");
+        }
+        let mut remap = vec![DEAD; map.len()];
+        for (new, old) in map.into_iter().enumerate() {
+            remap[old] = StateID::new_unchecked(new);
+        }
+        self.dfa.remap(|next| remap[next]);
+    }
+
+    /// Compile the given NFA transition into the DFA state given.
+    ///
+    /// If the DFA state already has a different transition defined for the
+    /// same input symbols, then the NFA is not one-pass and an error is
+    /// returned.
+    fn compile_transition(
+        &mut self,
+        dfa_id: StateID,
+        trans: &thompson::Transition,
+        epsilons: Epsilons,
+    ) -> Result<(), BuildError> {
+        let next_dfa_id = self.add_dfa_state_for_nfa_state(trans.next)?;
+        for byte in self.classes.representatives(trans.start..=trans.end).filter_map(|r| r.as_u8())
+        {
+            let oldtrans = self.dfa.transition(dfa_id, byte);
+            let newtrans = Transition::new(self.matched, next_dfa_id, epsilons);
+            if oldtrans.state_id() == DEAD {
+                self.dfa.set_transition(dfa_id, byte, newtrans);
+            } else if oldtrans != newtrans {
+                return Err(BuildError::not_one_pass("conflicting transition"));
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a start state to the DFA corresponding to the given NFA starting
+    /// state ID.
+    ///
+    /// The start state for all patterns must be added first, followed by the
+    /// anchored start state of each pattern in order.
+    fn add_start_state(
+        &mut self,
+        pid: Option<PatternID>,
+        nfa_id: StateID,
+    ) -> Result<StateID, BuildError> {
+        match pid {
+            None => assert!(self.dfa.starts.is_empty()),
+            Some(pid) => assert!(self.dfa.starts.len() == pid.one_more()),
+        }
+        let dfa_id = self.add_dfa_state_for_nfa_state(nfa_id)?;
+        self.dfa.starts.push(dfa_id);
+        Ok(dfa_id)
+    }
+
+    /// Add a new DFA state corresponding to the given NFA state, unless one
+    /// already exists in which case its ID is returned.
+    fn add_dfa_state_for_nfa_state(&mut self, nfa_id: StateID) -> Result<StateID, BuildError> {
+        let existing_dfa_id = self.nfa_to_dfa_id[nfa_id];
+        if existing_dfa_id != DEAD {
+            return Ok(existing_dfa_id);
+        }
+        let dfa_id = self.add_empty_state()?;
+        self.nfa_to_dfa_id[nfa_id] = dfa_id;
+        self.uncompiled_nfa_ids.push(nfa_id);
+        Ok(dfa_id)
+    }
+
+    /// Unconditionally add a new empty (non-match) DFA state and return its
+    /// ID. If that would exceed any limits, then an error is returned.
+    fn add_empty_state(&mut self) -> Result<StateID, BuildError> {
+        let state_limit = Transition::STATE_ID_LIMIT;
+        // State IDs are not premultiplied so that they fit into the upper
+        // bits of a transition.
+        let next_id = self.dfa.table.len() >> self.dfa.stride2();
+        let id = StateID::new(next_id).map_err(|_| BuildError::too_many_states(state_limit))?;
+        if id.as_usize() as u64 > Transition::STATE_ID_LIMIT {
+            return Err(BuildError::too_many_states(state_limit));
+        }
+        self.dfa.table.extend(std::iter::repeat(Transition(0)).take(self.dfa.stride()));
+        // The empty `PatternEpsilons` is not all zeroes, so it needs to be
+        // set explicitly.
+        self.dfa.set_pattern_epsilons(id, PatternEpsilons::empty());
+        if let Some(size_limit) = self.config.get_size_limit() {
+            if self.dfa.memory_usage() > size_limit {
+                return Err(BuildError::exceeded_size_limit(size_limit));
+            }
+        }
+        Ok(id)
+    }
+
+    /// Push the given NFA state ID and its corresponding epsilons on to the
+    /// traversal stack.
+    ///
+    /// Visiting the same NFA state twice while building a single DFA state
+    /// means the regex is not one-pass, in which case an error is returned.
+    fn stack_push(&mut self, nfa_id: StateID, epsilons: Epsilons) -> Result<(), BuildError> {
+        if !self.seen.insert(nfa_id) {
+            return Err(BuildError::not_one_pass("multiple epsilon transitions to same state"));
+        }
+        self.stack.push((nfa_id, epsilons));
+        Ok(())
+    }
+}
+
+/// A one-pass DFA for executing a subset of anchored regex searches while
+/// resolving capturing groups.
+///
+/// A one-pass DFA can be built from an NFA that is one-pass. An NFA is
+/// one-pass when there is never any ambiguity about how to continue a search.
+/// For example, `a*a` is not one-pass because during a search, it's not
+/// possible to know whether to continue matching the `a*` or to move on to
+/// the single `a`. However, `a*b` is one-pass, because for every byte in the
+/// input, it's always clear when to move on from `a*` to `b`.
+///
+/// Only anchored searches are supported: [`try_search`] returns an error if
+/// the given [`Input`] is configured to do an unanchored search (unless the
+/// regex itself is always anchored) or if it searches for a pattern ID
+/// without [`Config::starts_for_each_pattern`] being enabled.
+///
+/// See [`regex_automata::dfa::onepass::DFA`] for more details about the
+/// limitations of one-pass DFAs.
+#[derive(Clone, Debug)]
+pub struct DFA {
+    /// The configuration provided by the caller.
+    config: Config,
+    /// The NFA used to build this DFA.
+    nfa: NFA,
+    /// The transition table. Each state has `stride` entries: one transition
+    /// for each equivalence class followed by the `PatternEpsilons` of the
+    /// state (and padding).
+    table: Vec<Transition>,
+    /// The anchored starting state for all patterns, optionally followed by
+    /// the anchored starting state of each individual pattern.
+    starts: Vec<StateID>,
+    /// Every state ID at or above this one is a match state.
+    min_match_id: StateID,
+    /// The byte classes of this DFA.
+    classes: ByteClasses,
+    /// The number of equivalence classes, excluding EOI.
+    alphabet_len: usize,
+    /// The log base 2 of the stride of each state.
+    stride2: usize,
+    /// The offset of the `PatternEpsilons` within each state.
+    pateps_offset: usize,
+    /// The index of the first explicit slot, which is the number of implicit
+    /// slots (two for each pattern).
+    explicit_slot_start: usize,
+}
+
+impl DFA {
+    /// Parse the given regular expression using the default configuration and
+    /// return the corresponding one-pass DFA.
+    pub fn new(pattern: &str) -> Result<DFA, BuildError> {
+        DFA::builder().build(pattern)
+    }
+
+    /// Like `new`, but parses multiple patterns into a single "multi regex."
+    pub fn new_many<P: AsRef<str>>(patterns: &[P]) -> Result<DFA, BuildError> {
+        DFA::builder().build_many(patterns)
+    }
+
+    /// Like `new`, but builds a one-pass DFA directly from an NFA.
+    pub fn new_from_nfa(nfa: NFA) -> Result<DFA, BuildError> {
+        DFA::builder().build_from_nfa(nfa)
+    }
+
+    /// Return a default configuration for a DFA.
+    pub fn config() -> Config {
+        Config::new()
+    }
+
+    /// Return a builder for configuring the construction of a DFA.
+    pub fn builder() -> Builder {
+        Builder::new()
+    }
+
+    /// Create a new empty set of capturing groups that is guaranteed to be
+    /// valid for the search APIs on this DFA.
+    pub fn create_captures(&self) -> Captures {
+        Captures::all(self.nfa.group_info().clone())
+    }
+
+    /// Create a new cache for this DFA.
+    pub fn create_cache(&self) -> Cache {
+        Cache::new(self)
+    }
+
+    /// Reset the given cache such that it can be used for searching with
+    /// this DFA (and only this DFA).
+    pub fn reset_cache(&self, cache: &mut Cache) {
+        cache.reset(self);
+    }
+
+    /// Return the config for this one-pass DFA.
+    pub fn get_config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Returns a reference to the underlying NFA.
+    pub fn get_nfa(&self) -> &NFA {
+        &self.nfa
+    }
+
+    /// Returns the total number of patterns compiled into this DFA.
+    pub fn pattern_len(&self) -> usize {
+        self.nfa.pattern_len()
+    }
+
+    /// Returns the total number of states in this one-pass DFA.
+    pub fn state_len(&self) -> usize {
+        self.table.len() >> self.stride2()
+    }
+
+    /// Returns the total number of elements in the alphabet for this DFA.
+    pub fn alphabet_len(&self) -> usize {
+        self.alphabet_len
+    }
+
+    /// Returns the total stride for every state in this DFA, expressed as the
+    /// exponent of a power of 2.
+    pub fn stride2(&self) -> usize {
+        self.stride2
+    }
+
+    /// Returns the total stride for every state in this DFA.
+    pub fn stride(&self) -> usize {
+        1 << self.stride2()
+    }
+
+    /// Returns the memory usage, in bytes, of this DFA.
+    pub fn memory_usage(&self) -> usize {
+        self.table.len() * core::mem::size_of::<Transition>()
+            + self.starts.len() * core::mem::size_of::<StateID>()
+    }
+
+    fn start(&self) -> StateID {
+        self.starts[0]
+    }
+
+    fn start_pattern(&self, pid: PatternID) -> Result<StateID, MatchError> {
+        if !self.config.get_starts_for_each_pattern() {
+            return Err(MatchError::unsupported_anchored(Anchored::Pattern(pid)));
+        }
+        // The first entry is the start state for all patterns, so the start
+        // state for `pid` lives at `pid + 1`.
+        Ok(self.starts.get(pid.one_more()).copied().unwrap_or(DEAD))
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn transition(&self, sid: StateID, byte: u8) -> Transition {
+        let offset = sid.as_usize() << self.stride2();
+        let class = self.classes.get(byte) as usize;
+        self.table[offset + class]
+    }
+
+    fn set_transition(&mut self, sid: StateID, byte: u8, to: Transition) {
+        let offset = sid.as_usize() << self.stride2();
+        let class = self.classes.get(byte) as usize;
+        self.table[offset + class] = to;
+    }
+
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    fn pattern_epsilons(&self, sid: StateID) -> PatternEpsilons {
+        let offset = sid.as_usize() << self.stride2();
+        PatternEpsilons(self.table[offset + self.pateps_offset].0)
+    }
+
+    fn set_pattern_epsilons(&mut self, sid: StateID, pateps: PatternEpsilons) {
+        let offset = sid.as_usize() << self.stride2();
+        self.table[offset + self.pateps_offset] = Transition(pateps.0);
+    }
+
+    fn prev_state_id(&self, id: StateID) -> Option<StateID> {
+        if id == DEAD {
+            None
+        } else {
+            Some(StateID::new_unchecked(id.as_usize() - 1))
+        }
+    }
+
+    fn last_state_id(&self) -> StateID {
+        // A DFA always contains at least the dead state.
+        StateID::new_unchecked(self.state_len().checked_sub(1).unwrap())
+    }
+
+    fn swap_states(&mut self, id1: StateID, id2: StateID) {
+        let o1 = id1.as_usize() << self.stride2();
+        let o2 = id2.as_usize() << self.stride2();
+        for b in 0..self.stride() {
+            self.table.swap(o1 + b, o2 + b);
+        }
+    }
+
+    fn remap(&mut self, map: impl Fn(StateID) -> StateID) {
+        for i in 0..self.state_len() {
+            let offset = i << self.stride2();
+            for b in 0..self.alphabet_len() {
+                let next = self.table[offset + b].state_id();
+                self.table[offset + b].set_state_id(map(next));
+            }
+        }
+        for i in 0..self.starts.len() {
+            self.starts[i] = map(self.starts[i]);
+        }
+    }
+}
+
+/// Executes an anchored leftmost forward search and writes the spans of
+/// capturing groups that participated in a match into the provided
+/// [`Captures`] value. If no match was found, then [`Captures::is_match`] is
+/// guaranteed to return `false`.
+///
+/// # Errors
+///
+/// This routine errors if the search is not anchored (and the regex is not
+/// always anchored) or if [`Anchored::Pattern`] is used without enabling
+/// [`Config::starts_for_each_pattern`].
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::{engines::onepass::{try_search, DFA}, Input};
+/// use regex_automata::{Anchored, Span};
+///
+/// let re = DFA::new(r"(\w+)[[:space:]]+(\w+)")?;
+/// let (mut cache, mut caps) = (re.create_cache(), re.create_captures());
+///
+/// let mut input = Input::new("Шерлок Холмс");
+/// input.anchored(Anchored::Yes);
+/// try_search(&re, &mut cache, &mut input, &mut caps)?;
+/// assert_eq!(Some(Span::from(0..12)), caps.get_group(1));
+/// assert_eq!(Some(Span::from(13..23)), caps.get_group(2));
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[inline]
+pub fn try_search<C: Cursor>(
+    re: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    caps: &mut Captures,
+) -> Result<(), MatchError> {
+    let pid = try_search_slots(re, cache, input, caps.slots_mut())?;
+    caps.set_pattern(pid);
+    Ok(())
+}
+
+/// Executes an anchored leftmost forward search and writes the spans of
+/// capturing groups that participated in a match into the provided `slots`,
+/// and returns the matching pattern ID. The contents of the slots for
+/// patterns other than the matching pattern are unspecified. If no match
+/// was found, then `None` is returned and the contents of all `slots` is
+/// unspecified.
+///
+/// This is like [`try_search`], but it accepts a raw slots slice instead of
+/// a `Captures` value.
+#[inline]
+pub fn try_search_slots<C: Cursor>(
+    re: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    slots: &mut [Option<NonMaxUsize>],
+) -> Result<Option<PatternID>, MatchError> {
+    let utf8empty = re.get_nfa().has_empty() && re.get_nfa().is_utf8();
+    if !utf8empty {
+        return try_search_slots_imp(re, cache, input, slots);
+    }
+    // See pikevm::search_slots for why we do this.
+    let min = re.get_nfa().group_info().implicit_slot_len();
+    if slots.len() >= min {
+        return try_search_slots_imp(re, cache, input, slots);
+    }
+    if re.get_nfa().pattern_len() == 1 {
+        let mut enough = [None, None];
+        let got = try_search_slots_imp(re, cache, input, &mut enough)?;
+        // This is OK because we know `enough` is strictly bigger than
+        // `slots`, otherwise this special case isn't reached.
+        slots.copy_from_slice(&enough[..slots.len()]);
+        return Ok(got);
+    }
+    let mut enough = vec![None; min];
+    let got = try_search_slots_imp(re, cache, input, &mut enough)?;
+    // This is OK because we know `enough` is strictly bigger than `slots`,
+    // otherwise this special case isn't reached.
+    slots.copy_from_slice(&enough[..slots.len()]);
+    Ok(got)
+}
+
+/// This is the actual implementation of `try_search_slots` that doesn't
+/// account for the special case when 1) the NFA has UTF-8 mode enabled,
+/// 2) the NFA can match the empty string and 3) the caller has provided an
+/// insufficient number of slots to record match offsets.
+#[inline(never)]
+fn try_search_slots_imp<C: Cursor>(
+    re: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    slots: &mut [Option<NonMaxUsize>],
+) -> Result<Option<PatternID>, MatchError> {
+    let utf8empty = re.get_nfa().has_empty() && re.get_nfa().is_utf8();
+    let result = search_imp(re, cache, input, slots);
+    match input.check_backtrack(result)? {
+        None => Ok(None),
+        Some(pid) if !utf8empty => Ok(Some(pid)),
+        Some(pid) => {
+            // These slot indices are always valid because `pid` is valid and
+            // we made sure the slots are big enough above.
+            let slot_start = pid.as_usize() * 2;
+            let start = slots[slot_start].unwrap().get();
+            let end = slots[slot_start + 1].unwrap().get();
+            // An empty match that splits a codepoint can't be reported. And
+            // since the search is anchored, there is nowhere else to look.
+            if start == end {
+                input.move_to(start);
+                if !input.is_char_boundary() {
+                    return Ok(None);
+                }
+            }
+            Ok(Some(pid))
+        }
+    }
+}
+
+fn search_imp<C: Cursor>(
+    re: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    slots: &mut [Option<NonMaxUsize>],
+) -> Result<Option<PatternID>, MatchError> {
+    if input.is_done() {
+        return Ok(None);
+    }
+    // Clearing all slots is necessary because a capturing group that
+    // doesn't participate in the match might still hold a span from a
+    // previous search.
+    let explicit_slots_len =
+        core::cmp::min(Slots::LIMIT, slots.len().saturating_sub(re.explicit_slot_start));
+    cache.setup_search(explicit_slots_len);
+    for slot in cache.explicit_slots() {
+        *slot = None;
+    }
+    for slot in slots.iter_mut() {
+        *slot = None;
+    }
+    // The start slots of every pattern are set up front so that match
+    // states (which may be visited many times) only need to set the end.
+    for pid in re.nfa.patterns() {
+        let i = pid.as_usize() * 2;
+        if i >= slots.len() {
+            break;
+        }
+        slots[i] = NonMaxUsize::new(input.start());
+    }
+    let mut pid = None;
+    let mut next_sid = match input.get_anchored() {
+        Anchored::Yes => re.start(),
+        Anchored::Pattern(pid) => re.start_pattern(pid)?,
+        Anchored::No => {
+            // If the regex is itself always anchored, then we're fine,
+            // even if the search is configured to be unanchored.
+            if !re.nfa.is_always_start_anchored() {
+                return Err(MatchError::unsupported_anchored(Anchored::No));
+            }
+            re.start()
+        }
+    };
+    let leftmost_first = matches!(re.config.get_match_kind(), MatchKind::LeftmostFirst);
+    input.move_to(input.start());
+    input.clear_look_behind();
+    input.ensure_look_behind();
+    let mut at = input.start();
+    // Unlike upstream we can't iterate over `start..end` directly since the
+    // end of the span shrinks once a cursor of unknown length is exhausted.
+    while at < input.end() {
+        let sid = next_sid;
+        let trans = re.transition(sid, input.chunk()[input.chunk_pos()]);
+        next_sid = trans.state_id();
+        let epsilons = trans.epsilons();
+        if sid >= re.min_match_id
+            && find_match(re, cache, input, at, sid, slots, &mut pid)
+            && (input.get_earliest() || (leftmost_first && trans.match_wins()))
+        {
+            return Ok(pid);
+        }
+        if sid == DEAD
+            || (!epsilons.looks().is_empty() && !looks_match(re, input, epsilons.looks()))
+        {
+            return Ok(pid);
+        }
+        epsilons.slots().apply(at, cache.explicit_slots());
+        at += 1;
+        input.chunk_pos += 1;
+        if input.chunk_pos() >= input.chunk().len() {
+            input.advance_with_look_behind();
+        }
+    }
+    if next_sid >= re.min_match_id {
+        find_match(re, cache, input, at, next_sid, slots, &mut pid);
+    }
+    Ok(pid)
+}
+
+/// Records a match for the match state `sid` at position `at` (which must be
+/// the current position of `input`) if its conditional epsilon transitions
+/// are satisfied. Returns true if a match was recorded.
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn find_match<C: Cursor>(
+    re: &DFA,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    at: usize,
+    sid: StateID,
+    slots: &mut [Option<NonMaxUsize>],
+    matched_pid: &mut Option<PatternID>,
+) -> bool {
+    debug_assert!(sid >= re.min_match_id);
+    let pateps = re.pattern_epsilons(sid);
+    let epsilons = pateps.epsilons();
+    if !epsilons.looks().is_empty() && !looks_match(re, input, epsilons.looks()) {
+        return false;
+    }
+    let pid = pateps.pattern_id_unchecked();
+    // The start slot was already set at the beginning of the search.
+    let slot_end = pid.as_usize() * 2 + 1;
+    if slot_end < slots.len() {
+        slots[slot_end] = NonMaxUsize::new(at);
+    }
+    // Copy the explicit slots recorded so far to the caller and apply the
+    // ones that are set on the path to the match state itself.
+    if re.explicit_slot_start < slots.len() {
+        // The explicit slots of the cache always have the same length as
+        // `slots[explicit_slot_start..]` (see `Cache::setup_search`).
+        slots[re.explicit_slot_start..].copy_from_slice(cache.explicit_slots());
+        epsilons.slots().apply(at, &mut slots[re.explicit_slot_start..]);
+    }
+    *matched_pid = Some(pid);
+    true
+}
+
+/// Returns true if all look-around assertions in `looks` are satisfied at
+/// the current position of `input`.
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn looks_match<C: Cursor>(re: &DFA, input: &mut Input<C>, looks: LookSet) -> bool {
+    let (chunk, pos) = input.look_around();
+    re.nfa.look_matcher().matches_set(looks, chunk, pos)
+}
+
+/// A cache represents mutable state that a one-pass [`DFA`] requires during a
+/// search.
+///
+/// A particular `Cache` is coupled with the one-pass DFA from which it was
+/// created. It may only be used with that one-pass DFA. A cache and its
+/// allocations may be re-purposed via [`Cache::reset`], in which case, it can
+/// only be used with the new one-pass DFA (and not the old one).
+#[derive(Clone, Debug)]
+pub struct Cache {
+    /// Scratch space used to store slots during a search. The caller's slots
+    /// are only written to once a match is found.
+    explicit_slots: Vec<Option<NonMaxUsize>>,
+    /// The number of slots in the caller-provided slots that are explicit
+    /// slots (capped at `Slots::LIMIT`). This is set at the start of each
+    /// search.
+    explicit_slot_len: usize,
+}
+
+impl Cache {
+    /// Create a new [`onepass::DFA`](DFA) cache.
+    ///
+    /// If you want to reuse the returned `Cache` with some other DFA, then
+    /// you must call [`Cache::reset`] with the desired DFA.
+    pub fn new(re: &DFA) -> Cache {
+        let mut cache = Cache { explicit_slots: vec![], explicit_slot_len: 0 };
+        cache.reset(re);
+        cache
+    }
+
+    /// Reset this cache such that it can be used for searching with a
+    /// different [`onepass::DFA`](DFA).
+    pub fn reset(&mut self, re: &DFA) {
+        let explicit_slot_len = re.get_nfa().group_info().explicit_slot_len();
+        self.explicit_slots.resize(explicit_slot_len, None);
+        self.explicit_slot_len = explicit_slot_len;
+    }
+
+    /// Returns the heap memory usage, in bytes, of this cache.
+    ///
+    /// This does **not** include the stack size used up by this cache. To
+    /// compute that, use `std::mem::size_of::<Cache>()`.
+    pub fn memory_usage(&self) -> usize {
+        self.explicit_slots.len() * core::mem::size_of::<Option<NonMaxUsize>>()
+    }
+
+    fn explicit_slots(&mut self) -> &mut [Option<NonMaxUsize>] {
+        &mut self.explicit_slots[..self.explicit_slot_len]
+    }
+
+    fn setup_search(&mut self, explicit_slot_len: usize) {
+        self.explicit_slot_len = explicit_slot_len;
+    }
+}
+
+/// A single transition in a one-pass DFA.
+///
+/// The high 21 bits are the state ID of the next state, followed by a single
+/// bit indicating whether a preceding match wins over following this
+/// transition. The remaining 42 bits are the [`Epsilons`] of the transition.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Transition(u64);
+
+impl Transition {
+    const STATE_ID_BITS: u64 = 21;
+    const STATE_ID_SHIFT: u64 = 64 - Transition::STATE_ID_BITS;
+    const STATE_ID_LIMIT: u64 = 1 << Transition::STATE_ID_BITS;
+    const MATCH_WINS_SHIFT: u64 = 64 - (Transition::STATE_ID_BITS + 1);
+    const INFO_MASK: u64 = 0x000003FF_FFFFFFFF;
+
+    fn new(match_wins: bool, sid: StateID, epsilons: Epsilons) -> Transition {
+        let match_wins = if match_wins { 1 << Transition::MATCH_WINS_SHIFT } else { 0 };
+        let sid = (sid.as_usize() as u64) << Transition::STATE_ID_SHIFT;
+        Transition(sid | match_wins | epsilons.0)
+    }
+
+    fn match_wins(&self) -> bool {
+        (self.0 >> Transition::MATCH_WINS_SHIFT & 1) == 1
+    }
+
+    fn state_id(&self) -> StateID {
+        // OK because a transition always has a valid state ID in its upper
+        // bits by construction.
+        StateID::new_unchecked((self.0 >> Transition::STATE_ID_SHIFT) as usize)
+    }
+
+    fn set_state_id(&mut self, sid: StateID) {
+        *self = Transition::new(self.match_wins(), sid, self.epsilons());
+    }
+
+    fn epsilons(&self) -> Epsilons {
+        Epsilons(self.0 & Transition::INFO_MASK)
+    }
+}
+
+/// The pattern ID (high 22 bits) and [`Epsilons`] (low 42 bits) of a match
+/// state. Non-match states use a sentinel pattern ID.
+#[derive(Clone, Copy, Debug)]
+struct PatternEpsilons(u64);
+
+impl PatternEpsilons {
+    const PATTERN_ID_BITS: u64 = 22;
+    const PATTERN_ID_SHIFT: u64 = 64 - PatternEpsilons::PATTERN_ID_BITS;
+    // A sentinel value indicating that this is not a match state. We don't
+    // use 0 since 0 is a valid pattern ID.
+    const PATTERN_ID_NONE: u64 = 0x00000000_003FFFFF;
+    const PATTERN_ID_LIMIT: u64 = PatternEpsilons::PATTERN_ID_NONE;
+    const PATTERN_ID_MASK: u64 = 0xFFFFFC00_00000000;
+    const EPSILONS_MASK: u64 = 0x000003FF_FFFFFFFF;
+
+    fn empty() -> PatternEpsilons {
+        PatternEpsilons(PatternEpsilons::PATTERN_ID_NONE << PatternEpsilons::PATTERN_ID_SHIFT)
+    }
+
+    fn pattern_id(self) -> Option<PatternID> {
+        let pid = self.0 >> PatternEpsilons::PATTERN_ID_SHIFT;
+        if pid == PatternEpsilons::PATTERN_ID_LIMIT {
+            None
+        } else {
+            Some(PatternID::new_unchecked(pid as usize))
+        }
+    }
+
+    fn pattern_id_unchecked(self) -> PatternID {
+        let pid = self.0 >> PatternEpsilons::PATTERN_ID_SHIFT;
+        PatternID::new_unchecked(pid as usize)
+    }
+
+    fn set_pattern_id(self, pid: PatternID) -> PatternEpsilons {
+        PatternEpsilons(
+            ((pid.as_usize() as u64) << PatternEpsilons::PATTERN_ID_SHIFT)
+                | (self.0 & PatternEpsilons::EPSILONS_MASK),
+        )
+    }
+
+    fn epsilons(self) -> Epsilons {
+        Epsilons(self.0 & PatternEpsilons::EPSILONS_MASK)
+    }
+
+    fn set_epsilons(self, epsilons: Epsilons) -> PatternEpsilons {
+        PatternEpsilons(
+            (self.0 & PatternEpsilons::PATTERN_ID_MASK)
+                | (epsilons.0 & PatternEpsilons::EPSILONS_MASK),
+        )
+    }
+}
+
+/// The conditional epsilon transitions (look-around assertions, low 10 bits)
+/// and the slots to save (next 32 bits) when following a transition or
+/// reporting a match.
+#[derive(Clone, Copy, Debug)]
+struct Epsilons(u64);
+
+impl Epsilons {
+    const SLOT_MASK: u64 = 0x000003FF_FFFFFC00;
+    const SLOT_SHIFT: u64 = 10;
+    const LOOK_MASK: u64 = 0x00000000_000003FF;
+
+    fn empty() -> Epsilons {
+        Epsilons(0)
+    }
+
+    fn slots(self) -> Slots {
+        Slots((self.0 >> Epsilons::SLOT_SHIFT) as u32)
+    }
+
+    fn set_slots(self, slots: Slots) -> Epsilons {
+        Epsilons((u64::from(slots.0) << Epsilons::SLOT_SHIFT) | (self.0 & Epsilons::LOOK_MASK))
+    }
+
+    fn looks(self) -> LookSet {
+        LookSet { bits: (self.0 & Epsilons::LOOK_MASK) as u32 }
+    }
+
+    fn set_looks(self, look_set: LookSet) -> Epsilons {
+        Epsilons((self.0 & Epsilons::SLOT_MASK) | (u64::from(look_set.bits) & Epsilons::LOOK_MASK))
+    }
+}
+
+/// A set of explicit slot indices (relative to the first explicit slot).
+#[derive(Clone, Copy, Debug)]
+struct Slots(u32);
+
+impl Slots {
+    const LIMIT: usize = 32;
+
+    fn insert(self, slot: usize) -> Slots {
+        debug_assert!(slot < Slots::LIMIT);
+        Slots(self.0 | (1 << slot as u32))
+    }
+
+    fn remove(self, slot: usize) -> Slots {
+        debug_assert!(slot < Slots::LIMIT);
+        Slots(self.0 & !(1 << slot as u32))
+    }
+
+    fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    fn iter(self) -> SlotsIter {
+        SlotsIter { slots: self }
+    }
+
+    /// Sets every slot in this set to `at` (ignoring slots the caller didn't
+    /// provide room for).
+    fn apply(self, at: usize, caller_explicit_slots: &mut [Option<NonMaxUsize>]) {
+        if self.is_empty() {
+            return;
+        }
+        let at = NonMaxUsize::new(at);
+        for slot in self.iter() {
+            if slot >= caller_explicit_slots.len() {
+                break;
+            }
+            caller_explicit_slots[slot] = at;
+        }
+    }
+}
+
+#[derive(Debug)]
+struct SlotsIter {
+    slots: Slots,
+}
+
+impl Iterator for SlotsIter {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        let slot = self.slots.0.trailing_zeros() as usize;
+        if slot >= Slots::LIMIT {
+            return None;
+        }
+        self.slots = self.slots.remove(slot);
+        Some(slot)
+    }
+}
+
+/// An error that occurred during the construction of a one-pass DFA.
+///
+/// This error does not provide many introspection capabilities. There are
+/// generally only two things you can do with it:
+///
+/// * Obtain a human readable message via its `std::fmt::Display` impl.
+/// * Access an underlying [`thompson::BuildError`] type from its `source`
+///   method via the `std::error::Error` trait. This error only occurs when
+///   using convenience routines for building a one-pass DFA directly from a
+///   pattern string.
+#[derive(Clone, Debug)]
+pub struct BuildError {
+    kind: BuildErrorKind,
+}
+
+/// The kind of error that occurred during the construction of a one-pass
+/// DFA.
+#[derive(Clone, Debug)]
+enum BuildErrorKind {
+    Nfa(Box<thompson::BuildError>),
+    Word(UnicodeWordBoundaryError),
+    TooManyStates { limit: u64 },
+    TooManyPatterns { limit: u64 },
+    UnsupportedLook { look: Look },
+    ExceededSizeLimit { limit: usize },
+    NotOnePass { msg: &'static str },
+}
+
+impl BuildError {
+    fn nfa(err: thompson::BuildError) -> BuildError {
+        BuildError { kind: BuildErrorKind::Nfa(Box::new(err)) }
+    }
+
+    fn word(err: UnicodeWordBoundaryError) -> BuildError {
+        BuildError { kind: BuildErrorKind::Word(err) }
+    }
+
+    fn too_many_states(limit: u64) -> BuildError {
+        BuildError { kind: BuildErrorKind::TooManyStates { limit } }
+    }
+
+    fn too_many_patterns(limit: u64) -> BuildError {
+        BuildError { kind: BuildErrorKind::TooManyPatterns { limit } }
+    }
+
+    fn unsupported_look(look: Look) -> BuildError {
+        BuildError { kind: BuildErrorKind::UnsupportedLook { look } }
+    }
+
+    fn exceeded_size_limit(limit: usize) -> BuildError {
+        BuildError { kind: BuildErrorKind::ExceededSizeLimit { limit } }
+    }
+
+    fn not_one_pass(msg: &'static str) -> BuildError {
+        BuildError { kind: BuildErrorKind::NotOnePass { msg } }
+    }
+}
+
+impl std::error::Error for BuildError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        use self::BuildErrorKind::*;
+
+        match self.kind {
+            Nfa(ref err) => Some(err),
+            Word(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl core::fmt::Display for BuildError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        use self::BuildErrorKind::*;
+
+        match self.kind {
+            Nfa(_) => write!(f, "error building NFA"),
+            Word(_) => write!(f, "NFA contains Unicode word boundary"),
+            TooManyStates { limit } => {
+                write!(f, "one-pass DFA exceeded a limit of {:?} for number of states", limit)
+            }
+            TooManyPatterns { limit } => {
+                write!(f, "one-pass DFA exceeded a limit of {:?} for number of patterns", limit)
+            }
+            UnsupportedLook { look } => {
+                write!(f, "one-pass DFA does not support the {:?} assertion", look)
+            }
+            ExceededSizeLimit { limit } => {
+                write!(f, "one-pass DFA exceeded size limit of {:?} during building", limit)
+            }
+            NotOnePass { msg } => write!(
+                f,
+                "one-pass DFA could not be built because \
+                 pattern is not one-pass: {}",
+                msg,
+            ),
+        }
+    }
+}
@@ -0,0 +1,124 @@
+use proptest::{prop_assert_eq, proptest};
+use regex_automata::dfa::onepass;
+use regex_automata::nfa::thompson::{Compiler, Config as NfaConfig, NFA};
+use regex_automata::util::syntax::Config as SyntaxConfig;
+use regex_automata::{Anchored, PatternID};
+
+use crate::engines::onepass::{try_search, Builder, DFA};
+use crate::input::Input;
+use crate::test_rope::{RandomSlices, SingleByteChunks};
+
+use super::Cache;
+
+fn nfa(needle: &str, utf8: bool) -> Option<NFA> {
+    Compiler::new()
+        .syntax(SyntaxConfig::new().utf8(utf8))
+        .configure(NfaConfig::new().utf8(utf8))
+        .build(needle)
+        .ok()
+}
+
+/// Runs an anchored search at every position of `haystack` and compares the
+/// captures with the upstream one-pass DFA.
+fn test(needle: &str, haystack: &[u8]) {
+    for utf8 in [true, false] {
+        let nfa = nfa(needle, utf8).unwrap();
+        let config = onepass::Config::new().starts_for_each_pattern(true);
+        let regex1 =
+            onepass::Builder::new().configure(config.clone()).build_from_nfa(nfa.clone()).unwrap();
+        let regex2 = Builder::new().configure(config).build_from_nfa(nfa).unwrap();
+        compare(&regex1, &regex2, haystack, Anchored::Yes);
+        compare(&regex1, &regex2, haystack, Anchored::Pattern(PatternID::ZERO));
+    }
+}
+
+fn compare(regex1: &onepass::DFA, regex2: &DFA, haystack: &[u8], anchored: Anchored) {
+    let mut cache1 = regex1.create_cache();
+    let mut cache2 = Cache::new(regex2);
+    let mut caps1 = regex1.create_captures();
+    let mut caps2 = regex2.create_captures();
+    for start in 0..=haystack.len() {
+        let input = regex_automata::Input::new(haystack).range(start..).anchored(anchored);
+        regex1.try_search(&mut cache1, &input, &mut caps1).unwrap();
+        let expected: Vec<_> = caps1.iter().collect();
+
+        let mut input = Input::new(SingleByteChunks::new(haystack)).range(start..);
+        input.anchored(anchored);
+        try_search(regex2, &mut cache2, &mut input, &mut caps2).unwrap();
+        assert_eq!(expected, caps2.iter().collect::<Vec<_>>(), "{start}");
+
+        let mut input = Input::new(RandomSlices::new(haystack)).range(start..);
+        input.anchored(anchored);
+        try_search(regex2, &mut cache2, &mut input, &mut caps2).unwrap();
+        assert_eq!(expected, caps2.iter().collect::<Vec<_>>(), "{start}");
+    }
+}
+
+#[test]
+fn captures() {
+    test(r"(\w+)[[:space:]]+(\w+)", "Шерлок Холмс".as_bytes());
+    test(r"(?-u)([a-z]+)\s+([a-z]+)?(\d)", b"foo  bar1 baz 2");
+    test(r"([a-z]+)(?:(\d)|-)", b"foo-bar12");
+}
+
+#[test]
+fn look_around() {
+    test(r"(?m)^(a+)$", b"a\naaa\n");
+    test(r"(?-u:\b)(\w+)(?-u:\b)", b"foo bar");
+    test(r"\b(\w+)\b", "foö Öbar".as_bytes());
+    test(r"(a*)$", b"aaab");
+}
+
+#[test]
+fn maybe_empty() {
+    test(r"(x*)", "x☃x".as_bytes());
+    test(r"(?:(a)|b)*", b"abba");
+}
+
+#[test]
+fn unanchored() {
+    let regex = DFA::new(r"(a)").unwrap();
+    let mut cache = regex.create_cache();
+    let mut caps = regex.create_captures();
+    let mut input = Input::new("a");
+    assert!(try_search(&regex, &mut cache, &mut input, &mut caps).is_err());
+    let regex = DFA::new(r"^(a)").unwrap();
+    let mut input = Input::new("a");
+    try_search(&regex, &mut cache, &mut input, &mut caps).unwrap();
+    assert!(caps.is_match());
+}
+
+#[test]
+fn not_one_pass() {
+    let err = DFA::new(r"a*[ab]").unwrap_err().to_string();
+    assert!(err.contains("conflicting transition"), "{err}");
+    let err = DFA::new(r"(^|$)a").unwrap_err().to_string();
+    assert!(err.contains("multiple epsilon transitions to same state"), "{err}");
+    let err = DFA::new_many(&[r"^", r"$"]).unwrap_err().to_string();
+    assert!(err.contains("multiple epsilon transitions to match state"), "{err}");
+    assert!(DFA::new(r"(a)(b)(c)(d)(e)(f)(g)(h)(i)(j)(k)(l)(m)(n)(o)(p)(q)").is_err());
+    assert!(DFA::new(r"(a)(b)(c)(d)(e)(f)(g)(h)(i)(j)(k)(l)(m)(n)(o)(p)").is_ok());
+}
+
+proptest! {
+  #[test]
+  fn matches(haystack: String, needle: String) {
+    let Some(nfa) = nfa(&needle, true) else {
+        return Ok(())
+    };
+    let Ok(regex1) = onepass::DFA::new_from_nfa(nfa.clone()) else {
+        return Ok(())
+    };
+    let regex2 = DFA::new_from_nfa(nfa).unwrap();
+    let mut cache1 = regex1.create_cache();
+    let mut cache2 = Cache::new(&regex2);
+    let mut caps1 = regex1.create_captures();
+    let mut caps2 = regex2.create_captures();
+    let input = regex_automata::Input::new(&haystack).anchored(Anchored::Yes);
+    regex1.try_search(&mut cache1, &input, &mut caps1).unwrap();
+    let mut input = Input::new(RandomSlices::new(haystack.as_bytes()));
+    input.anchored(Anchored::Yes);
+    try_search(&regex2, &mut cache2, &mut input, &mut caps2).unwrap();
+    prop_assert_eq!(caps1.iter().collect::<Vec<_>>(), caps2.iter().collect::<Vec<_>>());
+  }
+}
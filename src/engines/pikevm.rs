@@ -4,14 +4,19 @@ use core::cell::RefCell;
 pub use regex_automata::nfa::thompson::pikevm::{Builder, Config, PikeVM};
 use regex_automata::nfa::thompson::State;
 use regex_automata::util::captures::Captures;
+use regex_automata::util::prefilter::Prefilter;
 use regex_automata::util::primitives::{NonMaxUsize, SmallIndex, StateID};
-use regex_automata::{Anchored, HalfMatch, Match, MatchKind, PatternID};
+use regex_automata::{Anchored, HalfMatch, Match, MatchKind, PatternID, PatternSet};
 
 use crate::cursor::Cursor;
 use crate::util::sparse_set::SparseSet;
-use crate::util::{empty, iter};
+use crate::util::{empty, iter, utf8};
 use crate::{literal, Input};
 
+pub(crate) use crate::engines::pikevm::stream::ChunkSearch;
+pub use crate::engines::pikevm::stream::StreamSearcher;
+
+mod stream;
 #[cfg(test)]
 mod tests;
 
@@ -271,6 +276,136 @@ pub fn search_slots<C: Cursor>(
     got.map(|hm| hm.pattern())
 }
 
+/// Writes the set of patterns that match anywhere in the given search
+/// configuration to `patset`. If multiple patterns match at the same
+/// position and this `PikeVM` was configured with [`MatchKind::All`]
+/// semantics, then all matching patterns are written to the given set.
+///
+/// Unless all of the patterns in this `PikeVM` are anchored, then
+/// generally speaking, this will visit every byte in the haystack.
+///
+/// This search routine *does not* clear the pattern set. This gives some
+/// flexibility to the caller (e.g., running multiple searches with the
+/// same pattern set), but does make the API bug-prone if you're reusing
+/// the same pattern set for multiple searches but intended them to be
+/// independent.
+///
+/// If a pattern ID matched but the given `PatternSet` does not have
+/// sufficient capacity to store it, then it is not inserted and silently
+/// dropped.
+///
+/// # Example
+///
+/// This example shows how to find all matching patterns in a haystack,
+/// even when some patterns match at the same position as other patterns.
+///
+/// ```
+/// use regex_cursor::engines::pikevm::{which_overlapping_matches, Cache, PikeVM};
+/// use regex_cursor::regex_automata::{MatchKind, PatternSet};
+/// use regex_cursor::Input;
+///
+/// let patterns = &[
+///     r"\w+", r"\d+", r"\pL+", r"foo", r"bar", r"barfoo", r"foobar",
+/// ];
+/// let re = PikeVM::builder()
+///     .configure(PikeVM::config().match_kind(MatchKind::All))
+///     .build_many(patterns)?;
+/// let mut cache = Cache::new(&re);
+///
+/// let mut input = Input::new("foobar");
+/// let mut patset = PatternSet::new(re.pattern_len());
+/// which_overlapping_matches(&re, &mut cache, &mut input, &mut patset);
+/// let expected = vec![0, 2, 3, 4, 6];
+/// let got: Vec<usize> = patset.iter().map(|p| p.as_usize()).collect();
+/// assert_eq!(expected, got);
+///
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[inline]
+pub fn which_overlapping_matches<C: Cursor>(
+    vm: &PikeVM,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    patset: &mut PatternSet,
+) {
+    search_overlapping(vm, cache, input, |pid, _| {
+        let _ = patset.try_insert(pid);
+        // If we found a match and filled our set, then there is no more
+        // additional info that we can provide. Thus, we can quit.
+        patset.is_full()
+    })
+}
+
+/// Reports every pattern that matches at every position of the search to
+/// `report`, which receives the pattern and the end offset of the match.
+/// When `report` returns `true` the search stops.
+///
+/// This is effectively a copy of `search_imp`, but with no captures
+/// support. It backs both `which_overlapping_matches` and the overlapping
+/// match iterator of the meta regex engine (which runs this anchored at
+/// each start position to find all match ends).
+pub(crate) fn search_overlapping<C: Cursor, F>(
+    vm: &PikeVM,
+    cache: &mut Cache,
+    input: &mut Input<C>,
+    mut report: F,
+) where
+    F: FnMut(PatternID, usize) -> bool,
+{
+    // NOTE: We somewhat go out of our way here to support things like
+    // 'input.get_earliest()' and 'leftmost-first' match semantics. Neither
+    // of those seem particularly relevant to this routine, but they are
+    // both supported by the DFA analogs of this routine by construction
+    // and composition, so it seems like good sense to have the PikeVM
+    // match that behavior.
+    cache.setup_search(0);
+    if input.is_done() {
+        return;
+    }
+    instrument!(|c| c.reset(&self.nfa));
+
+    let allmatches = vm.get_config().get_match_kind() == MatchKind::All;
+    let (anchored, start_id) = match start_config(vm, input) {
+        None => return,
+        Some(config) => config,
+    };
+
+    let Cache { ref mut stack, ref mut curr, ref mut next } = cache;
+    let mut any_matches = false;
+    input.move_to(input.start());
+    input.clear_look_behind();
+    input.ensure_look_behind();
+    while input.at() <= input.end() {
+        if curr.set.is_empty() {
+            if any_matches && !allmatches {
+                break;
+            }
+            if anchored && input.at() > input.start() {
+                break;
+            }
+        }
+        // Like in 'search_imp', an anchored search must only compute the
+        // epsilon closure of the start state at the beginning of the search.
+        if (!any_matches || allmatches) && (!anchored || input.at() == input.start()) {
+            let slots = &mut [];
+            epsilon_closure(vm, stack, slots, curr, input, start_id);
+        }
+        input.chunk_pos += 1;
+        if input.chunk_pos() >= input.chunk().len() {
+            input.advance_with_look_behind();
+        }
+        let stop = nexts_overlapping(vm, stack, curr, next, input, &mut any_matches, &mut report);
+        // We also quit if the caller asked us to stop at the earliest point
+        // that we know a match exists.
+        if stop || (input.get_earliest() && any_matches) {
+            break;
+        }
+        core::mem::swap(curr, next);
+        next.set.clear();
+    }
+    instrument!(|c| c.eprint(&self.nfa));
+}
+
 /// This is the actual implementation of `search_slots_imp` that
 /// doesn't account for the special case when 1) the NFA has UTF-8 mode
 /// enabled, 2) the NFA can match the empty string and 3) the caller has
@@ -339,17 +474,81 @@ fn search_imp<C: Cursor>(
     }
     instrument!(|c| c.reset(&self.nfa));
 
+    let mut state = SearchState::new(vm, input)?;
+    let pre = if state.anchored { None } else { vm.get_config().get_prefilter() };
+    input.move_to(input.start());
+    input.clear_look_behind();
+    input.ensure_look_behind();
+    search_chunks(vm, cache, &mut state, pre, input, slots, true);
+    instrument!(|c| c.eprint(&self.nfa));
+    state.hm
+}
+
+/// The state of a single PikeVM search, apart from the active states which
+/// are kept in the 'Cache'.
+///
+/// Together with the cache, this is everything that is needed to suspend a
+/// search at the end of a chunk and resume it once the next chunk is
+/// available (see 'search_chunks').
+#[derive(Clone, Debug)]
+struct SearchState {
+    /// Whether the search is anchored.
+    anchored: bool,
+    /// The anchored start state of the NFA (see 'start_config').
+    start_id: StateID,
+    /// The offset at which the search started.
+    start: usize,
+    /// The most recent match found by the search.
+    hm: Option<HalfMatch>,
+    /// Set when the search was suspended after moving past the last byte of
+    /// a chunk. The active states haven't been stepped over that byte yet,
+    /// since computing the epsilon closure after it requires looking ahead
+    /// into the next chunk.
+    step_pending: bool,
+}
+
+impl SearchState {
+    /// Starts a new search at the current start of the given input. Returns
+    /// `None` if the input asks for a pattern that isn't in this regex.
+    fn new<C: Cursor>(vm: &PikeVM, input: &Input<C>) -> Option<SearchState> {
+        let (anchored, start_id) = start_config(vm, input)?;
+        Some(SearchState {
+            anchored,
+            start_id,
+            start: input.start(),
+            hm: None,
+            step_pending: false,
+        })
+    }
+}
+
+/// Runs (or resumes) the search described by 'state' from the current
+/// position of 'input'. Returns true once the search is complete, at which
+/// point 'state.hm' holds its result.
+///
+/// If 'eoi' is false, the end of the current chunk isn't treated as the end
+/// of the haystack. Instead of advancing the cursor, the search is suspended
+/// right after it moved past the last byte of the chunk and false is
+/// returned. The search can then be resumed by calling this again, with the
+/// same cache and state, on an input positioned at the start of the next
+/// chunk that knows its look-behind. The cursor is never backtracked.
+fn search_chunks<C: Cursor>(
+    vm: &PikeVM,
+    cache: &mut Cache,
+    state: &mut SearchState,
+    pre: Option<&Prefilter>,
+    input: &mut Input<C>,
+    slots: &mut [Option<NonMaxUsize>],
+    eoi: bool,
+) -> bool {
     // Whether we want to visit all match states instead of emulating the
     // 'leftmost' semantics of typical backtracking regex engines.
     let allmatches = vm.get_config().get_match_kind() == MatchKind::All;
-    let (anchored, start_id) = match start_config(vm, input) {
-        None => return None,
-        Some(config) => config,
-    };
-
-    let pre = if anchored { None } else { vm.get_config().get_prefilter() };
     let Cache { ref mut stack, ref mut curr, ref mut next } = cache;
-    let mut hm = None;
+    if core::mem::take(&mut state.step_pending) && step(vm, stack, curr, next, state, input, slots)
+    {
+        return true;
+    }
     // Yes, our search doesn't end at input.end(), but includes it. This
     // is necessary because matches are delayed by one byte, just like
     // how the DFA engines work. The delay is used to handle look-behind
@@ -361,23 +560,20 @@ fn search_imp<C: Cursor>(
     // contains an NFA match state, but rather, whether the DFA state was
     // generated by a transition from a DFA state that contains an NFA
     // match state.)
-    input.move_to(input.start());
-    input.clear_look_behind();
-    input.ensure_look_behind();
     while input.at() <= input.end() {
         // If we have no states left to visit, then there are some cases
         // where we know we can quit early or even skip ahead.
         if curr.set.is_empty() {
             // We have a match and we haven't been instructed to continue
             // on even after finding a match, so we can quit.
-            if hm.is_some() && !allmatches {
-                break;
+            if state.hm.is_some() && !allmatches {
+                return true;
             }
             // If we're running an anchored search and we've advanced
             // beyond the start position with no other states to try, then
             // we will never observe a match and thus can stop.
-            if anchored && input.at() > input.start() {
-                break;
+            if state.anchored && input.at() > state.start {
+                return true;
             }
             // If there no states left to explore at this position and we
             // know we can't terminate early, then we are effectively at
@@ -389,7 +585,7 @@ fn search_imp<C: Cursor>(
             if let Some(pre) = pre {
                 let chunk_offst = input.chunk_offset();
                 match literal::find(pre, input) {
-                    None => break,
+                    None => return true,
                     Some(ref span) => {
                         input.move_to(span.start);
                         if chunk_offst != input.chunk_offset() {
@@ -446,7 +642,7 @@ fn search_imp<C: Cursor>(
         // search. If we re-computed it at every position, we would be
         // simulating an unanchored search when we were tasked to perform
         // an anchored search.
-        if (hm.is_none() || allmatches) && (!anchored || input.at() == input.start()) {
+        if (state.hm.is_none() || allmatches) && (!state.anchored || input.at() == state.start) {
             // Since we are adding to the 'curr' active states and since
             // this is for the start ID, we use a slots slice that is
             // guaranteed to have the right length but where every element
@@ -461,27 +657,46 @@ fn search_imp<C: Cursor>(
             // transitions, and thus must be able to write offsets to the
             // slots given which are later copied to slot values in 'curr'.
             let slots = next.slot_table.all_absent();
-            epsilon_closure(vm, stack, slots, curr, input, start_id);
+            epsilon_closure(vm, stack, slots, curr, input, state.start_id);
         }
         input.chunk_pos += 1;
-        if input.chunk_pos() >= input.chunk().len() {
-            input.advance_with_look_behind();
-        }
-        if let Some(pid) = nexts(vm, stack, curr, next, input, slots) {
-            hm = Some(HalfMatch::new(pid, input.at() - 1));
+        if input.chunk_pos() >= input.chunk().len() && !input.advance_with_look_behind() && !eoi {
+            state.step_pending = true;
+            return false;
         }
-        // Unless the caller asked us to return early, we need to mush on
-        // to see if we can extend our match. (But note that 'nexts' will
-        // quit right after seeing a match when match_kind==LeftmostFirst,
-        // as is consistent with leftmost-first match priority.)
-        if input.get_earliest() && hm.is_some() {
-            break;
+        if step(vm, stack, curr, next, state, input, slots) {
+            return true;
         }
-        core::mem::swap(curr, next);
-        next.set.clear();
     }
-    instrument!(|c| c.eprint(&self.nfa));
-    hm
+    true
+}
+
+/// Steps the active states in 'curr' over the byte before the current
+/// position of 'input' and records any match found. Returns true if the
+/// search is complete because the caller asked for the earliest match.
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn step<C: Cursor>(
+    vm: &PikeVM,
+    stack: &mut Vec<FollowEpsilon>,
+    curr: &mut ActiveStates,
+    next: &mut ActiveStates,
+    state: &mut SearchState,
+    input: &mut Input<C>,
+    slots: &mut [Option<NonMaxUsize>],
+) -> bool {
+    if let Some(pid) = nexts(vm, stack, curr, next, input, slots) {
+        state.hm = Some(HalfMatch::new(pid, input.at() - 1));
+    }
+    // Unless the caller asked us to return early, we need to mush on
+    // to see if we can extend our match. (But note that 'nexts' will
+    // quit right after seeing a match when match_kind==LeftmostFirst,
+    // as is consistent with leftmost-first match priority.)
+    if input.get_earliest() && state.hm.is_some() {
+        return true;
+    }
+    core::mem::swap(curr, next);
+    next.set.clear();
+    false
 }
 
 /// Process the active states in 'curr' to find the states (written to
@@ -518,6 +733,56 @@ fn nexts<C: Cursor>(
     pid
 }
 
+/// Like 'nexts', but for an overlapping search: every match found (only the
+/// first one, unless the PikeVM was configured with MatchKind::All semantics)
+/// is passed to 'report' instead of copying its slots. Returns true when
+/// 'report' asked for the search to stop.
+#[cfg_attr(feature = "perf-inline", inline(always))]
+fn nexts_overlapping<C: Cursor, F>(
+    vm: &PikeVM,
+    stack: &mut Vec<FollowEpsilon>,
+    curr: &mut ActiveStates,
+    next_: &mut ActiveStates,
+    input: &mut Input<C>,
+    any_matches: &mut bool,
+    report: &mut F,
+) -> bool
+where
+    F: FnMut(PatternID, usize) -> bool,
+{
+    instrument!(|c| c.record_state_set(&curr.set));
+    let utf8empty = vm.get_nfa().has_empty() && vm.get_nfa().is_utf8();
+    let ActiveStates { ref set, ref mut slot_table } = *curr;
+    for sid in set.iter() {
+        let pid = match next(vm, stack, slot_table, next_, input, sid) {
+            None => continue,
+            Some(pid) => pid,
+        };
+        // This handles the case of finding a zero-width match that splits
+        // a codepoint. Namely, if we're in UTF-8 mode AND we know we can
+        // match the empty string, then the only valid way of getting to
+        // this point with an offset that splits a codepoint is when we
+        // have an empty match. Such matches, in UTF-8 mode, must not be
+        // reported. So we just skip them here and pretend as if we did
+        // not see a match. The byte at the match offset is the one that
+        // was just consumed.
+        if utf8empty {
+            let (chunk, pos) = input.look_around();
+            if !utf8::is_boundary(chunk, pos - 1) {
+                continue;
+            }
+        }
+        *any_matches = true;
+        if report(pid, input.at() - 1) {
+            return true;
+        }
+        if vm.get_config().get_match_kind() != MatchKind::All {
+            break;
+        }
+    }
+    false
+}
+
 /// Starting from 'sid', if the position 'at' in the 'input' haystack has a
 /// transition defined out of 'sid', then add the state transitioned to and
 /// its epsilon closure to the 'next' set of states to explore.
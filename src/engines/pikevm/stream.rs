@@ -0,0 +1,327 @@
+use std::mem;
+
+use regex_automata::nfa::thompson::pikevm::PikeVM;
+use regex_automata::util::captures::Captures;
+use regex_automata::util::primitives::NonMaxUsize;
+use regex_automata::HalfMatch;
+
+use crate::cursor::Cursor;
+use crate::engines::pikevm::{search_chunks, Cache, SearchState};
+use crate::util::utf8;
+use crate::Input;
+
+/// The number of bytes before the current position that are needed to
+/// evaluate look-behind assertions.
+const LOOK_BEHIND_LEN: usize = 4;
+
+/// A PikeVM search that is driven by the caller and never backtracks.
+///
+/// The caller pushes chunks of the haystack into the searcher with
+/// [`StreamSearcher::feed`] as they arrive, and calls
+/// [`StreamSearcher::finish`] at the end of the stream. The active states of
+/// the PikeVM (including the capture slots of every thread) are suspended at
+/// the end of each chunk and resumed when the next one is pushed, so data
+/// that was already searched is never requested again. Look-behind
+/// assertions at the start of a chunk are evaluated using the last bytes of
+/// the previous one, which the searcher keeps around.
+///
+/// Every call returns the captures of the non-overlapping leftmost matches
+/// that were completed by the bytes pushed so far, in the same order as
+/// [`find_iter`](crate::engines::pikevm::find_iter). A match is completed
+/// once no thread of higher priority is alive anymore. The bytes searched
+/// after the end of the current match are buffered, since the next search
+/// has to start at the end of the match.
+///
+/// Prefilters are not used by this searcher. Unicode word boundaries are only
+/// evaluated correctly if chunks don't split codepoints.
+///
+/// # Example
+///
+/// ```
+/// use regex_cursor::engines::pikevm::{PikeVM, StreamSearcher};
+/// use regex_cursor::regex_automata::Span;
+///
+/// let re = PikeVM::new(r"(?<key>[a-z]+)=(?<value>[0-9]+)")?;
+/// let mut searcher = StreamSearcher::new(&re);
+/// let mut matches = vec![];
+/// for chunk in ["foo=1 ba", "r=2", "3 baz="] {
+///     matches.extend(searcher.feed(chunk.as_bytes()));
+/// }
+/// matches.extend(searcher.finish());
+/// let values: Vec<Span> = matches.iter().filter_map(|caps| caps.get_group_by_name("value")).collect();
+/// assert_eq!(values, vec![Span::from(4..5), Span::from(10..12)]);
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+#[derive(Debug)]
+pub struct StreamSearcher<'r> {
+    vm: &'r PikeVM,
+    cache: Cache,
+    /// The capture slots of the most recent match of the current search.
+    caps: Captures,
+    /// The current search, if one is in progress.
+    state: Option<SearchState>,
+    /// The bytes from `buf_start` up to the current position. This always
+    /// includes the look-behind of the current position and, if the current
+    /// search has found a match, everything after the end of that match.
+    buf: Vec<u8>,
+    buf_start: usize,
+    /// Bytes that need to be searched before any newly pushed ones, because
+    /// they were searched past the end of a match.
+    replay: Vec<u8>,
+    last_match_end: Option<usize>,
+    /// The number of bytes pushed into the searcher.
+    offset: usize,
+    /// Set once no more matches can be found.
+    done: bool,
+}
+
+impl<'r> StreamSearcher<'r> {
+    /// Creates a new searcher for the given PikeVM.
+    pub fn new(vm: &'r PikeVM) -> StreamSearcher<'r> {
+        StreamSearcher {
+            vm,
+            cache: Cache::new(vm),
+            caps: vm.create_captures(),
+            state: None,
+            buf: Vec::new(),
+            buf_start: 0,
+            replay: Vec::new(),
+            last_match_end: None,
+            offset: 0,
+            done: false,
+        }
+    }
+
+    /// Returns the number of bytes pushed into this searcher so far.
+    #[inline]
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Resets this searcher so that it can be used to search a new stream.
+    pub fn reset(&mut self) {
+        self.state = None;
+        self.buf.clear();
+        self.buf_start = 0;
+        self.replay.clear();
+        self.last_match_end = None;
+        self.offset = 0;
+        self.done = false;
+    }
+
+    /// Searches the next chunk of the stream and returns the captures of all
+    /// matches that were completed by it.
+    pub fn feed(&mut self, mut chunk: &[u8]) -> Vec<Captures> {
+        let mut matches = Vec::new();
+        self.offset += chunk.len();
+        loop {
+            self.search_replay(&mut matches);
+            if self.done || chunk.is_empty() {
+                break;
+            }
+            let consumed = self.search(chunk, false, &mut matches);
+            chunk = &chunk[consumed..];
+        }
+        matches
+    }
+
+    /// Marks the end of the stream and returns the captures of the remaining
+    /// matches. This is where look-around assertions that match at the end
+    /// of the haystack (like `$` or `\b`) are resolved.
+    ///
+    /// No more bytes may be pushed after calling this (until the searcher is
+    /// [reset](StreamSearcher::reset)).
+    pub fn finish(&mut self) -> Vec<Captures> {
+        let mut matches = Vec::new();
+        loop {
+            self.search_replay(&mut matches);
+            if self.done {
+                break;
+            }
+            self.search(&[], true, &mut matches);
+        }
+        matches
+    }
+
+    /// Searches the bytes that were searched past the end of the last match
+    /// again, until all of them have been consumed.
+    fn search_replay(&mut self, matches: &mut Vec<Captures>) {
+        while !self.replay.is_empty() && !self.done {
+            let replay = mem::take(&mut self.replay);
+            let consumed = self.search(&replay, false, matches);
+            // Any bytes that are searched past the end of a match found in
+            // the replayed bytes come before the rest of them.
+            self.replay.extend_from_slice(&replay[consumed..]);
+        }
+    }
+
+    /// Runs the current search (or a new one) over `data`, which starts
+    /// right after `buf`. Returns the number of bytes consumed, which is
+    /// less than `data.len()` if the search completed before the end of
+    /// `data`.
+    fn search(&mut self, data: &[u8], eoi: bool, matches: &mut Vec<Captures>) -> usize {
+        debug_assert!(eoi || !data.is_empty());
+        let offset = self.buf_start + self.buf.len();
+        let mut input = Input::new(StreamChunk { chunk: data, offset });
+        input.set_look_behind_bytes(&self.buf);
+        let state = match self.state {
+            Some(ref mut state) => state,
+            None => {
+                self.cache.setup_search(self.caps.slots_mut().len());
+                self.caps.set_pattern(None);
+                let state = SearchState::new(self.vm, &input)
+                    .expect("unanchored searches always have a start state");
+                self.state.insert(state)
+            }
+        };
+        let complete = search_chunks(
+            self.vm,
+            &mut self.cache,
+            state,
+            None,
+            &mut input,
+            self.caps.slots_mut(),
+            eoi,
+        );
+        let hm = state.hm;
+        let consumed = if complete { (input.at() - offset).min(data.len()) } else { data.len() };
+        let end = offset + consumed;
+        let keep_from =
+            hm.map_or(end, |hm| hm.offset()).saturating_sub(LOOK_BEHIND_LEN).max(self.buf_start);
+        if keep_from >= offset {
+            self.buf.clear();
+            self.buf.extend_from_slice(&data[keep_from - offset..consumed]);
+        } else {
+            self.buf.drain(..keep_from - self.buf_start);
+            self.buf.extend_from_slice(&data[..consumed]);
+        }
+        self.buf_start = keep_from;
+        if complete {
+            self.state = None;
+            match hm {
+                Some(hm) => self.report(hm, end, matches),
+                None => self.done = true,
+            }
+        }
+        consumed
+    }
+
+    /// Reports the match of the search that just completed (unless it's an
+    /// empty match that must be skipped) and moves the bytes searched after
+    /// it to `replay`. `end` is the position up to which the search
+    /// consumed bytes.
+    fn report(&mut self, hm: HalfMatch, end: usize, matches: &mut Vec<Captures>) {
+        let match_end = hm.offset();
+        // See 'Searcher::handle_overlapping_empty_match' and
+        // 'util::empty::skip_splits_fwd'. Since the search can't go back,
+        // the next search starts right after the end of the match.
+        let nfa = self.vm.get_nfa();
+        let after = &self.buf[match_end - self.buf_start..];
+        let split = nfa.has_empty() && nfa.is_utf8() && !utf8::is_boundary(after, 0);
+        let mut restart = match_end;
+        if self.last_match_end == Some(match_end) || split {
+            if match_end == end {
+                self.done = true;
+                return;
+            }
+            restart += 1;
+        } else {
+            self.caps.set_pattern(Some(hm.pattern()));
+            matches.push(self.caps.clone());
+            self.last_match_end = Some(match_end);
+        }
+        debug_assert!(self.replay.is_empty());
+        self.replay = self.buf.split_off(restart - self.buf_start);
+    }
+}
+
+/// A single PikeVM search over chunks of a haystack that are handed to it one
+/// after another. This is how the async search routines of the meta regex
+/// run the PikeVM, since they can't block until the next chunk arrives.
+#[derive(Clone, Debug)]
+pub(crate) struct ChunkSearch {
+    state: Option<SearchState>,
+    /// The last bytes before the next chunk.
+    look_behind: Vec<u8>,
+    earliest: bool,
+}
+
+impl ChunkSearch {
+    /// Creates a new search that starts at the beginning of the first chunk
+    /// passed to [`ChunkSearch::search`]. `look_behind` holds the bytes
+    /// before it (at most the last four are used).
+    pub(crate) fn new(look_behind: Vec<u8>, earliest: bool) -> ChunkSearch {
+        ChunkSearch { state: None, look_behind, earliest }
+    }
+
+    /// Runs the search over the next chunk, which starts at `offset`. An
+    /// empty chunk marks the end of the haystack. Returns true once the
+    /// search is complete, at which point [`ChunkSearch::half_match`] returns
+    /// its result and `slots` hold the offsets of the match.
+    pub(crate) fn search(
+        &mut self,
+        vm: &PikeVM,
+        cache: &mut Cache,
+        slots: &mut [Option<NonMaxUsize>],
+        chunk: &[u8],
+        offset: usize,
+    ) -> bool {
+        let mut input = Input::new(StreamChunk { chunk, offset });
+        input.set_look_behind_bytes(&self.look_behind);
+        input.set_earliest(self.earliest);
+        let state = match self.state {
+            Some(ref mut state) => state,
+            None => {
+                cache.setup_search(slots.len());
+                let state = SearchState::new(vm, &input)
+                    .expect("unanchored searches always have a start state");
+                self.state.insert(state)
+            }
+        };
+        if search_chunks(vm, cache, state, None, &mut input, slots, chunk.is_empty()) {
+            return true;
+        }
+        self.look_behind.extend_from_slice(chunk);
+        let excess = self.look_behind.len().saturating_sub(LOOK_BEHIND_LEN);
+        self.look_behind.drain(..excess);
+        false
+    }
+
+    /// Returns the match found by this search so far.
+    pub(crate) fn half_match(&self) -> Option<HalfMatch> {
+        self.state.as_ref().and_then(|state| state.hm)
+    }
+}
+
+/// A cursor over a single chunk of a stream, which can neither advance nor
+/// backtrack.
+struct StreamChunk<'a> {
+    chunk: &'a [u8],
+    offset: usize,
+}
+
+impl Cursor for StreamChunk<'_> {
+    fn chunk(&self) -> &[u8] {
+        self.chunk
+    }
+
+    fn utf8_aware(&self) -> bool {
+        false
+    }
+
+    fn advance(&mut self) -> bool {
+        false
+    }
+
+    fn backtrack(&mut self) -> bool {
+        false
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        None
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+}
@@ -92,3 +92,36 @@ proptest! {
     prop_assert_eq!(iter1, iter2);
   }
 }
+
+#[test]
+fn stream() {
+    let haystack = "foo1 bar23\nШерлок aab\n 45 a";
+    for needle in [
+        r"([0-9])+",
+        r"a*",
+        r"(?-u:\b)(\w)+(?-u:\b)",
+        r"(?m)$",
+        r"(a+)b|a",
+        r"^foo|a$",
+        "",
+        r"(?<x>\d)|(?<y>ок)",
+    ] {
+        let regex = PikeVM::new(needle).unwrap();
+        let mut cache = regex.create_cache();
+        let expected: Vec<_> = regex.captures_iter(&mut cache, haystack).collect();
+        for chunk_len in [1, 2, 3, 7, haystack.len()] {
+            let mut searcher = super::StreamSearcher::new(&regex);
+            let mut matches = Vec::new();
+            for chunk in haystack.as_bytes().chunks(chunk_len) {
+                matches.extend(searcher.feed(chunk));
+            }
+            matches.extend(searcher.finish());
+            assert_eq!(matches.len(), expected.len(), "{needle:?} {chunk_len}");
+            for (caps, expected) in matches.iter().zip(&expected) {
+                let groups: Vec<_> = caps.iter().collect();
+                let expected: Vec<_> = expected.iter().collect();
+                assert_eq!(groups, expected, "{needle:?} {chunk_len}");
+            }
+        }
+    }
+}
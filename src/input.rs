@@ -6,9 +6,10 @@ at the crate root due to the universality of most of the types and routines in
 this module.
 */
 
+use std::io;
 use std::ops::RangeBounds;
 
-use regex_automata::{Anchored, Span};
+use regex_automata::{Anchored, MatchError, Span};
 
 use crate::cursor::{Cursor, IntoCursor};
 use crate::util::utf8::is_boundary;
@@ -26,6 +27,9 @@ pub struct Input<C: Cursor> {
     look_behind_len: usize,
     /// the last 4 bytes before the current chunk
     look_around: [u8; MAX_CODEPOINT_LEN * 2],
+    /// The offset at which the cursor first failed to backtrack. The
+    /// results of searches are discarded once this is set.
+    backtrack_failed: Option<usize>,
     cursor: C,
 }
 
@@ -47,6 +51,7 @@ impl<C: Cursor> Input<C> {
             look_around: [255; 8],
             span: Span { start, end },
             look_behind_len: 0,
+            backtrack_failed: None,
         }
     }
 
@@ -132,6 +137,12 @@ impl<C: Cursor> Input<C> {
         self.span.end
     }
 
+    /// Returns the length of the haystack, if the cursor knows it.
+    #[inline]
+    pub(crate) fn haystack_len(&self) -> Option<usize> {
+        self.cursor.total_bytes()
+    }
+
     #[inline(always)]
     pub fn get_chunk_end(&self) -> usize {
         let end = self.span.end - self.cursor.offset();
@@ -177,6 +188,14 @@ impl<C: Cursor> Input<C> {
         }
     }
 
+    /// Sets the bytes preceding the current chunk without backtracking the
+    /// cursor. Only the last few bytes (enough to hold a codepoint) are kept.
+    pub(crate) fn set_look_behind_bytes(&mut self, look_behind: &[u8]) {
+        let look_behind = &look_behind[look_behind.len().saturating_sub(MAX_CODEPOINT_LEN)..];
+        self.look_around[..look_behind.len()].copy_from_slice(look_behind);
+        self.look_behind_len = look_behind.len();
+    }
+
     #[cfg_attr(feature = "perf-inline", inline(always))]
     pub(crate) fn advance(&mut self) -> bool {
         let old_len = self.cursor.chunk().len();
@@ -201,11 +220,52 @@ impl<C: Cursor> Input<C> {
         if backtracked {
             self.chunk_pos = self.chunk().len();
         } else if self.cursor.offset() != 0 {
-            unreachable!("cursor does not support backtracking {}", self.cursor.offset())
+            self.backtrack_failed.get_or_insert(self.cursor.offset());
         }
         backtracked
     }
 
+    /// Returns `result`, or panics if the cursor failed to backtrack. This is
+    /// used by infallible searches, which can't report that their result is
+    /// wrong.
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn panic_if_failed<T>(&self, result: T) -> T {
+        #[cold]
+        #[inline(never)]
+        fn failed(offset: usize) -> ! {
+            panic!(
+                "cursor failed to backtrack from offset {}\n\
+                 to handle cursor errors, use 'Regex::try_search' or \
+                 'Regex::try_find_iter'",
+                offset,
+            )
+        }
+
+        if let Some(offset) = self.backtrack_failed {
+            failed(offset);
+        }
+        result
+    }
+
+    /// Returns `result`, or an error that the search gave up where the
+    /// cursor failed to backtrack if it did.
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn check_backtrack<T>(
+        &self,
+        result: Result<T, MatchError>,
+    ) -> Result<T, MatchError> {
+        match self.backtrack_failed {
+            Some(offset) => Err(MatchError::gave_up(offset)),
+            None => result,
+        }
+    }
+
+    /// Returns (and clears) the error of the cursor, and the offset at which
+    /// it failed to backtrack.
+    pub(crate) fn take_cursor_error(&mut self) -> (Option<io::Error>, Option<usize>) {
+        (self.cursor.take_error(), self.backtrack_failed.take())
+    }
+
     #[cfg_attr(feature = "perf-inline", inline(always))]
     pub(crate) fn ensure_look_behind(&mut self) -> Option<u8> {
         if self.chunk_pos == 0 {
@@ -641,9 +701,24 @@ impl<C: Cursor> Input<C> {
     #[inline]
     pub(crate) fn move_to(&mut self, at: usize) {
         debug_assert!(at <= self.span.end.saturating_add(1));
-        // TODO: fastpath for O(log N) chunk jumping
+        let offset = self.cursor.offset();
+        if (at < offset || at > offset + self.chunk().len()) && self.cursor.seek(at) {
+            let (offset, len) = (self.cursor.offset(), self.chunk().len());
+            // like a failed advance, seeking past the end of the data
+            // reveals the real end of the haystack
+            if at > offset + len && self.span.end > offset + len {
+                self.span.end = offset + len;
+            }
+            self.set_chunk_pos((at - offset).min(len));
+            return;
+        }
         while at < self.cursor.offset() {
-            self.backtrack();
+            if !self.backtrack() {
+                // the failure is recorded and the result of the search
+                // is discarded
+                self.set_chunk_pos(0);
+                return;
+            }
         }
         if at != self.cursor.offset() {
             while at >= self.cursor.offset() + self.chunk().len() {
@@ -658,6 +733,31 @@ impl<C: Cursor> Input<C> {
         self.set_chunk_pos(at - self.cursor.offset());
     }
 
+    /// Like [`move_to`](Self::move_to) but also refreshes the look-behind
+    /// buffer whenever a different chunk is entered, so that
+    /// [`look_around`](Self::look_around) stays valid after jumping to an
+    /// arbitrary position (in either direction).
+    #[cfg_attr(feature = "perf-inline", inline(always))]
+    pub(crate) fn move_to_with_look_behind(&mut self, at: usize) {
+        let chunk_offset = self.chunk_offset();
+        self.move_to(at);
+        if chunk_offset != self.chunk_offset() {
+            self.sync_look_behind();
+        }
+    }
+
+    /// Reloads the look-behind buffer for the current chunk regardless of
+    /// the position within that chunk.
+    pub(crate) fn sync_look_behind(&mut self) {
+        let at = self.at();
+        self.chunk_pos = 0;
+        self.clear_look_behind();
+        self.ensure_look_behind();
+        // backtracking and advancing again is not guaranteed to produce the
+        // same chunk so `at` may now be located in one of the next chunks
+        self.move_to_with_look_behind(at);
+    }
+
     #[cfg_attr(feature = "perf-inline", inline(always))]
     pub(crate) fn at(&self) -> usize {
         self.cursor.offset() + self.chunk_pos()
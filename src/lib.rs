@@ -27,8 +27,10 @@ Working on this crate showed me that regex backtracks a lot more than expected
 with most functionality fundamentally requiring backtracking. For network
 usecases that do not buffer their input the primary usecase would likely be
 detecting a match (without necessarily requiring the matched byte range).
-Such usecases can be covered by manually feeding bytes into the hybrid and DFA
-engines from the regex-automata crate. This approach also has the advantage
+Such usecases are covered by the `StreamSearcher` of the hybrid and DFA engines
+(see [`engines::hybrid::StreamSearcher`]), which the caller feeds chunk by chunk
+and which never backtracks. It reports the end of each match, and its start if
+a bounded reverse window is configured. This approach also has the advantage
 of allowing the caller to pause the match (async) while waiting for more data
 allowing the caller to drive the search instead of the engine itself.
 
@@ -40,16 +42,29 @@ to disambiguate alternative matches
 * utf-8 mode can not be supported (empty matches may occur between unicode
 boundaries)
 
-Currently, the PikeVM implementation is not written with this use case in mind
-and may call backtrack unnecessarily, but that could be addressed in the future,
-but especially the first point is very limiting. The pikevm also does not allow
-the user to drive the search and would block on network calls for example (no
-async).
+The regular PikeVM search routines may call backtrack, but
+[`engines::pikevm::StreamSearcher`] lets the user drive the search by pushing
+chunks into it. It suspends the active states at the end of each chunk and
+keeps the last few bytes around for look-behind, so it never backtracks. It
+doesn't use prefilters, and to run more than a single search it buffers the
+bytes that were searched past the end of each match.
+
+Collections that load their chunks lazily can implement [`AsyncCursor`]
+instead, which reports a chunk that isn't available yet with `Poll::Pending`.
+[`engines::meta::Regex::find_iter_async`] and its siblings suspend the search
+at that chunk boundary instead of blocking. Unlike the stream searchers, these
+still move the cursor backwards to find the start of a match.
 */
 
 #[cfg(feature = "ropey")]
 pub use cursor::RopeyCursor;
-pub use cursor::{Cursor, IntoCursor};
+pub use cursor::{
+    AsyncCursor, Chain, Concealed, Cursor, Encoding, FileCursor, GapBufferCursor, IntoCursor,
+    LinesCursor, NormalizeNewlines, OffsetMap, PieceTableCursor, ReaderCursor, Reversed,
+    SliceChunksCursor, Transcoded, Utf8Boundaries, Window,
+};
+#[cfg(feature = "inflate")]
+pub use cursor::{Compression, InflateCursor};
 pub use input::Input;
 pub use regex_automata;
 
@@ -2,11 +2,12 @@ use std::cell::Cell;
 use std::collections::hash_map::DefaultHasher;
 use std::hash::Hasher;
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::task::{Context, Poll};
 
 use regex_automata::util::escape::DebugHaystack;
 
 use crate::util::utf8;
-use crate::Cursor;
+use crate::{AsyncCursor, Cursor};
 
 #[derive(Debug)]
 struct XorShift64Star {
@@ -183,3 +184,59 @@ impl Cursor for SingleByteChunks<'_> {
         self.pos
     }
 }
+
+/// An async cursor over another cursor that returns `Poll::Pending` (and
+/// wakes the task right away) every other time it's asked to move, as if
+/// every chunk had to be loaded first.
+#[derive(Debug)]
+pub(crate) struct AsyncChunks<C> {
+    cursor: C,
+    loaded: bool,
+    /// The number of times `Poll::Pending` was returned.
+    pub(crate) pending: usize,
+}
+
+impl<C: Cursor> AsyncChunks<C> {
+    pub fn new(cursor: C) -> Self {
+        Self { cursor, loaded: false, pending: 0 }
+    }
+
+    fn poll_load(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        self.loaded = !self.loaded;
+        if !self.loaded {
+            Poll::Ready(())
+        } else {
+            self.pending += 1;
+            cx.waker().wake_by_ref();
+            Poll::Pending
+        }
+    }
+}
+
+impl<C: Cursor> AsyncCursor for AsyncChunks<C> {
+    fn chunk(&self) -> &[u8] {
+        self.cursor.chunk()
+    }
+
+    fn utf8_aware(&self) -> bool {
+        self.cursor.utf8_aware()
+    }
+
+    fn poll_advance(&mut self, cx: &mut Context<'_>) -> Poll<bool> {
+        std::task::ready!(self.poll_load(cx));
+        Poll::Ready(self.cursor.advance())
+    }
+
+    fn poll_backtrack(&mut self, cx: &mut Context<'_>) -> Poll<bool> {
+        std::task::ready!(self.poll_load(cx));
+        Poll::Ready(self.cursor.backtrack())
+    }
+
+    fn total_bytes(&self) -> Option<usize> {
+        self.cursor.total_bytes()
+    }
+
+    fn offset(&self) -> usize {
+        self.cursor.offset()
+    }
+}
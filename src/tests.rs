@@ -1,10 +1,11 @@
-use crate::{test_rope::SingleByteChunks, Input};
+use crate::test_rope::{AsyncChunks, RandomSlices, SingleByteChunks};
+use crate::Input;
 
 use {
     crate::engines::meta::{self, Regex},
     anyhow::Result,
     regex_automata::util::syntax,
-    regex_automata::MatchKind,
+    regex_automata::{MatchKind, PatternSet},
     regex_test::{CompiledRegex, Match, RegexTest, SearchKind, Span, TestResult, TestRunner},
 };
 
@@ -92,7 +93,7 @@ fn default() -> Result<()> {
     let builder = Regex::builder();
     let mut runner = TestRunner::new()?;
     runner
-        .expand(&["is_match", "find", "captures"], |test| test.compiles())
+        .expand(&["is_match", "find", "find_overlapping", "captures"], |test| test.compiles())
         .blacklist_iter(BLACKLIST);
     for _ in 0..RUNS {
         runner.test_iter(suite()?.iter(), compiler(builder.clone()));
@@ -121,7 +122,7 @@ fn no_dfa() -> Result<()> {
     builder.configure(Regex::config().dfa(false));
     let mut runner = TestRunner::new()?;
     runner
-        .expand(&["is_match", "find", "captures"], |test| test.compiles())
+        .expand(&["is_match", "find", "find_overlapping", "captures"], |test| test.compiles())
         .blacklist_iter(BLACKLIST);
     for _ in 0..RUNS {
         runner.test_iter(suite()?.iter(), compiler(builder.clone()));
@@ -146,6 +147,40 @@ fn no_dfa_hybrid() -> Result<()> {
     Ok(())
 }
 
+/// Tests the default configuration minus the full DFA, lazy DFA and the
+/// one-pass DFA. This leaves the bounded backtracker and the PikeVM.
+#[test]
+fn no_dfa_hybrid_onepass() -> Result<()> {
+    let mut builder = Regex::builder();
+    builder.configure(Regex::config().dfa(false).hybrid(false).onepass(false));
+    let mut runner = TestRunner::new()?;
+    runner
+        .expand(&["is_match", "find", "captures"], |test| test.compiles())
+        .blacklist_iter(BLACKLIST);
+    for _ in 0..RUNS {
+        runner.test_iter(suite()?.iter(), compiler(builder.clone()));
+    }
+    runner.assert();
+    Ok(())
+}
+
+/// Tests the default configuration minus the full DFA, lazy DFA, one-pass
+/// DFA and the bounded backtracker. This leaves only the PikeVM.
+#[test]
+fn no_dfa_hybrid_onepass_backtrack() -> Result<()> {
+    let mut builder = Regex::builder();
+    builder.configure(Regex::config().dfa(false).hybrid(false).onepass(false).backtrack(false));
+    let mut runner = TestRunner::new()?;
+    runner
+        .expand(&["is_match", "find", "captures"], |test| test.compiles())
+        .blacklist_iter(BLACKLIST);
+    for _ in 0..RUNS {
+        runner.test_iter(suite()?.iter(), compiler(builder.clone()));
+    }
+    runner.assert();
+    Ok(())
+}
+
 fn compiler(
     mut builder: meta::Builder,
 ) -> impl FnMut(&RegexTest, &[String]) -> Result<CompiledRegex> {
@@ -183,7 +218,24 @@ fn run_test(re: &Regex, test: &RegexTest) -> TestResult {
                     }
                 }),
             ),
-            SearchKind::Overlapping => TestResult::skip(),
+            SearchKind::Overlapping => {
+                let mut patset = PatternSet::new(re.pattern_len());
+                re.which_overlapping_matches(input, &mut patset);
+                TestResult::which(patset.iter().map(|p| p.as_usize()))
+            }
+        },
+        "find_overlapping" => match (test.search_kind(), test.match_kind()) {
+            // The order of overlapping matches is only the one expected by
+            // the test suite (end offset first) when a DFA can be used.
+            (SearchKind::Overlapping, regex_test::MatchKind::All) => TestResult::matches(
+                re.find_overlapping_iter(input).take(test.match_limit().unwrap_or(usize::MAX)).map(
+                    |m| Match {
+                        id: m.pattern().as_usize(),
+                        span: Span { start: m.start(), end: m.end() },
+                    },
+                ),
+            ),
+            _ => TestResult::skip(),
         },
         "captures" => match test.search_kind() {
             SearchKind::Earliest => {
@@ -237,3 +289,211 @@ fn config_syntax(test: &RegexTest) -> syntax::Config {
         .utf8(test.utf8())
         .line_terminator(test.line_terminator())
 }
+
+/// Compares a meta regex that uses the given strategy against the upstream
+/// meta regex, with the haystack split into chunks at every byte and at random
+/// positions.
+fn compare_strategy(strategy: &str, needle: &str, haystack: &[u8]) {
+    let re1 = regex_automata::meta::Regex::new(needle).unwrap();
+    let re2 = Regex::new(needle).unwrap();
+    assert!(format!("{re2:?}").contains(strategy), "{needle} does not use {strategy}");
+    let matches: Vec<_> = re1.find_iter(haystack).collect();
+    let captures: Vec<Vec<_>> =
+        re1.captures_iter(haystack).map(|caps| caps.iter().collect()).collect();
+
+    let input = Input::new(SingleByteChunks::new(haystack));
+    assert_eq!(matches, re2.find_iter(input).collect::<Vec<_>>());
+    let input = Input::new(RandomSlices::new(haystack));
+    assert_eq!(matches, re2.find_iter(input).collect::<Vec<_>>());
+    let input = Input::new(RandomSlices::new(haystack));
+    assert_eq!(
+        captures,
+        re2.captures_iter(input).map(|caps| caps.iter().collect()).collect::<Vec<Vec<_>>>()
+    );
+    let input = Input::new(SingleByteChunks::new(haystack));
+    assert_eq!(!matches.is_empty(), re2.is_match(input));
+}
+
+#[test]
+fn reverse_suffix_strategy() {
+    let haystack = b"foo bar@example.com baz.qux@example.com @example.com quux@example.co";
+    compare_strategy("ReverseSuffix", r"\w+@example\.com", haystack);
+    compare_strategy("ReverseSuffix", r"(\w+)@(\w+)\.com", haystack);
+    compare_strategy("ReverseSuffix", r"[a-z]+ing", b"tingling ing singing");
+    // Every suffix candidate but the last fails to match and the reverse
+    // scans would re-scan the previous candidates, so this has to fall back
+    // to the core engine to avoid quadratic behavior.
+    compare_strategy("ReverseSuffix", r"[0-9][a-z]*xyz", b"abxyzabxyzabxyz");
+    compare_strategy("ReverseSuffix", r"[0-9][a-z]*xyz", b"abxyzabxyz1abxyz");
+}
+
+#[test]
+fn reverse_inner_strategy() {
+    let haystack = b"sing 1 ring 22 bring kings 333 thing\t4444 sing";
+    compare_strategy("ReverseInner", r"[a-z]+ing\s+\d+", haystack);
+    compare_strategy("ReverseInner", r"([a-z]+)ing\s+(\d+)", haystack);
+    compare_strategy("ReverseInner", r"\w+@@\d+", b"foo@@ bar@@12 @@3 baz@@");
+    // The forward scan from the first candidate runs to the end of the
+    // haystack without finding a match, so every later candidate would be
+    // re-scanned and the search has to fall back to the core engine.
+    compare_strategy("ReverseInner", r"[a-z]+ing\s+\d+", b"ring ring ring ring x1");
+}
+
+/// Compares the overlapping matches reported with and without the full and
+/// lazy DFAs (which differ in order), with the haystack split into chunks.
+fn compare_overlapping(needles: &[&str], haystack: &[u8]) {
+    let config = Regex::config().match_kind(MatchKind::All);
+    let re1 = Regex::builder().configure(config.clone()).build_many(needles).unwrap();
+    let re2 =
+        Regex::builder().configure(config.dfa(false).hybrid(false)).build_many(needles).unwrap();
+    let mut expected: Vec<_> =
+        re1.find_overlapping_iter(Input::new(SingleByteChunks::new(haystack))).collect();
+    let mut actual: Vec<_> =
+        re2.find_overlapping_iter(Input::new(RandomSlices::new(haystack))).collect();
+    expected.sort_by_key(|m| (m.start(), m.end(), m.pattern()));
+    actual.sort_by_key(|m| (m.start(), m.end(), m.pattern()));
+    assert_eq!(expected, actual);
+
+    let mut patset = PatternSet::new(re1.pattern_len());
+    re1.which_overlapping_matches(Input::new(RandomSlices::new(haystack)), &mut patset);
+    let which: Vec<_> = patset.iter().collect();
+    let mut expected: Vec<_> = expected.iter().map(|m| m.pattern()).collect();
+    expected.sort();
+    expected.dedup();
+    assert_eq!(expected, which);
+}
+
+#[test]
+fn overlapping() {
+    compare_overlapping(&[r"\w+", r"\d+", r"foo", r"foobar"], b"foobar 123 bar");
+    compare_overlapping(&[r"a*", r"(?:ab)+"], b"abab");
+    // The lazy DFA quits on the non-ASCII Unicode word boundary, so this
+    // switches to the PikeVM in the middle of the search.
+    compare_overlapping(&[r"\b\w+\b", r"\w"], "ab Шерлок cd".as_bytes());
+}
+
+fn compare_rfind(needle: &str, haystack: &[u8]) {
+    for config in
+        [Regex::config(), Regex::config().dfa(false), Regex::config().dfa(false).hybrid(false)]
+    {
+        let re = Regex::builder().configure(config).build(needle).unwrap();
+        let mut expected: Vec<_> = re.find_iter(Input::new(haystack)).collect();
+        expected.reverse();
+        let actual: Vec<_> = re.rfind_iter(Input::new(SingleByteChunks::new(haystack))).collect();
+        assert_eq!(expected, actual, "{needle}");
+        let actual: Vec<_> = re.rfind_iter(Input::new(RandomSlices::new(haystack))).collect();
+        assert_eq!(expected, actual, "{needle}");
+        for end in (0..=haystack.len()).step_by(haystack.len() / 16 + 1) {
+            let expected = re.find_iter(Input::new(haystack).range(..end)).last();
+            let actual = re.rfind(Input::new(RandomSlices::new(haystack)).range(..end));
+            assert_eq!(expected, actual, "{needle} {end}");
+        }
+    }
+}
+
+#[test]
+fn rfind() {
+    compare_rfind(r"foo[0-9]+", b"foo1 foo12 foo123");
+    compare_rfind(r"a*", b"baa");
+    compare_rfind(r"", "aШb".as_bytes());
+    // These are large enough for the search to start at a window near the
+    // end of the haystack.
+    let haystack = "foo bar 123\nfoobar\n\nШерлок Холмс\n".repeat(200);
+    // The Unicode word boundary makes the lazy DFA quit on the non-ASCII
+    // text, in which case the haystack is searched from the start.
+    for needle in
+        [r"[a-z]+", r"(?m)^", r"(?m)$", r"[0-9]+?", r"foo|foobar", r"\b[0-9]+\b", r"(?s:.)"]
+    {
+        compare_rfind(needle, haystack.as_bytes());
+    }
+    compare_rfind(r"x", &b"x".iter().chain(&[b'a'; 20_000]).copied().collect::<Vec<_>>());
+    // The matches of `find_iter` depend on the parity of the distance to the
+    // start of the haystack, which is beyond the first window.
+    compare_rfind(r"aa", &[b'a'; 5001]);
+    compare_rfind(r"aa|b", &[b'a'; 9001]);
+    compare_rfind(r"a+?a", &[b'a'; 5001]);
+}
+
+/// Polls the given future until it completes. The cursors used in the tests
+/// wake the task before returning `Poll::Pending`, so there is nothing to
+/// wait for.
+fn block_on<F: std::future::Future>(fut: F) -> F::Output {
+    struct NoopWaker;
+
+    impl std::task::Wake for NoopWaker {
+        fn wake(self: std::sync::Arc<Self>) {}
+    }
+
+    let waker = std::task::Waker::from(std::sync::Arc::new(NoopWaker));
+    let mut cx = std::task::Context::from_waker(&waker);
+    let mut fut = Box::pin(fut);
+    loop {
+        if let std::task::Poll::Ready(output) = fut.as_mut().poll(&mut cx) {
+            return output;
+        }
+    }
+}
+
+fn compare_async(needle: &str, haystack: &[u8]) {
+    for config in
+        [Regex::config(), Regex::config().dfa(false), Regex::config().dfa(false).hybrid(false)]
+    {
+        let re = Regex::builder().configure(config).build(needle).unwrap();
+        let expected: Vec<_> = re.find_iter(Input::new(haystack)).collect();
+        let (actual, pending) = block_on(async {
+            let mut it = re.find_iter_async(AsyncChunks::new(SingleByteChunks::new(haystack)));
+            let mut matches = Vec::new();
+            while let Some(m) = it.next().await {
+                matches.push(m);
+            }
+            (matches, it.cursor().pending)
+        });
+        assert_eq!(expected, actual, "{needle}");
+        assert!(haystack.len() < 2 || pending > 0, "{needle}");
+        let actual = block_on(re.find_async(AsyncChunks::new(RandomSlices::new(haystack))));
+        assert_eq!(expected.first().copied(), actual, "{needle}");
+        let actual = block_on(re.is_match_async(AsyncChunks::new(RandomSlices::new(haystack))));
+        assert_eq!(!expected.is_empty(), actual, "{needle}");
+    }
+}
+
+#[test]
+fn find_async() {
+    let haystack = "foo bar 123\nfoobar\n\nШерлок Холмс\n".repeat(4);
+    // The Unicode word boundary makes the lazy DFA quit on the non-ASCII
+    // text, in which case the search starts over with the PikeVM. The
+    // literal alternations are searched by the prefilter alone.
+    for needle in [
+        r"[a-z]+",
+        r"(?m)^",
+        r"(?m)$",
+        r"[0-9]+?",
+        r"\b[0-9]+\b",
+        r"\b\w+\b",
+        r"(?s:.)",
+        r"[a-z]+\s+\d+",
+        r"\w+bar",
+        r"foo|foobar",
+        r"foobar|foo",
+        r"xyz",
+        r"",
+    ] {
+        compare_async(needle, haystack.as_bytes());
+    }
+    compare_async(r"a*", b"baa");
+    compare_async(r"", "aШb".as_bytes());
+    compare_async(r"a|b", b"");
+}
+
+proptest::proptest! {
+  #[test]
+  fn reverse_suffix_matches(haystack in "[a-c0-9@. ]*(@b\\.c)?[a-c0-9@. ]*") {
+    compare_strategy("ReverseSuffix", r"\w+@b\.c", haystack.as_bytes());
+    compare_strategy("ReverseSuffix", r"[0-9][a-c]*@b", haystack.as_bytes());
+  }
+  #[test]
+  fn reverse_inner_matches(haystack in "[a-cgin0-9@ ]*(ing 1)?[a-cgin0-9@ ]*") {
+    compare_strategy("ReverseInner", r"[a-z]+ing\s+\d+", haystack.as_bytes());
+    compare_strategy("ReverseInner", r"(\w+)@@(\d+)", haystack.as_bytes());
+  }
+}
@@ -1,8 +1,11 @@
 pub(crate) mod empty;
+#[cfg(feature = "inflate")]
+pub(crate) mod inflate;
 pub mod iter;
 pub mod prefilter;
 pub mod primitives;
 pub mod sparse_set;
+pub mod stream;
 pub mod utf8;
 
 // #[cfg(test)]
@@ -0,0 +1,253 @@
+/*!
+A gzip (and raw DEFLATE) decoder that can pause after any byte of output and
+whose state can be cloned to restart decoding from there later.
+
+This is what [`InflateCursor`](crate::InflateCursor) uses to record checkpoints in a
+compressed stream. The DEFLATE stream itself is decoded by `miniz_oxide`, whose
+streaming state includes the last 32 KiB of output (which is all that DEFLATE
+can refer back to). This module only adds the gzip framing and tracks how many
+bytes of compressed input were consumed, so a clone of the decoder together
+with the position of the input is enough to resume decoding.
+*/
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crc32fast::Hasher;
+use miniz_oxide::inflate::stream::{inflate, InflateState};
+use miniz_oxide::{DataFormat, MZError, MZFlush, MZStatus};
+
+fn invalid(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+fn unexpected_eof() -> io::Error {
+    io::Error::new(io::ErrorKind::UnexpectedEof, "compressed stream ended unexpectedly")
+}
+
+/// The compressed input of a [`Decoder`], read from a seekable reader.
+#[derive(Debug)]
+pub(crate) struct Source<R> {
+    reader: R,
+    buf: Vec<u8>,
+    pos: usize,
+    /// The offset of `buf` in the compressed stream.
+    offset: u64,
+}
+
+impl<R: Read + Seek> Source<R> {
+    /// Creates a source that starts at the current position of `reader`.
+    pub(crate) fn new(mut reader: R) -> io::Result<Source<R>> {
+        let offset = reader.stream_position()?;
+        Ok(Source { reader, buf: Vec::new(), pos: 0, offset })
+    }
+
+    /// Returns the offset of the next byte in the compressed stream.
+    pub(crate) fn offset(&self) -> u64 {
+        self.offset + self.pos as u64
+    }
+
+    pub(crate) fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Moves to the given offset of the compressed stream.
+    pub(crate) fn seek(&mut self, offset: u64) -> io::Result<()> {
+        let end = self.offset + self.buf.len() as u64;
+        if (self.offset..=end).contains(&offset) {
+            self.pos = (offset - self.offset) as usize;
+            return Ok(());
+        }
+        self.reader.seek(SeekFrom::Start(offset))?;
+        self.buf.clear();
+        self.pos = 0;
+        self.offset = offset;
+        Ok(())
+    }
+
+    /// Returns the buffered input, reading more if the buffer was consumed.
+    /// The returned slice is only empty at the end of the stream.
+    fn fill(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.buf.len() {
+            self.offset += self.buf.len() as u64;
+            self.buf.resize(16 * 1024, 0);
+            self.pos = 0;
+            let read = loop {
+                match self.reader.read(&mut self.buf) {
+                    Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    res => break res,
+                }
+            };
+            match read {
+                Ok(n) => self.buf.truncate(n),
+                Err(err) => {
+                    self.buf.clear();
+                    return Err(err);
+                }
+            }
+        }
+        Ok(&self.buf[self.pos..])
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.pos += n;
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Phase {
+    GzipHeader,
+    Deflate,
+    GzipTrailer,
+    Done,
+}
+
+/// A gzip or DEFLATE decoder. See the module documentation.
+#[derive(Clone)]
+pub(crate) struct Decoder {
+    gzip: bool,
+    phase: Phase,
+    inflate: Box<InflateState>,
+    /// The checksum and length (modulo 2^32) of the current gzip member.
+    crc: Hasher,
+    len: u32,
+    /// The number of bytes of compressed input that were consumed.
+    consumed: u64,
+}
+
+impl fmt::Debug for Decoder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Decoder")
+            .field("gzip", &self.gzip)
+            .field("phase", &self.phase)
+            .field("consumed", &self.consumed)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Decoder {
+    /// Creates a decoder for a gzip stream (or a raw DEFLATE stream if `gzip`
+    /// is false) that starts at the given offset of the compressed input.
+    pub(crate) fn new(gzip: bool, offset: u64) -> Decoder {
+        Decoder {
+            gzip,
+            phase: if gzip { Phase::GzipHeader } else { Phase::Deflate },
+            inflate: InflateState::new_boxed(DataFormat::Raw),
+            crc: Hasher::new(),
+            len: 0,
+            consumed: offset,
+        }
+    }
+
+    /// Returns the offset of the compressed input where decoding continues.
+    pub(crate) fn input_offset(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Decodes bytes into `out` until it holds `limit` bytes or the end of
+    /// the stream is reached.
+    pub(crate) fn decode<R: Read + Seek>(
+        &mut self,
+        src: &mut Source<R>,
+        out: &mut Vec<u8>,
+        limit: usize,
+    ) -> io::Result<()> {
+        while out.len() < limit {
+            match self.phase {
+                Phase::GzipHeader => self.gzip_header(src)?,
+                Phase::Deflate => self.inflate(src, out, limit)?,
+                Phase::GzipTrailer => self.gzip_trailer(src)?,
+                Phase::Done => break,
+            }
+        }
+        Ok(())
+    }
+
+    fn inflate<R: Read + Seek>(
+        &mut self,
+        src: &mut Source<R>,
+        out: &mut Vec<u8>,
+        limit: usize,
+    ) -> io::Result<()> {
+        let start = out.len();
+        out.resize(limit, 0);
+        let input = src.fill()?;
+        let res = inflate(&mut self.inflate, input, &mut out[start..], MZFlush::None);
+        out.truncate(start + res.bytes_written);
+        src.consume(res.bytes_consumed);
+        self.consumed += res.bytes_consumed as u64;
+        self.crc.update(&out[start..]);
+        self.len = self.len.wrapping_add(res.bytes_written as u32);
+        match res.status {
+            Ok(MZStatus::StreamEnd) => {
+                self.phase = if self.gzip { Phase::GzipTrailer } else { Phase::Done };
+            }
+            Ok(_) => (),
+            // no progress is only possible if the input ran out
+            Err(MZError::Buf) if res.bytes_written == 0 => return Err(unexpected_eof()),
+            Err(MZError::Buf) => (),
+            Err(_) => return Err(invalid("corrupt DEFLATE stream")),
+        }
+        Ok(())
+    }
+
+    fn read_u8<R: Read + Seek>(&mut self, src: &mut Source<R>) -> io::Result<u8> {
+        let Some(&byte) = src.fill()?.first() else {
+            return Err(unexpected_eof());
+        };
+        src.consume(1);
+        self.consumed += 1;
+        Ok(byte)
+    }
+
+    fn read_u16<R: Read + Seek>(&mut self, src: &mut Source<R>) -> io::Result<u16> {
+        Ok(u16::from_le_bytes([self.read_u8(src)?, self.read_u8(src)?]))
+    }
+
+    fn read_u32<R: Read + Seek>(&mut self, src: &mut Source<R>) -> io::Result<u32> {
+        Ok(u32::from(self.read_u16(src)?) | u32::from(self.read_u16(src)?) << 16)
+    }
+
+    fn gzip_header<R: Read + Seek>(&mut self, src: &mut Source<R>) -> io::Result<()> {
+        if self.read_u16(src)? != 0x8B1F || self.read_u8(src)? != 8 {
+            return Err(invalid("invalid gzip header"));
+        }
+        let flags = self.read_u8(src)?;
+        // modification time, extra flags and operating system
+        for _ in 0..6 {
+            self.read_u8(src)?;
+        }
+        if flags & 0x04 != 0 {
+            let len = self.read_u16(src)?;
+            for _ in 0..len {
+                self.read_u8(src)?;
+            }
+        }
+        // file name and comment
+        for flag in [0x08, 0x10] {
+            if flags & flag != 0 {
+                while self.read_u8(src)? != 0 {}
+            }
+        }
+        if flags & 0x02 != 0 {
+            self.read_u16(src)?;
+        }
+        self.phase = Phase::Deflate;
+        self.inflate.reset(DataFormat::Raw);
+        self.crc = Hasher::new();
+        self.len = 0;
+        Ok(())
+    }
+
+    fn gzip_trailer<R: Read + Seek>(&mut self, src: &mut Source<R>) -> io::Result<()> {
+        let crc = self.read_u32(src)?;
+        let len = self.read_u32(src)?;
+        if crc != self.crc.clone().finalize() || len != self.len {
+            return Err(invalid("gzip checksum mismatch"));
+        }
+        // another member may follow (anything else is ignored, like gzip does)
+        let next_member = src.fill()?.first() == Some(&0x1F);
+        self.phase = if next_member { Phase::GzipHeader } else { Phase::Done };
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+use regex_automata::{HalfMatch, Match, PatternID, Span};
+
+/// A match reported by a push-based stream searcher.
+///
+/// The end of a match is always known. Its start is only known if the
+/// searcher was configured with a reverse window that still contained the
+/// beginning of the match when it was reported (or if the start can be
+/// determined without a reverse search, for example, because the regex is
+/// anchored).
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub struct StreamMatch {
+    pattern: PatternID,
+    start: Option<usize>,
+    end: usize,
+}
+
+impl StreamMatch {
+    pub(crate) fn new(pattern: PatternID, start: Option<usize>, end: usize) -> StreamMatch {
+        debug_assert!(start.map_or(true, |start| start <= end));
+        StreamMatch { pattern, start, end }
+    }
+
+    /// Returns the ID of the pattern that matched.
+    #[inline]
+    pub fn pattern(&self) -> PatternID {
+        self.pattern
+    }
+
+    /// Returns the starting offset of the match, if it is known.
+    #[inline]
+    pub fn start(&self) -> Option<usize> {
+        self.start
+    }
+
+    /// Returns the ending offset of the match, relative to the beginning of
+    /// the stream.
+    #[inline]
+    pub fn end(&self) -> usize {
+        self.end
+    }
+
+    /// Returns the span of the match, if its start is known.
+    #[inline]
+    pub fn span(&self) -> Option<Span> {
+        self.start.map(|start| Span { start, end: self.end })
+    }
+
+    /// Converts this into a [`Match`], if its start is known.
+    #[inline]
+    pub fn as_match(&self) -> Option<Match> {
+        self.span().map(|span| Match::new(self.pattern, span))
+    }
+
+    /// Converts this into a [`HalfMatch`] at the end of the match.
+    #[inline]
+    pub fn as_half_match(&self) -> HalfMatch {
+        HalfMatch::new(self.pattern, self.end)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use regex_automata::{Match, MatchError};
+
+    use super::StreamMatch;
+
+    /// A push-based stream searcher, so that the stream searchers of the
+    /// different engines can share their tests.
+    pub(crate) trait StreamSearch {
+        fn feed(&mut self, chunk: &[u8]) -> Result<Vec<StreamMatch>, MatchError>;
+        fn finish(&mut self) -> Result<Vec<StreamMatch>, MatchError>;
+    }
+
+    /// Checks that the stream searchers of an engine report the same matches
+    /// as its `find_iter`, no matter how the haystack is split into chunks.
+    /// `new_regex` builds the regex of the engine for a pattern and
+    /// `new_searcher` creates a stream searcher for it with the given reverse
+    /// window.
+    pub(crate) fn check_stream<R>(
+        new_regex: impl Fn(&str) -> R,
+        find_iter: impl Fn(&R, &str) -> Vec<Match>,
+        new_searcher: for<'r> fn(&'r R, usize) -> Box<dyn StreamSearch + 'r>,
+    ) {
+        let haystack = "foo1 bar23\nШерлок aab\n 45 a";
+        let needles =
+            [r"[0-9]+", r"a*", r"(?-u:\b)[a-z]+(?-u:\b)", r"(?m)$", r"a+b|a", r"^foo|a$", ""];
+        for needle in needles {
+            let regex = new_regex(needle);
+            let expected = find_iter(&regex, haystack);
+            for chunk_len in [1, 2, 3, 7, haystack.len()] {
+                for window in [0, 4, haystack.len()] {
+                    let mut searcher = new_searcher(&regex, window);
+                    let mut matches = Vec::new();
+                    for chunk in haystack.as_bytes().chunks(chunk_len) {
+                        matches.extend(searcher.feed(chunk).unwrap());
+                    }
+                    matches.extend(searcher.finish().unwrap());
+                    let ends: Vec<_> = matches.iter().map(|m| m.end()).collect();
+                    let expected_ends: Vec<_> = expected.iter().map(|m| m.end()).collect();
+                    assert_eq!(ends, expected_ends, "{needle:?} {chunk_len} {window}");
+                    for (m, expected) in matches.iter().zip(&expected) {
+                        if window == haystack.len() {
+                            assert_eq!(m.as_match(), Some(*expected), "{needle:?} {chunk_len}");
+                        } else if let Some(start) = m.start() {
+                            assert_eq!(start, expected.start(), "{needle:?} {chunk_len} {window}");
+                        }
+                    }
+                }
+            }
+        }
+    }
+}